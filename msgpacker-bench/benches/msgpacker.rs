@@ -29,7 +29,7 @@ pub fn pack(c: &mut Criterion) {
 
         let mut buf = Vec::new();
         let mut serializer = Serializer::new(&mut buf);
-        (&values[..count]).serialize(&mut serializer).unwrap();
+        values[..count].serialize(&mut serializer).unwrap();
         bufs_rmps.push(buf);
     }
 
@@ -108,5 +108,44 @@ pub fn pack(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, pack);
+pub fn numbers(c: &mut Criterion) {
+    let values: Vec<f32> = StdRng::from_seed([0xfa; 32])
+        .sample_iter(&Standard)
+        .take(1_000_000)
+        .collect();
+
+    let mut buf = Vec::new();
+    msgpacker::pack_numbers(&mut buf, &values);
+    let capacity = buf.len();
+
+    let mut group = c.benchmark_group("numbers");
+
+    group.bench_function("pack_array", |b| {
+        b.iter_batched(
+            || Vec::with_capacity(capacity),
+            |mut buf| msgpacker::pack_array(black_box(&mut buf), black_box(values.iter())),
+            BatchSize::LargeInput,
+        );
+    });
+
+    group.bench_function("pack_numbers", |b| {
+        b.iter_batched(
+            || Vec::with_capacity(capacity),
+            |mut buf| msgpacker::pack_numbers(black_box(&mut buf), black_box(&values)),
+            BatchSize::LargeInput,
+        );
+    });
+
+    group.bench_function("unpack_array", |b| {
+        b.iter(|| msgpacker::unpack_array::<f32, Vec<_>>(black_box(&buf)));
+    });
+
+    group.bench_function("unpack_numbers", |b| {
+        b.iter(|| msgpacker::unpack_numbers::<f32>(black_box(&buf)));
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, pack, numbers);
 criterion_main!(benches);
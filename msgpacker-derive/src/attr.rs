@@ -0,0 +1,320 @@
+use quote::ToTokens;
+use syn::{
+    meta::ParseNestedMeta, punctuated::Punctuated, Attribute, Expr, ExprLit, Ident, Lit, LitStr,
+    Meta, Path, Token, Type,
+};
+
+/// The integer types a discriminant can be packed as.
+const TAG_TYPES: &[&str] = &[
+    "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+];
+
+/// Wire representation of an enum, selected via container attributes.
+pub enum Representation {
+    /// The discriminant as `u32`, followed by the fields of the variant. This is the default.
+    Index,
+    /// `#[msgpacker(external)]`: a single-entry map `{"Variant": payload}`.
+    External,
+    /// `#[msgpacker(tag = "t")]`: a map `{"t": "Variant", "field": value, ..}`.
+    Internal { tag: LitStr },
+    /// `#[msgpacker(tag = "t", content = "c")]`: a map `{"t": "Variant", "c": payload}`.
+    Adjacent { tag: LitStr, content: LitStr },
+    /// `#[msgpacker(untagged)]`: the payload only; decoding picks the first matching variant.
+    Untagged,
+}
+
+/// Calls `f` for every option of the `#[msgpacker(...)]` attributes.
+fn parse<F>(attrs: &[Attribute], mut f: F) -> syn::Result<()>
+where
+    F: FnMut(ParseNestedMeta) -> syn::Result<()>,
+{
+    attrs
+        .iter()
+        .filter(|a| a.path().is_ident("msgpacker"))
+        .try_for_each(|a| a.parse_nested_meta(&mut f))
+}
+
+fn unknown(meta: &ParseNestedMeta, position: &str) -> syn::Error {
+    let path = meta.path.to_token_stream().to_string().replace(' ', "");
+    meta.error(format_args!(
+        "unknown msgpacker {position} attribute `{path}`"
+    ))
+}
+
+fn set<T>(meta: &ParseNestedMeta, slot: &mut Option<T>, value: T) -> syn::Result<()> {
+    if slot.is_some() {
+        let path = meta.path.to_token_stream().to_string();
+        return Err(meta.error(format_args!("duplicate msgpacker attribute `{path}`")));
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+/// Options of `#[msgpacker(...)]` on a struct or enum.
+#[derive(Default)]
+pub struct ContainerAttrs {
+    external: Option<Path>,
+    untagged: Option<Path>,
+    tag: Option<LitStr>,
+    content: Option<LitStr>,
+    tag_type: Option<LitStr>,
+    repr: Option<Ident>,
+    error: Option<LitStr>,
+    validate: Option<LitStr>,
+    try_from: Option<LitStr>,
+    into: Option<LitStr>,
+    remote: Option<LitStr>,
+}
+
+impl ContainerAttrs {
+    pub fn from_attrs(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut slf = Self::default();
+
+        // `#[repr(C, u8)]` is honoured as the tag type, ignoring the non-integer options
+        for attr in attrs.iter().filter(|a| a.path().is_ident("repr")) {
+            let metas = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+            slf.repr = metas
+                .iter()
+                .filter_map(|m| m.path().get_ident())
+                .find(|i| TAG_TYPES.iter().any(|t| i == t))
+                .cloned()
+                .or(slf.repr);
+        }
+
+        parse(attrs, |meta| {
+            if meta.path.is_ident("external") {
+                set(&meta, &mut slf.external, meta.path.clone())
+            } else if meta.path.is_ident("untagged") {
+                set(&meta, &mut slf.untagged, meta.path.clone())
+            } else if meta.path.is_ident("tag") {
+                set(&meta, &mut slf.tag, meta.value()?.parse()?)
+            } else if meta.path.is_ident("content") {
+                set(&meta, &mut slf.content, meta.value()?.parse()?)
+            } else if meta.path.is_ident("error") {
+                set(&meta, &mut slf.error, meta.value()?.parse()?)
+            } else if meta.path.is_ident("validate") {
+                set(&meta, &mut slf.validate, meta.value()?.parse()?)
+            } else if meta.path.is_ident("try_from") {
+                set(&meta, &mut slf.try_from, meta.value()?.parse()?)
+            } else if meta.path.is_ident("into") {
+                set(&meta, &mut slf.into, meta.value()?.parse()?)
+            } else if meta.path.is_ident("remote") {
+                set(&meta, &mut slf.remote, meta.value()?.parse()?)
+            } else if meta.path.is_ident("tag_type") {
+                let ty: LitStr = meta.value()?.parse()?;
+                if !TAG_TYPES.iter().any(|t| ty.value() == *t) {
+                    return Err(syn::Error::new_spanned(
+                        ty,
+                        "`tag_type` must be a primitive integer type",
+                    ));
+                }
+                set(&meta, &mut slf.tag_type, ty)
+            } else {
+                Err(unknown(&meta, "container"))
+            }
+        })?;
+        Ok(slf)
+    }
+
+    /// Rejects the options that apply only to enums.
+    pub fn check_struct(&self) -> syn::Result<()> {
+        let offending = self
+            .external
+            .as_ref()
+            .map(|p| (p.to_token_stream(), "external"))
+            .or_else(|| {
+                self.untagged
+                    .as_ref()
+                    .map(|p| (p.to_token_stream(), "untagged"))
+            })
+            .or_else(|| self.tag.as_ref().map(|t| (t.to_token_stream(), "tag")))
+            .or_else(|| {
+                self.content
+                    .as_ref()
+                    .map(|c| (c.to_token_stream(), "content"))
+            })
+            .or_else(|| {
+                self.tag_type
+                    .as_ref()
+                    .map(|t| (t.to_token_stream(), "tag_type"))
+            });
+
+        match offending {
+            Some((tokens, option)) => Err(syn::Error::new_spanned(
+                tokens,
+                format_args!("`{option}` is supported only on enums"),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// The error type set with `error = "MyError"`.
+    pub fn error(&self) -> syn::Result<Option<Type>> {
+        self.error.as_ref().map(LitStr::parse).transpose()
+    }
+
+    /// The function set with `validate = "path"`, checking the unpacked value.
+    pub fn validate(&self) -> syn::Result<Option<Path>> {
+        self.validate.as_ref().map(LitStr::parse).transpose()
+    }
+
+    /// The proxy type set with `try_from = "Raw"`, unpacked and then converted.
+    pub fn unpack_proxy(&self) -> syn::Result<Option<Type>> {
+        self.try_from.as_ref().map(LitStr::parse).transpose()
+    }
+
+    /// The proxy type set with `into = "Raw"`, converted into and then packed.
+    pub fn pack_proxy(&self) -> syn::Result<Option<Type>> {
+        self.into.as_ref().map(LitStr::parse).transpose()
+    }
+
+    /// The foreign type set with `remote = "path::Type"`, packed by free functions instead.
+    pub fn remote(&self) -> syn::Result<Option<Path>> {
+        self.remote.as_ref().map(LitStr::parse).transpose()
+    }
+
+    /// The type the discriminants are packed as: `tag_type`, the integer `repr` of the enum, or
+    /// `u32`.
+    pub fn tag_type(&self) -> syn::Result<Type> {
+        match (&self.tag_type, &self.repr) {
+            (Some(ty), _) => ty.parse(),
+            (None, Some(repr)) => Ok(syn::parse_quote! { #repr }),
+            (None, None) => Ok(syn::parse_quote! { u32 }),
+        }
+    }
+
+    pub fn representation(&self) -> syn::Result<Representation> {
+        let named = self.external.is_some() || self.untagged.is_some() || self.tag.is_some();
+        if let Some(ty) = self.tag_type.as_ref().filter(|_| named) {
+            return Err(syn::Error::new_spanned(
+                ty,
+                "`tag_type` applies only to enums packed by discriminant",
+            ));
+        }
+
+        match (&self.external, &self.untagged, &self.tag, &self.content) {
+            (None, None, None, None) => Ok(Representation::Index),
+            (Some(_), None, None, None) => Ok(Representation::External),
+            (None, Some(_), None, None) => Ok(Representation::Untagged),
+            (None, None, Some(tag), None) => Ok(Representation::Internal { tag: tag.clone() }),
+            (None, None, Some(tag), Some(content)) => Ok(Representation::Adjacent {
+                tag: tag.clone(),
+                content: content.clone(),
+            }),
+            (_, _, None, Some(content)) => Err(syn::Error::new_spanned(
+                content,
+                "`content` requires a `tag` to be set",
+            )),
+            (Some(path), _, _, _) | (_, Some(path), _, _) => Err(syn::Error::new_spanned(
+                path,
+                "only one of `external`, `untagged` or `tag` can be set",
+            )),
+        }
+    }
+}
+
+/// The tag of a variant set with `#[msgpacker(tag = ..)]`.
+#[derive(Clone)]
+pub enum VariantTag {
+    /// An integer expression, packed as the tag type of the enum.
+    Int(Expr),
+    /// A string, packed in place of the discriminant or the name of the variant.
+    Str(LitStr),
+}
+
+/// Options of `#[msgpacker(...)]` on an enum variant.
+#[derive(Default)]
+pub struct VariantAttrs {
+    pub tag: Option<VariantTag>,
+}
+
+impl VariantAttrs {
+    pub fn from_attrs(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut slf = Self::default();
+        parse(attrs, |meta| {
+            if meta.path.is_ident("tag") {
+                let tag = match meta.value()?.parse()? {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(s), ..
+                    }) => VariantTag::Str(s),
+                    e => VariantTag::Int(e),
+                };
+                set(&meta, &mut slf.tag, tag)
+            } else {
+                Err(unknown(&meta, "variant"))
+            }
+        })?;
+        Ok(slf)
+    }
+}
+
+/// Options of `#[msgpacker(...)]` on a field.
+#[derive(Default)]
+pub struct FieldAttrs {
+    pub map: bool,
+    pub array: bool,
+    pub validate: Option<Path>,
+    pub with: Option<Path>,
+    /// Splices the entries of the field into the map of a struct variant; positional elsewhere.
+    pub flatten: bool,
+    /// The path of the `FloatOptions` constant the field is unpacked with.
+    pub floats: Option<Path>,
+}
+
+impl FieldAttrs {
+    pub fn from_attrs(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut map = None;
+        let mut array = None;
+        let mut validate = None;
+        let mut with = None;
+        let mut flatten = None;
+        let mut floats = None;
+        parse(attrs, |meta| {
+            if meta.path.is_ident("map") {
+                set(&meta, &mut map, ())?;
+            } else if meta.path.is_ident("array") {
+                set(&meta, &mut array, ())?;
+            } else if meta.path.is_ident("validate") {
+                let path = meta.value()?.parse::<LitStr>()?.parse()?;
+                set(&meta, &mut validate, path)?;
+            } else if meta.path.is_ident("with") {
+                let path = meta.value()?.parse::<LitStr>()?.parse()?;
+                set(&meta, &mut with, path)?;
+            } else if meta.path.is_ident("flatten") {
+                set(&meta, &mut flatten, ())?;
+            } else if meta.path.is_ident("floats") {
+                let path = meta.value()?.parse::<LitStr>()?.parse()?;
+                set(&meta, &mut floats, path)?;
+            } else {
+                return Err(unknown(&meta, "field"));
+            }
+            if map.is_some() && array.is_some() {
+                return Err(meta.error("`map` and `array` cannot be combined"));
+            }
+            if with.is_some() && (map.is_some() || array.is_some()) {
+                return Err(meta.error("`with` cannot be combined with `map` or `array`"));
+            }
+            if flatten.is_some() && (map.is_some() || array.is_some() || with.is_some()) {
+                return Err(
+                    meta.error("`flatten` cannot be combined with `map`, `array` or `with`")
+                );
+            }
+            if floats.is_some()
+                && (map.is_some() || array.is_some() || with.is_some() || flatten.is_some())
+            {
+                return Err(meta.error(
+                    "`floats` cannot be combined with `map`, `array`, `with` or `flatten`",
+                ));
+            }
+            Ok(())
+        })?;
+        Ok(Self {
+            map: map.is_some(),
+            array: array.is_some(),
+            validate,
+            with,
+            flatten: flatten.is_some(),
+            floats,
+        })
+    }
+}
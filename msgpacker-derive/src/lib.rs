@@ -1,566 +1,1180 @@
 #![crate_type = "proc-macro"]
 extern crate proc_macro;
 
-// This code is bad and should be refactored into something cleaner. Maybe some syn-based
-// framework?
-
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::punctuated::Punctuated;
+use proc_macro2::{TokenStream as TokenStream2, TokenTree};
+use quote::{format_ident, quote, ToTokens};
 use syn::{
-    parse_macro_input, parse_quote, parse_str, Block, Data, DataEnum, DataStruct, DataUnion,
-    DeriveInput, Expr, ExprMatch, ExprTuple, Field, FieldPat, FieldValue, Fields, FieldsNamed,
-    FieldsUnnamed, GenericArgument, Ident, Member, Meta, Pat, PatIdent, PathArguments, Token, Type,
-    Variant,
+    parse_macro_input, parse_quote, Data, DeriveInput, Field, Fields, GenericArgument, Ident,
+    LitStr, Path, PathArguments, Type, Variant,
 };
 
-fn contains_attribute(field: &Field, name: &str) -> bool {
-    let name = name.to_string();
-    if let Some(attr) = field.attrs.first() {
-        if let Meta::List(list) = &attr.meta {
-            if list.path.is_ident("msgpacker")
-                && list
-                    .tokens
-                    .clone()
-                    .into_iter()
-                    .find(|a| a.to_string() == name)
-                    .is_some()
-            {
-                return true;
+mod attr;
+mod tagged;
+
+use attr::{ContainerAttrs, FieldAttrs, Representation, VariantAttrs, VariantTag};
+
+/// How a field is packed.
+enum FieldKind {
+    /// Via `pack_map`, with `#[msgpacker(map)]`.
+    Map,
+    /// Via `pack_array`, either explicitly with `#[msgpacker(array)]` or implicitly for `Vec<T>`
+    /// and `SmallVec<[T; N]>` where `T` is not `u8`.
+    Array,
+    /// Via `pack_numbers`, as an array, for `Vec<T>` where `T` is a primitive number.
+    Numbers,
+    /// Via `pack_array`, unpacked with `unpack_array_fixed`, for the collections of a fixed
+    /// capacity, `heapless::Vec<T, N>` and `ArrayVec<T, N>`, where `T` is not `u8`.
+    Fixed,
+    /// Via the functions generated by a remote derive, with `#[msgpacker(with = "Def")]`.
+    With(Path),
+    /// Via the `Packable` implementation of `f32` or `f64`, the identifier of the type, unpacked
+    /// with the `FloatOptions` constant of `#[msgpacker(floats = "OPTIONS")]`.
+    Floats(Path, Ident),
+    /// Via the `Packable` implementation of the type.
+    Value,
+}
+
+fn field_kind(field: &Field) -> FieldKind {
+    let mut is_vec = false;
+    let mut is_fixed = false;
+    let mut is_vec_u8 = false;
+    let mut is_vec_numeric = false;
+
+    match &field.ty {
+        Type::Path(p)
+            if p.path
+                .segments
+                .last()
+                .filter(|p| p.ident == "Vec" || p.ident == "ArrayVec" || p.ident == "SmallVec")
+                .is_some() =>
+        {
+            let last = p.path.segments.last().unwrap();
+            match &last.arguments {
+                PathArguments::AngleBracketed(a) if !a.args.is_empty() => {
+                    // the capacity follows the element in `heapless::Vec` and `ArrayVec`
+                    is_fixed = last.ident == "ArrayVec" || a.args.len() > 1;
+                    is_vec = !is_fixed;
+                    let elem = match a.args.first() {
+                        // the element of `SmallVec` is in its inline array, `[T; N]`
+                        Some(GenericArgument::Type(Type::Array(a))) if last.ident == "SmallVec" => {
+                            Some(&*a.elem)
+                        }
+                        Some(GenericArgument::Type(t)) => Some(t),
+                        _ => None,
+                    };
+                    if let Some(Type::Path(p)) = elem {
+                        if let Some(elem) = p.path.segments.last() {
+                            is_vec_u8 = elem.ident == "u8";
+                            is_vec_numeric = last.ident == "Vec"
+                                && !is_fixed
+                                && [
+                                    "u8", "u16", "u32", "u64", "i8", "i16", "i32", "i64", "f32",
+                                    "f64",
+                                ]
+                                .iter()
+                                .any(|n| elem.ident == n);
+                        }
+                    }
+                }
+                _ => is_vec = last.ident == "Vec",
             }
         }
+
+        _ => (),
+    }
+
+    // the attributes are validated by `check_fields` before any code is generated
+    let attrs = FieldAttrs::from_attrs(&field.attrs).unwrap_or_default();
+    if let Some(with) = attrs.with {
+        FieldKind::With(with)
+    } else if let (Some(options), Some(float)) = (attrs.floats, float_type(&field.ty)) {
+        FieldKind::Floats(options, float.clone())
+    } else if attrs.map {
+        FieldKind::Map
+    } else if is_vec_numeric && (attrs.array || !is_vec_u8) {
+        FieldKind::Numbers
+    } else if is_fixed && (attrs.array || !is_vec_u8) {
+        FieldKind::Fixed
+    } else if attrs.array || is_vec && !is_vec_u8 {
+        FieldKind::Array
+    } else {
+        FieldKind::Value
     }
-    false
 }
 
-fn impl_fields_named(name: Ident, f: FieldsNamed) -> impl Into<TokenStream> {
-    let mut values: Punctuated<FieldValue, Token![,]> = Punctuated::new();
-    let block_packable: Block = parse_quote! {
-        {
-            let mut n = 0;
+/// The local variables the fields are bound to in the generated code.
+fn bindings(fields: &Fields) -> Vec<Ident> {
+    (0..fields.len())
+        .map(|i| format_ident!("__field{}", i))
+        .collect()
+}
+
+/// How the generated code packs the values.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Packing {
+    /// As `pack` does.
+    Plain,
+    /// In canonical form, failing with `Error` on the values that have none, as
+    /// `try_pack_canonical` does.
+    Canonical,
+    /// Failing with `PackError` on the values too large for the protocol, as `try_pack` does.
+    Fallible,
+}
+
+impl Packing {
+    /// The expression returning `n`, the amount of written bytes, from the generated function.
+    fn written(self) -> TokenStream2 {
+        match self {
+            Packing::Fallible | Packing::Canonical => quote! { Ok(n) },
+            Packing::Plain => quote! { n },
         }
-    };
-    let block_unpackable: Block = parse_quote! {
-        {
-            let mut n = 0;
+    }
+}
+
+/// An expression packing `value`, a reference to the field, into `buf`.
+///
+/// The expression counts the written bytes; with [Packing::Fallible] and [Packing::Canonical],
+/// it propagates the error.
+fn pack_field(field: &Field, value: impl ToTokens, packing: Packing) -> TokenStream2 {
+    let ty = &field.ty;
+    match (field_kind(field), packing) {
+        (FieldKind::Map, Packing::Plain) => quote! { ::msgpacker::pack_map(buf, #value) },
+        (FieldKind::Map, Packing::Canonical) => {
+            quote! { ::msgpacker::canonical::pack_map(buf, #value)? }
         }
-    };
-    let block_unpackable_iter: Block = parse_quote! {
-        {
-            let mut bytes = bytes.into_iter();
-            let mut n = 0;
+        (FieldKind::Map, Packing::Fallible) => quote! { ::msgpacker::try_pack_map(buf, #value)? },
+        (FieldKind::Array | FieldKind::Fixed, Packing::Plain) => {
+            quote! { ::msgpacker::pack_array(buf, #value) }
         }
-    };
+        (FieldKind::Array | FieldKind::Fixed, Packing::Canonical) => {
+            quote! { ::msgpacker::canonical::pack_array(buf, #value)? }
+        }
+        (FieldKind::Array | FieldKind::Fixed, Packing::Fallible) => {
+            quote! { ::msgpacker::try_pack_array(buf, #value)? }
+        }
+        (FieldKind::Numbers, Packing::Plain) => quote! { ::msgpacker::pack_numbers(buf, #value) },
+        (FieldKind::Numbers, Packing::Canonical) => {
+            quote! { ::msgpacker::canonical::pack_array(buf, #value)? }
+        }
+        (FieldKind::Numbers, Packing::Fallible) => {
+            quote! { ::msgpacker::try_pack_numbers(buf, #value)? }
+        }
+        (FieldKind::With(with), Packing::Plain) => quote! { #with::pack(#value, buf) },
+        // `with` modules have no canonical packing, so their output is re-encoded
+        (FieldKind::With(with), Packing::Canonical) => quote! {
+            ::msgpacker::canonical::pack_with(buf, |buf| #with::pack(#value, buf))?
+        },
+        (FieldKind::With(with), Packing::Fallible) => quote! { #with::try_pack(#value, buf)? },
+        (FieldKind::Value | FieldKind::Floats(..), Packing::Plain) => {
+            quote! { <#ty as ::msgpacker::Packable>::pack(#value, buf) }
+        }
+        (FieldKind::Value | FieldKind::Floats(..), Packing::Canonical) => {
+            quote! { <#ty as ::msgpacker::Packable>::try_pack_canonical(#value, buf)? }
+        }
+        (FieldKind::Value | FieldKind::Floats(..), Packing::Fallible) => {
+            quote! { <#ty as ::msgpacker::Packable>::try_pack(#value, buf)? }
+        }
+    }
+}
 
-    let (
-        mut block_packable,
-        mut block_unpackable,
-        mut block_unpackable_iter
-    ) = f.named.into_pairs().map(|p| p.into_value()).fold(
-            (block_packable, block_unpackable, block_unpackable_iter),
-            |(mut block_packable, mut block_unpackable, mut block_unpackable_iter), field| {
-                let ident = field.ident.as_ref().cloned().unwrap();
-                let ty = field.ty.clone();
-
-                let mut is_vec = false;
-                let mut is_vec_u8 = false;
-
-                match &ty {
-                    Type::Path(p) if p.path.segments.last().filter(|p| p.ident == "Vec").is_some() => {
-                        is_vec = true;
-                        match &p.path.segments.last().unwrap().arguments {
-                            PathArguments::AngleBracketed(a) if a.args.len() == 1 => {
-                                if let Some(GenericArgument::Type(Type::Path(p))) = a.args.first() {
-                                    if p.path.segments.last().filter(|p| p.ident == "u8").is_some() {
-                                        is_vec_u8 = true;
-                                    }
-                                }
-                            }
-                            _ => (),
-                        }
-                    }
+/// An expression packing the header of an array of `len` elements, as [pack_field] does.
+fn pack_array_len(len: impl ToTokens, packing: Packing) -> TokenStream2 {
+    match packing {
+        Packing::Fallible => quote! { ::msgpacker::try_pack_array_len(buf, #len)? },
+        _ => quote! { ::msgpacker::pack_array_len(buf, #len) },
+    }
+}
 
-                    _ => (),
-                }
+/// An expression packing the header of a map of `len` entries, as [pack_field] does.
+fn pack_map_len(len: impl ToTokens, packing: Packing) -> TokenStream2 {
+    match packing {
+        Packing::Fallible => quote! { ::msgpacker::try_pack_map_len(buf, #len)? },
+        _ => quote! { ::msgpacker::pack_map_len(buf, #len) },
+    }
+}
 
-                if contains_attribute(&field, "map") {
-                    block_packable.stmts.push(parse_quote! {
-                        n += ::msgpacker::pack_map(buf, &self.#ident);
-                    });
-
-                    block_unpackable.stmts.push(parse_quote! {
-                        let #ident = ::msgpacker::unpack_map(buf).map(|(nv, t)| {
-                            n += nv;
-                            buf = &buf[nv..];
-                            t
-                        })?;
-                    });
-
-                    block_unpackable_iter.stmts.push(parse_quote! {
-                        let #ident = ::msgpacker::unpack_map_iter(bytes.by_ref()).map(|(nv, t)| {
-                            n += nv;
-                            t
-                        })?;
-                    });
-                } else if contains_attribute(&field, "array") || is_vec && !is_vec_u8 {
-                    block_packable.stmts.push(parse_quote! {
-                        n += ::msgpacker::pack_array(buf, &self.#ident);
-                    });
-
-                    block_unpackable.stmts.push(parse_quote! {
-                        let #ident = ::msgpacker::unpack_array(buf).map(|(nv, t)| {
-                            n += nv;
-                            buf = &buf[nv..];
-                            t
-                        })?;
-                    });
-
-                    block_unpackable_iter.stmts.push(parse_quote! {
-                        let #ident = ::msgpacker::unpack_array_iter(bytes.by_ref()).map(|(nv, t)| {
-                            n += nv;
-                            t
-                        })?;
-                    });
-                } else {
-                    block_packable.stmts.push(parse_quote! {
-                        n += <#ty as ::msgpacker::Packable>::pack(&self.#ident, buf);
-                    });
-
-                    block_unpackable.stmts.push(parse_quote! {
-                        let #ident = ::msgpacker::Unpackable::unpack(buf).map(|(nv, t)| {
-                            n += nv;
-                            buf = &buf[nv..];
-                            t
-                        })?;
-                    });
-
-                    block_unpackable_iter.stmts.push(parse_quote! {
-                        let #ident = ::msgpacker::Unpackable::unpack_iter(bytes.by_ref()).map(|(nv, t)| {
-                            n += nv;
-                            t
-                        })?;
-                    });
-                }
+/// The module of the functions unpacking strings and the headers of arrays and maps: the root
+/// of `msgpacker` or, with `strict`, the `strict` module, which checks them.
+fn decoders(strict: bool) -> TokenStream2 {
+    match strict {
+        true => quote! { ::msgpacker::strict },
+        false => quote! { ::msgpacker },
+    }
+}
 
+/// The name of the function unpacking a value from a slice, `unpack` or `unpack_strict`.
+fn unpack_fn(strict: bool) -> Ident {
+    match strict {
+        true => format_ident!("unpack_strict"),
+        false => format_ident!("unpack"),
+    }
+}
 
-                values.push(FieldValue {
-                    attrs: vec![],
-                    member: Member::Named(ident.clone()),
-                    colon_token: Some(<Token![:]>::default()),
-                    expr: parse_quote! { #ident },
-                });
+/// An expression unpacking the field from `buf`.
+///
+/// With `strict`, the field is unpacked under the strict rules, as `unpack_strict` does.
+fn unpack_field(field: &Field, strict: bool) -> TokenStream2 {
+    let ty = &field.ty;
+    let (decoders, unpack) = (decoders(strict), unpack_fn(strict));
+    match field_kind(field) {
+        FieldKind::Map => quote! { #decoders::unpack_map(buf) },
+        FieldKind::Array => quote! { #decoders::unpack_array(buf) },
+        FieldKind::Fixed => quote! { #decoders::unpack_array_fixed(buf) },
+        // the strict rules are checked element by element
+        FieldKind::Numbers if strict => quote! { #decoders::unpack_array(buf) },
+        FieldKind::Numbers => quote! { ::msgpacker::unpack_numbers(buf) },
+        FieldKind::With(with) => quote! { #with::#unpack(buf) },
+        // the options decode integers too, which are checked as the other values
+        FieldKind::Floats(options, float) if strict => {
+            let unpack = format_ident!("unpack_{}", float);
+            quote! {
+                #options.#unpack(buf).and_then(|(c, v)| {
+                    ::msgpacker::strict::check(&buf[..c]).map(|_| (c, v))
+                })
+            }
+        }
+        FieldKind::Floats(options, float) => {
+            let unpack = format_ident!("unpack_{}", float);
+            quote! { #options.#unpack(buf) }
+        }
+        FieldKind::Value => quote! { <#ty as ::msgpacker::Unpackable>::#unpack(buf) },
+    }
+}
 
-                (block_packable, block_unpackable, block_unpackable_iter)
-            },
-        );
+/// An expression unpacking the field from the `bytes` iterator.
+fn unpack_field_iter(field: &Field) -> TokenStream2 {
+    let ty = &field.ty;
+    match field_kind(field) {
+        FieldKind::Map => quote! { ::msgpacker::unpack_map_iter(bytes.by_ref()) },
+        FieldKind::Array | FieldKind::Numbers => {
+            quote! { ::msgpacker::unpack_array_iter(bytes.by_ref()) }
+        }
+        FieldKind::Fixed => quote! { ::msgpacker::unpack_array_fixed_iter(bytes.by_ref()) },
+        FieldKind::With(with) => quote! { #with::unpack_iter(bytes.by_ref()) },
+        FieldKind::Floats(options, float) => {
+            let unpack = format_ident!("unpack_{}_iter", float);
+            quote! { #options.#unpack(bytes.by_ref()) }
+        }
+        FieldKind::Value => {
+            quote! { <#ty as ::msgpacker::Unpackable>::unpack_iter(bytes.by_ref()) }
+        }
+    }
+}
 
-    block_packable.stmts.push(parse_quote! {
-        return n;
-    });
+/// Folds the errors into a single one, so all of them are reported at once.
+fn combine(errors: impl IntoIterator<Item = syn::Error>) -> syn::Result<()> {
+    errors
+        .into_iter()
+        .reduce(|mut e, f| {
+            e.combine(f);
+            e
+        })
+        .map_or(Ok(()), Err)
+}
 
-    block_unpackable.stmts.push(parse_quote! {
-        return Ok((
-            n,
-            Self {
-                #values
-            },
-        ));
-    });
+fn check_fields<'a>(fields: impl IntoIterator<Item = &'a Field>) -> syn::Result<()> {
+    combine(
+        fields
+            .into_iter()
+            .filter_map(|f| match FieldAttrs::from_attrs(&f.attrs) {
+                Ok(attrs) if attrs.floats.is_some() && float_type(&f.ty).is_none() => {
+                    Some(syn::Error::new_spanned(
+                        &f.ty,
+                        "`floats` applies only to `f32` and `f64` fields",
+                    ))
+                }
+                Ok(_) => None,
+                Err(e) => Some(e),
+            }),
+    )
+}
 
-    block_unpackable_iter.stmts.push(parse_quote! {
-        return Ok((
-            n,
-            Self {
-                #values
-            },
-        ));
-    });
+/// The identifier of the type if it's `f32` or `f64`.
+fn float_type(ty: &Type) -> Option<&Ident> {
+    match ty {
+        Type::Path(p) if p.qself.is_none() => p
+            .path
+            .segments
+            .last()
+            .map(|s| &s.ident)
+            .filter(|i| *i == "f32" || *i == "f64"),
+        _ => None,
+    }
+}
 
-    quote! {
-        impl ::msgpacker::Packable for #name {
-            fn pack<T>(&self, buf: &mut T) -> usize
-            where
-                T: Extend<u8>,
-                #block_packable
-        }
+/// Reports the invalid variant attributes, the tags unsupported by the representation and the
+/// duplicate string tags.
+fn check_variants(repr: &Representation, variants: &[Variant]) -> syn::Result<()> {
+    let mut errors = vec![];
+    let mut labels: Vec<LitStr> = vec![];
+
+    for v in variants {
+        let tag = match VariantAttrs::from_attrs(&v.attrs) {
+            Ok(attrs) => attrs.tag,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+
+        let label = match (tag, repr) {
+            (Some(_), Representation::Untagged) => {
+                errors.push(syn::Error::new_spanned(
+                    &v.ident,
+                    "the variants of untagged enums can't have a `tag`",
+                ));
+                None
+            }
+            (Some(VariantTag::Str(s)), _) => Some(s),
+            (Some(VariantTag::Int(_)), Representation::Index) => None,
+            (Some(VariantTag::Int(e)), _) => {
+                errors.push(syn::Error::new_spanned(
+                    e,
+                    "integer tags are supported only by enums packed by discriminant",
+                ));
+                None
+            }
+            (None, Representation::Index | Representation::Untagged) => None,
+            (None, _) => Some(tagged::label(&v.ident)),
+        };
 
-        impl ::msgpacker::Unpackable for #name {
-            type Error = ::msgpacker::Error;
+        if let Some(label) = label {
+            if labels.iter().any(|l| l.value() == label.value()) {
+                errors.push(syn::Error::new_spanned(&label, "duplicate variant tag"));
+            }
+            labels.push(label);
+        }
+    }
 
-            fn unpack(mut buf: &[u8]) -> Result<(usize, Self), Self::Error>
-                #block_unpackable
+    combine(errors)
+}
 
-            fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
-            where
-                I: IntoIterator<Item = u8>,
-                #block_unpackable_iter
+/// Whether the type is known to fail with `msgpacker::Error`.
+fn is_primitive(ty: &Type) -> bool {
+    match ty {
+        Type::Array(a) => is_primitive(&a.elem),
+        Type::Paren(p) => is_primitive(&p.elem),
+        Type::Tuple(t) => t.elems.iter().all(is_primitive),
+        Type::Path(p) if p.qself.is_none() => {
+            let Some(last) = p.path.segments.last() else {
+                return false;
+            };
+            let args = match &last.arguments {
+                PathArguments::AngleBracketed(a) => a
+                    .args
+                    .iter()
+                    .filter_map(|a| match a {
+                        GenericArgument::Type(t) => Some(t),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => vec![],
+            };
+            match last.ident.to_string().as_str() {
+                "u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16" | "i32" | "i64"
+                | "i128" | "isize" | "f32" | "f64" | "bool" | "String" | "PhantomData" => true,
+                "Option" | "Vec" => args.into_iter().all(is_primitive),
+                _ => false,
+            }
         }
+        _ => false,
     }
 }
 
-fn impl_fields_unnamed(name: Ident, f: FieldsUnnamed) -> impl Into<TokenStream> {
-    let mut values: Punctuated<Expr, Token![,]> = Punctuated::new();
-    let block_packable: Block = parse_quote! {
-        {
-            let mut n = 0;
-        }
+/// The type whose `Unpackable::Error` is returned when unpacking the field.
+///
+/// Arrays and maps fail with the error of their elements and values, respectively, taken from
+/// the generic arguments of the collection.
+fn error_source(field: &Field) -> Option<&Type> {
+    let args = type_args(&field.ty);
+    match field_kind(field) {
+        FieldKind::Map => args.get(1).copied(),
+        FieldKind::Array | FieldKind::Numbers | FieldKind::Fixed => element(args.first()?),
+        // the error of the remote functions isn't known from the field
+        FieldKind::With(_) => None,
+        FieldKind::Floats(..) | FieldKind::Value => Some(&field.ty),
+    }
+}
+
+/// The type arguments of the last segment of the path of the type.
+fn type_args(ty: &Type) -> Vec<&Type> {
+    let Type::Path(p) = ty else {
+        return vec![];
     };
-    let block_unpackable: Block = parse_quote! {
-        {
-            let mut n = 0;
+    match p.path.segments.last().map(|s| &s.arguments) {
+        Some(PathArguments::AngleBracketed(a)) => a
+            .args
+            .iter()
+            .filter_map(|a| match a {
+                GenericArgument::Type(t) => Some(t),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// The element of a collection from its first type argument, looking into the inline array of
+/// `SmallVec<[T; N]>`.
+fn element(arg: &Type) -> Option<&Type> {
+    match arg {
+        Type::Array(a) => Some(&*a.elem),
+        t => Some(t),
+    }
+}
+
+/// An expression telling whether the field is packed as a single msgpack value, as
+/// `Packable::SELF_DELIMITING` does.
+///
+/// The fields referring to the type being derived count as single values, as the constant can't
+/// be defined in terms of itself.
+fn self_delimiting(field: &Field, name: &Ident) -> TokenStream2 {
+    let delimited = |ty: &Type| quote! { <#ty as ::msgpacker::Packable>::SELF_DELIMITING };
+    if mentions(field.ty.to_token_stream(), name) {
+        return quote! { true };
+    }
+    let args = type_args(&field.ty);
+    let parts = match field_kind(field) {
+        FieldKind::Map => match args.first() {
+            // `Vec<(K, V)>`
+            Some(Type::Tuple(t)) => t.elems.iter().map(delimited).collect(),
+            _ => args.iter().take(2).map(|t| delimited(t)).collect(),
+        },
+        FieldKind::Array | FieldKind::Fixed => args
+            .first()
+            .and_then(|a| element(a))
+            .map(delimited)
+            .into_iter()
+            .collect(),
+        FieldKind::Numbers | FieldKind::Floats(..) => vec![],
+        FieldKind::With(with) => vec![quote! { #with::SELF_DELIMITING }],
+        FieldKind::Value => vec![delimited(&field.ty)],
+    };
+    quote! { true #(&& #parts)* }
+}
+
+/// Whether the tokens refer to the type being derived, which can't be asked for its own error.
+fn mentions(tokens: TokenStream2, name: &Ident) -> bool {
+    tokens.into_iter().any(|t| match t {
+        TokenTree::Ident(i) => &i == name || i == "Self",
+        TokenTree::Group(g) => mentions(g.stream(), name),
+        _ => false,
+    })
+}
+
+/// The error inferred for the derived `Unpackable`: the error of the first field that isn't known
+/// to fail with `msgpacker::Error`.
+///
+/// The errors of the remaining fields must convert into it.
+fn error_type<'a>(name: &Ident, fields: impl IntoIterator<Item = &'a Field>) -> Type {
+    fields
+        .into_iter()
+        .filter_map(error_source)
+        .find(|ty| !is_primitive(ty) && !mentions(ty.to_token_stream(), name))
+        .map(|ty| parse_quote! { <#ty as ::msgpacker::Unpackable>::Error })
+        .unwrap_or_else(|| parse_quote! { ::msgpacker::Error })
+}
+
+/// A statement running the `validate` function of the field on its unpacked value.
+fn validate_field(field: &Field, value: &Ident) -> TokenStream2 {
+    // the attributes are validated by `check_fields` before any code is generated
+    match FieldAttrs::from_attrs(&field.attrs)
+        .unwrap_or_default()
+        .validate
+    {
+        Some(path) => quote! { #path(&#value)?; },
+        None => quote! {},
+    }
+}
+
+/// The bodies of the generated `pack`, `try_pack_canonical`, `try_pack`, `unpack`,
+/// `unpack_strict` and `unpack_iter`, along with the value of `SELF_DELIMITING`.
+struct Bodies {
+    /// The expression of `Packable::SELF_DELIMITING`.
+    self_delimiting: TokenStream2,
+    /// Items asserting at compile time that the values skipped over when unpacking are single
+    /// msgpack values.
+    checks: TokenStream2,
+    pack: TokenStream2,
+    try_pack_canonical: TokenStream2,
+    try_pack: TokenStream2,
+    unpack: TokenStream2,
+    unpack_strict: TokenStream2,
+    /// `None` to take the whole value from the iterator and unpack it from the slice.
+    unpack_iter: Option<TokenStream2>,
+}
+
+/// The container options the trait implementations are assembled with.
+struct Container {
+    /// The identifier of the deriving type.
+    name: Ident,
+    /// The type being packed: the deriving type or, with `remote`, the foreign one.
+    ty: Path,
+    /// The expression referencing the value being packed.
+    this: TokenStream2,
+    /// Whether free functions are generated for a foreign type instead of the trait impls.
+    remote: bool,
+    error: Type,
+    validate: Option<Path>,
+    try_from: Option<Type>,
+    into: Option<Type>,
+}
+
+impl Container {
+    fn new<'a>(
+        name: &Ident,
+        attrs: &ContainerAttrs,
+        fields: impl IntoIterator<Item = &'a Field>,
+    ) -> syn::Result<Self> {
+        let try_from = attrs.unpack_proxy()?;
+        let remote = attrs.remote()?;
+        let fields = fields.into_iter().collect::<Vec<_>>();
+
+        // the fields aren't unpacked one by one, but converted from the proxy as a whole
+        if try_from.is_some() {
+            combine(fields.iter().filter_map(|f| {
+                let path = FieldAttrs::from_attrs(&f.attrs).ok()?.validate?;
+                Some(syn::Error::new_spanned(
+                    path,
+                    "fields can't be validated with `try_from`; validate in the conversion",
+                ))
+            }))?;
         }
+
+        let error = match (attrs.error()?, &try_from) {
+            (Some(error), _) => error,
+            (None, Some(raw)) => {
+                let ty = remote.clone().unwrap_or_else(|| name.clone().into());
+                parse_quote! { <#ty as ::core::convert::TryFrom<#raw>>::Error }
+            }
+            (None, None) => error_type(name, fields),
+        };
+
+        Ok(Self {
+            name: name.clone(),
+            ty: remote.clone().unwrap_or_else(|| name.clone().into()),
+            this: match remote {
+                Some(_) => quote! { value },
+                None => quote! { self },
+            },
+            remote: remote.is_some(),
+            error,
+            validate: attrs.validate()?,
+            try_from,
+            into: attrs.pack_proxy()?,
+        })
+    }
+}
+
+fn impl_traits(input: &DeriveInput, container: &Container, bodies: Bodies) -> TokenStream2 {
+    let Container {
+        name: _,
+        ty,
+        this,
+        remote,
+        error,
+        validate,
+        try_from,
+        into,
+    } = container;
+
+    let checks = bodies.checks;
+    let self_delimiting = match into {
+        Some(raw) => quote! { <#raw as ::msgpacker::Packable>::SELF_DELIMITING },
+        None => bodies.self_delimiting,
     };
-    let block_unpackable_iter: Block = parse_quote! {
-        {
-            let mut bytes = bytes.into_iter();
-            let mut n = 0;
+
+    let (pack, try_pack_canonical, try_pack) = match into {
+        Some(raw) => {
+            let convert = |method: Ident| {
+                quote! {
+                    let raw: #raw =
+                        ::core::convert::Into::into(::core::clone::Clone::clone(#this));
+                    ::msgpacker::Packable::#method(&raw, buf)
+                }
+            };
+            (
+                convert(format_ident!("pack")),
+                convert(format_ident!("try_pack_canonical")),
+                convert(format_ident!("try_pack")),
+            )
         }
+        None => (bodies.pack, bodies.try_pack_canonical, bodies.try_pack),
     };
 
-    let (mut block_packable, mut block_unpackable, mut block_unpackable_iter) = f
-        .unnamed
-        .into_pairs()
-        .map(|p| p.into_value())
-        .enumerate()
-        .fold(
-            (block_packable, block_unpackable, block_unpackable_iter),
-            |(mut block_packable, mut block_unpackable, mut block_unpackable_iter), (i, field)| {
-                let ty = field.ty.clone();
-                let var: Expr = parse_str(format!("v{}", i).as_str()).unwrap();
-                let slf: Expr = parse_str(format!("self.{}", i).as_str()).unwrap();
-
-                if contains_attribute(&field, "map") {
-                    todo!("unnamed map is not implemented for derive macro; implement the traits manually")
-                } else if contains_attribute(&field, "array") {
-                    todo!("unnamed array is not implemented for derive macro; implement the traits manually")
-                } else {
-                    block_packable.stmts.push(parse_quote! {
-                        n += <#ty as ::msgpacker::Packable>::pack(&#slf, buf);
-                    });
-
-                    block_unpackable.stmts.push(parse_quote! {
-                        let #var = ::msgpacker::Unpackable::unpack(buf).map(|(nv, t)| {
-                            n += nv;
-                            buf = &buf[nv..];
-                            t
-                        })?;
-                    });
-
-                    block_unpackable_iter.stmts.push(parse_quote! {
-                        let #var = ::msgpacker::Unpackable::unpack_iter(bytes.by_ref()).map(|(nv, t)| {
-                            n += nv;
-                            t
-                        })?;
-                    });
+    let (unpack, unpack_strict, unpack_iter) = match try_from {
+        Some(raw) => {
+            let convert = |method: Ident, input: TokenStream2| {
+                quote! {
+                    let (n, raw) = <#raw as ::msgpacker::Unpackable>::#method(#input)?;
+                    Ok((n, <#ty as ::core::convert::TryFrom<#raw>>::try_from(raw)?))
                 }
+            };
+            (
+                convert(unpack_fn(false), quote! { buf }),
+                convert(unpack_fn(true), quote! { buf }),
+                Some(convert(format_ident!("unpack_iter"), quote! { bytes })),
+            )
+        }
+        None => (bodies.unpack, bodies.unpack_strict, bodies.unpack_iter),
+    };
 
-                values.push(var);
+    let validated = |body: TokenStream2| match validate {
+        Some(path) => quote! {
+            let (n, slf) = (|| -> Result<(usize, #ty), #error> { #body })()?;
+            #path(&slf)?;
+            Ok((n, slf))
+        },
+        None => body,
+    };
+    let unpack = validated(unpack);
+    let unpack_strict = validated(unpack_strict);
+    let unpack_iter = match unpack_iter {
+        Some(body) => validated(body),
+        None if *remote => quote! {
+            let buf = ::msgpacker::take_value_iter(bytes)?;
+            Self::unpack(&buf)
+        },
+        None => quote! {
+            let buf = ::msgpacker::take_value_iter(bytes)?;
+            <Self as ::msgpacker::Unpackable>::unpack(&buf)
+        },
+    };
 
-                (block_packable, block_unpackable, block_unpackable_iter)
-            },
+    // `try_pack_canonical` is available only with the `alloc` feature of `msgpacker`
+    let canonical = |signature: TokenStream2| match cfg!(feature = "alloc") {
+        true => quote! {
+            #signature
+            where
+                T: ::msgpacker::Writer,
+            {
+                #try_pack_canonical
+            }
+        },
+        false => quote! {},
+    };
+
+    // so is `unpack_strict`
+    let strict = |signature: TokenStream2| match cfg!(feature = "alloc") {
+        true => quote! {
+            #[allow(unused_mut)]
+            #signature {
+                #unpack_strict
+            }
+        },
+        false => quote! {},
+    };
+
+    let name = &input.ident;
+    if *remote {
+        let vis = &input.vis;
+        let pretend = pretend_used(input);
+        let try_pack_canonical = canonical(quote! {
+            #vis fn try_pack_canonical<T>(
+                value: &#ty,
+                buf: &mut T,
+            ) -> Result<usize, ::msgpacker::Error>
+        });
+        let unpack_strict = strict(
+            quote! { #vis fn unpack_strict(mut buf: &[u8]) -> Result<(usize, #ty), #error> },
         );
+        return quote! {
+            #pretend
 
-    block_packable.stmts.push(parse_quote! {
-        return n;
-    });
+            #checks
 
-    block_unpackable.stmts.push(parse_quote! {
-        return Ok((n, Self(#values)));
-    });
+            impl #name {
+                #vis const SELF_DELIMITING: bool = #self_delimiting;
 
-    block_unpackable_iter.stmts.push(parse_quote! {
-        return Ok((n, Self(#values)));
-    });
+                #vis fn pack<T>(value: &#ty, buf: &mut T) -> usize
+                where
+                    T: ::msgpacker::Writer,
+                {
+                    #pack
+                }
 
-    quote! {
-        impl ::msgpacker::Packable for #name {
-            fn pack<T>(&self, buf: &mut T) -> usize
-            where
-                T: Extend<u8>,
-                #block_packable
-        }
+                #try_pack_canonical
 
-        impl ::msgpacker::Unpackable for #name {
-            type Error = ::msgpacker::Error;
+                #vis fn try_pack<T>(
+                    value: &#ty,
+                    buf: &mut T,
+                ) -> Result<usize, ::msgpacker::PackError>
+                where
+                    T: ::msgpacker::Writer,
+                {
+                    #try_pack
+                }
 
-            fn unpack(mut buf: &[u8]) -> Result<(usize, Self), Self::Error>
-                #block_unpackable
+                #[allow(unused_mut)]
+                #vis fn unpack(mut buf: &[u8]) -> Result<(usize, #ty), #error> {
+                    #unpack
+                }
 
-            fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
-            where
-                I: IntoIterator<Item = u8>,
-                #block_unpackable_iter
-        }
+                #unpack_strict
+
+                #vis fn unpack_iter<I>(bytes: I) -> Result<(usize, #ty), #error>
+                where
+                    I: IntoIterator<Item = u8>,
+                {
+                    #unpack_iter
+                }
+            }
+        };
     }
-}
 
-fn impl_fields_unit(name: Ident) -> impl Into<TokenStream> {
+    let try_pack_canonical = canonical(quote! {
+        fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, ::msgpacker::Error>
+    });
+    let unpack_strict =
+        strict(quote! { fn unpack_strict(mut buf: &[u8]) -> Result<(usize, Self), Self::Error> });
     quote! {
+        #checks
+
         impl ::msgpacker::Packable for #name {
-            fn pack<T>(&self, _buf: &mut T) -> usize
+            const SELF_DELIMITING: bool = #self_delimiting;
+
+            fn pack<T>(&self, buf: &mut T) -> usize
             where
-                T: Extend<u8>,
+                T: ::msgpacker::Writer,
             {
-                0
+                #pack
+            }
+
+            #try_pack_canonical
+
+            fn try_pack<T>(&self, buf: &mut T) -> Result<usize, ::msgpacker::PackError>
+            where
+                T: ::msgpacker::Writer,
+            {
+                #try_pack
             }
         }
 
         impl ::msgpacker::Unpackable for #name {
-            type Error = ::msgpacker::Error;
+            type Error = #error;
 
+            #[allow(unused_mut)]
             fn unpack(mut buf: &[u8]) -> Result<(usize, Self), Self::Error> {
-                Ok((0, Self))
+                #unpack
             }
 
+            #unpack_strict
+
             fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
             where
                 I: IntoIterator<Item = u8>,
             {
-                Ok((0, Self))
+                #unpack_iter
             }
         }
     }
 }
 
-fn impl_fields_enum(name: Ident, v: Punctuated<Variant, Token![,]>) -> impl Into<TokenStream> {
-    if v.is_empty() {
-        todo!("empty enum is not implemented for derive macro; implement the traits manually");
+/// Destructures and constructs the definition of a remote derive, so its fields and variants count
+/// as used even though only the foreign type is packed.
+fn pretend_used(input: &DeriveInput) -> TokenStream2 {
+    let name = &input.ident;
+    let shapes = match &input.data {
+        Data::Struct(s) => vec![(quote! { #name }, &s.fields)],
+        Data::Enum(e) => e
+            .variants
+            .iter()
+            .map(|v| {
+                let ident = &v.ident;
+                (quote! { #name::#ident }, &v.fields)
+            })
+            .collect(),
+        Data::Union(_) => vec![],
+    };
+    let (arms, constructors): (Vec<_>, Vec<_>) = shapes
+        .into_iter()
+        .map(|(path, fields)| {
+            let members = fields.members();
+            let b = bindings(fields);
+            let tys = fields.iter().map(|f| &f.ty);
+            let value = quote! { #path { #(#members: #b),* } };
+            (
+                quote! { #value => {} },
+                quote! { let _ = |#(#b: #tys),*| #value; },
+            )
+        })
+        .unzip();
+
+    quote! {
+        const _: () = {
+            let _ = |this: #name| match this {
+                #(#arms)*
+            };
+            #(#constructors)*
+        };
     }
+}
 
-    let mut block_packable: ExprMatch = parse_quote! {
-        match self {
+fn impl_fields_struct(c: &Container, fields: &Fields) -> Bodies {
+    let (ty, this) = (&c.ty, &c.this);
+    let members = fields.members().collect::<Vec<_>>();
+    let b = bindings(fields);
+    let tys = fields.iter().map(|f| &f.ty).collect::<Vec<_>>();
+    let pack = |packing: Packing| {
+        let values = fields
+            .iter()
+            .zip(members.iter())
+            .map(|(f, m)| pack_field(f, quote! { &#this.#m }, packing));
+        let written = packing.written();
+        quote! {
+            let mut n = 0;
+            #(n += #values;)*
+            #written
         }
     };
-
-    let mut block_unpackable: ExprMatch = parse_quote! {
-        match discriminant {
+    let unpack_iter = fields.iter().map(unpack_field_iter);
+    let validate = fields
+        .iter()
+        .zip(b.iter())
+        .map(|(f, b)| validate_field(f, b))
+        .collect::<Vec<_>>();
+    let unpack = |strict| {
+        let values = fields.iter().map(|f| unpack_field(f, strict));
+        quote! {
+            let mut n = 0;
+            #(
+                let (c, #b): (usize, #tys) = #values?;
+                #validate
+                n += c;
+                buf = &buf[c..];
+            )*
+            let _ = buf;
+            Ok((n, #ty { #(#members: #b),* }))
         }
     };
 
-    let mut block_unpackable_iter: ExprMatch = parse_quote! {
-        match discriminant {
-        }
+    // the fields are packed in sequence, without a header
+    let self_delimiting = match fields.len() {
+        1 => fields.iter().map(|f| self_delimiting(f, &c.name)).collect(),
+        _ => quote! { false },
     };
 
-    v.into_iter().enumerate().for_each(|(i, v)| {
-        let discriminant = v
-            .discriminant
-            .map(|(_, d)| d)
-            .unwrap_or_else(|| parse_str(format!("{}", i).as_str()).unwrap());
-
-        // TODO check attributes of the field
-        let ident = v.ident.clone();
-        match v.fields {
-            Fields::Named(f) => {
-                let mut blk: Block = parse_str("{}").unwrap();
-                let mut blk_unpack: Block = parse_str("{}").unwrap();
-                let mut blk_unpack_iter: Block = parse_str("{}").unwrap();
-                let mut blk_unpack_fields: Punctuated<FieldValue, Token![,]> = Punctuated::new();
-
-                blk.stmts.push(parse_quote! {
-                    n += (#discriminant as u32).pack(buf);
-                });
-
-                f.named
-                    .iter()
-                    .filter_map(|n| n.ident.as_ref())
-                    .for_each(|field| {
-                        blk.stmts.push(parse_quote! {
-                            n += #field.pack(buf);
-                        });
-
-                        blk_unpack_fields.push(parse_quote! { #field });
-
-                        blk_unpack.stmts.push(parse_quote! {
-                            let #field =::msgpacker::Unpackable::unpack(buf).map(|(nv, t)| {
-                                n += nv;
-                                buf = &buf[nv..];
-                                t
-                            })?;
-                        });
-
-                        blk_unpack_iter.stmts.push(parse_quote! {
-                            let #field =::msgpacker::Unpackable::unpack_iter(bytes.by_ref()).map(|(nv, t)| {
-                                n += nv;
-                                t
-                            })?;
-                        });
-                    });
-
-                let mut arm: syn::Arm = parse_quote! {
-                    #name::#ident {} => #blk,
-                };
-
-                f.named
-                    .iter()
-                    .filter_map(|n| n.ident.as_ref())
-                    .for_each(|field| {
-                        match &mut arm.pat {
-                            Pat::Struct(s) => {
-                                s.fields.push(FieldPat {
-                                    attrs: vec![],
-                                    member: Member::Named(field.clone()),
-                                    colon_token: None,
-                                    pat: Box::new(Pat::Ident(PatIdent {
-                                        attrs: vec![],
-                                        by_ref: None,
-                                        mutability: None,
-                                        ident: field.clone(),
-                                        subpat: None,
-                                    })),
-                                });
-                            }
-                            _ => todo!(
-                                "enum variant is not implemented for derive macro; implement the traits manually"
-                            ),
-                        }
-                    });
+    Bodies {
+        self_delimiting,
+        checks: quote! {},
+        pack: pack(Packing::Plain),
+        try_pack_canonical: pack(Packing::Canonical),
+        try_pack: pack(Packing::Fallible),
+        unpack: unpack(false),
+        unpack_strict: unpack(true),
+        unpack_iter: Some(quote! {
+            let mut bytes = bytes.into_iter();
+            let mut n = 0;
+            #(
+                let (c, #b): (usize, #tys) = #unpack_iter?;
+                #validate
+                n += c;
+            )*
+            Ok((n, #ty { #(#members: #b),* }))
+        }),
+    }
+}
 
-                block_packable.arms.push(arm);
+/// An enum without variants can't be constructed, so there is nothing to pack nor to unpack.
+fn impl_fields_empty_enum(c: &Container) -> Bodies {
+    let this = &c.this;
+    Bodies {
+        self_delimiting: quote! { true },
+        checks: quote! {},
+        pack: quote! { match *#this {} },
+        try_pack_canonical: quote! { match *#this {} },
+        try_pack: quote! { match *#this {} },
+        unpack: quote! { Err(::msgpacker::Error::InvalidEnumVariant.into()) },
+        unpack_strict: quote! { Err(::msgpacker::Error::InvalidEnumVariant.into()) },
+        unpack_iter: Some(quote! { Err(::msgpacker::Error::InvalidEnumVariant.into()) }),
+    }
+}
 
-                blk_unpack.stmts.push(parse_quote! {
-                    slf = #name::#ident { #blk_unpack_fields };
-                });
+/// How a variant is identified on the wire when packed by discriminant.
+enum Tag {
+    /// A constant of the tag type.
+    Int(Ident),
+    Str(LitStr),
+}
 
-                blk_unpack_iter.stmts.push(parse_quote! {
-                    slf = #name::#ident { #blk_unpack_fields };
-                });
+/// Resolves the tag of every variant, along with the definitions of the integer constants.
+///
+/// Integer tags follow the discriminants of the enum; a variant without an explicit one takes
+/// the previous integer tag plus one, as the compiler does for discriminants.
+fn variant_tags(tag_type: &Type, variants: &[Variant]) -> (Vec<Tag>, TokenStream2) {
+    let mut tags = Vec::with_capacity(variants.len());
+    let mut consts = TokenStream2::new();
+    let mut prev = None;
+
+    for (i, v) in variants.iter().enumerate() {
+        let attrs = VariantAttrs::from_attrs(&v.attrs).unwrap_or_default();
+        let value = match (attrs.tag.as_ref(), &v.discriminant) {
+            (Some(VariantTag::Str(s)), _) => {
+                tags.push(Tag::Str(s.clone()));
+                continue;
+            }
+            (Some(VariantTag::Int(e)), _) | (None, Some((_, e))) => quote! { #e },
+            (None, None) => match &prev {
+                Some(prev) => quote! { #prev + 1 },
+                None => quote! { 0 },
+            },
+        };
+
+        let ident = format_ident!("__TAG{}", i);
+        consts.extend(quote! {
+            const #ident: #tag_type = #value;
+        });
+        prev = Some(ident.clone());
+        tags.push(Tag::Int(ident));
+    }
 
-                block_unpackable.arms.push(parse_quote! {
-                    #discriminant => #blk_unpack,
-                });
+    (tags, consts)
+}
 
-                block_unpackable_iter.arms.push(parse_quote! {
-                    #discriminant => #blk_unpack_iter,
-                });
+fn impl_fields_enum(c: &Container, tag_type: &Type, variants: &[Variant]) -> Bodies {
+    let (name, this) = (&c.ty, &c.this);
+    let (tags, consts) = variant_tags(tag_type, variants);
+
+    let pack = |packing: Packing| {
+        let arms = variants.iter().zip(tags.iter()).map(|(v, tag)| {
+            let pattern = tagged::pattern(name, v);
+            let b = bindings(&v.fields);
+            let values = v
+                .fields
+                .iter()
+                .zip(b.iter())
+                .map(|(f, b)| pack_field(f, b, packing));
+            let tag = match tag {
+                Tag::Int(t) => quote! { <#tag_type as ::msgpacker::Packable>::pack(&#t, buf) },
+                Tag::Str(s) => quote! { <str as ::msgpacker::Packable>::pack(#s, buf) },
+            };
+            quote! {
+                #pattern => {
+                    n += #tag;
+                    #(n += #values;)*
+                }
             }
+        });
+        let written = packing.written();
+        quote! {
+            #consts
 
-            Fields::Unnamed(f) => {
-                let mut blk: Block = parse_str("{}").unwrap();
-                let mut blk_unpack: Block = parse_str("{}").unwrap();
-                let mut blk_unpack_iter: Block = parse_str("{}").unwrap();
-
-                blk.stmts.push(parse_quote! {
-                    n += (#discriminant as u32).pack(buf);
-                });
-
-                let mut tuple_arm: ExprTuple = parse_str("()").unwrap();
-                f.unnamed.iter().enumerate().for_each(|(ii, _field)| {
-                    let ti: Expr = parse_str(format!("t{}", ii).as_str()).unwrap();
-                    tuple_arm.elems.push(ti.clone());
-
-                    blk.stmts.push(parse_quote! {
-                        n += #ti.pack(buf);
-                    });
-
-                    blk_unpack.stmts.push(parse_quote! {
-                        let #ti =::msgpacker::Unpackable::unpack(buf).map(|(nv, t)| {
-                            n += nv;
-                            buf = &buf[nv..];
-                            t
-                        })?;
-                    });
-
-                    blk_unpack_iter.stmts.push(parse_quote! {
-                        let #ti =::msgpacker::Unpackable::unpack_iter(bytes.by_ref()).map(|(nv, t)| {
-                            n += nv;
-                            t
-                        })?;
-                    });
-                });
-
-                blk_unpack.stmts.push(parse_quote! {
-                    slf = #name::#ident #tuple_arm;
-                });
-
-                blk_unpack_iter.stmts.push(parse_quote! {
-                    slf = #name::#ident #tuple_arm;
-                });
-
-                block_packable.arms.push(parse_quote! {
-                    #name::#ident #tuple_arm => #blk,
-                });
-
-                block_unpackable.arms.push(parse_quote! {
-                    #discriminant => #blk_unpack,
-                });
-
-                block_unpackable_iter.arms.push(parse_quote! {
-                    #discriminant => #blk_unpack_iter,
-                });
-            }
+            let mut n = 0;
 
-            Fields::Unit => {
-                block_packable.arms.push(parse_quote! {
-                    #name::#ident => {
-                        n += (#discriminant as u32).pack(buf);
-                    }
-                });
+            match #this {
+                #(#arms)*
+            }
 
-                block_unpackable.arms.push(parse_quote! {
-                    #discriminant => slf = #name::#ident,
-                });
+            #written
+        }
+    };
 
-                block_unpackable_iter.arms.push(parse_quote! {
-                    #discriminant => slf = #name::#ident,
-                });
+    // the arms matching the integer and the string tags, respectively, unpacking the fields
+    // from the iterator or, under the strict rules if `strict`, from the slice
+    let arms = |iter: bool, strict: bool| {
+        let mut int = vec![];
+        let mut str = vec![];
+        for (v, tag) in variants.iter().zip(tags.iter()) {
+            let ident = &v.ident;
+            let members = v.fields.members();
+            let b = bindings(&v.fields);
+            let tys = v.fields.iter().map(|f| &f.ty);
+            let validate = v
+                .fields
+                .iter()
+                .zip(b.iter())
+                .map(|(f, b)| validate_field(f, b))
+                .collect::<Vec<_>>();
+            let body = if iter {
+                let values = v.fields.iter().map(unpack_field_iter);
+                quote! {
+                    #(
+                        let (c, #b): (usize, #tys) = #values?;
+                        #validate
+                        n += c;
+                    )*
+                    #name::#ident { #(#members: #b),* }
+                }
+            } else {
+                let values = v.fields.iter().map(|f| unpack_field(f, strict));
+                quote! {
+                    #(
+                        let (c, #b): (usize, #tys) = #values?;
+                        #validate
+                        n += c;
+                        buf = &buf[c..];
+                    )*
+                    #name::#ident { #(#members: #b),* }
+                }
+            };
+            match tag {
+                Tag::Int(t) => int.push(quote! { #t => { #body } }),
+                Tag::Str(s) => str.push(quote! { #s => { #body } }),
             }
         }
-    });
+        (int, str)
+    };
 
-    block_unpackable.arms.push(parse_quote! {
-        _ => {
-            return Err(::msgpacker::Error::InvalidEnumVariant);
+    let has_str = tags.iter().any(|t| matches!(t, Tag::Str(_)));
+    let unpack = |strict| {
+        let (int, str) = arms(false, strict);
+        let (decoders, unpack) = (decoders(strict), unpack_fn(strict));
+        let tag = quote! { <#tag_type as ::msgpacker::Unpackable>::#unpack(buf) };
+        if has_str {
+            quote! {
+                match #decoders::unpack_str(buf) {
+                    Ok((c, tag)) => {
+                        n += c;
+                        buf = &buf[c..];
+                        match tag {
+                            #(#str)*
+                            _ => return Err(::msgpacker::Error::InvalidEnumVariant.into()),
+                        }
+                    }
+                    Err(_) => {
+                        let (c, tag) = #tag?;
+                        n += c;
+                        buf = &buf[c..];
+                        match tag {
+                            #(#int)*
+                            _ => return Err(::msgpacker::Error::InvalidEnumVariant.into()),
+                        }
+                    }
+                }
+            }
+        } else {
+            quote! {
+                {
+                    let (c, tag) = #tag?;
+                    n += c;
+                    buf = &buf[c..];
+                    match tag {
+                        #(#int)*
+                        _ => return Err(::msgpacker::Error::InvalidEnumVariant.into()),
+                    }
+                }
+            }
         }
-    });
+    };
 
-    block_unpackable_iter.arms.push(parse_quote! {
-        _ => {
-            return Err(::msgpacker::Error::InvalidEnumVariant);
+    let (int, str) = arms(true, false);
+    let unpack_iter = if has_str {
+        // the kind of the tag is known only after reading it whole
+        quote! {
+            {
+                let tag = ::msgpacker::take_value_iter(bytes.by_ref())?;
+                n += tag.len();
+                match ::msgpacker::unpack_str(&tag) {
+                    Ok((_, tag)) => match tag {
+                        #(#str)*
+                        _ => return Err(::msgpacker::Error::InvalidEnumVariant.into()),
+                    },
+                    Err(_) => match <#tag_type as ::msgpacker::Unpackable>::unpack(&tag)?.1 {
+                        #(#int)*
+                        _ => return Err(::msgpacker::Error::InvalidEnumVariant.into()),
+                    },
+                }
+            }
         }
-    });
-
-    quote! {
-        impl ::msgpacker::Packable for #name {
-            fn pack<T>(&self, buf: &mut T) -> usize
-            where
-                T: Extend<u8>,
+    } else {
+        quote! {
             {
-                let mut n = 0;
-
-                #block_packable;
-
-                return n;
+                let (c, tag) =
+                    <#tag_type as ::msgpacker::Unpackable>::unpack_iter(bytes.by_ref())?;
+                n += c;
+                match tag {
+                    #(#int)*
+                    _ => return Err(::msgpacker::Error::InvalidEnumVariant.into()),
+                }
             }
         }
+    };
 
-        impl ::msgpacker::Unpackable for #name {
-            type Error = ::msgpacker::Error;
+    let with_consts = |unpack| {
+        quote! {
+            #consts
 
-            #[allow(unused_mut)]
-            fn unpack(mut buf: &[u8]) -> Result<(usize, Self), Self::Error> {
-                let (mut n, discriminant) = u32::unpack(&mut buf)?;
-                buf = &buf[n..];
-                let slf;
+            let mut n = 0;
+            let slf = #unpack;
 
-                #block_unpackable;
+            let _ = buf;
+            Ok((n, slf))
+        }
+    };
 
-                Ok((n, slf))
-            }
+    // the tag is followed by the fields, without a header
+    let unit = variants.iter().all(|v| v.fields.is_empty());
 
-            fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
-            where
-                I: IntoIterator<Item = u8>,
-            {
-                let mut bytes = bytes.into_iter();
-                let (mut n, discriminant) = u32::unpack_iter(bytes.by_ref())?;
-                let slf;
+    Bodies {
+        self_delimiting: quote! { #unit },
+        checks: quote! {},
+        pack: pack(Packing::Plain),
+        try_pack_canonical: pack(Packing::Canonical),
+        try_pack: pack(Packing::Fallible),
+        unpack: with_consts(unpack(false)),
+        unpack_strict: with_consts(unpack(true)),
+        unpack_iter: Some(quote! {
+            #consts
+
+            let mut bytes = bytes.into_iter();
+            let mut n = 0;
+            let slf = #unpack_iter;
+
+            Ok((n, slf))
+        }),
+    }
+}
 
-                #block_unpackable_iter;
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let attrs = ContainerAttrs::from_attrs(&input.attrs);
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "generic types are not supported by the derive macro; implement the traits manually",
+        ));
+    }
 
-                Ok((n, slf))
+    match &input.data {
+        Data::Struct(s) => {
+            let attrs = attrs?;
+            attrs.check_struct()?;
+            check_fields(&s.fields)?;
+            let container = Container::new(name, &attrs, &s.fields)?;
+            let bodies = impl_fields_struct(&container, &s.fields);
+            let mut tokens = impl_traits(input, &container, bodies);
+
+            // proxies and foreign types aren't packed by their fields, so they can't be flattened
+            let plain =
+                !container.remote && container.try_from.is_none() && container.into.is_none();
+            if plain && matches!(s.fields, Fields::Named(_)) {
+                tokens.extend(tagged::impl_map_entries(&container, &s.fields));
             }
+            Ok(tokens)
+        }
+
+        Data::Enum(e) => {
+            let attrs = attrs?;
+            let repr = attrs.representation()?;
+            let tag_type = attrs.tag_type()?;
+            let variants = e.variants.iter().cloned().collect::<Vec<_>>();
+            check_variants(&repr, &variants)?;
+            check_fields(variants.iter().flat_map(|v| &v.fields))?;
+            let container = Container::new(name, &attrs, variants.iter().flat_map(|v| &v.fields))?;
+
+            let bodies = match repr {
+                _ if variants.is_empty() => impl_fields_empty_enum(&container),
+                Representation::Index => impl_fields_enum(&container, &tag_type, &variants),
+                repr => tagged::impl_tagged_enum(&container, repr, &variants)?,
+            };
+            Ok(impl_traits(input, &container, bodies))
         }
+
+        Data::Union(u) => Err(syn::Error::new_spanned(
+            u.union_token,
+            "unions are not supported by the derive macro; implement the traits manually",
+        )),
     }
 }
 
@@ -568,30 +1182,7 @@ fn impl_fields_enum(name: Ident, v: Punctuated<Variant, Token![,]>) -> impl Into
 pub fn msg_packer(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
-    let name = input.ident;
-    let data = input.data;
-    match data {
-        Data::Struct(DataStruct {
-            fields: Fields::Named(f),
-            ..
-        }) => impl_fields_named(name, f).into(),
-
-        Data::Struct(DataStruct {
-            fields: Fields::Unnamed(f),
-            ..
-        }) => impl_fields_unnamed(name, f).into(),
-
-        Data::Struct(DataStruct {
-            fields: Fields::Unit,
-            ..
-        }) => impl_fields_unit(name).into(),
-
-        Data::Enum(DataEnum { variants, .. }) => impl_fields_enum(name, variants).into(),
-
-        Data::Union(DataUnion { .. }) => {
-            todo!(
-                "union support is not implemented for derive macro; implement the traits manually"
-            )
-        }
-    }
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
@@ -0,0 +1,1100 @@
+//! Enum representations that identify variants by name rather than by discriminant.
+//!
+//! The payload of a variant is encoded as follows:
+//!
+//! - unit variants as nil;
+//! - newtype variants as the inner value;
+//! - tuple variants as an array of the fields;
+//! - struct variants as a map of the field names to the values.
+//!
+//! The fields marked with `#[msgpacker(flatten)]` splice their `MapEntries` into that map, so the
+//! derive also implements it for structs with named fields.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::{ext::IdentExt, spanned::Spanned, Field, Fields, Ident, LitStr, Path, Type, Variant};
+
+use crate::{
+    attr::{FieldAttrs, Representation, VariantAttrs, VariantTag},
+    bindings, decoders, field_kind, pack_array_len, pack_field, pack_map_len, self_delimiting,
+    unpack_field, validate_field, Bodies, Container, FieldKind, Packing,
+};
+
+pub fn label(ident: &Ident) -> LitStr {
+    LitStr::new(&ident.unraw().to_string(), ident.span())
+}
+
+/// The name the variant is identified by: its string tag, if set, or its identifier.
+fn variant_label(v: &Variant) -> LitStr {
+    match VariantAttrs::from_attrs(&v.attrs).map(|a| a.tag) {
+        Ok(Some(VariantTag::Str(s))) => s,
+        _ => label(&v.ident),
+    }
+}
+
+/// The match pattern of the variant, binding its fields to [bindings].
+pub fn pattern(name: &Path, v: &Variant) -> TokenStream {
+    let ident = &v.ident;
+    let b = bindings(&v.fields);
+    match &v.fields {
+        Fields::Unit => quote! { #name::#ident },
+        Fields::Unnamed(_) => quote! { #name::#ident(#(#b),*) },
+        Fields::Named(f) => {
+            let members = f.named.iter().map(|f| f.ident.as_ref().unwrap());
+            quote! { #name::#ident { #(#members: #b),* } }
+        }
+    }
+}
+
+fn is_flattened(field: &Field) -> bool {
+    // the attributes are validated by `check_fields` before any code is generated
+    FieldAttrs::from_attrs(&field.attrs).is_ok_and(|a| a.flatten)
+}
+
+/// An expression telling whether the field is packed as a single msgpack value or, if flattened,
+/// whether the values of its entries are.
+fn field_delimited(field: &Field, name: &Ident) -> TokenStream {
+    let ty = &field.ty;
+    match is_flattened(field) {
+        true => quote! { <#ty as ::msgpacker::MapEntries>::VALUES_SELF_DELIMITING },
+        false => self_delimiting(field, name),
+    }
+}
+
+/// Items asserting at compile time that the fields of the variants are packed as single msgpack
+/// values, as unpacking skips over them.
+fn check_delimited<'a>(
+    name: &Ident,
+    variants: impl IntoIterator<Item = &'a Variant>,
+) -> TokenStream {
+    let checks = variants.into_iter().flat_map(|v| {
+        v.fields.iter().zip(v.fields.members()).map(move |(f, m)| {
+            let delimited = field_delimited(f, name);
+            let message = format!(
+                "the field `{}` of `{}::{}` is skipped over when unpacking, so it must be packed \
+                 as a single msgpack value",
+                m.to_token_stream(),
+                name,
+                v.ident,
+            );
+            quote_spanned! { f.ty.span() =>
+                const _: () = ::core::assert!(#delimited, #message);
+            }
+        })
+    });
+    quote! { #(#checks)* }
+}
+
+/// Items asserting at compile time that the flattened fields don't pack the keys of the other
+/// fields, of each other, nor the `tag` of the map, as `MapEntries::KEYS` lists them.
+///
+/// `owner` names the struct or the variant in the messages.
+fn check_keys(owner: &str, fields: &Fields, tag: Option<&LitStr>) -> TokenStream {
+    let keys = fields
+        .iter()
+        .filter(|f| !is_flattened(f))
+        .map(|f| label(f.ident.as_ref().unwrap()))
+        .map(|k| (k, "the key of another field"))
+        .chain(tag.map(|t| (t.clone(), "the tag of the map")))
+        .collect::<Vec<_>>();
+    let flattened = fields
+        .iter()
+        .filter(|f| is_flattened(f))
+        .collect::<Vec<_>>();
+    let mut checks = vec![];
+    for (i, f) in flattened.iter().enumerate() {
+        let (ty, field) = (&f.ty, f.ident.as_ref().unwrap());
+        for (key, what) in &keys {
+            let message = format!(
+                "the flattened field `{field}` of `{owner}` packs `{}`, {what}",
+                key.value(),
+            );
+            checks.push(quote_spanned! { f.ty.span() =>
+                const _: () = ::core::assert!(
+                    !::msgpacker::has_key(<#ty as ::msgpacker::MapEntries>::KEYS, #key),
+                    #message,
+                );
+            });
+        }
+        for g in &flattened[i + 1..] {
+            let (other, other_field) = (&g.ty, g.ident.as_ref().unwrap());
+            let message = format!(
+                "the flattened fields `{field}` and `{other_field}` of `{owner}` pack the same key",
+            );
+            checks.push(quote_spanned! { g.ty.span() =>
+                const _: () = ::core::assert!(
+                    !::msgpacker::share_key(
+                        <#ty as ::msgpacker::MapEntries>::KEYS,
+                        <#other as ::msgpacker::MapEntries>::KEYS,
+                    ),
+                    #message,
+                );
+            });
+        }
+    }
+    quote! { #(#checks)* }
+}
+
+/// An expression counting the map entries of the named fields bound to [bindings].
+fn entries_len(fields: &Fields) -> TokenStream {
+    let b = bindings(fields);
+    let plain = fields.iter().filter(|f| !is_flattened(f)).count();
+    let flattened = fields
+        .iter()
+        .zip(b.iter())
+        .filter(|(f, _)| is_flattened(f))
+        .map(|(f, b)| {
+            let ty = &f.ty;
+            quote! { <#ty as ::msgpacker::MapEntries>::entries_len(#b) }
+        });
+    quote! { #plain #(+ #flattened)* }
+}
+
+/// Statements packing the named fields as `key value` map entries, without the map header.
+fn pack_entries(fields: &Fields, packing: Packing) -> TokenStream {
+    let b = bindings(fields);
+    let entries = fields.iter().zip(b.iter()).map(|(field, b)| {
+        let ty = &field.ty;
+        if is_flattened(field) {
+            let entries = match packing {
+                Packing::Fallible => quote! {
+                    <#ty as ::msgpacker::MapEntries>::try_pack_entries(#b, buf)?
+                },
+                _ => quote! { <#ty as ::msgpacker::MapEntries>::pack_entries(#b, buf) },
+            };
+            return quote! {
+                n += #entries;
+            };
+        }
+        let key = label(field.ident.as_ref().unwrap());
+        let value = pack_field(field, b, packing);
+        quote! {
+            n += <str as ::msgpacker::Packable>::pack(#key, buf);
+            n += #value;
+        }
+    });
+    quote! { #(#entries)* }
+}
+
+/// Statements adding the named fields in canonical form to `entries`, a mutable reference.
+fn canonical_entries(fields: &Fields) -> TokenStream {
+    let b = bindings(fields);
+    let entries = fields.iter().zip(b.iter()).map(|(field, b)| {
+        let ty = &field.ty;
+        if is_flattened(field) {
+            return quote! {
+                <#ty as ::msgpacker::MapEntries>::canonical_entries(#b, entries)?;
+            };
+        }
+        let key = label(field.ident.as_ref().unwrap());
+        let value = pack_field(field, b, Packing::Canonical);
+        quote! {
+            entries.push(::msgpacker::canonical::entry(#key, |buf| {
+                let n = #value;
+                Ok(n)
+            })?);
+        }
+    });
+    quote! { #(#entries)* }
+}
+
+/// Statements packing the entries, the canonical ones of the named fields among them, as a map
+/// sorted by key.
+fn pack_canonical_map(entries: TokenStream) -> TokenStream {
+    quote! {
+        let entries = &mut ::msgpacker::canonical::Entries::new();
+        #entries
+        n += ::msgpacker::canonical::pack_entries(buf, ::core::mem::take(entries))?;
+    }
+}
+
+/// An expression checking whether `key` is one of the keys of the fields that aren't flattened.
+fn is_key(fields: &Fields) -> TokenStream {
+    let keys = fields
+        .iter()
+        .filter(|f| !is_flattened(f))
+        .map(|f| label(f.ident.as_ref().unwrap()))
+        .collect::<Vec<_>>();
+    match keys.is_empty() {
+        true => quote! { false },
+        false => quote! { matches!(key, #(#keys)|*) },
+    }
+}
+
+/// An expression checking whether `key` is decoded by the named fields, flattened or not.
+fn knows(fields: &Fields) -> TokenStream {
+    let is_key = is_key(fields);
+    let tys = fields.iter().filter(|f| is_flattened(f)).map(|f| &f.ty);
+    quote! {
+        #is_key #(|| <#tys as ::msgpacker::MapEntries>::knows(key))*
+    }
+}
+
+/// Statements skipping the value in `input`, binding its length to `c`.
+///
+/// With `strict`, the encoding of the value is checked, as it isn't decoded.
+fn skip_value(input: &TokenStream, strict: bool) -> TokenStream {
+    let check = strict.then(|| quote! { ::msgpacker::strict::check(&#input[..c])?; });
+    quote! {
+        let c = ::msgpacker::skip_value(#input)?;
+        #check
+    }
+}
+
+/// Statements declaring the keys of a map, adding `key` to them and checking them for
+/// repetitions, respectively; only the strict rules check the keys.
+fn keys(strict: bool) -> [TokenStream; 3] {
+    match strict {
+        true => [
+            quote! { let mut keys = ::msgpacker::strict::Keys::new(); },
+            quote! { keys.push(key.as_bytes()); },
+            quote! { keys.check()?; },
+        ],
+        false => Default::default(),
+    }
+}
+
+/// Statements unpacking the named fields from the `len` entries of the map body in `buf`,
+/// adding the read bytes to `n` and binding the value to `slf`.
+///
+/// Unknown keys are skipped, as are the ones `skip` returns `true` for, if provided. Every
+/// flattened field decodes from the same entries, ignoring the keys the others decode. With
+/// `strict`, the entries are unpacked under the strict rules.
+fn unpack_entries(
+    ctor: &TokenStream,
+    fields: &Fields,
+    skip: Option<&TokenStream>,
+    strict: bool,
+) -> TokenStream {
+    let b = bindings(fields);
+    let v = format_ident!("v");
+    let is_key = is_key(fields);
+
+    let mut declare = vec![];
+    let mut arms = vec![];
+    let mut flattened = vec![];
+    let mut values = vec![];
+    for (i, (f, b)) in fields.iter().zip(b.iter()).enumerate() {
+        let ty = &f.ty;
+        if !is_flattened(f) {
+            let key = label(f.ident.as_ref().unwrap());
+            let value = unpack_field(f, strict);
+            let validate = validate_field(f, &v);
+            declare.push(quote! { let mut #b = None; });
+            arms.push(quote! {
+                #key => {
+                    let (c, v): (usize, #ty) = #value?;
+                    #validate
+                    n += c;
+                    buf = &buf[c..];
+                    #b = Some(v);
+                }
+            });
+            values.push(quote! { #b.ok_or(::msgpacker::Error::MissingField)? });
+            continue;
+        }
+
+        let others = fields
+            .iter()
+            .enumerate()
+            .filter(|(j, f)| *j != i && is_flattened(f))
+            .map(|(_, f)| &f.ty);
+        let outer = skip.map(|skip| quote! { (#skip)(key) || });
+        let validate = validate_field(f, b);
+        let unpack = match strict {
+            true => format_ident!("unpack_entries_strict"),
+            false => format_ident!("unpack_entries"),
+        };
+        flattened.push(quote! {
+            let #b = <#ty as ::msgpacker::MapEntries>::#unpack(entries, len, &|key: &str| {
+                #outer #is_key
+                    #(|| <#others as ::msgpacker::MapEntries>::knows(key))*
+            })?;
+            #validate
+        });
+        values.push(quote! { #b });
+    }
+
+    let members = fields.members();
+    let decoders = decoders(strict);
+    let skip_value = skip_value(&quote! { buf }, strict);
+    let skipped = skip.map(|skip| {
+        quote! {
+            if (#skip)(key) {
+                #skip_value
+                n += c;
+                buf = &buf[c..];
+                continue;
+            }
+        }
+    });
+    let [declare_keys, push_key, check_keys] = keys(strict);
+    quote! {
+        let entries = buf;
+        #(#declare)*
+        #declare_keys
+        for _ in 0..len {
+            let (c, key) = #decoders::unpack_str(buf)?;
+            n += c;
+            buf = &buf[c..];
+            #push_key
+            #skipped
+            match key {
+                #(#arms)*
+                _ => {
+                    #skip_value
+                    n += c;
+                    buf = &buf[c..];
+                }
+            }
+        }
+        #check_keys
+        let _ = (buf, entries);
+        #(#flattened)*
+        let slf = #ctor {
+            #(#members: #values,)*
+        };
+    }
+}
+
+/// Statements packing the payload of the variant.
+fn pack_payload(v: &Variant, packing: Packing) -> TokenStream {
+    let b = bindings(&v.fields);
+    match &v.fields {
+        Fields::Unit => quote! {
+            n += <() as ::msgpacker::Packable>::pack(&(), buf);
+        },
+        Fields::Unnamed(f) if f.unnamed.len() == 1 => {
+            let value = pack_field(&f.unnamed[0], &b[0], packing);
+            quote! {
+                n += #value;
+            }
+        }
+        Fields::Unnamed(f) => {
+            let len = f.unnamed.len();
+            let values = f
+                .unnamed
+                .iter()
+                .zip(b.iter())
+                .map(|(f, b)| pack_field(f, b, packing));
+            let header = pack_array_len(len, packing);
+            quote! {
+                n += #header;
+                #(n += #values;)*
+            }
+        }
+        Fields::Named(_) if packing == Packing::Canonical => {
+            pack_canonical_map(canonical_entries(&v.fields))
+        }
+        Fields::Named(_) => {
+            let header = pack_map_len(entries_len(&v.fields), packing);
+            let entries = pack_entries(&v.fields, packing);
+            quote! {
+                n += #header;
+                #entries
+            }
+        }
+    }
+}
+
+/// A block unpacking the payload of the variant from `input`, evaluating to `(usize, Self)`.
+///
+/// Struct variants are read from a map, skipping unknown keys and the ones `skip` returns `true`
+/// for. With `strict`, the payload is unpacked under the strict rules.
+fn unpack_payload(
+    name: &Path,
+    v: &Variant,
+    input: &TokenStream,
+    skip: Option<&TokenStream>,
+    strict: bool,
+) -> TokenStream {
+    let ident = &v.ident;
+    let decoders = decoders(strict);
+    let b = bindings(&v.fields);
+    let tys = v.fields.iter().map(|f| &f.ty).collect::<Vec<_>>();
+    let values = v
+        .fields
+        .iter()
+        .map(|f| unpack_field(f, strict))
+        .collect::<Vec<_>>();
+    let validate = v
+        .fields
+        .iter()
+        .zip(b.iter())
+        .map(|(f, b)| validate_field(f, b))
+        .collect::<Vec<_>>();
+    match &v.fields {
+        Fields::Unit => quote! {
+            {
+                let (n, _) = <() as ::msgpacker::Unpackable>::unpack(#input)?;
+                (n, #name::#ident)
+            }
+        },
+        Fields::Unnamed(f) if f.unnamed.len() == 1 => {
+            let (b, ty, value, validate) = (&b[0], tys[0], &values[0], &validate[0]);
+            quote! {
+                {
+                    let buf: &[u8] = #input;
+                    let (n, #b): (usize, #ty) = #value?;
+                    #validate
+                    (n, #name::#ident(#b))
+                }
+            }
+        }
+        Fields::Unnamed(f) => {
+            let len = f.unnamed.len();
+            quote! {
+                {
+                    let mut buf: &[u8] = #input;
+                    let (mut n, len) = #decoders::unpack_array_len(buf)?;
+                    if len != #len {
+                        return Err(::msgpacker::Error::InvalidEnumVariant.into());
+                    }
+                    buf = &buf[n..];
+                    #(
+                        let (c, #b): (usize, #tys) = #values?;
+                        #validate
+                        n += c;
+                        buf = &buf[c..];
+                    )*
+                    let _ = buf;
+                    (n, #name::#ident(#(#b),*))
+                }
+            }
+        }
+        Fields::Named(_) => {
+            let entries = unpack_entries(&quote! { #name::#ident }, &v.fields, skip, strict);
+            quote! {
+                {
+                    let mut buf: &[u8] = #input;
+                    let (mut n, len) = #decoders::unpack_map_len(buf)?;
+                    buf = &buf[n..];
+                    #entries
+                    (n, slf)
+                }
+            }
+        }
+    }
+}
+
+/// The generated `pack`, `try_pack_canonical`, `try_pack`, `unpack` and `unpack_strict` of a
+/// tagged enum.
+type Generated = (
+    TokenStream,
+    TokenStream,
+    TokenStream,
+    TokenStream,
+    TokenStream,
+);
+
+fn impl_external(c: &Container, variants: &[Variant]) -> Generated {
+    let (name, this) = (&c.ty, &c.this);
+    let pack = |packing| {
+        let arms = variants.iter().map(|v| {
+            let pattern = pattern(name, v);
+            let label = variant_label(v);
+            let payload = pack_payload(v, packing);
+            quote! {
+                #pattern => {
+                    n += ::msgpacker::pack_map_len(buf, 1);
+                    n += <str as ::msgpacker::Packable>::pack(#label, buf);
+                    #payload
+                }
+            }
+        });
+        quote! {
+            match #this {
+                #(#arms)*
+            }
+        }
+    };
+
+    // unit variants are also accepted as a bare string, as other implementations emit them
+    let units = variants
+        .iter()
+        .filter(|v| matches!(v.fields, Fields::Unit))
+        .map(|v| {
+            let ident = &v.ident;
+            let label = variant_label(v);
+            quote! { #label => Ok((n, #name::#ident)), }
+        });
+    let units = units.collect::<Vec<_>>();
+    let input = quote! { buf };
+    let unpack = |strict| {
+        let decoders = decoders(strict);
+        let arms = variants.iter().map(|v| {
+            let label = variant_label(v);
+            let payload = unpack_payload(name, v, &input, None, strict);
+            quote! { #label => #payload, }
+        });
+        quote! {
+            if let Ok((n, variant)) = #decoders::unpack_str(buf) {
+                return match variant {
+                    #(#units)*
+                    _ => Err(::msgpacker::Error::InvalidEnumVariant.into()),
+                };
+            }
+            let (mut n, len) = #decoders::unpack_map_len(buf)?;
+            if len != 1 {
+                return Err(::msgpacker::Error::InvalidEnumVariant.into());
+            }
+            buf = &buf[n..];
+            let (c, variant) = #decoders::unpack_str(buf)?;
+            n += c;
+            buf = &buf[c..];
+            let (c, slf) = match variant {
+                #(#arms)*
+                _ => return Err(::msgpacker::Error::InvalidEnumVariant.into()),
+            };
+            Ok((n + c, slf))
+        }
+    };
+
+    (
+        pack(Packing::Plain),
+        pack(Packing::Canonical),
+        pack(Packing::Fallible),
+        unpack(false),
+        unpack(true),
+    )
+}
+
+fn impl_internal(c: &Container, tag: &LitStr, variants: &[Variant]) -> syn::Result<Generated> {
+    let (name, this) = (&c.ty, &c.this);
+    if let Some(v) = variants
+        .iter()
+        .find(|v| matches!(v.fields, Fields::Unnamed(_)))
+    {
+        return Err(syn::Error::new_spanned(
+            v,
+            "internally tagged enums support only unit and struct variants",
+        ));
+    }
+
+    let pack = |packing| {
+        let arms = variants.iter().map(|v| {
+            let pattern = pattern(name, v);
+            let label = variant_label(v);
+            if packing == Packing::Canonical {
+                let entries = canonical_entries(&v.fields);
+                let map = pack_canonical_map(quote! {
+                    entries.push(::msgpacker::canonical::entry(#tag, |buf| {
+                        Ok(<str as ::msgpacker::Packable>::pack(#label, buf))
+                    })?);
+                    #entries
+                });
+                return quote! {
+                    #pattern => {
+                        #map
+                    }
+                };
+            }
+            let len = entries_len(&v.fields);
+            let header = pack_map_len(quote! { 1 + #len }, packing);
+            let entries = pack_entries(&v.fields, packing);
+            quote! {
+                #pattern => {
+                    n += #header;
+                    n += <str as ::msgpacker::Packable>::pack(#tag, buf);
+                    n += <str as ::msgpacker::Packable>::pack(#label, buf);
+                    #entries
+                }
+            }
+        });
+        quote! {
+            match #this {
+                #(#arms)*
+            }
+        }
+    };
+
+    // the tag is skipped with the map decoding, so flattened fields don't collect it either
+    let input = quote! { buf };
+    let skip = quote! { |key: &str| key == #tag };
+    let unpack = |strict: bool| {
+        let decoders = decoders(strict);
+        let arms = variants.iter().map(|v| {
+            let ident = &v.ident;
+            let label = variant_label(v);
+            match &v.fields {
+                Fields::Unit => quote! {
+                    #label => Ok((::msgpacker::skip_value(buf)?, #name::#ident)),
+                },
+                _ => {
+                    let payload = unpack_payload(name, v, &input, Some(&skip), strict);
+                    quote! { #label => Ok(#payload), }
+                }
+            }
+        });
+        // the strict rules check every entry, so the scan doesn't stop at the tag
+        let found = (!strict).then(|| quote! { break; });
+        let skip_value = skip_value(&quote! { scan }, strict);
+        let [declare_keys, push_key, check_keys] = keys(strict);
+        quote! {
+            let mut scan = buf;
+            let (c, len) = #decoders::unpack_map_len(scan)?;
+            scan = &scan[c..];
+            let mut variant = None;
+            #declare_keys
+            for _ in 0..len {
+                let (c, key) = #decoders::unpack_str(scan)?;
+                scan = &scan[c..];
+                #push_key
+                if key == #tag {
+                    variant = Some(#decoders::unpack_str(scan)?.1);
+                    #found
+                }
+                #skip_value
+                scan = &scan[c..];
+            }
+            #check_keys
+            match variant.ok_or(::msgpacker::Error::MissingField)? {
+                #(#arms)*
+                _ => Err(::msgpacker::Error::InvalidEnumVariant.into()),
+            }
+        }
+    };
+
+    Ok((
+        pack(Packing::Plain),
+        pack(Packing::Canonical),
+        pack(Packing::Fallible),
+        unpack(false),
+        unpack(true),
+    ))
+}
+
+fn impl_adjacent(c: &Container, tag: &LitStr, content: &LitStr, variants: &[Variant]) -> Generated {
+    let (name, this) = (&c.ty, &c.this);
+    let pack = |packing| {
+        let arms = variants.iter().map(|v| {
+            let pattern = pattern(name, v);
+            let label = variant_label(v);
+            match &v.fields {
+                Fields::Unit => quote! {
+                    #pattern => {
+                        n += ::msgpacker::pack_map_len(buf, 1);
+                        n += <str as ::msgpacker::Packable>::pack(#tag, buf);
+                        n += <str as ::msgpacker::Packable>::pack(#label, buf);
+                    }
+                },
+                _ if packing == Packing::Canonical => {
+                    let payload = pack_payload(v, packing);
+                    let map = pack_canonical_map(quote! {
+                        entries.push(::msgpacker::canonical::entry(#tag, |buf| {
+                            Ok(<str as ::msgpacker::Packable>::pack(#label, buf))
+                        })?);
+                        entries.push(::msgpacker::canonical::entry(#content, |buf| {
+                            let mut n = 0;
+                            #payload
+                            Ok(n)
+                        })?);
+                    });
+                    quote! {
+                        #pattern => {
+                            #map
+                        }
+                    }
+                }
+                _ => {
+                    let payload = pack_payload(v, packing);
+                    quote! {
+                        #pattern => {
+                            n += ::msgpacker::pack_map_len(buf, 2);
+                            n += <str as ::msgpacker::Packable>::pack(#tag, buf);
+                            n += <str as ::msgpacker::Packable>::pack(#label, buf);
+                            n += <str as ::msgpacker::Packable>::pack(#content, buf);
+                            #payload
+                        }
+                    }
+                }
+            }
+        });
+        quote! {
+            match #this {
+                #(#arms)*
+            }
+        }
+    };
+
+    let has_content = variants.iter().any(|v| !matches!(v.fields, Fields::Unit));
+    let input = quote! { content.ok_or(::msgpacker::Error::MissingField)? };
+    let arms = |strict| {
+        variants
+            .iter()
+            .map(|v| {
+                let ident = &v.ident;
+                let label = variant_label(v);
+                match &v.fields {
+                    Fields::Unit => quote! { #label => #name::#ident, },
+                    _ => {
+                        let payload = unpack_payload(name, v, &input, None, strict);
+                        quote! {
+                            #label => {
+                                let (_, slf) = #payload;
+                                slf
+                            }
+                        }
+                    }
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+    let (declare, record) = if has_content {
+        (
+            quote! { let mut content = None; },
+            quote! {
+                else if key == #content {
+                    content = Some(&buf[..c]);
+                }
+            },
+        )
+    } else {
+        (quote! {}, quote! {})
+    };
+    let unpack = |strict| {
+        let decoders = decoders(strict);
+        let arms = arms(strict);
+        let skip_value = skip_value(&quote! { buf }, strict);
+        let [declare_keys, push_key, check_keys] = keys(strict);
+        quote! {
+            let (mut n, len) = #decoders::unpack_map_len(buf)?;
+            buf = &buf[n..];
+            let mut variant = None;
+            #declare
+            #declare_keys
+            for _ in 0..len {
+                let (c, key) = #decoders::unpack_str(buf)?;
+                n += c;
+                buf = &buf[c..];
+                #push_key
+                #skip_value
+                if key == #tag {
+                    variant = Some(#decoders::unpack_str(buf)?.1);
+                }
+                #record
+                n += c;
+                buf = &buf[c..];
+            }
+            #check_keys
+            let slf = match variant.ok_or(::msgpacker::Error::MissingField)? {
+                #(#arms)*
+                _ => return Err(::msgpacker::Error::InvalidEnumVariant.into()),
+            };
+            Ok((n, slf))
+        }
+    };
+
+    (
+        pack(Packing::Plain),
+        pack(Packing::Canonical),
+        pack(Packing::Fallible),
+        unpack(false),
+        unpack(true),
+    )
+}
+
+fn impl_untagged(c: &Container, variants: &[Variant]) -> Generated {
+    let (name, this, error) = (&c.ty, &c.this, &c.error);
+    let pack = |packing| {
+        let arms = variants.iter().map(|v| {
+            let pattern = pattern(name, v);
+            let payload = pack_payload(v, packing);
+            quote! {
+                #pattern => {
+                    #payload
+                }
+            }
+        });
+        quote! {
+            match #this {
+                #(#arms)*
+            }
+        }
+    };
+
+    // the variants are attempted in declaration order, skipping the ones whose payload can't
+    // start with the marker byte
+    let markers = variants.iter().map(payload_markers).collect::<Vec<_>>();
+    let unpack = |strict| {
+        let attempts = variants
+            .iter()
+            .zip(&markers)
+            .enumerate()
+            .map(|(i, (v, markers))| untagged_attempt(c, v, i, markers.as_ref(), strict));
+        let marker = markers
+            .iter()
+            .any(Option::is_some)
+            .then(|| quote! { let __marker = buf.first().copied(); });
+        quote! {
+            #marker
+            let mut __error: Option<#error> = None;
+            #(#attempts)*
+            Err(__error.unwrap_or_else(|| ::msgpacker::Error::InvalidEnumVariant.into()))
+        }
+    };
+
+    (
+        pack(Packing::Plain),
+        pack(Packing::Canonical),
+        pack(Packing::Fallible),
+        unpack(false),
+        unpack(true),
+    )
+}
+
+/// The statements attempting to unpack the `i`th variant of an untagged enum from `buf`,
+/// returning it on success.
+///
+/// With `markers`, the variant is attempted only if the payload starts with one of them, and its
+/// error replaces the previous ones in `__error`. The variants of unknown markers are attempted
+/// for any payload, and their error is kept only if no other was, so the error of the last
+/// variant expecting the marker is returned if none matches.
+fn untagged_attempt(
+    c: &Container,
+    v: &Variant,
+    i: usize,
+    markers: Option<&TokenStream>,
+    strict: bool,
+) -> TokenStream {
+    let (name, error) = (&c.ty, &c.error);
+    let attempt = format_ident!("__variant{}", i);
+    let payload = unpack_payload(name, v, &quote! { buf }, None, strict);
+    let failed = match markers {
+        Some(_) => quote! { __error = Some(e) },
+        None => quote! { __error = __error.or(Some(e)) },
+    };
+    let attempt = quote! {
+        let #attempt = |buf: &[u8]| -> Result<(usize, #name), #error> { Ok(#payload) };
+        match #attempt(buf) {
+            Ok(r) => return Ok(r),
+            Err(e) => #failed,
+        }
+    };
+    match markers {
+        Some(markers) => quote! {
+            if matches!(__marker, Some(#markers)) {
+                #attempt
+            }
+        },
+        None => attempt,
+    }
+}
+
+/// The pattern of the marker bytes the payload of the variant starts with, or `None` if it
+/// isn't known from the syntax of the variant.
+///
+/// Newtype variants are known by the field kind, or by the type of a primitive or a `String`.
+fn payload_markers(v: &Variant) -> Option<TokenStream> {
+    let int = quote! { 0x00..=0x7f | 0xcc..=0xd3 | 0xe0..=0xff };
+    let array = quote! { 0x90..=0x9f | 0xdc | 0xdd };
+    let map = quote! { 0x80..=0x8f | 0xde | 0xdf };
+    let field = match &v.fields {
+        Fields::Unit => return Some(quote! { 0xc0 }),
+        Fields::Unnamed(f) if f.unnamed.len() == 1 => &f.unnamed[0],
+        Fields::Unnamed(_) => return Some(array),
+        Fields::Named(_) => return Some(map),
+    };
+    match field_kind(field) {
+        FieldKind::Map => return Some(map),
+        FieldKind::Array | FieldKind::Numbers | FieldKind::Fixed => return Some(array),
+        FieldKind::With(_) | FieldKind::Floats(..) => return None,
+        FieldKind::Value => (),
+    }
+    let ident = match &field.ty {
+        Type::Path(p) if p.qself.is_none() => &p.path.segments.last()?.ident,
+        _ => return None,
+    };
+    let markers = match ident.to_string().as_str() {
+        "bool" => quote! { 0xc2 | 0xc3 },
+        "u8" | "u16" | "u32" | "u64" | "usize" | "i8" | "i16" | "i32" | "i64" | "isize" => int,
+        "f32" | "f64" => quote! { 0xca | 0xcb },
+        "String" => quote! { 0xa0..=0xbf | 0xd9..=0xdb },
+        _ => return None,
+    };
+    Some(markers)
+}
+
+/// The `MapEntries` implementation of a struct with named fields, so it can be flattened.
+pub fn impl_map_entries(c: &Container, fields: &Fields) -> TokenStream {
+    let ty = &c.ty;
+    let b = bindings(fields);
+    let members = fields.members();
+    let destructure = quote! { let #ty { #(#members: #b),* } = self; };
+    let knows = knows(fields);
+    let len = entries_len(fields);
+    let pack = pack_entries(fields, Packing::Plain);
+    let try_pack = pack_entries(fields, Packing::Fallible);
+    let canonical = canonical_entries(fields);
+    let unpack = |strict| unpack_entries(&quote! { #ty }, fields, Some(&quote! { skip }), strict);
+    let validate = c.validate.as_ref().map(|path| quote! { #path(&slf)?; });
+    let unpack_entries = |name: Ident, unpack: TokenStream| {
+        quote! {
+            fn #name(
+                mut buf: &[u8],
+                len: usize,
+                skip: &dyn Fn(&str) -> bool,
+            ) -> Result<Self, Self::Error> {
+                let mut n = 0;
+                #unpack
+                let _ = n;
+                #validate
+                Ok(slf)
+            }
+        }
+    };
+    let unpack_entries_strict =
+        unpack_entries(format_ident!("unpack_entries_strict"), unpack(true));
+    let unpack_entries = unpack_entries(format_ident!("unpack_entries"), unpack(false));
+    let (canonical_entries, unpack_entries_strict) = match cfg!(feature = "alloc") {
+        true => (
+            quote! {
+                fn canonical_entries(
+                    &self,
+                    entries: &mut ::msgpacker::canonical::Entries,
+                ) -> Result<(), ::msgpacker::Error> {
+                    #destructure
+                    #canonical
+                    Ok(())
+                }
+            },
+            unpack_entries_strict,
+        ),
+        false => (quote! {}, quote! {}),
+    };
+
+    let delimited = fields.iter().map(|f| field_delimited(f, &c.name));
+    let plain = fields
+        .iter()
+        .filter(|f| !is_flattened(f))
+        .map(|f| label(f.ident.as_ref().unwrap()))
+        .collect::<Vec<_>>();
+    let plain_len = plain.len();
+    let flattened = fields
+        .iter()
+        .filter(|f| is_flattened(f))
+        .map(|f| &f.ty)
+        .collect::<Vec<_>>();
+    let checks = check_keys(&c.name.to_string(), fields, None);
+
+    quote! {
+        #checks
+
+        impl ::msgpacker::MapEntries for #ty {
+            const VALUES_SELF_DELIMITING: bool = true #(&& #delimited)*;
+
+            const KEYS: &'static [&'static str] = {
+                const LEN: usize =
+                    #plain_len #(+ <#flattened as ::msgpacker::MapEntries>::KEYS.len())*;
+                const KEYS: [&str; LEN] = ::msgpacker::concat_keys(&[
+                    &[#(#plain),*],
+                    #(<#flattened as ::msgpacker::MapEntries>::KEYS),*
+                ]);
+                &KEYS
+            };
+
+            fn knows(key: &str) -> bool {
+                #knows
+            }
+
+            fn entries_len(&self) -> usize {
+                #destructure
+                #len
+            }
+
+            fn pack_entries<T>(&self, buf: &mut T) -> usize
+            where
+                T: ::msgpacker::Writer,
+            {
+                #destructure
+                let mut n = 0;
+                #pack
+                n
+            }
+
+            fn try_pack_entries<T>(&self, buf: &mut T) -> Result<usize, ::msgpacker::PackError>
+            where
+                T: ::msgpacker::Writer,
+            {
+                #destructure
+                let mut n = 0;
+                #try_pack
+                Ok(n)
+            }
+
+            #canonical_entries
+
+            #unpack_entries
+
+            #unpack_entries_strict
+        }
+    }
+}
+
+pub fn impl_tagged_enum(
+    c: &Container,
+    repr: Representation,
+    variants: &[Variant],
+) -> syn::Result<Bodies> {
+    let (pack, pack_canonical, try_pack, unpack, unpack_strict) = match &repr {
+        Representation::Index => unreachable!("index representation is not tagged"),
+        Representation::External => impl_external(c, variants),
+        Representation::Internal { tag } => impl_internal(c, tag, variants)?,
+        Representation::Adjacent { tag, content } => impl_adjacent(c, tag, content, variants),
+        Representation::Untagged => impl_untagged(c, variants),
+    };
+
+    let wrap = |pack, packing: Packing| {
+        let written = packing.written();
+        quote! {
+            let mut n = 0;
+
+            #pack
+
+            #written
+        }
+    };
+    // the fields are skipped over when unpacking, except for the payloads of externally tagged
+    // variants, which are read in place, unless flattened fields share their map
+    let name = &c.name;
+    let (self_delimiting, checks) = match &repr {
+        Representation::External => {
+            let delimited = variants
+                .iter()
+                .flat_map(|v| &v.fields)
+                .map(|f| field_delimited(f, name));
+            let flattening = variants
+                .iter()
+                .filter(|v| v.fields.iter().any(is_flattened));
+            (
+                quote! { true #(&& #delimited)* },
+                check_delimited(name, flattening),
+            )
+        }
+        _ => (quote! { true }, check_delimited(name, variants)),
+    };
+    let tag = match &repr {
+        Representation::Internal { tag } => Some(tag),
+        _ => None,
+    };
+    let keys = variants
+        .iter()
+        .filter(|v| v.fields.iter().any(is_flattened))
+        .map(|v| check_keys(&format!("{}::{}", name, v.ident), &v.fields, tag));
+    let checks = quote! {
+        #checks
+        #(#keys)*
+    };
+
+    Ok(Bodies {
+        self_delimiting,
+        checks,
+        pack: wrap(pack, Packing::Plain),
+        try_pack_canonical: wrap(pack_canonical, Packing::Canonical),
+        try_pack: wrap(try_pack, Packing::Fallible),
+        unpack,
+        unpack_strict,
+        // the variant can only be resolved with the whole value at hand
+        unpack_iter: None,
+    })
+}
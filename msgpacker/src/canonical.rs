@@ -0,0 +1,357 @@
+//! Canonical encoding, so equal values are packed into identical bytes.
+//!
+//! The canonical form of a value follows these rules:
+//!
+//! - integers use the smallest representation of their value, with the unsigned formats for
+//!   non-negative values, whatever the type they're packed from;
+//! - the lengths of strings, bins, arrays, maps and extensions use the smallest representation;
+//! - floats keep their width, and NaN is packed as the quiet NaN with a zero payload;
+//! - map entries are sorted by the bytes of their canonical keys, which must be unique;
+//! - sets are sorted by the bytes of their canonical elements.
+//!
+//! [Packable::pack_canonical] packs values in canonical form, and [canonicalize] re-encodes
+//! packed bytes. Sets are packed as arrays, so only [Packable::pack_canonical] can tell them
+//! apart and sort them. Structs and tuples are packed without a header, so [canonicalize] and
+//! [is_canonical] can't parse them once nested in arrays or maps. Maps with two keys of the same
+//! canonical bytes have no canonical form: [Packable::try_pack_canonical] fails for them with
+//! [Error::DuplicateKey].
+//!
+//! ```rust
+//! use msgpacker::prelude::*;
+//! use std::collections::HashSet;
+//!
+//! let a: HashSet<i64> = (-300..300).collect();
+//! let b: HashSet<i64> = (-300..300).rev().collect();
+//!
+//! let mut x = Vec::new();
+//! let mut y = Vec::new();
+//! a.pack_canonical(&mut x);
+//! b.pack_canonical(&mut y);
+//!
+//! assert_eq!(x, y);
+//! assert!(msgpacker::canonical::is_canonical(&x));
+//! ```
+
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+
+use crate::{
+    format::Format, pack_array_len, pack_map_len, unpack::unpack_ext, unpack_array_len,
+    unpack_bytes, unpack_map_len, unpack_str, Error, ExtensionRef, Packable, Unpackable as _,
+    Writer,
+};
+
+/// A map being re-encoded into the work buffer; its entries are sorted once all of them are
+/// read.
+struct Map {
+    left: usize,
+    /// The offset of the first entry, after the header.
+    start: usize,
+    /// The offset of the end of the key of the entry being read.
+    key: usize,
+    /// The offsets of the start, the end of the key and the end of every entry.
+    entries: Vec<(usize, usize, usize)>,
+}
+
+/// An array or a map being re-encoded.
+enum Frame {
+    Array { left: usize },
+    Map,
+}
+
+/// Re-encodes the values of the buffer in canonical form, returning the amount of written bytes.
+///
+/// Nested arrays and maps are tracked without recursion, so deep values can't overflow the
+/// stack. Fails with [Error::DuplicateKey] if two keys of a map have the same canonical form.
+///
+/// Every packed value must be self-describing: structs packed as consecutive fields, without a
+/// header, can't be told apart from the values around them once nested in arrays or maps.
+pub fn canonicalize<T>(buf: &[u8], out: &mut T) -> Result<usize, Error>
+where
+    T: Writer,
+{
+    let mut rest = buf;
+    let mut frames = Vec::new();
+    let mut maps: Vec<Map> = Vec::new();
+    // the maps are re-encoded here until the outermost is complete
+    let mut work = Vec::new();
+    let mut scratch = Vec::new();
+    let mut n = 0;
+
+    while !rest.is_empty() {
+        scratch.clear();
+        let format = rest[0];
+        let (c, container) = match format {
+            Format::NIL | Format::TRUE | Format::FALSE => {
+                scratch.push(format);
+                (1, None)
+            }
+            0x00..=Format::POSITIVE_FIXINT
+            | Format::UINT8
+            | Format::UINT16
+            | Format::UINT32
+            | Format::UINT64 => {
+                let (c, v) = u64::unpack(rest)?;
+                v.pack(&mut scratch);
+                (c, None)
+            }
+            0xe0.. | Format::INT8 | Format::INT16 | Format::INT32 | Format::INT64 => {
+                let (c, v) = i64::unpack(rest)?;
+                v.try_pack_canonical(&mut scratch)?;
+                (c, None)
+            }
+            Format::FLOAT32 => {
+                let (c, v) = f32::unpack(rest)?;
+                v.try_pack_canonical(&mut scratch)?;
+                (c, None)
+            }
+            Format::FLOAT64 => {
+                let (c, v) = f64::unpack(rest)?;
+                v.try_pack_canonical(&mut scratch)?;
+                (c, None)
+            }
+            0xa0..=0xbf | Format::STR8 | Format::STR16 | Format::STR32 => {
+                let (c, v) = unpack_str(rest)?;
+                v.pack(&mut scratch);
+                (c, None)
+            }
+            Format::BIN8 | Format::BIN16 | Format::BIN32 => {
+                let (c, v) = unpack_bytes(rest)?;
+                v.pack(&mut scratch);
+                (c, None)
+            }
+            0x90..=0x9f | Format::ARRAY16 | Format::ARRAY32 => {
+                let (c, len) = unpack_array_len(rest)?;
+                pack_array_len(&mut scratch, len);
+                (c, Some(Frame::Array { left: len }).filter(|_| len > 0))
+            }
+            0x80..=0x8f | Format::MAP16 | Format::MAP32 => {
+                let (c, len) = unpack_map_len(rest)?;
+                pack_map_len(&mut scratch, len);
+                (c, Some(Frame::Map).filter(|_| len > 0))
+            }
+            Format::FIXEXT1
+            | Format::FIXEXT2
+            | Format::FIXEXT4
+            | Format::FIXEXT8
+            | Format::FIXEXT16
+            | Format::EXT8
+            | Format::EXT16
+            | Format::EXT32 => {
+                let (c, t, data) = unpack_ext(rest)?;
+                ExtensionRef::Ext(t, data).pack(&mut scratch);
+                (c, None)
+            }
+            _ => return Err(Error::UnexpectedFormatTag),
+        };
+        rest = &rest[c..];
+        match (&container, maps.is_empty()) {
+            (Some(Frame::Map), _) | (_, false) => work.extend_from_slice(&scratch),
+            (_, true) => {
+                out.write_all(&scratch);
+                n += scratch.len();
+            }
+        }
+        if let Some(frame) = container {
+            if let Frame::Map = frame {
+                let left = unpack_map_len(&scratch)?.1;
+                maps.push(Map {
+                    left: left.checked_mul(2).ok_or(Error::BufferTooShort)?,
+                    start: work.len(),
+                    key: 0,
+                    entries: Vec::new(),
+                });
+            }
+            frames.push(frame);
+            continue;
+        }
+
+        // the value is complete; so may be the arrays and maps it closes
+        while let Some(frame) = frames.last_mut() {
+            match frame {
+                Frame::Array { left } => {
+                    *left -= 1;
+                    if *left > 0 {
+                        break;
+                    }
+                }
+                Frame::Map => {
+                    // the frame is a map, so is the innermost of the maps
+                    let map = maps.last_mut().ok_or(Error::UnexpectedFormatTag)?;
+                    map.left -= 1;
+                    if map.left % 2 == 1 {
+                        map.key = work.len();
+                        break;
+                    }
+                    let start = map.entries.last().map(|e| e.2).unwrap_or(map.start);
+                    map.entries.push((start, map.key, work.len()));
+                    if map.left > 0 {
+                        break;
+                    }
+
+                    let mut map = maps.pop().ok_or(Error::UnexpectedFormatTag)?;
+                    map.entries
+                        .sort_unstable_by(|a, b| work[a.0..a.1].cmp(&work[b.0..b.1]));
+                    if map
+                        .entries
+                        .windows(2)
+                        .any(|w| work[w[0].0..w[0].1] == work[w[1].0..w[1].1])
+                    {
+                        return Err(Error::DuplicateKey);
+                    }
+                    if map.entries.len() > 1 {
+                        let mut sorted = Vec::with_capacity(work.len() - map.start);
+                        for (start, _, end) in &map.entries {
+                            sorted.extend_from_slice(&work[*start..*end]);
+                        }
+                        work.truncate(map.start);
+                        work.extend(sorted);
+                    }
+                    if maps.is_empty() {
+                        n += work.len();
+                        out.write_all(&work);
+                        work.clear();
+                    }
+                }
+            }
+            frames.pop();
+        }
+    }
+
+    if !frames.is_empty() {
+        return Err(Error::BufferTooShort);
+    }
+    Ok(n)
+}
+
+/// Whether the buffer holds values in canonical form.
+///
+/// Malformed buffers aren't canonical, and neither are the ones [canonicalize] can't parse.
+pub fn is_canonical(buf: &[u8]) -> bool {
+    let mut canonical = Vec::with_capacity(buf.len());
+    canonicalize(buf, &mut canonical).is_ok() && canonical == buf
+}
+
+/// Packs the bytes `f` writes in canonical form, returning the amount of written bytes.
+///
+/// It's the default of [Packable::try_pack_canonical]. The bytes are re-encoded with
+/// [canonicalize], failing as it does; nothing is written on failure.
+pub fn pack_with<T, F>(buf: &mut T, f: F) -> Result<usize, Error>
+where
+    T: Writer,
+    F: FnOnce(&mut Vec<u8>) -> usize,
+{
+    let mut bytes = Vec::new();
+    f(&mut bytes);
+    let mut canonical = Vec::with_capacity(bytes.len());
+    let n = canonicalize(&bytes, &mut canonical)?;
+    buf.write_all(&canonical);
+    Ok(n)
+}
+
+/// The canonical bytes of the key and of the value of a map entry.
+pub type Entry = (Vec<u8>, Vec<u8>);
+
+/// The entries of a map, to be sorted by [pack_entries].
+pub type Entries = Vec<Entry>;
+
+/// The entry of the key and of the value `f` packs in canonical form.
+pub fn entry<K, F>(key: &K, f: F) -> Result<Entry, Error>
+where
+    K: Packable + ?Sized,
+    F: FnOnce(&mut Vec<u8>) -> Result<usize, Error>,
+{
+    let mut k = Vec::new();
+    key.try_pack_canonical(&mut k)?;
+    let mut v = Vec::new();
+    f(&mut v)?;
+    Ok((k, v))
+}
+
+/// Packs the entries as a map, sorted by the bytes of their keys, returning the amount of
+/// written bytes.
+///
+/// Fails with [Error::DuplicateKey] if two entries have the same key, writing nothing.
+pub fn pack_entries<T>(buf: &mut T, mut entries: Entries) -> Result<usize, Error>
+where
+    T: Writer,
+{
+    entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    if entries.windows(2).any(|w| w[0].0 == w[1].0) {
+        return Err(Error::DuplicateKey);
+    }
+    let n = pack_map_len(buf, entries.len());
+    Ok(n + entries
+        .into_iter()
+        .map(|(k, v)| {
+            let n = k.len() + v.len();
+            buf.write_all(&k);
+            buf.write_all(&v);
+            n
+        })
+        .sum::<usize>())
+}
+
+/// Packs an array in canonical form, keeping the order of the values.
+pub fn pack_array<T, A, I, V>(buf: &mut T, iter: A) -> Result<usize, Error>
+where
+    T: Writer,
+    A: IntoIterator<IntoIter = I>,
+    I: Iterator<Item = V> + ExactSizeIterator,
+    V: Packable,
+{
+    let mut values = iter.into_iter();
+    let n = pack_array_len(buf, values.len());
+    values.try_fold(n, |n, v| Ok(n + v.try_pack_canonical(buf)?))
+}
+
+/// Packs a set as an array in canonical form, sorting the values by their canonical bytes.
+pub fn pack_set<T, A, I, V>(buf: &mut T, iter: A) -> Result<usize, Error>
+where
+    T: Writer,
+    A: IntoIterator<IntoIter = I>,
+    I: Iterator<Item = V> + ExactSizeIterator,
+    V: Packable,
+{
+    let mut values = iter
+        .into_iter()
+        .map(|v| {
+            let mut bytes = Vec::new();
+            v.try_pack_canonical(&mut bytes)?;
+            Ok(bytes)
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    values.sort_unstable();
+
+    let n = pack_array_len(buf, values.len());
+    Ok(n + values
+        .into_iter()
+        .map(|v| {
+            let n = v.len();
+            buf.write_all(&v);
+            n
+        })
+        .sum::<usize>())
+}
+
+/// Packs a map in canonical form, sorting the entries by the canonical bytes of their keys.
+///
+/// Fails with [Error::DuplicateKey] if two keys have the same canonical bytes, writing nothing.
+pub fn pack_map<T, A, I, B, K, V>(buf: &mut T, iter: A) -> Result<usize, Error>
+where
+    T: Writer,
+    A: IntoIterator<IntoIter = I>,
+    B: Borrow<(K, V)>,
+    I: Iterator<Item = B> + ExactSizeIterator,
+    K: Packable,
+    V: Packable,
+{
+    let entries = iter
+        .into_iter()
+        .map(|b| {
+            let (k, v) = b.borrow();
+            entry(k, |buf| v.try_pack_canonical(buf))
+        })
+        .collect::<Result<_, Error>>()?;
+    pack_entries(buf, entries)
+}
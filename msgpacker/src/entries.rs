@@ -0,0 +1,296 @@
+use super::{PackError, Packable, Unpackable, Writer};
+
+#[cfg(feature = "alloc")]
+use super::{canonical, skip_value, unpack_str, Error};
+#[cfg(feature = "alloc")]
+use ::alloc::vec::Vec;
+
+/// A type packed as the entries of a string-keyed map, without the map header.
+///
+/// `#[msgpacker(flatten)]` splices the entries of a field into the map of the enclosing struct
+/// variant. It only has an effect in the map of a struct variant of an enum tagged by name, or
+/// untagged; plain structs and the variants tagged by index pack their fields in sequence, so
+/// flattening a field there packs the same bytes. The derive implements it for structs with
+/// named fields; string-keyed maps implement it to collect the keys no other field decodes.
+pub trait MapEntries: Packable + Unpackable {
+    /// Whether the value of every entry is packed as a single msgpack value, as
+    /// [Packable::SELF_DELIMITING] tells. Defaults to `true`.
+    const VALUES_SELF_DELIMITING: bool = true;
+
+    /// The keys always packed by [MapEntries::pack_entries].
+    ///
+    /// The derive checks at compile time that the other fields of the enclosing map don't pack
+    /// them too. Defaults to none, as for maps, whose keys are only known at runtime.
+    const KEYS: &'static [&'static str] = &[];
+
+    /// Whether the key is decoded by the type, so other flattened fields don't collect it.
+    fn knows(key: &str) -> bool;
+
+    /// The amount of entries packed by [MapEntries::pack_entries].
+    fn entries_len(&self) -> usize;
+
+    /// Packs the entries into the buffer, returning the amount of written bytes.
+    fn pack_entries<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer;
+
+    /// Packs the entries as [MapEntries::pack_entries], failing as [Packable::try_pack].
+    ///
+    /// The default implementation calls [MapEntries::pack_entries], so it needs to be overridden
+    /// only by the types whose keys or values may be too large.
+    fn try_pack_entries<T>(&self, buf: &mut T) -> Result<usize, PackError>
+    where
+        T: Writer,
+    {
+        Ok(self.pack_entries(buf))
+    }
+
+    /// Adds the entries in canonical form, so the enclosing map can sort them.
+    ///
+    /// The default implementation splits the bytes of [MapEntries::pack_entries], re-encoding
+    /// them with [canonical::canonicalize]; it requires every value to be self-describing, and
+    /// fails as [canonical::canonicalize] does.
+    #[cfg(feature = "alloc")]
+    fn canonical_entries(&self, entries: &mut canonical::Entries) -> Result<(), Error> {
+        let canonical = |bytes: &[u8]| {
+            let mut out = Vec::new();
+            canonical::pack_with(&mut out, |buf| {
+                buf.extend_from_slice(bytes);
+                bytes.len()
+            })?;
+            Ok::<_, Error>(out)
+        };
+
+        let mut bytes = Vec::new();
+        self.pack_entries(&mut bytes);
+        let mut buf = bytes.as_slice();
+        while !buf.is_empty() {
+            let (c, _) = unpack_str(buf)?;
+            let n = c + skip_value(&buf[c..])?;
+            entries.push((canonical(&buf[..c])?, canonical(&buf[c..n])?));
+            buf = &buf[n..];
+        }
+        Ok(())
+    }
+
+    /// Unpacks the value from the `len` entries of a map body, ignoring the keys for which
+    /// `skip` returns `true`.
+    ///
+    /// The bytes are shared with the enclosing type, which accounts for their length.
+    fn unpack_entries(
+        buf: &[u8],
+        len: usize,
+        skip: &dyn Fn(&str) -> bool,
+    ) -> Result<Self, Self::Error>;
+
+    /// Unpacks the value from the entries as [MapEntries::unpack_entries], under the rules of
+    /// [strict](crate::strict).
+    ///
+    /// The enclosing type checks the keys and the encoding of every entry, so the default
+    /// implementation needs to be overridden only by the types whose values may hold maps or sets.
+    #[cfg(feature = "alloc")]
+    fn unpack_entries_strict(
+        buf: &[u8],
+        len: usize,
+        skip: &dyn Fn(&str) -> bool,
+    ) -> Result<Self, Self::Error> {
+        Self::unpack_entries(buf, len, skip)
+    }
+}
+
+/// Whether the strings are equal, as `==` isn't available in constants.
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Whether `keys` holds `key`; used by the derive to check [MapEntries::KEYS].
+#[doc(hidden)]
+pub const fn has_key(keys: &[&str], key: &str) -> bool {
+    let mut i = 0;
+    while i < keys.len() {
+        if str_eq(keys[i], key) {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+/// Whether `a` and `b` hold a common key; used by the derive to check [MapEntries::KEYS].
+#[doc(hidden)]
+pub const fn share_key(a: &[&str], b: &[&str]) -> bool {
+    let mut i = 0;
+    while i < a.len() {
+        if has_key(b, a[i]) {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+/// The `N` keys of the parts, in order; used by the derive to define [MapEntries::KEYS].
+#[doc(hidden)]
+pub const fn concat_keys<const N: usize>(parts: &[&[&'static str]]) -> [&'static str; N] {
+    let mut keys = [""; N];
+    let mut n = 0;
+    let mut i = 0;
+    while i < parts.len() {
+        let mut j = 0;
+        while j < parts[i].len() {
+            keys[n] = parts[i][j];
+            n += 1;
+            j += 1;
+        }
+        i += 1;
+    }
+    assert!(n == N, "the amount of keys doesn't match the parts");
+    keys
+}
+
+/// Calls `f` with the key and the buffer at its value for every entry of the map body that
+/// isn't skipped, returning the amount of read bytes.
+#[cfg(feature = "alloc")]
+fn for_each_entry<E, F>(
+    mut buf: &[u8],
+    len: usize,
+    skip: &dyn Fn(&str) -> bool,
+    mut f: F,
+) -> Result<usize, E>
+where
+    E: From<Error>,
+    F: FnMut(&str, &[u8]) -> Result<usize, E>,
+{
+    let mut n = 0;
+    for _ in 0..len {
+        let (c, key) = unpack_str(buf)?;
+        n += c;
+        buf = &buf[c..];
+        let c = match skip(key) {
+            true => skip_value(buf)?,
+            false => f(key, buf)?,
+        };
+        n += c;
+        buf = &buf[c..];
+    }
+    Ok(n)
+}
+
+/// Implements [MapEntries] for a string-keyed map, collecting the keys no other field decodes.
+#[cfg(feature = "alloc")]
+macro_rules! map_entries {
+    ($map:ident<V>) => {
+        impl<V> MapEntries for $map<::alloc::string::String, V>
+        where
+            V: Packable + Unpackable,
+        {
+            map_entries!(@items);
+        }
+    };
+    ($map:ident<V, S>) => {
+        impl<V, S> MapEntries for $map<::alloc::string::String, V, S>
+        where
+            V: Packable + Unpackable,
+            S: ::core::hash::BuildHasher + Default,
+        {
+            map_entries!(@items);
+        }
+    };
+    (@items) => {
+        const VALUES_SELF_DELIMITING: bool = V::SELF_DELIMITING;
+
+        fn knows(_key: &str) -> bool {
+            false
+        }
+
+        fn entries_len(&self) -> usize {
+            self.len()
+        }
+
+        fn pack_entries<T>(&self, buf: &mut T) -> usize
+        where
+            T: Writer,
+        {
+            self.iter().map(|(k, v)| k.pack(buf) + v.pack(buf)).sum()
+        }
+
+        fn try_pack_entries<T>(&self, buf: &mut T) -> Result<usize, PackError>
+        where
+            T: Writer,
+        {
+            self.iter()
+                .map(|(k, v)| Ok(k.try_pack(buf)? + v.try_pack(buf)?))
+                .sum()
+        }
+
+        fn canonical_entries(&self, entries: &mut canonical::Entries) -> Result<(), Error> {
+            for (k, v) in self.iter() {
+                entries.push(canonical::entry(k, |buf| v.try_pack_canonical(buf))?);
+            }
+            Ok(())
+        }
+
+        fn unpack_entries(
+            buf: &[u8],
+            len: usize,
+            skip: &dyn Fn(&str) -> bool,
+        ) -> Result<Self, Self::Error> {
+            let mut map = Self::default();
+            for_each_entry(buf, len, skip, |key, buf| {
+                let (c, v) = V::unpack(buf)?;
+                map.insert(key.into(), v);
+                Ok::<_, V::Error>(c)
+            })?;
+            Ok(map)
+        }
+
+        fn unpack_entries_strict(
+            buf: &[u8],
+            len: usize,
+            skip: &dyn Fn(&str) -> bool,
+        ) -> Result<Self, Self::Error> {
+            let mut map = Self::default();
+            for_each_entry(buf, len, skip, |key, buf| {
+                let (c, v) = V::unpack_strict(buf)?;
+                map.insert(key.into(), v);
+                Ok::<_, V::Error>(c)
+            })?;
+            Ok(map)
+        }
+    };
+}
+
+#[cfg(feature = "alloc")]
+mod alloc {
+    use super::*;
+    use ::alloc::collections::BTreeMap;
+
+    map_entries!(BTreeMap<V>);
+}
+
+#[cfg(feature = "std")]
+mod std {
+    use super::*;
+    use ::std::collections::HashMap;
+
+    map_entries!(HashMap<V, S>);
+}
+
+#[cfg(feature = "indexmap")]
+mod indexmap {
+    use super::*;
+    use ::indexmap::IndexMap;
+
+    map_entries!(IndexMap<V, S>);
+}
@@ -1,7 +1,12 @@
 use core::fmt;
 
 /// Deserialization errors for the protocol implementation.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+///
+/// The variants depend on the features, `Custom` requiring `alloc` and `Io` requiring `std`, so
+/// the enum is non-exhaustive and matches need a wildcard arm. It's `Clone` but not `Copy`, since
+/// `Custom` holds a message.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum Error {
     /// The provided buffer is too short and yielded an unexpected EOF.
     BufferTooShort,
@@ -11,18 +16,76 @@ pub enum Error {
     InvalidExtension,
     /// The string is not a valid UTF-8.
     InvalidUtf8,
+    /// A required field is missing from the map.
+    MissingField,
     /// The protocol format tag is not valid.
     UnexpectedFormatTag,
     /// The provided bin length is not valid.
     UnexpectedBinLength,
+    /// The integer is valid, but its value doesn't fit in the target type.
+    IntegerOutOfRange,
+    /// The number can't be represented exactly by the target float type.
+    InexactFloat,
     /// Not yet implemented.
     NotImplemented,
+    /// The buffer has bytes left after the value.
+    TrailingBytes,
+    /// The map has the same key more than once, or the set the same element.
+    DuplicateKey,
+    /// The value has no representation in the target format under the configured policy, such
+    /// as a NaN float in JSON.
+    Unrepresentable,
+    /// The integer is packed with a larger format than its value needs.
+    NonMinimalInteger,
+    /// The length of a string, bin, array, map or extension is packed with a larger format than
+    /// it needs.
+    NonMinimalLength,
+    /// The timestamp extension has an invalid length or a billion nanoseconds or more.
+    InvalidTimestamp,
+    /// The string, bin, array or map has more elements than the capacity of the collection it's
+    /// unpacked into.
+    CapacityExceeded,
+    /// A message reported by an implementation of [serde], such as a missing field.
+    ///
+    /// Without `alloc`, these are reported as [Error::NotImplemented].
+    #[cfg(feature = "alloc")]
+    Custom(::alloc::string::String),
+    /// The reader or the writer failed with the provided kind of I/O error.
+    #[cfg(feature = "std")]
+    Io(::std::io::ErrorKind),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt::Debug::fmt(self, f)
+        match self {
+            #[cfg(feature = "alloc")]
+            Error::Custom(msg) => f.write_str(msg),
+            _ => fmt::Debug::fmt(self, f),
+        }
     }
 }
 
 impl core::error::Error for Error {}
+
+#[cfg(feature = "std")]
+impl From<::std::io::Error> for Error {
+    fn from(e: ::std::io::Error) -> Self {
+        Error::Io(e.kind())
+    }
+}
+
+/// Serialization errors for the protocol implementation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PackError {
+    /// The length of a string, bin, array, map or extension is above `u32::MAX`, the largest the
+    /// protocol can represent.
+    TooLarge,
+}
+
+impl fmt::Display for PackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl core::error::Error for PackError {}
@@ -1,123 +1,112 @@
 use super::{
-    error::Error,
-    helpers::{take_buffer, take_buffer_iter, take_byte, take_byte_iter, take_num, take_num_iter},
-    Format, Packable, Unpackable,
+    error::Error, pack::too_large, unpack::unpack_ext, Format, PackError, Packable, Writer,
 };
+use core::time::Duration;
+
+#[cfg(feature = "alloc")]
+use super::{
+    helpers::{take_buffer_iter, take_byte_iter, take_num_iter},
+    skip_value, strict, Unpackable,
+};
+#[cfg(feature = "alloc")]
 use alloc::{vec, vec::Vec};
-use core::{iter, time::Duration};
 
-/// Custom extension definition as reference to a bytes source.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub enum Extension {
+/// Custom extension definition borrowing its data, available without `alloc`.
+///
+/// It's packed as [Extension] is, and unpacked from a buffer with [unpack_extension].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExtensionRef<'a> {
     /// n-bytes custom extension
-    Ext(i8, Vec<u8>),
+    Ext(i8, &'a [u8]),
     /// Protocol reserved extension to represent timestamps
     Timestamp(Duration),
 }
 
-impl Extension {
+impl ExtensionRef<'_> {
     /// Protocol constant for a timestamp extension
     pub const TIMESTAMP: i8 = -1;
 }
 
-impl Packable for Extension {
-    #[allow(unreachable_code)]
+impl Packable for ExtensionRef<'_> {
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
-        match self {
-            Extension::Ext(t, b) if b.len() == 1 => {
-                buf.extend(
-                    iter::once(Format::FIXEXT1)
-                        .chain(iter::once(*t as u8))
-                        .chain(iter::once(b[0])),
-                );
+        self.try_pack(buf).unwrap_or_else(too_large)
+    }
+
+    fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+    where
+        T: Writer,
+    {
+        let n = match *self {
+            ExtensionRef::Ext(t, b) if b.len() == 1 => {
+                buf.write_all(&[Format::FIXEXT1, t as u8, b[0]]);
                 3
             }
 
-            Extension::Ext(t, b) if b.len() == 2 => {
-                buf.extend(
-                    iter::once(Format::FIXEXT2)
-                        .chain(iter::once(*t as u8))
-                        .chain(b.iter().copied()),
-                );
+            ExtensionRef::Ext(t, b) if b.len() == 2 => {
+                buf.reserve(4);
+                buf.write_all(&[Format::FIXEXT2, t as u8]);
+                buf.write_all(b);
                 4
             }
 
-            Extension::Ext(t, b) if b.len() == 4 => {
-                buf.extend(
-                    iter::once(Format::FIXEXT4)
-                        .chain(iter::once(*t as u8))
-                        .chain(b.iter().copied()),
-                );
+            ExtensionRef::Ext(t, b) if b.len() == 4 => {
+                buf.reserve(6);
+                buf.write_all(&[Format::FIXEXT4, t as u8]);
+                buf.write_all(b);
                 6
             }
 
-            Extension::Ext(t, b) if b.len() == 8 => {
-                buf.extend(
-                    iter::once(Format::FIXEXT8)
-                        .chain(iter::once(*t as u8))
-                        .chain(b.iter().copied()),
-                );
+            ExtensionRef::Ext(t, b) if b.len() == 8 => {
+                buf.reserve(10);
+                buf.write_all(&[Format::FIXEXT8, t as u8]);
+                buf.write_all(b);
                 10
             }
 
-            Extension::Ext(t, b) if b.len() == 16 => {
-                buf.extend(
-                    iter::once(Format::FIXEXT16)
-                        .chain(iter::once(*t as u8))
-                        .chain(b.iter().copied()),
-                );
+            ExtensionRef::Ext(t, b) if b.len() == 16 => {
+                buf.reserve(18);
+                buf.write_all(&[Format::FIXEXT16, t as u8]);
+                buf.write_all(b);
                 18
             }
 
-            Extension::Ext(t, b) if b.len() <= u8::MAX as usize => {
-                buf.extend(
-                    iter::once(Format::EXT8)
-                        .chain(iter::once(b.len() as u8))
-                        .chain(iter::once(*t as u8))
-                        .chain(b.iter().copied()),
-                );
+            ExtensionRef::Ext(t, b) if b.len() <= u8::MAX as usize => {
+                buf.reserve(3 + b.len());
+                buf.write_all(&[Format::EXT8, b.len() as u8, t as u8]);
+                buf.write_all(b);
                 3 + b.len()
             }
 
-            Extension::Ext(t, b) if b.len() <= u16::MAX as usize => {
-                buf.extend(
-                    iter::once(Format::EXT16)
-                        .chain((b.len() as u16).to_be_bytes().iter().copied())
-                        .chain(iter::once(*t as u8))
-                        .chain(b.iter().copied()),
-                );
+            ExtensionRef::Ext(t, b) if b.len() <= u16::MAX as usize => {
+                let [l0, l1] = (b.len() as u16).to_be_bytes();
+                buf.reserve(4 + b.len());
+                buf.write_all(&[Format::EXT16, l0, l1, t as u8]);
+                buf.write_all(b);
                 4 + b.len()
             }
 
-            Extension::Ext(t, b) if b.len() <= u32::MAX as usize => {
-                buf.extend(
-                    iter::once(Format::EXT32)
-                        .chain((b.len() as u32).to_be_bytes().iter().copied())
-                        .chain(iter::once(*t as u8))
-                        .chain(b.iter().copied()),
-                );
+            ExtensionRef::Ext(t, b) if b.len() <= u32::MAX as usize => {
+                let [l0, l1, l2, l3] = (b.len() as u32).to_be_bytes();
+                buf.reserve(6 + b.len());
+                buf.write_all(&[Format::EXT32, l0, l1, l2, l3, t as u8]);
+                buf.write_all(b);
                 6 + b.len()
             }
 
-            Extension::Ext(_, _) => {
-                #[cfg(feature = "strict")]
-                panic!("strict serialization enabled; the buffer is too large");
-                0
-            }
+            ExtensionRef::Ext(_, _) => return Err(PackError::TooLarge),
 
-            Extension::Timestamp(d) if d.as_secs() <= u32::MAX as u64 && d.subsec_nanos() == 0 => {
-                buf.extend(
-                    iter::once(Format::FIXEXT4)
-                        .chain(iter::once(Self::TIMESTAMP as u8))
-                        .chain((d.as_secs() as u32).to_be_bytes().iter().copied()),
-                );
+            ExtensionRef::Timestamp(d)
+                if d.as_secs() <= u32::MAX as u64 && d.subsec_nanos() == 0 =>
+            {
+                let [s0, s1, s2, s3] = (d.as_secs() as u32).to_be_bytes();
+                buf.write_all(&[Format::FIXEXT4, Self::TIMESTAMP as u8, s0, s1, s2, s3]);
                 6
             }
 
-            Extension::Timestamp(d)
+            ExtensionRef::Timestamp(d)
                 if d.as_secs() < 1u64 << 34 && d.subsec_nanos() < 1u32 << 30 =>
             {
                 let secs = d.as_secs();
@@ -127,101 +116,136 @@ impl Packable for Extension {
                 let nanos = d.subsec_nanos() << 2;
                 let nanos = nanos | secs_nanos;
 
-                buf.extend(
-                    iter::once(Format::FIXEXT8)
-                        .chain(iter::once(Self::TIMESTAMP as u8))
-                        .chain(nanos.to_be_bytes().iter().copied())
-                        .chain(secs.to_be_bytes().iter().copied()),
-                );
+                let mut bytes = [
+                    Format::FIXEXT8,
+                    Self::TIMESTAMP as u8,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                ];
+                bytes[2..6].copy_from_slice(&nanos.to_be_bytes());
+                bytes[6..].copy_from_slice(&secs.to_be_bytes());
+                buf.write_all(&bytes);
                 10
             }
 
-            Extension::Timestamp(d) => {
-                buf.extend(
-                    iter::once(Format::EXT8)
-                        .chain(iter::once(12))
-                        .chain(iter::once(Self::TIMESTAMP as u8))
-                        .chain(d.subsec_nanos().to_be_bytes().iter().copied())
-                        .chain(d.as_secs().to_be_bytes().iter().copied()),
-                );
+            ExtensionRef::Timestamp(d) => {
+                let mut bytes = [0; 15];
+                bytes[..3].copy_from_slice(&[Format::EXT8, 12, Self::TIMESTAMP as u8]);
+                bytes[3..7].copy_from_slice(&d.subsec_nanos().to_be_bytes());
+                bytes[7..].copy_from_slice(&d.as_secs().to_be_bytes());
+                buf.write_all(&bytes);
                 15
             }
-        }
+        };
+        Ok(n)
     }
 }
 
-impl Unpackable for Extension {
-    type Error = Error;
+/// The duration of a timestamp, failing with [Error::InvalidExtension] if it overflows.
+///
+/// Nanoseconds of a billion or more carry into the seconds, as only the strict rules reject them.
+fn timestamp(secs: u64, nanos: u32) -> Result<Duration, Error> {
+    Duration::from_secs(secs)
+        .checked_add(Duration::from_nanos(nanos as u64))
+        .ok_or(Error::InvalidExtension)
+}
 
-    fn unpack(mut buf: &[u8]) -> Result<(usize, Self), Self::Error> {
-        let format = take_byte(&mut buf)?;
-        match format {
-            Format::FIXEXT1 => {
-                let t = take_byte(&mut buf)? as i8;
-                let x = take_byte(&mut buf)?;
-                Ok((3, Extension::Ext(t, vec![x])))
-            }
-            Format::FIXEXT2 => {
-                let t = take_byte(&mut buf)? as i8;
-                let b = take_buffer(&mut buf, 2)?;
-                Ok((4, Extension::Ext(t, b.to_vec())))
-            }
-            Format::FIXEXT4 => {
-                let t = take_byte(&mut buf)? as i8;
-                if t == Self::TIMESTAMP {
-                    let secs = take_num(&mut buf, u32::from_be_bytes)?;
-                    Ok((6, Extension::Timestamp(Duration::from_secs(secs as u64))))
-                } else {
-                    let b = take_buffer(&mut buf, 4)?;
-                    Ok((6, Extension::Ext(t, b.to_vec())))
-                }
-            }
-            Format::FIXEXT8 => {
-                let t = take_byte(&mut buf)? as i8;
-                if t == Self::TIMESTAMP {
-                    let data = take_num(&mut buf, u64::from_be_bytes)?;
+/// Unpacks an extension from the buffer, returning the borrowed extension and the amount of read
+/// bytes.
+pub fn unpack_extension(buf: &[u8]) -> Result<(usize, ExtensionRef<'_>), Error> {
+    let (n, t, data) = unpack_ext(buf)?;
+    // timestamps are packed as fixext 4, fixext 8, or ext 8 with 12 bytes, told apart by the
+    // length of the header
+    let ext = match (t, n - data.len(), data.len()) {
+        (ExtensionRef::TIMESTAMP, 2, 4) => {
+            let secs = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+            ExtensionRef::Timestamp(Duration::from_secs(secs as u64))
+        }
+        (ExtensionRef::TIMESTAMP, 2, 8) => {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(data);
+            let data = u64::from_be_bytes(bytes);
 
-                    let nanos = (data >> 34) as u32;
-                    let secs = data & ((1u64 << 34) - 1);
+            let nanos = (data >> 34) as u32;
+            let secs = data & ((1u64 << 34) - 1);
 
-                    Ok((10, Extension::Timestamp(Duration::new(secs, nanos))))
-                } else {
-                    let b = take_buffer(&mut buf, 8)?;
-                    Ok((10, Extension::Ext(t, b.to_vec())))
-                }
-            }
-            Format::FIXEXT16 => {
-                let t = take_byte(&mut buf)? as i8;
-                let b = take_buffer(&mut buf, 16)?;
-                Ok((18, Extension::Ext(t, b.to_vec())))
-            }
-            Format::EXT8 => {
-                let len = take_byte(&mut buf)? as usize;
-                let t = take_byte(&mut buf)? as i8;
-                if len == 12 && t == Self::TIMESTAMP {
-                    let nanos = take_num(&mut buf, u32::from_be_bytes)?;
-                    let secs = take_num(&mut buf, u64::from_be_bytes)?;
-                    Ok((15, Extension::Timestamp(Duration::new(secs, nanos))))
-                } else {
-                    let b = take_buffer(&mut buf, len)?;
-                    Ok((3 + len, Extension::Ext(t, b.to_vec())))
-                }
-            }
-            Format::EXT16 => {
-                let len = take_num(&mut buf, u16::from_be_bytes)? as usize;
-                let t = take_byte(&mut buf)? as i8;
-                let b = take_buffer(&mut buf, len)?;
-                Ok((4 + len, Extension::Ext(t, b.to_vec())))
-            }
-            Format::EXT32 => {
-                let len = take_num(&mut buf, u32::from_be_bytes)? as usize;
-                let t = take_byte(&mut buf)? as i8;
-                let b = take_buffer(&mut buf, len)?;
-                Ok((6 + len, Extension::Ext(t, b.to_vec())))
-            }
-            _ => Err(Error::InvalidExtension),
+            ExtensionRef::Timestamp(timestamp(secs, nanos)?)
+        }
+        (ExtensionRef::TIMESTAMP, 3, 12) => {
+            let nanos = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+            let mut secs = [0; 8];
+            secs.copy_from_slice(&data[4..]);
+            ExtensionRef::Timestamp(timestamp(u64::from_be_bytes(secs), nanos)?)
+        }
+        _ => ExtensionRef::Ext(t, data),
+    };
+    Ok((n, ext))
+}
+
+/// Custom extension definition as reference to a bytes source.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Extension {
+    /// n-bytes custom extension
+    Ext(i8, Vec<u8>),
+    /// Protocol reserved extension to represent timestamps
+    Timestamp(Duration),
+}
+
+#[cfg(feature = "alloc")]
+impl Extension {
+    /// Protocol constant for a timestamp extension
+    pub const TIMESTAMP: i8 = ExtensionRef::TIMESTAMP;
+
+    /// Borrows the extension.
+    pub fn as_borrowed(&self) -> ExtensionRef<'_> {
+        match self {
+            Extension::Ext(t, b) => ExtensionRef::Ext(*t, b),
+            Extension::Timestamp(d) => ExtensionRef::Timestamp(*d),
         }
     }
+}
+
+#[cfg(feature = "alloc")]
+impl From<ExtensionRef<'_>> for Extension {
+    fn from(ext: ExtensionRef<'_>) -> Self {
+        match ext {
+            ExtensionRef::Ext(t, b) => Extension::Ext(t, b.to_vec()),
+            ExtensionRef::Timestamp(d) => Extension::Timestamp(d),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Packable for Extension {
+    fn pack<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer,
+    {
+        self.as_borrowed().pack(buf)
+    }
+
+    fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+    where
+        T: Writer,
+    {
+        self.as_borrowed().try_pack(buf)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Unpackable for Extension {
+    type Error = Error;
+
+    fn unpack(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+        unpack_extension(buf).map(|(n, ext)| (n, ext.into()))
+    }
 
     fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
     where
@@ -258,7 +282,7 @@ impl Unpackable for Extension {
                     let nanos = (data >> 34) as u32;
                     let secs = data & ((1u64 << 34) - 1);
 
-                    Ok((10, Extension::Timestamp(Duration::new(secs, nanos))))
+                    Ok((10, Extension::Timestamp(timestamp(secs, nanos)?)))
                 } else {
                     let b = take_buffer_iter(bytes.by_ref(), 8)?;
                     Ok((10, Extension::Ext(t, b)))
@@ -275,7 +299,7 @@ impl Unpackable for Extension {
                 if len == 12 && t == Self::TIMESTAMP {
                     let nanos = take_num_iter(bytes.by_ref(), u32::from_be_bytes)?;
                     let secs = take_num_iter(bytes.by_ref(), u64::from_be_bytes)?;
-                    Ok((15, Extension::Timestamp(Duration::new(secs, nanos))))
+                    Ok((15, Extension::Timestamp(timestamp(secs, nanos)?)))
                 } else {
                     let b = take_buffer_iter(bytes.by_ref(), len)?;
                     Ok((3 + len, Extension::Ext(t, b)))
@@ -296,9 +320,15 @@ impl Unpackable for Extension {
             _ => Err(Error::InvalidExtension),
         }
     }
+
+    fn unpack_strict(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+        // checked first, as invalid nanoseconds overflow into the seconds
+        strict::check(&buf[..skip_value(buf)?])?;
+        Self::unpack(buf)
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "alloc"))]
 mod tests {
     use super::*;
     use proptest::prelude::*;
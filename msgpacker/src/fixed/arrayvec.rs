@@ -0,0 +1,91 @@
+use core::str;
+
+use ::arrayvec::{ArrayString, ArrayVec};
+
+use super::{take_fixed_iter, FixedCapacity};
+use crate::{
+    unpack::binary::{unpack_bytes_len_iter, unpack_str_len_iter},
+    unpack_bytes, unpack_str, Error, PackError, Packable, Unpackable, Writer,
+};
+
+impl<T, const CAP: usize> FixedCapacity<T> for ArrayVec<T, CAP> {
+    fn try_push(&mut self, value: T) -> Result<(), Error> {
+        ArrayVec::try_push(self, value).map_err(|_| Error::CapacityExceeded)
+    }
+}
+
+impl<const CAP: usize> Packable for ArrayVec<u8, CAP> {
+    fn pack<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer,
+    {
+        self.as_slice().pack(buf)
+    }
+
+    fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+    where
+        T: Writer,
+    {
+        self.as_slice().try_pack(buf)
+    }
+}
+
+impl<const CAP: usize> Unpackable for ArrayVec<u8, CAP> {
+    type Error = Error;
+
+    fn unpack(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+        let (n, b) = unpack_bytes(buf)?;
+        let mut v = Self::new();
+        v.try_extend_from_slice(b)
+            .map_err(|_| Error::CapacityExceeded)?;
+        Ok((n, v))
+    }
+
+    fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        let mut bytes = bytes.into_iter();
+        let (n, len) = unpack_bytes_len_iter(bytes.by_ref())?;
+        Ok((n + len, take_fixed_iter(bytes.by_ref(), len)?))
+    }
+}
+
+impl<const CAP: usize> Packable for ArrayString<CAP> {
+    fn pack<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer,
+    {
+        self.as_str().pack(buf)
+    }
+
+    fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+    where
+        T: Writer,
+    {
+        self.as_str().try_pack(buf)
+    }
+}
+
+impl<const CAP: usize> Unpackable for ArrayString<CAP> {
+    type Error = Error;
+
+    fn unpack(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+        let (n, s) = unpack_str(buf)?;
+        let s = ArrayString::from(s).map_err(|_| Error::CapacityExceeded)?;
+        Ok((n, s))
+    }
+
+    fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        let mut bytes = bytes.into_iter();
+        let (n, len) = unpack_str_len_iter(bytes.by_ref())?;
+        let v: ArrayVec<u8, CAP> = take_fixed_iter(bytes.by_ref(), len)?;
+        let s = str::from_utf8(&v).map_err(|_| Error::InvalidUtf8)?;
+        // the string has the capacity of the bytes
+        let s = ArrayString::from(s).map_err(|_| Error::CapacityExceeded)?;
+        Ok((n + len, s))
+    }
+}
@@ -0,0 +1,198 @@
+use core::hash::{BuildHasher, Hash};
+
+use ::heapless::{IndexMap, LenType, String, Vec};
+
+use super::{take_fixed_iter, unpack_map_fixed, unpack_map_fixed_iter, FixedCapacity};
+use crate::{
+    pack::too_large,
+    try_pack_map_len,
+    unpack::binary::{unpack_bytes_len_iter, unpack_str_len_iter},
+    unpack_bytes, unpack_str, Error, PackError, Packable, Unpackable, Writer,
+};
+
+impl<T, LenT, const N: usize> FixedCapacity<T> for Vec<T, N, LenT>
+where
+    LenT: LenType,
+{
+    fn try_push(&mut self, value: T) -> Result<(), Error> {
+        self.push(value).map_err(|_| Error::CapacityExceeded)
+    }
+}
+
+impl<K, V, S, const N: usize> FixedCapacity<(K, V)> for IndexMap<K, V, S, N>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn try_push(&mut self, (k, v): (K, V)) -> Result<(), Error> {
+        self.insert(k, v)
+            .map(drop)
+            .map_err(|_| Error::CapacityExceeded)
+    }
+}
+
+impl<LenT, const N: usize> Packable for Vec<u8, N, LenT>
+where
+    LenT: LenType,
+{
+    fn pack<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer,
+    {
+        self.as_slice().pack(buf)
+    }
+
+    fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+    where
+        T: Writer,
+    {
+        self.as_slice().try_pack(buf)
+    }
+}
+
+impl<LenT, const N: usize> Unpackable for Vec<u8, N, LenT>
+where
+    LenT: LenType,
+{
+    type Error = Error;
+
+    fn unpack(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+        let (n, b) = unpack_bytes(buf)?;
+        let mut v = Self::new();
+        v.extend_from_slice(b)
+            .map_err(|_| Error::CapacityExceeded)?;
+        Ok((n, v))
+    }
+
+    fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        let mut bytes = bytes.into_iter();
+        let (n, len) = unpack_bytes_len_iter(bytes.by_ref())?;
+        Ok((n + len, take_fixed_iter(bytes.by_ref(), len)?))
+    }
+}
+
+impl<LenT, const N: usize> Packable for String<N, LenT>
+where
+    LenT: LenType,
+{
+    fn pack<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer,
+    {
+        self.as_str().pack(buf)
+    }
+
+    fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+    where
+        T: Writer,
+    {
+        self.as_str().try_pack(buf)
+    }
+}
+
+impl<LenT, const N: usize> Unpackable for String<N, LenT>
+where
+    LenT: LenType,
+{
+    type Error = Error;
+
+    fn unpack(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+        let (n, s) = unpack_str(buf)?;
+        let mut string = Self::new();
+        string.push_str(s).map_err(|_| Error::CapacityExceeded)?;
+        Ok((n, string))
+    }
+
+    fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        let mut bytes = bytes.into_iter();
+        let (n, len) = unpack_str_len_iter(bytes.by_ref())?;
+        let v = take_fixed_iter(bytes.by_ref(), len)?;
+        let s = String::from_utf8(v).map_err(|_| Error::InvalidUtf8)?;
+        Ok((n + len, s))
+    }
+}
+
+impl<K, V, S, const N: usize> Packable for IndexMap<K, V, S, N>
+where
+    K: Packable,
+    V: Packable,
+{
+    const SELF_DELIMITING: bool = K::SELF_DELIMITING && V::SELF_DELIMITING;
+
+    fn pack<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer,
+    {
+        match try_pack_map_len(buf, self.len()) {
+            Ok(n) => {
+                n + self
+                    .iter()
+                    .map(|(k, v)| k.pack(buf) + v.pack(buf))
+                    .sum::<usize>()
+            }
+            Err(e) => too_large(e),
+        }
+    }
+
+    fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+    where
+        T: Writer,
+    {
+        let mut n = try_pack_map_len(buf, self.len())?;
+        for (k, v) in self.iter() {
+            n += k.try_pack(buf)?;
+            n += v.try_pack(buf)?;
+        }
+        Ok(n)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+    where
+        T: Writer,
+    {
+        use crate::canonical;
+
+        let entries = self
+            .iter()
+            .map(|(k, v)| canonical::entry(k, |buf| v.try_pack_canonical(buf)))
+            .collect::<Result<_, Error>>()?;
+        canonical::pack_entries(buf, entries)
+    }
+}
+
+impl<K, V, S, const N: usize> Unpackable for IndexMap<K, V, S, N>
+where
+    K: Unpackable + Eq + Hash,
+    V: Unpackable,
+    <V as Unpackable>::Error: From<<K as Unpackable>::Error>,
+    S: BuildHasher + Default,
+{
+    type Error = <V as Unpackable>::Error;
+
+    fn unpack(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+        unpack_map_fixed(buf)
+    }
+
+    fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        unpack_map_fixed_iter(bytes)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn unpack_strict(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+        let (n, map) = crate::strict::unpack_map_fixed::<K, V, Self>(buf)?;
+        if map.len() != crate::unpack_map_len(buf)?.1 {
+            return Err(Error::DuplicateKey.into());
+        }
+        Ok((n, map))
+    }
+}
@@ -0,0 +1,126 @@
+use super::{
+    helpers::take_byte_iter,
+    unpack::collections::{unpack_array_len_iter, unpack_map_len_iter},
+    unpack_array_len, unpack_map_len, Error, Unpackable,
+};
+
+#[cfg(feature = "arrayvec")]
+mod arrayvec;
+#[cfg(feature = "heapless")]
+mod heapless;
+
+/// A collection of a fixed capacity, such as the ones of `heapless` and `arrayvec`, that values
+/// are unpacked into without allocating.
+///
+/// Unpacking into a full collection fails with [Error::CapacityExceeded], where collecting it
+/// with [FromIterator] would panic.
+pub trait FixedCapacity<V>: Default {
+    /// Adds the value, failing with [Error::CapacityExceeded] if the collection is full.
+    fn try_push(&mut self, value: V) -> Result<(), Error>;
+}
+
+/// Unpacks an array from the buffer into a collection of a fixed capacity, returning the
+/// amount of read bytes.
+///
+/// Fails with [Error::CapacityExceeded] if the array has more elements than the collection holds.
+pub fn unpack_array_fixed<V, C>(mut buf: &[u8]) -> Result<(usize, C), <V as Unpackable>::Error>
+where
+    V: Unpackable,
+    C: FixedCapacity<V>,
+{
+    let (mut n, len) = unpack_array_len(buf)?;
+    buf = &buf[n..];
+    let mut array = C::default();
+    for _ in 0..len {
+        let (count, v) = V::unpack(buf)?;
+        buf = &buf[count..];
+        n += count;
+        array.try_push(v)?;
+    }
+    Ok((n, array))
+}
+
+/// Unpacks an array from the iterator into a collection of a fixed capacity, returning the
+/// amount of read bytes.
+///
+/// Fails with [Error::CapacityExceeded] if the array has more elements than the collection holds.
+pub fn unpack_array_fixed_iter<I, V, C>(iter: I) -> Result<(usize, C), <V as Unpackable>::Error>
+where
+    I: IntoIterator<Item = u8>,
+    V: Unpackable,
+    C: FixedCapacity<V>,
+{
+    let mut bytes = iter.into_iter();
+    let (mut n, len) = unpack_array_len_iter(bytes.by_ref())?;
+    let mut array = C::default();
+    for _ in 0..len {
+        let (count, v) = V::unpack_iter(bytes.by_ref())?;
+        n += count;
+        array.try_push(v)?;
+    }
+    Ok((n, array))
+}
+
+/// Unpacks a map from the buffer into a collection of a fixed capacity, returning the amount of
+/// read bytes.
+///
+/// Fails with [Error::CapacityExceeded] if the map has more entries than the collection holds.
+pub fn unpack_map_fixed<K, V, C>(mut buf: &[u8]) -> Result<(usize, C), <V as Unpackable>::Error>
+where
+    K: Unpackable,
+    V: Unpackable,
+    <V as Unpackable>::Error: From<<K as Unpackable>::Error>,
+    C: FixedCapacity<(K, V)>,
+{
+    let (mut n, len) = unpack_map_len(buf)?;
+    buf = &buf[n..];
+    let mut map = C::default();
+    for _ in 0..len {
+        let (count, k) = K::unpack(buf)?;
+        buf = &buf[count..];
+        n += count;
+        let (count, v) = V::unpack(buf)?;
+        buf = &buf[count..];
+        n += count;
+        map.try_push((k, v))?;
+    }
+    Ok((n, map))
+}
+
+/// Unpacks a map from the iterator into a collection of a fixed capacity, returning the amount
+/// of read bytes.
+///
+/// Fails with [Error::CapacityExceeded] if the map has more entries than the collection holds.
+pub fn unpack_map_fixed_iter<I, K, V, C>(iter: I) -> Result<(usize, C), <V as Unpackable>::Error>
+where
+    I: IntoIterator<Item = u8>,
+    K: Unpackable,
+    V: Unpackable,
+    <V as Unpackable>::Error: From<<K as Unpackable>::Error>,
+    C: FixedCapacity<(K, V)>,
+{
+    let mut bytes = iter.into_iter();
+    let (mut n, len) = unpack_map_len_iter(bytes.by_ref())?;
+    let mut map = C::default();
+    for _ in 0..len {
+        let (count, k) = K::unpack_iter(bytes.by_ref())?;
+        n += count;
+        let (count, v) = V::unpack_iter(bytes.by_ref())?;
+        n += count;
+        map.try_push((k, v))?;
+    }
+    Ok((n, map))
+}
+
+/// Takes `len` bytes from the iterator into a collection of a fixed capacity.
+fn take_fixed_iter<I, C>(bytes: &mut I, len: usize) -> Result<C, Error>
+where
+    I: Iterator<Item = u8>,
+    C: FixedCapacity<u8>,
+{
+    let mut buf = C::default();
+    for _ in 0..len {
+        buf.try_push(take_byte_iter(bytes.by_ref())?)?;
+    }
+    Ok(buf)
+}
@@ -25,10 +25,6 @@ impl Format {
     pub const ARRAY32: u8 = 0xdd;
     pub const MAP16: u8 = 0xde;
     pub const MAP32: u8 = 0xdf;
-}
-
-#[cfg(feature = "alloc")]
-impl Format {
     pub const FIXEXT1: u8 = 0xd4;
     pub const FIXEXT2: u8 = 0xd5;
     pub const FIXEXT4: u8 = 0xd6;
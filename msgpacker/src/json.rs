@@ -0,0 +1,500 @@
+//! Transcoding between msgpack and JSON text, without building an intermediate tree.
+//!
+//! [to_writer] walks the msgpack markers and writes the JSON text of the value as it goes, and
+//! [from_reader] packs the JSON values of a reader as they're parsed. The representation of the
+//! msgpack types that JSON lacks is configured with a [Transcoder].
+//!
+//! ```rust
+//! use msgpacker::json::{self, BinPolicy, Transcoder};
+//! use msgpacker::prelude::*;
+//! use std::collections::BTreeMap;
+//!
+//! let mut map = BTreeMap::new();
+//! map.insert("id".to_string(), vec![0xca, 0xfe]);
+//! let bytes = map.pack_to_vec();
+//!
+//! assert_eq!(json::to_string(&bytes).unwrap(), r#"{"id":"yv4="}"#);
+//!
+//! let json = Transcoder::new()
+//!     .with_bin(BinPolicy::Array)
+//!     .to_string(&bytes)
+//!     .unwrap();
+//! assert_eq!(json, r#"{"id":[202,254]}"#);
+//!
+//! let bytes = json::from_str(r#"{"id":[202,254],"tags":["a",null]}"#).unwrap();
+//! assert_eq!(json::to_string(&bytes).unwrap(), r#"{"id":[202,254],"tags":["a",null]}"#);
+//! ```
+
+use std::{
+    io::{self, Write},
+    string::String,
+    vec::Vec,
+};
+
+use serde::de::{self, DeserializeSeed as _};
+
+use crate::{
+    format::Format,
+    pack_array_len, pack_map_len,
+    unpack::{is_ext, unpack_ext},
+    unpack_array_len, unpack_bytes, unpack_map_len, unpack_str, Error, Packable as _,
+    Unpackable as _, Writer,
+};
+
+/// The JSON representation of bin values.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinPolicy {
+    /// A string of the standard base64 encoding, with padding.
+    #[default]
+    Base64,
+    /// An array of the bytes as numbers.
+    Array,
+}
+
+/// The JSON representation of extension values.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExtPolicy {
+    /// An object of the type and the data, such as `{"type":-1,"data":"AAAAAA=="}`.
+    #[default]
+    Object,
+    /// An array of the type and the data, such as `[-1,"AAAAAA=="]`.
+    Array,
+    /// Fails with [Error::Unrepresentable].
+    Error,
+}
+
+/// The JSON representation of map keys that aren't strings.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyPolicy {
+    /// Writes nil, booleans, numbers and base64 bins as the string of their JSON text, such as
+    /// `"1"`. Arrays, maps and extensions fail with [Error::Unrepresentable].
+    #[default]
+    Stringify,
+    /// Fails with [Error::Unrepresentable].
+    Error,
+}
+
+/// The JSON representation of NaN and infinite floats.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NonFinitePolicy {
+    /// Writes `null`, as `serde_json` does.
+    #[default]
+    Null,
+    /// Writes the strings `"NaN"`, `"Infinity"` and `"-Infinity"`.
+    String,
+    /// Fails with [Error::Unrepresentable].
+    Error,
+}
+
+/// The options of the transcoding of msgpack into JSON.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Transcoder {
+    bin: BinPolicy,
+    ext: ExtPolicy,
+    keys: KeyPolicy,
+    non_finite: NonFinitePolicy,
+}
+
+/// An array or a map being written, with the amount of values left, counting keys and values
+/// separately for maps.
+struct Frame {
+    map: bool,
+    len: usize,
+    left: usize,
+}
+
+impl Transcoder {
+    /// Creates a transcoder with the default policies.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the representation of bin values.
+    pub fn with_bin(mut self, bin: BinPolicy) -> Self {
+        self.bin = bin;
+        self
+    }
+
+    /// Sets the representation of extension values.
+    pub fn with_ext(mut self, ext: ExtPolicy) -> Self {
+        self.ext = ext;
+        self
+    }
+
+    /// Sets the representation of map keys that aren't strings.
+    pub fn with_keys(mut self, keys: KeyPolicy) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    /// Sets the representation of NaN and infinite floats.
+    pub fn with_non_finite(mut self, non_finite: NonFinitePolicy) -> Self {
+        self.non_finite = non_finite;
+        self
+    }
+
+    /// Writes the first msgpack value of the buffer as JSON text, returning the amount of read
+    /// bytes.
+    ///
+    /// Nested arrays and maps are tracked without recursion, so deep values can't overflow the
+    /// stack.
+    pub fn to_writer<W>(&self, buf: &[u8], mut writer: W) -> Result<usize, Error>
+    where
+        W: Write,
+    {
+        let mut rest = buf;
+        let mut stack: Vec<Frame> = Vec::new();
+
+        loop {
+            let key = match stack.last_mut() {
+                Some(frame) => {
+                    let i = frame.len - frame.left;
+                    frame.left -= 1;
+                    let key = frame.map && i % 2 == 0;
+                    if i > 0 {
+                        writer.write_all(if key || !frame.map { b"," } else { b":" })?;
+                    }
+                    key
+                }
+                None => false,
+            };
+            if key && !self.is_key(rest)? {
+                return Err(Error::Unrepresentable);
+            }
+
+            let format = *rest.first().ok_or(Error::BufferTooShort)?;
+            let n = match format {
+                Format::NIL => {
+                    quoted(&mut writer, key, |w| w.write_all(b"null"))?;
+                    1
+                }
+                Format::TRUE | Format::FALSE => {
+                    let (n, v) = bool::unpack(rest)?;
+                    quoted(&mut writer, key, |w| write!(w, "{v}"))?;
+                    n
+                }
+                0x00..=Format::POSITIVE_FIXINT
+                | Format::UINT8
+                | Format::UINT16
+                | Format::UINT32
+                | Format::UINT64 => {
+                    let (n, v) = u64::unpack(rest)?;
+                    quoted(&mut writer, key, |w| write!(w, "{v}"))?;
+                    n
+                }
+                0xe0.. | Format::INT8 | Format::INT16 | Format::INT32 | Format::INT64 => {
+                    let (n, v) = i64::unpack(rest)?;
+                    quoted(&mut writer, key, |w| write!(w, "{v}"))?;
+                    n
+                }
+                Format::FLOAT32 => {
+                    let (n, v) = f32::unpack(rest)?;
+                    self.float(&mut writer, key, v as f64, &v)?;
+                    n
+                }
+                Format::FLOAT64 => {
+                    let (n, v) = f64::unpack(rest)?;
+                    self.float(&mut writer, key, v, &v)?;
+                    n
+                }
+                0xa0..=0xbf | Format::STR8 | Format::STR16 | Format::STR32 => {
+                    let (n, v) = unpack_str(rest)?;
+                    serde_json::to_writer(&mut writer, v).map_err(json_error)?;
+                    n
+                }
+                Format::BIN8 | Format::BIN16 | Format::BIN32 => {
+                    let (n, v) = unpack_bytes(rest)?;
+                    self.bin(&mut writer, v)?;
+                    n
+                }
+                0x90..=0x9f | Format::ARRAY16 | Format::ARRAY32 => {
+                    let (n, len) = unpack_array_len(rest)?;
+                    writer.write_all(b"[")?;
+                    stack.push(Frame {
+                        map: false,
+                        len,
+                        left: len,
+                    });
+                    n
+                }
+                0x80..=0x8f | Format::MAP16 | Format::MAP32 => {
+                    let (n, len) = unpack_map_len(rest)?;
+                    let len = len.checked_mul(2).ok_or(Error::BufferTooShort)?;
+                    writer.write_all(b"{")?;
+                    stack.push(Frame {
+                        map: true,
+                        len,
+                        left: len,
+                    });
+                    n
+                }
+                _ if is_ext(format) => {
+                    let (n, t, data) = unpack_ext(rest)?;
+                    self.ext(&mut writer, t, data)?;
+                    n
+                }
+                _ => return Err(Error::UnexpectedFormatTag),
+            };
+            rest = &rest[n..];
+
+            while let Some(frame) = stack.last() {
+                if frame.left > 0 {
+                    break;
+                }
+                writer.write_all(if frame.map { b"}" } else { b"]" })?;
+                stack.pop();
+            }
+            if stack.is_empty() {
+                return Ok(buf.len() - rest.len());
+            }
+        }
+    }
+
+    /// Writes the msgpack value of the buffer as a string of JSON text, failing with
+    /// [Error::TrailingBytes] if bytes are left after it.
+    pub fn to_string(&self, buf: &[u8]) -> Result<String, Error> {
+        let mut json = Vec::new();
+        if self.to_writer(buf, &mut json)? != buf.len() {
+            return Err(Error::TrailingBytes);
+        }
+        String::from_utf8(json).map_err(|_| Error::InvalidUtf8)
+    }
+
+    /// Whether the next value can be written as a JSON object key under the key policy.
+    fn is_key(&self, buf: &[u8]) -> Result<bool, Error> {
+        let format = *buf.first().ok_or(Error::BufferTooShort)?;
+        Ok(match format {
+            0xa0..=0xbf | Format::STR8 | Format::STR16 | Format::STR32 => true,
+            _ if self.keys == KeyPolicy::Error => false,
+            0x90..=0x9f | Format::ARRAY16 | Format::ARRAY32 => false,
+            0x80..=0x8f | Format::MAP16 | Format::MAP32 => false,
+            Format::BIN8 | Format::BIN16 | Format::BIN32 => self.bin == BinPolicy::Base64,
+            _ => !is_ext(format),
+        })
+    }
+
+    /// Writes a float, or the representation of the non-finite policy.
+    fn float<W, F>(&self, w: &mut W, key: bool, v: f64, f: &F) -> Result<(), Error>
+    where
+        W: Write,
+        F: serde::Serialize,
+    {
+        let text = match self.non_finite {
+            _ if v.is_finite() => {
+                return quoted(w, key, |w| {
+                    serde_json::to_writer(w, f).map_err(io::Error::from)
+                });
+            }
+            NonFinitePolicy::Null => return quoted(w, key, |w| w.write_all(b"null")),
+            NonFinitePolicy::String if v.is_nan() => "NaN",
+            NonFinitePolicy::String if v > 0.0 => "Infinity",
+            NonFinitePolicy::String => "-Infinity",
+            NonFinitePolicy::Error => return Err(Error::Unrepresentable),
+        };
+        Ok(write!(w, "\"{text}\"")?)
+    }
+
+    fn bin<W: Write>(&self, w: &mut W, data: &[u8]) -> io::Result<()> {
+        match self.bin {
+            BinPolicy::Base64 => write_base64(w, data),
+            BinPolicy::Array => {
+                w.write_all(b"[")?;
+                for (i, b) in data.iter().enumerate() {
+                    if i > 0 {
+                        w.write_all(b",")?;
+                    }
+                    write!(w, "{b}")?;
+                }
+                w.write_all(b"]")
+            }
+        }
+    }
+
+    fn ext<W: Write>(&self, w: &mut W, t: i8, data: &[u8]) -> Result<(), Error> {
+        match self.ext {
+            ExtPolicy::Object => write!(w, "{{\"type\":{t},\"data\":")?,
+            ExtPolicy::Array => write!(w, "[{t},")?,
+            ExtPolicy::Error => return Err(Error::Unrepresentable),
+        }
+        self.bin(w, data)?;
+        w.write_all(if self.ext == ExtPolicy::Object {
+            b"}"
+        } else {
+            b"]"
+        })?;
+        Ok(())
+    }
+}
+
+/// Writes the JSON text, quoted as a string if it's a map key.
+fn quoted<W, F>(w: &mut W, key: bool, f: F) -> Result<(), Error>
+where
+    W: Write,
+    F: FnOnce(&mut W) -> io::Result<()>,
+{
+    if key {
+        w.write_all(b"\"")?;
+    }
+    f(w)?;
+    if key {
+        w.write_all(b"\"")?;
+    }
+    Ok(())
+}
+
+fn write_base64<W: Write>(w: &mut W, data: &[u8]) -> io::Result<()> {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    w.write_all(b"\"")?;
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        let mut out = [b'='; 4];
+        for (i, c) in out.iter_mut().enumerate().take(chunk.len() + 1) {
+            *c = ALPHABET[(n >> (18 - 6 * i)) as usize & 63];
+        }
+        w.write_all(&out)?;
+    }
+    w.write_all(b"\"")
+}
+
+fn json_error(e: serde_json::Error) -> Error {
+    if e.is_io() {
+        Error::Io(io::Error::from(e).kind())
+    } else {
+        Error::Custom(e.to_string())
+    }
+}
+
+/// Packs the JSON values that serde_json visits into the buffer, returning the amount of
+/// written bytes.
+///
+/// Arrays and objects are buffered until their length is known.
+struct Transcode<'a, X>(&'a mut X);
+
+impl<'de, X> de::DeserializeSeed<'de> for Transcode<'_, X>
+where
+    X: Writer,
+{
+    type Value = usize;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, X> de::Visitor<'de> for Transcode<'_, X>
+where
+    X: Writer,
+{
+    type Value = usize;
+
+    fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(().pack(self.0))
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(v.pack(self.0))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(v.pack(self.0))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        if v >= 0 {
+            Ok((v as u64).pack(self.0))
+        } else {
+            Ok(v.pack(self.0))
+        }
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(v.pack(self.0))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(v.pack(self.0))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut items = Vec::new();
+        let mut len = 0;
+        while seq.next_element_seed(Transcode(&mut items))?.is_some() {
+            len += 1;
+        }
+        let n = pack_array_len(self.0, len);
+        self.0.write_all(&items);
+        Ok(n + items.len())
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let mut entries = Vec::new();
+        let mut len = 0;
+        while map.next_key_seed(Transcode(&mut entries))?.is_some() {
+            map.next_value_seed(Transcode(&mut entries))?;
+            len += 1;
+        }
+        let n = pack_map_len(self.0, len);
+        self.0.write_all(&entries);
+        Ok(n + entries.len())
+    }
+}
+
+/// Writes the first msgpack value of the buffer as JSON text with the default [Transcoder],
+/// returning the amount of read bytes.
+pub fn to_writer<W>(buf: &[u8], writer: W) -> Result<usize, Error>
+where
+    W: Write,
+{
+    Transcoder::new().to_writer(buf, writer)
+}
+
+/// Writes the msgpack value of the buffer as a string of JSON text with the default
+/// [Transcoder].
+pub fn to_string(buf: &[u8]) -> Result<String, Error> {
+    Transcoder::new().to_string(buf)
+}
+
+/// Packs the JSON value of the reader into the buffer, returning the amount of written bytes.
+///
+/// Integers are packed with their smallest format, other numbers as `f64`, and objects as maps
+/// keyed by strings. Only whitespace may follow the value.
+pub fn from_reader<R, X>(reader: R, buf: &mut X) -> Result<usize, Error>
+where
+    R: io::Read,
+    X: Writer,
+{
+    let mut de = serde_json::Deserializer::from_reader(reader);
+    let n = Transcode(buf).deserialize(&mut de).map_err(json_error)?;
+    de.end().map_err(json_error)?;
+    Ok(n)
+}
+
+/// Packs the JSON value of the string into a vector.
+pub fn from_str(json: &str) -> Result<Vec<u8>, Error> {
+    let mut buf = Vec::new();
+    let mut de = serde_json::Deserializer::from_str(json);
+    Transcode(&mut buf)
+        .deserialize(&mut de)
+        .map_err(json_error)?;
+    de.end().map_err(json_error)?;
+    Ok(buf)
+}
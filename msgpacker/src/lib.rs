@@ -6,25 +6,71 @@
 extern crate alloc;
 
 #[cfg(feature = "alloc")]
-mod extension;
+pub mod canonical;
+#[cfg(feature = "alloc")]
+pub mod strict;
 
+mod entries;
 mod error;
+mod extension;
+#[cfg(any(feature = "arrayvec", feature = "heapless"))]
+mod fixed;
 mod format;
 mod helpers;
+mod numeric;
 pub(crate) mod pack;
+mod raw;
+#[cfg(feature = "bytes")]
+mod shared;
 pub(crate) mod unpack;
+mod writer;
 
 #[cfg(feature = "serde")]
 pub mod serde;
 
-pub use error::Error;
+#[cfg(feature = "json")]
+pub mod json;
+
+pub use entries::MapEntries;
+#[doc(hidden)]
+pub use entries::{concat_keys, has_key, share_key};
+pub use error::{Error, PackError};
+pub use extension::{unpack_extension, ExtensionRef};
 use format::Format;
-pub use pack::{pack_array, pack_map};
-pub use unpack::{unpack_array, unpack_array_iter, unpack_map, unpack_map_iter};
+pub use numeric::Numeric;
+pub use pack::{
+    pack_array, pack_array_len, pack_f32_compact, pack_f64_compact, pack_map, pack_map_len,
+    pack_numbers, try_pack_array, try_pack_array_len, try_pack_map, try_pack_map_len,
+    try_pack_numbers,
+};
+pub use raw::{unpack_raw, Raw};
+pub use unpack::{
+    skip_value, unpack_array, unpack_array_iter, unpack_array_len, unpack_bytes, unpack_map,
+    unpack_map_iter, unpack_map_len, unpack_str, FloatOptions,
+};
+pub use writer::{ExtendWriter, Writer};
+
+#[cfg(feature = "std")]
+pub use writer::IoWriter;
+
+#[cfg(feature = "alloc")]
+pub use unpack::{take_value_iter, unpack_numbers};
 
 #[cfg(feature = "alloc")]
 pub use extension::Extension;
 
+#[cfg(feature = "alloc")]
+pub use raw::RawBuf;
+
+#[cfg(any(feature = "arrayvec", feature = "heapless"))]
+pub use fixed::{
+    unpack_array_fixed, unpack_array_fixed_iter, unpack_map_fixed, unpack_map_fixed_iter,
+    FixedCapacity,
+};
+
+#[cfg(feature = "bytes")]
+pub use shared::{unpack_from_bytes, ByteStr};
+
 #[cfg(feature = "alloc")]
 use alloc::vec::Vec;
 
@@ -40,12 +86,30 @@ where
     value.pack_to_vec()
 }
 
+/// Packs the provided packable value into a vector, failing if any of its parts is too large for
+/// the protocol.
+#[cfg(feature = "alloc")]
+pub fn try_pack_to_vec<T>(value: &T) -> Result<Vec<u8>, PackError>
+where
+    T: Packable,
+{
+    value.try_pack_to_vec()
+}
+
 /// A packable type.
 pub trait Packable {
-    /// Pack a value into the extendable buffer, returning the amount of written bytes.
+    /// Whether the value is packed as a single msgpack value, so it can be skipped over without
+    /// knowing its type.
+    ///
+    /// Tuples, arrays and the structs and enums the derive packs as their fields in sequence
+    /// are not, and neither are the collections of them. The derive rejects them where enums
+    /// tagged by name, or untagged, need to skip over the values. Defaults to `true`.
+    const SELF_DELIMITING: bool = true;
+
+    /// Pack a value into the buffer, returning the amount of written bytes.
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>;
+        T: Writer;
 
     /// Packs the value into a vector of bytes.
     #[cfg(feature = "alloc")]
@@ -56,30 +120,157 @@ pub trait Packable {
 
         bytes
     }
+
+    /// Packs a value into the buffer, returning the amount of written bytes, or
+    /// [PackError::TooLarge] if a string, bin, array, map or extension is longer than `u32::MAX`.
+    ///
+    /// [Packable::pack] writes nothing for such a part, or panics with the `strict` feature. On
+    /// failure, the parts packed before it are left in the buffer. The default implementation
+    /// calls [Packable::pack], so it needs to be overridden only by the types holding such parts.
+    fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+    where
+        T: Writer,
+    {
+        Ok(self.pack(buf))
+    }
+
+    /// Packs the value into a vector of bytes, failing as [Packable::try_pack].
+    #[cfg(feature = "alloc")]
+    fn try_pack_to_vec(&self) -> Result<Vec<u8>, PackError> {
+        let mut bytes = Vec::new();
+
+        self.try_pack(&mut bytes)?;
+
+        Ok(bytes)
+    }
+
+    /// Packs the value in canonical form, returning the amount of written bytes.
+    ///
+    /// Equal values are packed into identical bytes; [canonical] lists the rules. A value with no
+    /// canonical form, such as a map with two keys of the same canonical bytes, is packed as
+    /// [Packable::pack] does, or panics with the `strict` feature; [Packable::try_pack_canonical]
+    /// reports it instead. The default implementation calls [Packable::try_pack_canonical].
+    #[cfg(feature = "alloc")]
+    fn pack_canonical<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer,
+    {
+        let mut bytes = Vec::new();
+        match self.try_pack_canonical(&mut bytes) {
+            Ok(n) => {
+                buf.write_all(&bytes);
+                n
+            }
+            #[cfg(feature = "strict")]
+            Err(e) => panic!("strict serialization enabled; the value has no canonical form: {e}"),
+            #[cfg(not(feature = "strict"))]
+            Err(_) => self.pack(buf),
+        }
+    }
+
+    /// Packs the value in canonical form as [Packable::pack_canonical], returning the amount of
+    /// written bytes, or [Error::DuplicateKey] if two keys of a map have the same canonical bytes.
+    ///
+    /// On failure, the parts packed before the map are left in the buffer. The default
+    /// implementation re-encodes the bytes of [Packable::pack] with [canonical::canonicalize],
+    /// failing as it does, so it needs to be overridden by sets and the types that may hold them
+    /// or values packed without a header.
+    #[cfg(feature = "alloc")]
+    fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+    where
+        T: Writer,
+    {
+        canonical::pack_with(buf, |bytes| self.pack(bytes))
+    }
+
+    /// Packs a value into an `Extend<u8>` buffer through an [ExtendWriter], returning the amount
+    /// of written bytes.
+    ///
+    /// The buffers that aren't a [Writer], such as a `VecDeque<u8>`, were accepted by
+    /// [Packable::pack] before it took a [Writer].
+    fn pack_extend<T>(&self, buf: &mut T) -> usize
+    where
+        T: Extend<u8>,
+    {
+        self.pack(&mut ExtendWriter(buf))
+    }
+
+    /// Packs a value into an `Extend<u8>` buffer as [Packable::pack_extend], failing as
+    /// [Packable::try_pack].
+    fn try_pack_extend<T>(&self, buf: &mut T) -> Result<usize, PackError>
+    where
+        T: Extend<u8>,
+    {
+        self.try_pack(&mut ExtendWriter(buf))
+    }
+
+    /// Packs the value in canonical form into an `Extend<u8>` buffer as
+    /// [Packable::pack_extend].
+    #[cfg(feature = "alloc")]
+    fn pack_canonical_extend<T>(&self, buf: &mut T) -> usize
+    where
+        T: Extend<u8>,
+    {
+        self.pack_canonical(&mut ExtendWriter(buf))
+    }
 }
 
 impl<X> Packable for &X
 where
     X: Packable,
 {
+    const SELF_DELIMITING: bool = X::SELF_DELIMITING;
+
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
         X::pack(self, buf)
     }
+
+    fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+    where
+        T: Writer,
+    {
+        X::try_pack(self, buf)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+    where
+        T: Writer,
+    {
+        X::try_pack_canonical(self, buf)
+    }
 }
 
 impl<X> Packable for &mut X
 where
     X: Packable,
 {
+    const SELF_DELIMITING: bool = X::SELF_DELIMITING;
+
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
         X::pack(self, buf)
     }
+
+    fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+    where
+        T: Writer,
+    {
+        X::try_pack(self, buf)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+    where
+        T: Writer,
+    {
+        X::try_pack_canonical(self, buf)
+    }
 }
 
 /// An unpackable type.
@@ -106,11 +297,23 @@ pub trait Unpackable: Sized {
     fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
     where
         I: IntoIterator<Item = u8>;
+
+    /// Unpacks a value from the buffer under the rules of [strict], returning the deserialized
+    /// value and the amount of read bytes.
+    ///
+    /// The default implementation checks the bytes read by [Unpackable::unpack], so it needs to be
+    /// overridden only by maps, sets and the types that may hold them.
+    #[cfg(feature = "alloc")]
+    fn unpack_strict(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+        let (n, value) = Self::unpack(buf)?;
+        strict::check(&buf[..n])?;
+        Ok((n, value))
+    }
 }
 
 /// Required types for the library.
 pub mod prelude {
-    pub use super::{Error, Packable, Unpackable};
+    pub use super::{Error, ExtensionRef, PackError, Packable, Unpackable, Writer};
 
     #[cfg(feature = "derive")]
     pub use super::MsgPacker;
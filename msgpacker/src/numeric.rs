@@ -0,0 +1,191 @@
+use super::{Format, Packable, Unpackable};
+
+/// A primitive number that arrays pack and unpack in bulk, with [pack_numbers](crate::pack_numbers)
+/// and [unpack_numbers](crate::unpack_numbers).
+///
+/// It's implemented for the integers up to 64 bits and the floats.
+pub trait Numeric: Packable + Unpackable<Error = crate::Error> + Copy + sealed::Bulk {}
+
+/// The classes of the positive and of the negative fixints, as returned by [class].
+const FIXINT: u8 = 0x00;
+const NEGATIVE_FIXINT: u8 = 0xe0;
+
+/// The format of the value with the marker byte, folding every fixint of a sign into a single
+/// class.
+#[cfg(feature = "alloc")]
+pub(crate) const fn class(marker: u8) -> u8 {
+    match marker {
+        0x00..=Format::POSITIVE_FIXINT => FIXINT,
+        0xe0.. => NEGATIVE_FIXINT,
+        _ => marker,
+    }
+}
+
+/// The amount of bytes of a value packed with the format class, with the marker byte.
+///
+/// Only the numbers of a fixed width are packed and unpacked in bulk.
+pub(crate) const fn width(class: u8) -> Option<usize> {
+    match class {
+        FIXINT | NEGATIVE_FIXINT => Some(1),
+        Format::UINT8 | Format::INT8 => Some(2),
+        Format::UINT16 | Format::INT16 => Some(3),
+        Format::UINT32 | Format::INT32 | Format::FLOAT32 => Some(5),
+        Format::UINT64 | Format::INT64 | Format::FLOAT64 => Some(9),
+        _ => None,
+    }
+}
+
+/// The bits of an integer packed with the format class, from the big endian bytes that follow
+/// the marker, or from the marker itself for the fixints.
+fn sign_extend(class: u8, raw: u64) -> u64 {
+    match class {
+        FIXINT | NEGATIVE_FIXINT | Format::INT8 => raw as u8 as i8 as u64,
+        Format::INT16 => raw as u16 as i16 as u64,
+        Format::INT32 => raw as u32 as i32 as u64,
+        _ => raw,
+    }
+}
+
+pub(crate) mod sealed {
+    /// The conversions between the numbers and their packed bits, private to the crate.
+    pub trait Bulk: Sized {
+        /// The format class [Packable::pack](crate::Packable::pack) writes the value with.
+        fn class(self) -> u8;
+
+        /// The bits of the value, which packing truncates to the width of its format.
+        fn bits(self) -> u64;
+
+        /// Whether every value packed with the format class fits in the type.
+        fn decodes(class: u8) -> bool;
+
+        /// The value of the bits following a marker of the format class; see
+        /// [Bulk::decodes].
+        fn from_raw(class: u8, raw: u64) -> Self;
+    }
+}
+
+macro_rules! impl_unsigned {
+    ($($t:ty => $($format:ident),*;)*) => {
+        $(
+            impl sealed::Bulk for $t {
+                fn class(self) -> u8 {
+                    match self as u64 {
+                        0..=0x7f => FIXINT,
+                        0x80..=0xff => Format::UINT8,
+                        0x100..=0xffff => Format::UINT16,
+                        0x1_0000..=0xffff_ffff => Format::UINT32,
+                        _ => Format::UINT64,
+                    }
+                }
+
+                fn bits(self) -> u64 {
+                    self as u64
+                }
+
+                fn decodes(class: u8) -> bool {
+                    matches!(class, FIXINT $(| Format::$format)*)
+                }
+
+                fn from_raw(class: u8, raw: u64) -> Self {
+                    sign_extend(class, raw) as $t
+                }
+            }
+
+            impl Numeric for $t {}
+        )*
+    };
+}
+
+impl_unsigned! {
+    u8 => UINT8;
+    u16 => UINT8, UINT16;
+    u32 => UINT8, UINT16, UINT32;
+    u64 => UINT8, UINT16, UINT32, UINT64;
+}
+
+macro_rules! impl_signed {
+    ($($t:ty => $($format:ident),*;)*) => {
+        $(
+            impl sealed::Bulk for $t {
+                fn class(self) -> u8 {
+                    let v = self as i64;
+                    if (-32..=127).contains(&v) {
+                        FIXINT
+                    } else if (-128..=127).contains(&v) {
+                        Format::INT8
+                    } else if (-0x8000..=0x7fff).contains(&v) {
+                        Format::INT16
+                    } else if (-0x8000_0000..=0x7fff_ffff).contains(&v) {
+                        Format::INT32
+                    } else {
+                        Format::INT64
+                    }
+                }
+
+                fn bits(self) -> u64 {
+                    self as u64
+                }
+
+                fn decodes(class: u8) -> bool {
+                    matches!(class, FIXINT | NEGATIVE_FIXINT $(| Format::$format)*)
+                }
+
+                fn from_raw(class: u8, raw: u64) -> Self {
+                    sign_extend(class, raw) as $t
+                }
+            }
+
+            impl Numeric for $t {}
+        )*
+    };
+}
+
+impl_signed! {
+    i8 => INT8;
+    i16 => INT8, INT16, UINT8;
+    i32 => INT8, INT16, INT32, UINT8, UINT16;
+    i64 => INT8, INT16, INT32, INT64, UINT8, UINT16, UINT32;
+}
+
+impl sealed::Bulk for f32 {
+    fn class(self) -> u8 {
+        Format::FLOAT32
+    }
+
+    fn bits(self) -> u64 {
+        self.to_bits() as u64
+    }
+
+    fn decodes(class: u8) -> bool {
+        class == Format::FLOAT32
+    }
+
+    fn from_raw(_class: u8, raw: u64) -> Self {
+        f32::from_bits(raw as u32)
+    }
+}
+
+impl Numeric for f32 {}
+
+impl sealed::Bulk for f64 {
+    fn class(self) -> u8 {
+        Format::FLOAT64
+    }
+
+    fn bits(self) -> u64 {
+        self.to_bits()
+    }
+
+    fn decodes(class: u8) -> bool {
+        matches!(class, Format::FLOAT32 | Format::FLOAT64)
+    }
+
+    fn from_raw(class: u8, raw: u64) -> Self {
+        match class {
+            Format::FLOAT32 => f32::from_bits(raw as u32) as f64,
+            _ => f64::from_bits(raw),
+        }
+    }
+}
+
+impl Numeric for f64 {}
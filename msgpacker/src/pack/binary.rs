@@ -1,60 +1,73 @@
-use super::{Format, Packable};
-use core::iter;
+use super::{too_large, write_format, Format, PackError, Packable, Writer};
 
-pub fn pack_bytes_slice_len<T: Extend<u8>>(buf: &mut T, slice: &[u8]) -> usize {
+fn try_pack_bytes_slice_len<T: Writer>(buf: &mut T, slice: &[u8]) -> Result<usize, PackError> {
+    if slice.len() <= u32::MAX as usize {
+        buf.reserve(5 + slice.len());
+    }
     if slice.len() <= u8::MAX as usize {
-        buf.extend(iter::once(Format::BIN8).chain(iter::once(slice.len() as u8)));
-        2
+        buf.write_all(&[Format::BIN8, slice.len() as u8]);
+        Ok(2)
     } else if slice.len() <= u16::MAX as usize {
-        buf.extend(iter::once(Format::BIN16).chain((slice.len() as u16).to_be_bytes()));
-        3
+        write_format(buf, Format::BIN16, &(slice.len() as u16).to_be_bytes());
+        Ok(3)
     } else if slice.len() <= u32::MAX as usize {
-        buf.extend(iter::once(Format::BIN32).chain((slice.len() as u32).to_be_bytes()));
-        5
+        write_format(buf, Format::BIN32, &(slice.len() as u32).to_be_bytes());
+        Ok(5)
     } else {
-        #[cfg(feature = "strict")]
-        panic!("strict serialization enabled; the buffer is too large");
-        return 0;
+        Err(PackError::TooLarge)
     }
 }
 
 impl Packable for [u8] {
-    #[allow(unreachable_code)]
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
+    {
+        self.try_pack(buf).unwrap_or_else(too_large)
+    }
+
+    fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+    where
+        T: Writer,
     {
-        let n = pack_bytes_slice_len(buf, self);
-        buf.extend(self.iter().copied());
-        n + self.len()
+        let n = try_pack_bytes_slice_len(buf, self)?;
+        buf.write_all(self);
+        Ok(n + self.len())
     }
 }
 
-#[allow(unreachable_code)]
 impl Packable for str {
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
+    {
+        self.try_pack(buf).unwrap_or_else(too_large)
+    }
+
+    fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+    where
+        T: Writer,
     {
+        if self.len() <= u32::MAX as usize {
+            buf.reserve(5 + self.len());
+        }
         let n = if self.len() <= 31 {
-            buf.extend(iter::once((self.len() as u8 & 0x1f) | 0xa0));
+            buf.write_all(&[(self.len() as u8 & 0x1f) | 0xa0]);
             1
         } else if self.len() <= u8::MAX as usize {
-            buf.extend(iter::once(Format::STR8).chain(iter::once(self.len() as u8)));
+            buf.write_all(&[Format::STR8, self.len() as u8]);
             2
         } else if self.len() <= u16::MAX as usize {
-            buf.extend(iter::once(Format::STR16).chain((self.len() as u16).to_be_bytes()));
+            write_format(buf, Format::STR16, &(self.len() as u16).to_be_bytes());
             3
         } else if self.len() <= u32::MAX as usize {
-            buf.extend(iter::once(Format::STR32).chain((self.len() as u32).to_be_bytes()));
+            write_format(buf, Format::STR32, &(self.len() as u32).to_be_bytes());
             5
         } else {
-            #[cfg(feature = "strict")]
-            panic!("strict serialization enabled; the buffer is too large");
-            return 0;
+            return Err(PackError::TooLarge);
         };
-        buf.extend(self.as_bytes().iter().copied());
-        n + self.len()
+        buf.write_all(self.as_bytes());
+        Ok(n + self.len())
     }
 }
 
@@ -66,18 +79,57 @@ mod alloc {
     impl Packable for Vec<u8> {
         fn pack<T>(&self, buf: &mut T) -> usize
         where
-            T: Extend<u8>,
+            T: Writer,
         {
             self.as_slice().pack(buf)
         }
+
+        fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+        where
+            T: Writer,
+        {
+            self.as_slice().try_pack(buf)
+        }
     }
 
     impl Packable for String {
         fn pack<T>(&self, buf: &mut T) -> usize
         where
-            T: Extend<u8>,
+            T: Writer,
         {
             self.as_str().pack(buf)
         }
+
+        fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+        where
+            T: Writer,
+        {
+            self.as_str().try_pack(buf)
+        }
+    }
+}
+
+#[cfg(feature = "smallvec")]
+mod smallvec {
+    use super::*;
+    use ::smallvec::{Array, SmallVec};
+
+    impl<A> Packable for SmallVec<A>
+    where
+        A: Array<Item = u8>,
+    {
+        fn pack<T>(&self, buf: &mut T) -> usize
+        where
+            T: Writer,
+        {
+            self.as_slice().pack(buf)
+        }
+
+        fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+        where
+            T: Writer,
+        {
+            self.as_slice().try_pack(buf)
+        }
     }
 }
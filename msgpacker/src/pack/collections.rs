@@ -1,68 +1,102 @@
-use super::{Format, Packable};
-use core::{borrow::Borrow, iter};
+use super::{too_large, write_format, Format, PackError, Packable, Writer};
+use core::borrow::Borrow;
 
 /// Packs the length of an array.
 pub fn pack_array_len<T>(buf: &mut T, len: usize) -> usize
 where
-    T: Extend<u8>,
+    T: Writer,
+{
+    try_pack_array_len(buf, len).unwrap_or_else(too_large)
+}
+
+/// Packs the length of an array, failing if it's above `u32::MAX`.
+pub fn try_pack_array_len<T>(buf: &mut T, len: usize) -> Result<usize, PackError>
+where
+    T: Writer,
 {
     if len <= 15 {
-        buf.extend(iter::once(((len & 0x0f) as u8) | 0x90));
-        1
+        buf.write_all(&[((len & 0x0f) as u8) | 0x90]);
+        Ok(1)
     } else if len <= u16::MAX as usize {
-        buf.extend(iter::once(Format::ARRAY16).chain((len as u16).to_be_bytes()));
-        3
+        write_format(buf, Format::ARRAY16, &(len as u16).to_be_bytes());
+        Ok(3)
     } else if len <= u32::MAX as usize {
-        buf.extend(iter::once(Format::ARRAY32).chain((len as u32).to_be_bytes()));
-        5
+        write_format(buf, Format::ARRAY32, &(len as u32).to_be_bytes());
+        Ok(5)
     } else {
-        #[cfg(feature = "strict")]
-        panic!("strict serialization enabled; the buffer is too large");
-        return 0;
+        Err(PackError::TooLarge)
     }
 }
 
-/// Packs an array into the extendable buffer, returning the amount of written bytes.
-#[allow(unreachable_code)]
+/// Packs an array into the buffer, returning the amount of written bytes.
+///
+/// An array longer than `u32::MAX` is not packed.
 pub fn pack_array<T, A, I, V>(buf: &mut T, iter: A) -> usize
 where
-    T: Extend<u8>,
+    T: Writer,
     A: IntoIterator<IntoIter = I>,
     I: Iterator<Item = V> + ExactSizeIterator,
     V: Packable,
 {
     let values = iter.into_iter();
     let len = values.len();
-    let n = pack_array_len(buf, len);
-    n + values.map(|v| v.pack(buf)).sum::<usize>()
+    match try_pack_array_len(buf, len) {
+        Ok(n) => n + values.map(|v| v.pack(buf)).sum::<usize>(),
+        Err(e) => too_large(e),
+    }
+}
+
+/// Packs an array into the buffer, returning the amount of written bytes, or
+/// [PackError::TooLarge] if the array or one of its elements is too large.
+pub fn try_pack_array<T, A, I, V>(buf: &mut T, iter: A) -> Result<usize, PackError>
+where
+    T: Writer,
+    A: IntoIterator<IntoIter = I>,
+    I: Iterator<Item = V> + ExactSizeIterator,
+    V: Packable,
+{
+    let values = iter.into_iter();
+    let len = values.len();
+    let mut n = try_pack_array_len(buf, len)?;
+    for v in values {
+        n += v.try_pack(buf)?;
+    }
+    Ok(n)
 }
 
 /// Packs the length of a map.
 pub fn pack_map_len<T>(buf: &mut T, len: usize) -> usize
 where
-    T: Extend<u8>,
+    T: Writer,
+{
+    try_pack_map_len(buf, len).unwrap_or_else(too_large)
+}
+
+/// Packs the length of a map, failing if it's above `u32::MAX`.
+pub fn try_pack_map_len<T>(buf: &mut T, len: usize) -> Result<usize, PackError>
+where
+    T: Writer,
 {
     if len <= 15 {
-        buf.extend(iter::once(((len & 0x0f) as u8) | 0x80));
-        1
+        buf.write_all(&[((len & 0x0f) as u8) | 0x80]);
+        Ok(1)
     } else if len <= u16::MAX as usize {
-        buf.extend(iter::once(Format::MAP16).chain((len as u16).to_be_bytes()));
-        3
+        write_format(buf, Format::MAP16, &(len as u16).to_be_bytes());
+        Ok(3)
     } else if len <= u32::MAX as usize {
-        buf.extend(iter::once(Format::MAP32).chain((len as u32).to_be_bytes()));
-        5
+        write_format(buf, Format::MAP32, &(len as u32).to_be_bytes());
+        Ok(5)
     } else {
-        #[cfg(feature = "strict")]
-        panic!("strict serialization enabled; the buffer is too large");
-        return 0;
+        Err(PackError::TooLarge)
     }
 }
 
-/// Packs a map into the extendable buffer, returning the amount of written bytes.
-#[allow(unreachable_code)]
+/// Packs a map into the buffer, returning the amount of written bytes.
+///
+/// A map longer than `u32::MAX` is not packed.
 pub fn pack_map<T, A, I, B, K, V>(buf: &mut T, iter: A) -> usize
 where
-    T: Extend<u8>,
+    T: Writer,
     A: IntoIterator<IntoIter = I>,
     B: Borrow<(K, V)>,
     I: Iterator<Item = B> + ExactSizeIterator,
@@ -71,66 +105,157 @@ where
 {
     let map = iter.into_iter();
     let len = map.len();
-    let n = pack_map_len(buf, len);
-    n + map
-        .map(|b| {
-            let (k, v) = b.borrow();
-            k.pack(buf) + v.pack(buf)
-        })
-        .sum::<usize>()
+    match try_pack_map_len(buf, len) {
+        Ok(n) => {
+            n + map
+                .map(|b| {
+                    let (k, v) = b.borrow();
+                    k.pack(buf) + v.pack(buf)
+                })
+                .sum::<usize>()
+        }
+        Err(e) => too_large(e),
+    }
+}
+
+/// Packs a map into the buffer, returning the amount of written bytes, or
+/// [PackError::TooLarge] if the map or one of its entries is too large.
+pub fn try_pack_map<T, A, I, B, K, V>(buf: &mut T, iter: A) -> Result<usize, PackError>
+where
+    T: Writer,
+    A: IntoIterator<IntoIter = I>,
+    B: Borrow<(K, V)>,
+    I: Iterator<Item = B> + ExactSizeIterator,
+    K: Packable,
+    V: Packable,
+{
+    let map = iter.into_iter();
+    let len = map.len();
+    let mut n = try_pack_map_len(buf, len)?;
+    for b in map {
+        let (k, v) = b.borrow();
+        n += k.try_pack(buf)?;
+        n += v.try_pack(buf)?;
+    }
+    Ok(n)
 }
 
 #[cfg(feature = "alloc")]
 mod alloc {
     use super::*;
+    use crate::{canonical, Error};
     use ::alloc::collections::{BTreeMap, BTreeSet, BinaryHeap, LinkedList, VecDeque};
 
     impl<X> Packable for BTreeSet<X>
     where
         X: Packable,
     {
+        const SELF_DELIMITING: bool = X::SELF_DELIMITING;
+
         fn pack<T>(&self, buf: &mut T) -> usize
         where
-            T: Extend<u8>,
+            T: Writer,
         {
             pack_array(buf, self)
         }
+
+        fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+        where
+            T: Writer,
+        {
+            try_pack_array(buf, self)
+        }
+
+        fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+        where
+            T: Writer,
+        {
+            canonical::pack_set(buf, self)
+        }
     }
 
     impl<X> Packable for BinaryHeap<X>
     where
         X: Packable,
     {
+        const SELF_DELIMITING: bool = X::SELF_DELIMITING;
+
         fn pack<T>(&self, buf: &mut T) -> usize
         where
-            T: Extend<u8>,
+            T: Writer,
         {
             pack_array(buf, self)
         }
+
+        fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+        where
+            T: Writer,
+        {
+            try_pack_array(buf, self)
+        }
+
+        fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+        where
+            T: Writer,
+        {
+            canonical::pack_set(buf, self)
+        }
     }
 
     impl<X> Packable for LinkedList<X>
     where
         X: Packable,
     {
+        const SELF_DELIMITING: bool = X::SELF_DELIMITING;
+
         fn pack<T>(&self, buf: &mut T) -> usize
         where
-            T: Extend<u8>,
+            T: Writer,
         {
             pack_array(buf, self)
         }
+
+        fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+        where
+            T: Writer,
+        {
+            try_pack_array(buf, self)
+        }
+
+        fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+        where
+            T: Writer,
+        {
+            canonical::pack_array(buf, self)
+        }
     }
 
     impl<X> Packable for VecDeque<X>
     where
         X: Packable,
     {
+        const SELF_DELIMITING: bool = X::SELF_DELIMITING;
+
         fn pack<T>(&self, buf: &mut T) -> usize
         where
-            T: Extend<u8>,
+            T: Writer,
         {
             pack_array(buf, self)
         }
+
+        fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+        where
+            T: Writer,
+        {
+            try_pack_array(buf, self)
+        }
+
+        fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+        where
+            T: Writer,
+        {
+            canonical::pack_array(buf, self)
+        }
     }
 
     impl<K, V> Packable for BTreeMap<K, V>
@@ -138,42 +263,219 @@ mod alloc {
         K: Packable,
         V: Packable,
     {
+        const SELF_DELIMITING: bool = K::SELF_DELIMITING && V::SELF_DELIMITING;
+
         fn pack<T>(&self, buf: &mut T) -> usize
         where
-            T: Extend<u8>,
+            T: Writer,
         {
             pack_map(buf, self)
         }
+
+        fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+        where
+            T: Writer,
+        {
+            try_pack_map(buf, self)
+        }
+
+        fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+        where
+            T: Writer,
+        {
+            canonical::pack_map(buf, self)
+        }
     }
 }
 
 #[cfg(feature = "std")]
 mod std {
     use super::*;
+    use crate::{canonical, Error};
     use ::std::collections::{HashMap, HashSet};
 
-    impl<X> Packable for HashSet<X>
+    impl<X, S> Packable for HashSet<X, S>
+    where
+        X: Packable,
+    {
+        const SELF_DELIMITING: bool = X::SELF_DELIMITING;
+
+        fn pack<T>(&self, buf: &mut T) -> usize
+        where
+            T: Writer,
+        {
+            pack_array(buf, self)
+        }
+
+        fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+        where
+            T: Writer,
+        {
+            try_pack_array(buf, self)
+        }
+
+        fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+        where
+            T: Writer,
+        {
+            canonical::pack_set(buf, self)
+        }
+    }
+
+    impl<K, V, S> Packable for HashMap<K, V, S>
+    where
+        K: Packable,
+        V: Packable,
+    {
+        const SELF_DELIMITING: bool = K::SELF_DELIMITING && V::SELF_DELIMITING;
+
+        fn pack<T>(&self, buf: &mut T) -> usize
+        where
+            T: Writer,
+        {
+            pack_map(buf, self)
+        }
+
+        fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+        where
+            T: Writer,
+        {
+            try_pack_map(buf, self)
+        }
+
+        fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+        where
+            T: Writer,
+        {
+            canonical::pack_map(buf, self)
+        }
+    }
+}
+
+#[cfg(feature = "hashbrown")]
+mod hashbrown {
+    use super::*;
+    use crate::{canonical, Error};
+    use ::hashbrown::{HashMap, HashSet};
+
+    impl<X, S> Packable for HashSet<X, S>
+    where
+        X: Packable,
+    {
+        const SELF_DELIMITING: bool = X::SELF_DELIMITING;
+
+        fn pack<T>(&self, buf: &mut T) -> usize
+        where
+            T: Writer,
+        {
+            pack_array(buf, self)
+        }
+
+        fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+        where
+            T: Writer,
+        {
+            try_pack_array(buf, self)
+        }
+
+        fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+        where
+            T: Writer,
+        {
+            canonical::pack_set(buf, self)
+        }
+    }
+
+    impl<K, V, S> Packable for HashMap<K, V, S>
+    where
+        K: Packable,
+        V: Packable,
+    {
+        const SELF_DELIMITING: bool = K::SELF_DELIMITING && V::SELF_DELIMITING;
+
+        fn pack<T>(&self, buf: &mut T) -> usize
+        where
+            T: Writer,
+        {
+            pack_map(buf, self)
+        }
+
+        fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+        where
+            T: Writer,
+        {
+            try_pack_map(buf, self)
+        }
+
+        fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+        where
+            T: Writer,
+        {
+            canonical::pack_map(buf, self)
+        }
+    }
+}
+
+#[cfg(feature = "indexmap")]
+mod indexmap {
+    use super::*;
+    use crate::{canonical, Error};
+    use ::indexmap::{IndexMap, IndexSet};
+
+    impl<X, S> Packable for IndexSet<X, S>
     where
         X: Packable,
     {
+        const SELF_DELIMITING: bool = X::SELF_DELIMITING;
+
         fn pack<T>(&self, buf: &mut T) -> usize
         where
-            T: Extend<u8>,
+            T: Writer,
         {
             pack_array(buf, self)
         }
+
+        fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+        where
+            T: Writer,
+        {
+            try_pack_array(buf, self)
+        }
+
+        fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+        where
+            T: Writer,
+        {
+            canonical::pack_set(buf, self)
+        }
     }
 
-    impl<K, V> Packable for HashMap<K, V>
+    impl<K, V, S> Packable for IndexMap<K, V, S>
     where
         K: Packable,
         V: Packable,
     {
+        const SELF_DELIMITING: bool = K::SELF_DELIMITING && V::SELF_DELIMITING;
+
         fn pack<T>(&self, buf: &mut T) -> usize
         where
-            T: Extend<u8>,
+            T: Writer,
         {
             pack_map(buf, self)
         }
+
+        fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+        where
+            T: Writer,
+        {
+            try_pack_map(buf, self)
+        }
+
+        fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+        where
+            T: Writer,
+        {
+            canonical::pack_map(buf, self)
+        }
     }
 }
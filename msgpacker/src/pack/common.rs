@@ -1,12 +1,14 @@
-use super::{Format, Packable};
-use core::{iter, marker::PhantomData};
+use super::{Format, PackError, Packable, Writer};
+#[cfg(feature = "alloc")]
+use crate::Error;
+use core::marker::PhantomData;
 
 impl Packable for () {
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
-        buf.extend(iter::once(Format::NIL));
+        buf.write_all(&[Format::NIL]);
         1
     }
 }
@@ -14,9 +16,9 @@ impl Packable for () {
 impl<X> Packable for PhantomData<X> {
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
-        buf.extend(iter::once(Format::NIL));
+        buf.write_all(&[Format::NIL]);
         1
     }
 }
@@ -24,12 +26,12 @@ impl<X> Packable for PhantomData<X> {
 impl Packable for bool {
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
         if *self {
-            buf.extend(iter::once(Format::TRUE));
+            buf.write_all(&[Format::TRUE]);
         } else {
-            buf.extend(iter::once(Format::FALSE));
+            buf.write_all(&[Format::FALSE]);
         }
         1
     }
@@ -38,7 +40,7 @@ impl Packable for bool {
 impl Packable for char {
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
         (*self as u32).pack(buf)
     }
@@ -48,18 +50,41 @@ impl<X> Packable for Option<X>
 where
     X: Packable,
 {
+    const SELF_DELIMITING: bool = X::SELF_DELIMITING;
+
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
         match self {
             Some(t) => t.pack(buf),
             None => {
-                buf.extend(iter::once(Format::NIL));
+                buf.write_all(&[Format::NIL]);
                 1
             }
         }
     }
+
+    fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+    where
+        T: Writer,
+    {
+        match self {
+            Some(t) => t.try_pack(buf),
+            None => Ok(self.pack(buf)),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+    where
+        T: Writer,
+    {
+        match self {
+            Some(t) => t.try_pack_canonical(buf),
+            None => Ok(self.pack(buf)),
+        }
+    }
 }
 
 macro_rules! array {
@@ -68,12 +93,30 @@ macro_rules! array {
         where
             X: Packable,
         {
+            // the elements are packed in sequence, without a header
+            const SELF_DELIMITING: bool = $n == 1 && X::SELF_DELIMITING;
+
             fn pack<T>(&self, buf: &mut T) -> usize
             where
-                T: Extend<u8>,
+                T: Writer,
             {
                 self.iter().map(|t| t.pack(buf)).sum()
             }
+
+            fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+            where
+                T: Writer,
+            {
+                self.iter().map(|t| t.try_pack(buf)).sum()
+            }
+
+            #[cfg(feature = "alloc")]
+            fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+            where
+                T: Writer,
+            {
+                self.iter().map(|t| t.try_pack_canonical(buf)).sum()
+            }
         }
     };
 }
@@ -149,15 +192,42 @@ macro_rules! tuple {
         impl<$($name,)+> Packable for ($($name,)+)
         where $($name: Packable,)+
         {
+            // the elements are packed in sequence, without a header
+            const SELF_DELIMITING: bool = {
+                let elements = [$($name::SELF_DELIMITING,)+];
+                elements.len() == 1 && elements[0]
+            };
+
             #[allow(non_snake_case)]
             fn pack<TT>(&self, buf: &mut TT) -> usize
             where
-                TT: Extend<u8>,
+                TT: Writer,
             {
                 let ($(ref $name,)+) = *self;
 
                 0 $( + $name.pack(buf))+
             }
+
+            #[allow(non_snake_case)]
+            fn try_pack<TT>(&self, buf: &mut TT) -> Result<usize, PackError>
+            where
+                TT: Writer,
+            {
+                let ($(ref $name,)+) = *self;
+
+                Ok(0 $( + $name.try_pack(buf)?)+)
+            }
+
+            #[cfg(feature = "alloc")]
+            #[allow(non_snake_case)]
+            fn try_pack_canonical<TT>(&self, buf: &mut TT) -> Result<usize, Error>
+            where
+                TT: Writer,
+            {
+                let ($(ref $name,)+) = *self;
+
+                Ok(0 $( + $name.try_pack_canonical(buf)?)+)
+            }
         }
     );
 }
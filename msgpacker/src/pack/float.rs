@@ -1,22 +1,108 @@
-use super::{Format, Packable};
-use core::iter;
+use super::{write_format, Format, Packable, Writer};
+#[cfg(feature = "alloc")]
+use crate::Error;
 
 impl Packable for f32 {
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
-        buf.extend(iter::once(Format::FLOAT32).chain(self.to_be_bytes()));
+        write_format(buf, Format::FLOAT32, &self.to_be_bytes());
         5
     }
+
+    #[cfg(feature = "alloc")]
+    fn pack_canonical<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer,
+    {
+        match self.is_nan() {
+            true => f32::from_bits(0x7fc0_0000).pack(buf),
+            false => self.pack(buf),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+    where
+        T: Writer,
+    {
+        Ok(self.pack_canonical(buf))
+    }
 }
 
 impl Packable for f64 {
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
-        buf.extend(iter::once(Format::FLOAT64).chain(self.to_be_bytes()));
+        write_format(buf, Format::FLOAT64, &self.to_be_bytes());
         9
     }
+
+    #[cfg(feature = "alloc")]
+    fn pack_canonical<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer,
+    {
+        match self.is_nan() {
+            true => f64::from_bits(0x7ff8_0000_0000_0000).pack(buf),
+            false => self.pack(buf),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+    where
+        T: Writer,
+    {
+        Ok(self.pack_canonical(buf))
+    }
+}
+
+/// The value of the float as an integer, if it's one that an integer format represents
+/// exactly. Negative zero keeps its sign only as a float.
+fn as_integer(v: f64) -> Option<Result<u64, i64>> {
+    if v == 0.0 && v.is_sign_negative() {
+        return None;
+    }
+    // the bounds are powers of two, so they're exact; the casts truncate the fractional part
+    match v {
+        0.0..18_446_744_073_709_551_616.0 if v as u64 as f64 == v => Some(Ok(v as u64)),
+        -9_223_372_036_854_775_808.0..0.0 if v as i64 as f64 == v => Some(Err(v as i64)),
+        _ => None,
+    }
+}
+
+/// Packs the float in the smallest format that represents it exactly: an integer if it has no
+/// fractional part, and `FLOAT32` otherwise. Returns the amount of written bytes.
+///
+/// Decoding integers into floats requires [FloatOptions::with_integers](crate::FloatOptions).
+pub fn pack_f32_compact<T>(buf: &mut T, v: f32) -> usize
+where
+    T: Writer,
+{
+    match as_integer(v as f64) {
+        Some(Ok(i)) => i.pack(buf),
+        Some(Err(i)) => i.pack(buf),
+        None => v.pack(buf),
+    }
+}
+
+/// Packs the float in the smallest format that represents it exactly: an integer if it has no
+/// fractional part, then `FLOAT32`, and `FLOAT64` otherwise. Returns the amount of written
+/// bytes.
+///
+/// Decoding integers into floats requires [FloatOptions::with_integers](crate::FloatOptions).
+pub fn pack_f64_compact<T>(buf: &mut T, v: f64) -> usize
+where
+    T: Writer,
+{
+    let f = v as f32;
+    match as_integer(v) {
+        Some(Ok(i)) => i.pack(buf),
+        Some(Err(i)) => i.pack(buf),
+        None if f as f64 == v || v.is_nan() => f.pack(buf),
+        None => v.pack(buf),
+    }
 }
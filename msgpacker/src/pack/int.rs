@@ -1,16 +1,17 @@
-use super::{Format, Packable};
-use core::iter;
+use super::{write_format, Format, Packable, Writer};
+#[cfg(feature = "alloc")]
+use crate::Error;
 
 impl Packable for u8 {
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
         if *self <= 127 {
-            buf.extend(iter::once(self & Format::POSITIVE_FIXINT));
+            buf.write_all(&[self & Format::POSITIVE_FIXINT]);
             1
         } else {
-            buf.extend(iter::once(Format::UINT8).chain(iter::once(*self)));
+            buf.write_all(&[Format::UINT8, *self]);
             2
         }
     }
@@ -19,16 +20,16 @@ impl Packable for u8 {
 impl Packable for u16 {
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
         if *self <= 127 {
-            buf.extend(iter::once(*self as u8 & Format::POSITIVE_FIXINT));
+            buf.write_all(&[*self as u8 & Format::POSITIVE_FIXINT]);
             1
         } else if *self <= u8::MAX as u16 {
-            buf.extend(iter::once(Format::UINT8).chain(iter::once(*self as u8)));
+            buf.write_all(&[Format::UINT8, *self as u8]);
             2
         } else {
-            buf.extend(iter::once(Format::UINT16).chain(self.to_be_bytes()));
+            write_format(buf, Format::UINT16, &self.to_be_bytes());
             3
         }
     }
@@ -37,19 +38,19 @@ impl Packable for u16 {
 impl Packable for u32 {
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
         if *self <= 127 {
-            buf.extend(iter::once(*self as u8 & Format::POSITIVE_FIXINT));
+            buf.write_all(&[*self as u8 & Format::POSITIVE_FIXINT]);
             1
         } else if *self <= u8::MAX as u32 {
-            buf.extend(iter::once(Format::UINT8).chain(iter::once(*self as u8)));
+            buf.write_all(&[Format::UINT8, *self as u8]);
             2
         } else if *self <= u16::MAX as u32 {
-            buf.extend(iter::once(Format::UINT16).chain((*self as u16).to_be_bytes()));
+            write_format(buf, Format::UINT16, &(*self as u16).to_be_bytes());
             3
         } else {
-            buf.extend(iter::once(Format::UINT32).chain(self.to_be_bytes()));
+            write_format(buf, Format::UINT32, &self.to_be_bytes());
             5
         }
     }
@@ -58,22 +59,22 @@ impl Packable for u32 {
 impl Packable for u64 {
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
         if *self <= 127 {
-            buf.extend(iter::once(*self as u8 & Format::POSITIVE_FIXINT));
+            buf.write_all(&[*self as u8 & Format::POSITIVE_FIXINT]);
             1
         } else if *self <= u8::MAX as u64 {
-            buf.extend(iter::once(Format::UINT8).chain(iter::once(*self as u8)));
+            buf.write_all(&[Format::UINT8, *self as u8]);
             2
         } else if *self <= u16::MAX as u64 {
-            buf.extend(iter::once(Format::UINT16).chain((*self as u16).to_be_bytes()));
+            write_format(buf, Format::UINT16, &(*self as u16).to_be_bytes());
             3
         } else if *self <= u32::MAX as u64 {
-            buf.extend(iter::once(Format::UINT32).chain((*self as u32).to_be_bytes()));
+            write_format(buf, Format::UINT32, &(*self as u32).to_be_bytes());
             5
         } else {
-            buf.extend(iter::once(Format::UINT64).chain(self.to_be_bytes()));
+            write_format(buf, Format::UINT64, &self.to_be_bytes());
             9
         }
     }
@@ -82,29 +83,26 @@ impl Packable for u64 {
 impl Packable for u128 {
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
         if *self <= 127 {
-            buf.extend(iter::once(*self as u8 & Format::POSITIVE_FIXINT));
+            buf.write_all(&[*self as u8 & Format::POSITIVE_FIXINT]);
             1
         } else if *self <= u8::MAX as u128 {
-            buf.extend(iter::once(Format::UINT8).chain(iter::once(*self as u8)));
+            buf.write_all(&[Format::UINT8, *self as u8]);
             2
         } else if *self <= u16::MAX as u128 {
-            buf.extend(iter::once(Format::UINT16).chain((*self as u16).to_be_bytes()));
+            write_format(buf, Format::UINT16, &(*self as u16).to_be_bytes());
             3
         } else if *self <= u32::MAX as u128 {
-            buf.extend(iter::once(Format::UINT32).chain((*self as u32).to_be_bytes()));
+            write_format(buf, Format::UINT32, &(*self as u32).to_be_bytes());
             5
         } else if *self <= u64::MAX as u128 {
-            buf.extend(iter::once(Format::UINT64).chain((*self as u64).to_be_bytes()));
+            write_format(buf, Format::UINT64, &(*self as u64).to_be_bytes());
             9
         } else {
-            buf.extend(
-                iter::once(Format::BIN8)
-                    .chain(iter::once(16))
-                    .chain(self.to_be_bytes()),
-            );
+            buf.write_all(&[Format::BIN8, 16]);
+            buf.write_all(&self.to_be_bytes());
             18
         }
     }
@@ -113,22 +111,22 @@ impl Packable for u128 {
 impl Packable for usize {
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
         if *self <= 127 {
-            buf.extend(iter::once(*self as u8 & Format::POSITIVE_FIXINT));
+            buf.write_all(&[*self as u8 & Format::POSITIVE_FIXINT]);
             1
         } else if *self <= u8::MAX as usize {
-            buf.extend(iter::once(Format::UINT8).chain(iter::once(*self as u8)));
+            buf.write_all(&[Format::UINT8, *self as u8]);
             2
         } else if *self <= u16::MAX as usize {
-            buf.extend(iter::once(Format::UINT16).chain((*self as u16).to_be_bytes()));
+            write_format(buf, Format::UINT16, &(*self as u16).to_be_bytes());
             3
         } else if *self <= u32::MAX as usize {
-            buf.extend(iter::once(Format::UINT32).chain((*self as u32).to_be_bytes()));
+            write_format(buf, Format::UINT32, &(*self as u32).to_be_bytes());
             5
         } else {
-            buf.extend(iter::once(Format::UINT64).chain(self.to_be_bytes()));
+            write_format(buf, Format::UINT64, &self.to_be_bytes());
             9
         }
     }
@@ -137,193 +135,301 @@ impl Packable for usize {
 impl Packable for i8 {
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
         if *self <= -33 {
-            buf.extend(iter::once(Format::INT8).chain(iter::once(*self as u8)));
+            buf.write_all(&[Format::INT8, *self as u8]);
             2
         } else if *self <= -1 {
-            buf.extend(iter::once((*self | -32i8) as u8));
+            buf.write_all(&[(*self | -32i8) as u8]);
             1
         } else {
-            buf.extend(iter::once(*self as u8 & Format::POSITIVE_FIXINT));
+            buf.write_all(&[*self as u8 & Format::POSITIVE_FIXINT]);
             1
         }
     }
+
+    #[cfg(feature = "alloc")]
+    fn pack_canonical<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer,
+    {
+        match u64::try_from(*self) {
+            Ok(v) => v.pack(buf),
+            Err(_) => self.pack(buf),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+    where
+        T: Writer,
+    {
+        Ok(self.pack_canonical(buf))
+    }
 }
 
 impl Packable for i16 {
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
         if *self < i8::MIN as i16 {
-            buf.extend(iter::once(Format::INT16).chain(self.to_be_bytes()));
+            write_format(buf, Format::INT16, &self.to_be_bytes());
             3
         } else if *self <= -33 {
-            buf.extend(iter::once(Format::INT8).chain(iter::once((*self as i8) as u8)));
+            buf.write_all(&[Format::INT8, (*self as i8) as u8]);
             2
         } else if *self <= -1 {
-            buf.extend(iter::once((*self as i8 | -32i8) as u8));
+            buf.write_all(&[(*self as i8 | -32i8) as u8]);
             1
         } else if *self <= i8::MAX as i16 {
-            buf.extend(iter::once(*self as u8 & Format::POSITIVE_FIXINT));
+            buf.write_all(&[*self as u8 & Format::POSITIVE_FIXINT]);
             1
         } else {
-            buf.extend(iter::once(Format::INT16).chain(self.to_be_bytes()));
+            write_format(buf, Format::INT16, &self.to_be_bytes());
             3
         }
     }
+
+    #[cfg(feature = "alloc")]
+    fn pack_canonical<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer,
+    {
+        match u64::try_from(*self) {
+            Ok(v) => v.pack(buf),
+            Err(_) => self.pack(buf),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+    where
+        T: Writer,
+    {
+        Ok(self.pack_canonical(buf))
+    }
 }
 
 impl Packable for i32 {
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
         if *self < i16::MIN as i32 {
-            buf.extend(iter::once(Format::INT32).chain(self.to_be_bytes()));
+            write_format(buf, Format::INT32, &self.to_be_bytes());
             5
         } else if *self < i8::MIN as i32 {
-            buf.extend(iter::once(Format::INT16).chain((*self as i16).to_be_bytes()));
+            write_format(buf, Format::INT16, &(*self as i16).to_be_bytes());
             3
         } else if *self <= -33 {
-            buf.extend(iter::once(Format::INT8).chain(iter::once((*self as i8) as u8)));
+            buf.write_all(&[Format::INT8, (*self as i8) as u8]);
             2
         } else if *self <= -1 {
-            buf.extend(iter::once((*self | -32i32) as u8));
+            buf.write_all(&[(*self | -32i32) as u8]);
             1
         } else if *self <= i8::MAX as i32 {
-            buf.extend(iter::once(*self as u8 & Format::POSITIVE_FIXINT));
+            buf.write_all(&[*self as u8 & Format::POSITIVE_FIXINT]);
             1
         } else if *self <= i16::MAX as i32 {
-            buf.extend(iter::once(Format::INT16).chain((*self as i16).to_be_bytes()));
+            write_format(buf, Format::INT16, &(*self as i16).to_be_bytes());
             3
         } else {
-            buf.extend(iter::once(Format::INT32).chain(self.to_be_bytes()));
+            write_format(buf, Format::INT32, &self.to_be_bytes());
             5
         }
     }
+
+    #[cfg(feature = "alloc")]
+    fn pack_canonical<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer,
+    {
+        match u64::try_from(*self) {
+            Ok(v) => v.pack(buf),
+            Err(_) => self.pack(buf),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+    where
+        T: Writer,
+    {
+        Ok(self.pack_canonical(buf))
+    }
 }
 
 impl Packable for i64 {
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
         if *self < i32::MIN as i64 {
-            buf.extend(iter::once(Format::INT64).chain(self.to_be_bytes()));
+            write_format(buf, Format::INT64, &self.to_be_bytes());
             9
         } else if *self < i16::MIN as i64 {
-            buf.extend(iter::once(Format::INT32).chain((*self as i32).to_be_bytes()));
+            write_format(buf, Format::INT32, &(*self as i32).to_be_bytes());
             5
         } else if *self < i8::MIN as i64 {
-            buf.extend(iter::once(Format::INT16).chain((*self as i16).to_be_bytes()));
+            write_format(buf, Format::INT16, &(*self as i16).to_be_bytes());
             3
         } else if *self <= -33 {
-            buf.extend(iter::once(Format::INT8).chain(iter::once((*self as i8) as u8)));
+            buf.write_all(&[Format::INT8, (*self as i8) as u8]);
             2
         } else if *self <= -1 {
-            buf.extend(iter::once((*self | -32i64) as u8));
+            buf.write_all(&[(*self | -32i64) as u8]);
             1
         } else if *self <= i8::MAX as i64 {
-            buf.extend(iter::once(*self as u8 & Format::POSITIVE_FIXINT));
+            buf.write_all(&[*self as u8 & Format::POSITIVE_FIXINT]);
             1
         } else if *self <= i16::MAX as i64 {
-            buf.extend(iter::once(Format::INT16).chain((*self as i16).to_be_bytes()));
+            write_format(buf, Format::INT16, &(*self as i16).to_be_bytes());
             3
         } else if *self <= i32::MAX as i64 {
-            buf.extend(iter::once(Format::INT32).chain((*self as i32).to_be_bytes()));
+            write_format(buf, Format::INT32, &(*self as i32).to_be_bytes());
             5
         } else {
-            buf.extend(iter::once(Format::INT64).chain(self.to_be_bytes()));
+            write_format(buf, Format::INT64, &self.to_be_bytes());
             9
         }
     }
+
+    #[cfg(feature = "alloc")]
+    fn pack_canonical<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer,
+    {
+        match u64::try_from(*self) {
+            Ok(v) => v.pack(buf),
+            Err(_) => self.pack(buf),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+    where
+        T: Writer,
+    {
+        Ok(self.pack_canonical(buf))
+    }
 }
 
 impl Packable for i128 {
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
         if *self < i64::MIN as i128 {
-            buf.extend(
-                iter::once(Format::BIN8)
-                    .chain(iter::once(16))
-                    .chain(self.to_be_bytes()),
-            );
+            buf.write_all(&[Format::BIN8, 16]);
+            buf.write_all(&self.to_be_bytes());
             18
         } else if *self < i32::MIN as i128 {
-            buf.extend(iter::once(Format::INT64).chain((*self as i64).to_be_bytes()));
+            write_format(buf, Format::INT64, &(*self as i64).to_be_bytes());
             9
         } else if *self < i16::MIN as i128 {
-            buf.extend(iter::once(Format::INT32).chain((*self as i32).to_be_bytes()));
+            write_format(buf, Format::INT32, &(*self as i32).to_be_bytes());
             5
         } else if *self < i8::MIN as i128 {
-            buf.extend(iter::once(Format::INT16).chain((*self as i16).to_be_bytes()));
+            write_format(buf, Format::INT16, &(*self as i16).to_be_bytes());
             3
         } else if *self <= -33 {
-            buf.extend(iter::once(Format::INT8).chain(iter::once((*self as i8) as u8)));
+            buf.write_all(&[Format::INT8, (*self as i8) as u8]);
             2
         } else if *self <= -1 {
-            buf.extend(iter::once((*self | -32i128) as u8));
+            buf.write_all(&[(*self | -32i128) as u8]);
             1
         } else if *self <= i8::MAX as i128 {
-            buf.extend(iter::once(*self as u8 & Format::POSITIVE_FIXINT));
+            buf.write_all(&[*self as u8 & Format::POSITIVE_FIXINT]);
             1
         } else if *self <= i16::MAX as i128 {
-            buf.extend(iter::once(Format::INT16).chain((*self as i16).to_be_bytes()));
+            write_format(buf, Format::INT16, &(*self as i16).to_be_bytes());
             3
         } else if *self <= i32::MAX as i128 {
-            buf.extend(iter::once(Format::INT32).chain((*self as i32).to_be_bytes()));
+            write_format(buf, Format::INT32, &(*self as i32).to_be_bytes());
             5
         } else if *self <= i64::MAX as i128 {
-            buf.extend(iter::once(Format::INT64).chain((*self as i64).to_be_bytes()));
+            write_format(buf, Format::INT64, &(*self as i64).to_be_bytes());
             9
         } else {
-            buf.extend(
-                iter::once(Format::BIN8)
-                    .chain(iter::once(16))
-                    .chain(self.to_be_bytes()),
-            );
+            buf.write_all(&[Format::BIN8, 16]);
+            buf.write_all(&self.to_be_bytes());
             18
         }
     }
+
+    #[cfg(feature = "alloc")]
+    fn pack_canonical<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer,
+    {
+        match u64::try_from(*self) {
+            Ok(v) => v.pack(buf),
+            Err(_) => self.pack(buf),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+    where
+        T: Writer,
+    {
+        Ok(self.pack_canonical(buf))
+    }
 }
 
 impl Packable for isize {
     fn pack<T>(&self, buf: &mut T) -> usize
     where
-        T: Extend<u8>,
+        T: Writer,
     {
         if *self < i32::MIN as isize {
-            buf.extend(iter::once(Format::INT64).chain(self.to_be_bytes()));
+            write_format(buf, Format::INT64, &self.to_be_bytes());
             9
         } else if *self < i16::MIN as isize {
-            buf.extend(iter::once(Format::INT32).chain((*self as i32).to_be_bytes()));
+            write_format(buf, Format::INT32, &(*self as i32).to_be_bytes());
             5
         } else if *self < i8::MIN as isize {
-            buf.extend(iter::once(Format::INT16).chain((*self as i16).to_be_bytes()));
+            write_format(buf, Format::INT16, &(*self as i16).to_be_bytes());
             3
         } else if *self <= -33 {
-            buf.extend(iter::once(Format::INT8).chain(iter::once((*self as i8) as u8)));
+            buf.write_all(&[Format::INT8, (*self as i8) as u8]);
             2
         } else if *self <= -1 {
-            buf.extend(iter::once((*self | -32isize) as u8));
+            buf.write_all(&[(*self | -32isize) as u8]);
             1
         } else if *self <= i8::MAX as isize {
-            buf.extend(iter::once(*self as u8 & Format::POSITIVE_FIXINT));
+            buf.write_all(&[*self as u8 & Format::POSITIVE_FIXINT]);
             1
         } else if *self <= i16::MAX as isize {
-            buf.extend(iter::once(Format::INT16).chain((*self as i16).to_be_bytes()));
+            write_format(buf, Format::INT16, &(*self as i16).to_be_bytes());
             3
         } else if *self <= i32::MAX as isize {
-            buf.extend(iter::once(Format::INT32).chain((*self as i32).to_be_bytes()));
+            write_format(buf, Format::INT32, &(*self as i32).to_be_bytes());
             5
         } else {
-            buf.extend(iter::once(Format::INT64).chain(self.to_be_bytes()));
+            write_format(buf, Format::INT64, &self.to_be_bytes());
             9
         }
     }
+
+    #[cfg(feature = "alloc")]
+    fn pack_canonical<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer,
+    {
+        match u64::try_from(*self) {
+            Ok(v) => v.pack(buf),
+            Err(_) => self.pack(buf),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn try_pack_canonical<T>(&self, buf: &mut T) -> Result<usize, Error>
+    where
+        T: Writer,
+    {
+        Ok(self.pack_canonical(buf))
+    }
 }
@@ -1,9 +1,32 @@
-use super::{Format, Packable};
+use super::{Format, PackError, Packable, Writer};
 
 mod binary;
 pub(crate) mod collections;
 mod common;
 mod float;
 mod int;
+mod numeric;
 
-pub use collections::{pack_array, pack_map};
+pub use collections::{
+    pack_array, pack_array_len, pack_map, pack_map_len, try_pack_array, try_pack_array_len,
+    try_pack_map, try_pack_map_len,
+};
+pub use float::{pack_f32_compact, pack_f64_compact};
+pub use numeric::{pack_numbers, try_pack_numbers};
+
+/// The amount of bytes the infallible packing writes for a value the protocol can't represent:
+/// none, or a panic with the `strict` feature.
+pub(crate) fn too_large(_: PackError) -> usize {
+    #[cfg(feature = "strict")]
+    panic!("strict serialization enabled; the buffer is too large");
+    #[cfg(not(feature = "strict"))]
+    0
+}
+
+/// Writes the format marker followed by its data, such as the big endian bytes of a length, in a
+/// single write.
+pub(crate) fn write_format<T: Writer>(buf: &mut T, format: u8, data: &[u8]) {
+    let mut bytes = [format; 9];
+    bytes[1..=data.len()].copy_from_slice(data);
+    buf.write_all(&bytes[..=data.len()]);
+}
@@ -0,0 +1,77 @@
+use super::{too_large, try_pack_array_len, PackError, Writer};
+use crate::numeric::{width, Numeric};
+
+/// The amount of numbers encoded into the stack before they're written.
+const CHUNK: usize = 256;
+
+/// Packs the numbers as an array into the buffer, returning the amount of written bytes.
+///
+/// The bytes are the ones [pack_array](crate::pack_array) writes, but the numbers are encoded a
+/// chunk at a time: if all the numbers of a chunk share a fixed-width format, they're converted
+/// to big endian in a single pass, and every chunk is written at once.
+///
+/// An array longer than `u32::MAX` is not packed.
+pub fn pack_numbers<T, N>(buf: &mut T, values: &[N]) -> usize
+where
+    T: Writer,
+    N: Numeric,
+{
+    try_pack_numbers(buf, values).unwrap_or_else(too_large)
+}
+
+/// Packs the numbers as [pack_numbers] does, or fails with [PackError::TooLarge] if the array is
+/// too large.
+pub fn try_pack_numbers<T, N>(buf: &mut T, values: &[N]) -> Result<usize, PackError>
+where
+    T: Writer,
+    N: Numeric,
+{
+    let n = try_pack_array_len(buf, values.len())?;
+    buf.reserve(values.len());
+    let mut chunk = [0u8; CHUNK * 9];
+    let n = n + values
+        .chunks(CHUNK)
+        .map(|values| {
+            let len = encode(values, &mut chunk);
+            buf.write_all(&chunk[..len]);
+            len
+        })
+        .sum::<usize>();
+    Ok(n)
+}
+
+/// Encodes the numbers into the slice, returning the amount of written bytes.
+fn encode<N>(values: &[N], mut out: &mut [u8]) -> usize
+where
+    N: Numeric,
+{
+    let class = values[0].class();
+    if values.iter().any(|v| v.class() != class) {
+        return values.iter().map(|v| v.pack(&mut out)).sum();
+    }
+    match width(class) {
+        Some(1) => {
+            for (v, out) in values.iter().zip(out.iter_mut()) {
+                *out = v.bits() as u8;
+            }
+            values.len()
+        }
+        Some(2) => fill::<1, N>(class, values, out),
+        Some(3) => fill::<2, N>(class, values, out),
+        Some(5) => fill::<4, N>(class, values, out),
+        _ => fill::<8, N>(class, values, out),
+    }
+}
+
+/// Encodes every number as the format byte followed by the `D` low bytes of its bits in big
+/// endian, returning the amount of written bytes.
+fn fill<const D: usize, N>(format: u8, values: &[N], out: &mut [u8]) -> usize
+where
+    N: Numeric,
+{
+    for (v, out) in values.iter().zip(out.chunks_exact_mut(D + 1)) {
+        out[0] = format;
+        out[1..].copy_from_slice(&v.bits().to_be_bytes()[8 - D..]);
+    }
+    values.len() * (D + 1)
+}
@@ -0,0 +1,114 @@
+use super::{skip_value, Error, Packable, Unpackable, Writer};
+
+/// The encoded bytes of a single value, borrowed without decoding them.
+///
+/// It's packed verbatim, so a value can be forwarded without a decode and re-encode round trip.
+/// Arrays and maps are captured as a whole; derived structs pack their fields in sequence, and a
+/// [Raw] of them captures only the first field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Raw<'a>(pub(crate) &'a [u8]);
+
+impl<'a> Raw<'a> {
+    /// Wraps the bytes, failing unless they hold exactly one encoded value.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Error> {
+        match skip_value(bytes)? {
+            n if n == bytes.len() => Ok(Self(bytes)),
+            _ => Err(Error::TrailingBytes),
+        }
+    }
+
+    /// The encoded bytes of the value.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+
+    /// Decodes the value.
+    pub fn decode<T>(&self) -> Result<T, T::Error>
+    where
+        T: Unpackable,
+    {
+        T::unpack(self.0).map(|(_, t)| t)
+    }
+}
+
+impl Packable for Raw<'_> {
+    fn pack<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer,
+    {
+        buf.write_all(self.0);
+        self.0.len()
+    }
+}
+
+/// Unpacks the next value from the buffer without decoding it, returning its encoded bytes and
+/// the amount of read bytes.
+pub fn unpack_raw(buf: &[u8]) -> Result<(usize, Raw<'_>), Error> {
+    let n = skip_value(buf)?;
+    Ok((n, Raw(&buf[..n])))
+}
+
+#[cfg(feature = "alloc")]
+pub use alloc::RawBuf;
+
+#[cfg(feature = "alloc")]
+mod alloc {
+    use super::*;
+    use crate::take_value_iter;
+    use ::alloc::vec::Vec;
+
+    /// The owned encoded bytes of a single value, unpacked without decoding them.
+    ///
+    /// Unlike [Raw], it implements [Unpackable], so it can be a field of a derived type.
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    pub struct RawBuf(Vec<u8>);
+
+    impl RawBuf {
+        /// Wraps the bytes, failing unless they hold exactly one encoded value.
+        pub fn new(bytes: Vec<u8>) -> Result<Self, Error> {
+            Raw::new(&bytes)?;
+            Ok(Self(bytes))
+        }
+
+        /// Borrows the encoded value.
+        pub fn as_raw(&self) -> Raw<'_> {
+            Raw(&self.0)
+        }
+
+        /// Returns the encoded bytes of the value.
+        pub fn into_bytes(self) -> Vec<u8> {
+            self.0
+        }
+    }
+
+    impl From<Raw<'_>> for RawBuf {
+        fn from(raw: Raw<'_>) -> Self {
+            Self(raw.0.to_vec())
+        }
+    }
+
+    impl Packable for RawBuf {
+        fn pack<T>(&self, buf: &mut T) -> usize
+        where
+            T: Writer,
+        {
+            self.as_raw().pack(buf)
+        }
+    }
+
+    impl Unpackable for RawBuf {
+        type Error = Error;
+
+        fn unpack(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+            unpack_raw(buf).map(|(n, raw)| (n, raw.into()))
+        }
+
+        fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
+        where
+            I: IntoIterator<Item = u8>,
+        {
+            let v = take_value_iter(bytes)?;
+            Ok((v.len(), Self(v)))
+        }
+    }
+}
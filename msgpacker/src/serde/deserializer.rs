@@ -1,35 +1,166 @@
-use core::fmt;
+use core::{fmt, marker::PhantomData};
 
 use serde::{de, Deserializer as _};
 
-use crate::{
-    format::Format,
-    unpack::{binary, collections},
-    Error, Unpackable as _,
+#[cfg(feature = "alloc")]
+use super::IterRead;
+use super::{
+    extension::{self, EXT_STRUCT_NAME},
+    raw::RAW_VALUE_NAME,
+    Config, Read, SliceRead,
 };
+use crate::{format::Format, unpack::collections, Error, FloatOptions};
+#[cfg(feature = "alloc")]
+use ::alloc::{collections::BTreeSet, vec::Vec};
+
+/// A configurable [serde] deserializer from a slice or an iterator.
+///
+/// Enums are accepted both as the index followed by the bare fields and as the name or a
+/// single-entry map from the name or the index to the fields. Structs are the bare fields by
+/// default; [Deserializer::with_struct_map] reads them as self-contained values instead.
+///
+/// ```rust
+/// use msgpacker::serde::Deserializer;
+/// use serde::Deserialize;
+///
+/// #[derive(Debug, PartialEq, Deserialize)]
+/// struct Point {
+///     x: u8,
+/// }
+///
+/// let mut de = Deserializer::new(&[0x81, 0xa1, b'x', 0x01]).with_struct_map(true);
+///
+/// assert_eq!(Point::deserialize(&mut de).unwrap(), Point { x: 1 });
+/// assert!(de.remaining().is_empty());
+/// ```
+pub struct Deserializer<'de, R = SliceRead<'de>> {
+    read: R,
+    config: Config,
+    marker: PhantomData<&'de ()>,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Creates a deserializer from the slice with the default options.
+    pub fn new(buf: &'de [u8]) -> Self {
+        Self::with_read(SliceRead::new(buf))
+    }
+
+    /// The bytes that weren't read yet.
+    pub fn remaining(&self) -> &'de [u8] {
+        self.read.buf
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I> Deserializer<'_, IterRead<I>>
+where
+    I: Iterator<Item = u8>,
+{
+    /// Creates a deserializer from the iterator with the default options.
+    ///
+    /// Strings and bytes are copied out of the iterator, so the deserialized types must own
+    /// their data. Only the bytes of the deserialized values are consumed.
+    pub fn new_iter<B>(bytes: B) -> Self
+    where
+        B: IntoIterator<IntoIter = I>,
+    {
+        Self::with_read(IterRead::new(bytes.into_iter()))
+    }
 
-pub struct MsgpackDeserializer<'a>(pub &'a [u8]);
+    #[cfg(feature = "std")]
+    pub(super) fn into_inner(self) -> I {
+        self.read.iter
+    }
+}
+
+impl<'de, R: Read<'de>> Deserializer<'de, R> {
+    fn with_read(read: R) -> Self {
+        Self {
+            read,
+            config: Config::default(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Checks that all the bytes were read, failing with [Error::TrailingBytes] otherwise.
+    pub fn end(&mut self) -> Result<(), Error> {
+        match self.read.peek() {
+            Ok(_) => Err(Error::TrailingBytes),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Reads structs as self-contained values: either a map keyed by the field names or
+    /// indexes, or an array of the fields. Otherwise, structs are the bare fields.
+    pub fn with_struct_map(mut self, struct_map: bool) -> Self {
+        self.config.struct_map = struct_map;
+        self
+    }
+
+    /// Reads the fields of struct variants as self-contained values, as packed by
+    /// [Serializer::with_enum_names](super::Serializer::with_enum_names). Enums are accepted
+    /// by name regardless of this option.
+    pub fn with_enum_names(mut self, enum_names: bool) -> Self {
+        self.config.enum_names = enum_names;
+        self
+    }
+
+    /// Sets the value of `is_human_readable`, which types may check to pick a compact form.
+    /// Defaults to `true`.
+    pub fn with_human_readable(mut self, human_readable: bool) -> Self {
+        self.config.human_readable = human_readable;
+        self
+    }
+
+    /// Sets how floats are read from the formats of other numbers, such as the integers of
+    /// [Serializer::with_compact_floats](super::Serializer::with_compact_floats).
+    pub fn with_floats(mut self, floats: FloatOptions) -> Self {
+        self.config.floats = floats;
+        self
+    }
+
+    /// Reads under the rules of [strict](crate::strict), failing on non-minimal integers and
+    /// lengths, repeated map keys and invalid timestamps.
+    ///
+    /// Map keys are compared by their bytes. The elements of sets aren't checked, as sets are
+    /// deserialized as sequences.
+    #[cfg(feature = "alloc")]
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.config.strict = strict;
+        self.read.set_strict(strict);
+        self
+    }
+
+    /// Reads a struct packed as a map or an array.
+    fn deserialize_container<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.read.peek()? {
+            0x80..=0x8f | Format::MAP16 | Format::MAP32 => self.deserialize_map(visitor),
+            0x90..=0x9f | Format::ARRAY16 | Format::ARRAY32 => self.deserialize_seq(visitor),
+            _ => Err(Error::UnexpectedFormatTag),
+        }
+    }
+}
 
 impl de::Error for Error {
-    fn custom<T>(_msg: T) -> Self
+    fn custom<T>(msg: T) -> Self
     where
         T: fmt::Display,
     {
-        Error::NotImplemented
+        super::custom(msg)
     }
 }
 
-impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
+impl<'de, R: Read<'de>> de::Deserializer<'de> for &mut Deserializer<'de, R> {
     type Error = Error;
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        if self.0.is_empty() {
-            return Err(Error::BufferTooShort);
-        }
-        match self.0[0] {
+        match self.read.peek()? {
             0x00..=Format::POSITIVE_FIXINT => self.deserialize_u8(visitor),
             0x80..=0x8f => self.deserialize_map(visitor),
             0x90..=0x9f => self.deserialize_seq(visitor),
@@ -52,7 +183,6 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
             Format::STR8 | Format::STR16 | Format::STR32 => self.deserialize_str(visitor),
             Format::ARRAY16 | Format::ARRAY32 => self.deserialize_seq(visitor),
             Format::MAP16 | Format::MAP32 => self.deserialize_map(visitor),
-            #[cfg(feature = "alloc")]
             Format::FIXEXT1
             | Format::FIXEXT2
             | Format::FIXEXT4
@@ -60,7 +190,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
             | Format::FIXEXT16
             | Format::EXT8
             | Format::EXT16
-            | Format::EXT32 => self.deserialize_byte_buf(visitor),
+            | Format::EXT32 => self.read.visit_ext(visitor, false),
             _ => Err(Error::UnexpectedFormatTag),
         }
     }
@@ -69,8 +199,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let (n, v) = bool::unpack(self.0)?;
-        self.0 = &self.0[n..];
+        let v = self.read.unpack::<bool>()?;
         visitor.visit_bool(v)
     }
 
@@ -78,8 +207,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let (n, v) = i8::unpack(self.0)?;
-        self.0 = &self.0[n..];
+        let v = self.read.unpack::<i8>()?;
         visitor.visit_i8(v)
     }
 
@@ -87,8 +215,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let (n, v) = i16::unpack(self.0)?;
-        self.0 = &self.0[n..];
+        let v = self.read.unpack::<i16>()?;
         visitor.visit_i16(v)
     }
 
@@ -96,8 +223,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let (n, v) = i32::unpack(self.0)?;
-        self.0 = &self.0[n..];
+        let v = self.read.unpack::<i32>()?;
         visitor.visit_i32(v)
     }
 
@@ -105,8 +231,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let (n, v) = i64::unpack(self.0)?;
-        self.0 = &self.0[n..];
+        let v = self.read.unpack::<i64>()?;
         visitor.visit_i64(v)
     }
 
@@ -114,8 +239,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let (n, v) = i128::unpack(self.0)?;
-        self.0 = &self.0[n..];
+        let v = self.read.unpack::<i128>()?;
         visitor.visit_i128(v)
     }
 
@@ -123,8 +247,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let (n, v) = u8::unpack(self.0)?;
-        self.0 = &self.0[n..];
+        let v = self.read.unpack::<u8>()?;
         visitor.visit_u8(v)
     }
 
@@ -132,8 +255,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let (n, v) = u16::unpack(self.0)?;
-        self.0 = &self.0[n..];
+        let v = self.read.unpack::<u16>()?;
         visitor.visit_u16(v)
     }
 
@@ -141,8 +263,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let (n, v) = u32::unpack(self.0)?;
-        self.0 = &self.0[n..];
+        let v = self.read.unpack::<u32>()?;
         visitor.visit_u32(v)
     }
 
@@ -150,8 +271,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let (n, v) = u64::unpack(self.0)?;
-        self.0 = &self.0[n..];
+        let v = self.read.unpack::<u64>()?;
         visitor.visit_u64(v)
     }
 
@@ -159,8 +279,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let (n, v) = u128::unpack(self.0)?;
-        self.0 = &self.0[n..];
+        let v = self.read.unpack::<u128>()?;
         visitor.visit_u128(v)
     }
 
@@ -168,8 +287,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let (n, v) = f32::unpack(self.0)?;
-        self.0 = &self.0[n..];
+        let v = self.read.unpack_f32(self.config.floats)?;
         visitor.visit_f32(v)
     }
 
@@ -177,8 +295,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let (n, v) = f64::unpack(self.0)?;
-        self.0 = &self.0[n..];
+        let v = self.read.unpack_f64(self.config.floats)?;
         visitor.visit_f64(v)
     }
 
@@ -186,8 +303,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let (n, v) = char::unpack(self.0)?;
-        self.0 = &self.0[n..];
+        let v = self.read.unpack::<char>()?;
         visitor.visit_char(v)
     }
 
@@ -195,9 +311,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let (n, v) = binary::unpack_str(self.0)?;
-        self.0 = &self.0[n..];
-        visitor.visit_borrowed_str(v)
+        self.read.visit_str(visitor)
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -212,9 +326,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
 
         #[cfg(feature = "alloc")]
         {
-            let (n, v) = ::alloc::string::String::unpack(self.0)?;
-            self.0 = &self.0[n..];
-            return visitor.visit_string(v);
+            visitor.visit_string(self.read.unpack::<::alloc::string::String>()?)
         }
     }
 
@@ -222,9 +334,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let (n, v) = binary::unpack_bytes(self.0)?;
-        self.0 = &self.0[n..];
-        visitor.visit_borrowed_bytes(v)
+        self.read.visit_bytes(visitor)
     }
 
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -239,9 +349,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
 
         #[cfg(feature = "alloc")]
         {
-            let (n, v) = ::alloc::vec::Vec::unpack(self.0)?;
-            self.0 = &self.0[n..];
-            return visitor.visit_byte_buf(v);
+            visitor.visit_byte_buf(self.read.unpack::<::alloc::vec::Vec<u8>>()?)
         }
     }
 
@@ -249,11 +357,8 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        if self.0.is_empty() {
-            return Err(Error::BufferTooShort);
-        }
-        if self.0[0] == Format::NIL {
-            self.0 = &self.0[1..];
+        if self.read.peek()? == Format::NIL {
+            self.read.unpack::<()>()?;
             visitor.visit_none()
         } else {
             visitor.visit_some(self)
@@ -264,8 +369,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let (n, _) = <()>::unpack(self.0)?;
-        self.0 = &self.0[n..];
+        self.read.unpack::<()>()?;
         visitor.visit_unit()
     }
 
@@ -282,12 +386,18 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
 
     fn deserialize_newtype_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
+        if name == RAW_VALUE_NAME {
+            return self.read.visit_raw(visitor);
+        }
+        if name == EXT_STRUCT_NAME && extension::is_ext(self.read.peek()?) {
+            return self.read.visit_ext(visitor, true);
+        }
         visitor.visit_newtype_struct(self)
     }
 
@@ -295,22 +405,15 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let (n, len) = collections::unpack_array_len(self.0)?;
-        self.0 = &self.0[n..];
-        visitor.visit_seq(MsgpackDeserializerSeq {
-            m: self,
-            count: len,
-        })
+        let len = self.read.unpack_len(collections::unpack_array_len)?;
+        visitor.visit_seq(MsgpackDeserializerSeq::new(self, len))
     }
 
     fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_seq(MsgpackDeserializerSeq {
-            m: self,
-            count: len,
-        })
+        visitor.visit_seq(MsgpackDeserializerSeq::new(self, len))
     }
 
     fn deserialize_tuple_struct<V>(
@@ -322,22 +425,15 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_seq(MsgpackDeserializerSeq {
-            m: self,
-            count: len,
-        })
+        visitor.visit_seq(MsgpackDeserializerSeq::new(self, len))
     }
 
     fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        let (n, len) = collections::unpack_map_len(self.0)?;
-        self.0 = &self.0[n..];
-        visitor.visit_map(MsgpackDeserializerSeq {
-            m: self,
-            count: len,
-        })
+        let len = self.read.unpack_len(collections::unpack_map_len)?;
+        visitor.visit_map(MsgpackDeserializerSeq::map(self, len))
     }
 
     fn deserialize_struct<V>(
@@ -349,10 +445,10 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_seq(MsgpackDeserializerSeq {
-            m: self,
-            count: fields.len(),
-        })
+        if self.config.struct_map {
+            return self.deserialize_container(visitor);
+        }
+        visitor.visit_seq(MsgpackDeserializerSeq::new(self, fields.len()))
     }
 
     fn deserialize_enum<V>(
@@ -364,14 +460,33 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_enum(MsgpackEnumHandler { de: self })
+        let (named, map) = match self.read.peek()? {
+            0xa0..=0xbf | Format::STR8 | Format::STR16 | Format::STR32 => (true, false),
+            0x80..=0x8f | Format::MAP16 | Format::MAP32 => {
+                if self.read.unpack_len(collections::unpack_map_len)? != 1 {
+                    return Err(Error::UnexpectedFormatTag);
+                }
+                (true, true)
+            }
+            _ => (false, false),
+        };
+        visitor.visit_enum(MsgpackEnumHandler {
+            de: self,
+            named,
+            map,
+        })
     }
 
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_u32(visitor)
+        match self.read.peek()? {
+            0xa0..=0xbf | Format::STR8 | Format::STR16 | Format::STR32 => {
+                self.deserialize_str(visitor)
+            }
+            _ => self.deserialize_u32(visitor),
+        }
     }
 
     fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -380,14 +495,43 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut MsgpackDeserializer<'de> {
     {
         self.deserialize_any(visitor)
     }
+
+    fn is_human_readable(&self) -> bool {
+        self.config.human_readable
+    }
 }
 
-struct MsgpackDeserializerSeq<'a, 'de: 'a> {
-    m: &'a mut MsgpackDeserializer<'de>,
+struct MsgpackDeserializerSeq<'a, 'de: 'a, R> {
+    m: &'a mut Deserializer<'de, R>,
     count: usize,
+    /// The bytes of the read map keys, kept under the strict rules to reject repeated ones.
+    #[cfg(feature = "alloc")]
+    keys: Option<BTreeSet<Vec<u8>>>,
 }
 
-impl<'de, 'a> de::SeqAccess<'de> for MsgpackDeserializerSeq<'a, 'de> {
+impl<'a, 'de, R> MsgpackDeserializerSeq<'a, 'de, R> {
+    fn new(m: &'a mut Deserializer<'de, R>, count: usize) -> Self {
+        Self {
+            m,
+            count,
+            #[cfg(feature = "alloc")]
+            keys: None,
+        }
+    }
+
+    fn map(m: &'a mut Deserializer<'de, R>, count: usize) -> Self {
+        #[cfg(feature = "alloc")]
+        let keys = m.config.strict.then(BTreeSet::new);
+        Self {
+            m,
+            count,
+            #[cfg(feature = "alloc")]
+            keys,
+        }
+    }
+}
+
+impl<'de, 'a, R: Read<'de>> de::SeqAccess<'de> for MsgpackDeserializerSeq<'a, 'de, R> {
     type Error = Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
@@ -402,7 +546,7 @@ impl<'de, 'a> de::SeqAccess<'de> for MsgpackDeserializerSeq<'a, 'de> {
     }
 }
 
-impl<'de, 'a> de::MapAccess<'de> for MsgpackDeserializerSeq<'a, 'de> {
+impl<'de, 'a, R: Read<'de>> de::MapAccess<'de> for MsgpackDeserializerSeq<'a, 'de, R> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
@@ -413,6 +557,17 @@ impl<'de, 'a> de::MapAccess<'de> for MsgpackDeserializerSeq<'a, 'de> {
             return Ok(None);
         }
         self.count -= 1;
+        #[cfg(feature = "alloc")]
+        if let Some(keys) = &mut self.keys {
+            let mark = self.m.read.mark();
+            let key = seed.deserialize(&mut *self.m);
+            let bytes = self.m.read.recorded(mark);
+            let key = key?;
+            if !keys.insert(crate::strict::key_bytes(&bytes).into_owned()) {
+                return Err(Error::DuplicateKey);
+            }
+            return Ok(Some(key));
+        }
         seed.deserialize(&mut *self.m).map(Some)
     }
 
@@ -424,15 +579,33 @@ impl<'de, 'a> de::MapAccess<'de> for MsgpackDeserializerSeq<'a, 'de> {
     }
 }
 
-struct MsgpackEnumHandler<'a, 'de: 'a> {
-    de: &'a mut MsgpackDeserializer<'de>,
+/// The variant of an enum, either by index followed by the bare fields, or `named` by a string
+/// or a single-entry map with self-contained fields.
+struct MsgpackEnumHandler<'a, 'de: 'a, R> {
+    de: &'a mut Deserializer<'de, R>,
+    named: bool,
+    /// Whether the variant is the key of a single-entry map, followed by its payload.
+    map: bool,
 }
 
-impl<'de, 'a> de::VariantAccess<'de> for MsgpackEnumHandler<'a, 'de> {
+impl<'de, 'a, R: Read<'de>> de::VariantAccess<'de> for MsgpackEnumHandler<'a, 'de, R> {
     type Error = Error;
 
     fn unit_variant(self) -> Result<(), Self::Error> {
-        Ok(())
+        if !self.map {
+            return Ok(());
+        }
+        // the payload of a unit variant in a map is nil, or an empty array
+        match self.de.read.peek()? {
+            Format::NIL => self.de.read.unpack::<()>(),
+            0x90 | Format::ARRAY16 | Format::ARRAY32 => {
+                match self.de.read.unpack_len(collections::unpack_array_len)? {
+                    0 => Ok(()),
+                    _ => Err(Error::UnexpectedFormatTag),
+                }
+            }
+            _ => Err(Error::UnexpectedFormatTag),
+        }
     }
 
     fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
@@ -446,7 +619,10 @@ impl<'de, 'a> de::VariantAccess<'de> for MsgpackEnumHandler<'a, 'de> {
     where
         V: de::Visitor<'de>,
     {
-        self.de.deserialize_tuple(len, visitor)
+        match self.named {
+            true => self.de.deserialize_seq(visitor),
+            false => self.de.deserialize_tuple(len, visitor),
+        }
     }
 
     fn struct_variant<V>(
@@ -457,11 +633,14 @@ impl<'de, 'a> de::VariantAccess<'de> for MsgpackEnumHandler<'a, 'de> {
     where
         V: de::Visitor<'de>,
     {
-        self.de.deserialize_struct("", fields, visitor)
+        match self.named || self.de.config.enum_names {
+            true => self.de.deserialize_container(visitor),
+            false => self.de.deserialize_struct("", fields, visitor),
+        }
     }
 }
 
-impl<'de, 'a> de::EnumAccess<'de> for MsgpackEnumHandler<'a, 'de> {
+impl<'de, 'a, R: Read<'de>> de::EnumAccess<'de> for MsgpackEnumHandler<'a, 'de, R> {
     type Error = Error;
     type Variant = Self;
 
@@ -475,18 +654,18 @@ impl<'de, 'a> de::EnumAccess<'de> for MsgpackEnumHandler<'a, 'de> {
     }
 }
 
-struct MsgpackTagDeserializer<'a, 'de: 'a> {
-    de: &'a mut MsgpackDeserializer<'de>,
+struct MsgpackTagDeserializer<'a, 'de: 'a, R> {
+    de: &'a mut Deserializer<'de, R>,
 }
 
-impl<'de, 'a> serde::Deserializer<'de> for MsgpackTagDeserializer<'a, 'de> {
+impl<'de, 'a, R: Read<'de>> serde::Deserializer<'de> for MsgpackTagDeserializer<'a, 'de, R> {
     type Error = Error;
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_identifier(visitor)
+        self.de.deserialize_identifier(visitor)
     }
 
     fn deserialize_bool<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
@@ -698,7 +877,7 @@ impl<'de, 'a> serde::Deserializer<'de> for MsgpackTagDeserializer<'a, 'de> {
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_u32(visitor)
+        self.de.deserialize_identifier(visitor)
     }
 
     fn deserialize_ignored_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
@@ -0,0 +1,248 @@
+use serde::de::{
+    self,
+    value::{SeqAccessDeserializer, SeqDeserializer},
+    IntoDeserializer as _,
+};
+
+pub(super) use crate::unpack::{is_ext, unpack_ext};
+use crate::Error;
+
+/// The name of the newtype struct packed as an extension.
+///
+/// The serializer packs a newtype struct with this name, wrapping a tuple of the `i8` type and
+/// the data bytes, as an extension value. The deserializer hands extension values to the
+/// visitor of such a newtype struct, and to `deserialize_any` as the bare two-element sequence,
+/// so generic visitors such as `serde_json::Value` accept them.
+pub const EXT_STRUCT_NAME: &str = "_ExtStruct";
+
+/// The `(i8, bytes)` tuple of an extension value, with the data bytes deserialized by `D`.
+struct ExtAccess<D> {
+    t: Option<i8>,
+    data: Option<D>,
+}
+
+impl<D> ExtAccess<D> {
+    fn new(t: i8, data: D) -> Self {
+        Self {
+            t: Some(t),
+            data: Some(data),
+        }
+    }
+}
+
+/// Hands an extension to the visitor, as a newtype struct wrapping the `(i8, bytes)` tuple with
+/// the data deserialized by `data` if `newtype`, or as the bare tuple with the data as a
+/// sequence of bytes, which generic visitors such as `serde_json::Value` accept.
+pub(super) fn visit<'de, D, V>(
+    t: i8,
+    data: &[u8],
+    bytes: D,
+    visitor: V,
+    newtype: bool,
+) -> Result<V::Value, Error>
+where
+    D: de::Deserializer<'de, Error = Error>,
+    V: de::Visitor<'de>,
+{
+    match newtype {
+        true => visitor.visit_newtype_struct(SeqAccessDeserializer::new(ExtAccess::new(t, bytes))),
+        false => visitor.visit_seq(ExtAccess::new(
+            t,
+            SeqDeserializer::<_, Error>::new(data.iter().copied()),
+        )),
+    }
+}
+
+impl<'de, D> de::SeqAccess<'de> for ExtAccess<D>
+where
+    D: de::Deserializer<'de, Error = Error>,
+{
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        if let Some(t) = self.t.take() {
+            return seed.deserialize(t.into_deserializer()).map(Some);
+        }
+        match self.data.take() {
+            Some(data) => seed.deserialize(data).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.t.is_some() as usize + self.data.is_some() as usize)
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod alloc {
+    use super::*;
+    use crate::{Extension, Packable as _, Unpackable as _};
+    use ::alloc::vec::Vec;
+    use core::fmt;
+    use serde::{ser, Deserialize, Deserializer, Serialize, Serializer};
+
+    /// The data of an extension, packed as bytes.
+    struct Bytes<'a>(&'a [u8]);
+
+    impl Serialize for Bytes<'_> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.serialize_bytes(self.0)
+        }
+    }
+
+    impl Serialize for Extension {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let bytes = self.pack_to_vec();
+            let (_, t, data) = unpack_ext(&bytes).map_err(ser::Error::custom)?;
+            serializer.serialize_newtype_struct(EXT_STRUCT_NAME, &(t, Bytes(data)))
+        }
+    }
+
+    /// The data of an extension, unpacked from bytes or a sequence of bytes.
+    struct ByteBuf(Vec<u8>);
+
+    impl<'de> Deserialize<'de> for ByteBuf {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            struct Visitor;
+
+            impl<'de> de::Visitor<'de> for Visitor {
+                type Value = ByteBuf;
+
+                fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    f.write_str("the data of an extension")
+                }
+
+                fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> {
+                    Ok(ByteBuf(v.to_vec()))
+                }
+
+                fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+                    Ok(ByteBuf(v))
+                }
+
+                fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+                where
+                    A: de::SeqAccess<'de>,
+                {
+                    let mut v = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                    while let Some(b) = seq.next_element()? {
+                        v.push(b);
+                    }
+                    Ok(ByteBuf(v))
+                }
+            }
+
+            deserializer.deserialize_bytes(Visitor)
+        }
+    }
+
+    struct ExtensionVisitor;
+
+    impl<'de> de::Visitor<'de> for ExtensionVisitor {
+        type Value = Extension;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a msgpack extension")
+        }
+
+        fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_tuple(2, self)
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: de::SeqAccess<'de>,
+        {
+            let t: i8 = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+            let ByteBuf(data) = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+
+            let ext = Extension::Ext(t, data);
+            if t != Extension::TIMESTAMP {
+                return Ok(ext);
+            }
+            Extension::unpack(&ext.pack_to_vec())
+                .map(|(_, ext)| ext)
+                .map_err(de::Error::custom)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Extension {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_newtype_struct(EXT_STRUCT_NAME, ExtensionVisitor)
+        }
+    }
+}
+
+/// Packs a [Duration](core::time::Duration) since the Unix epoch as a timestamp extension.
+///
+/// ```rust
+/// use core::time::Duration;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// struct Event {
+///     #[serde(with = "msgpacker::serde::timestamp")]
+///     at: Duration,
+/// }
+///
+/// let event = Event {
+///     at: Duration::new(1_700_000_000, 5),
+/// };
+/// let bytes = msgpacker::serde::to_vec(&event);
+///
+/// assert_eq!(bytes[..2], [0xd7, 0xff]);
+/// assert_eq!(msgpacker::serde::from_slice::<Event>(&bytes).unwrap(), event);
+/// ```
+#[cfg(feature = "alloc")]
+pub mod timestamp {
+    use core::time::Duration;
+
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::Extension;
+
+    /// Serializes the duration as a timestamp extension.
+    pub fn serialize<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Extension::Timestamp(*duration).serialize(serializer)
+    }
+
+    /// Deserializes a timestamp extension into the duration.
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Extension::deserialize(deserializer)? {
+            Extension::Timestamp(d) => Ok(d),
+            Extension::Ext(t, _) => Err(de::Error::invalid_value(
+                de::Unexpected::Signed(t as i64),
+                &"a timestamp extension",
+            )),
+        }
+    }
+}
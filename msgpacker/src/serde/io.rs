@@ -0,0 +1,70 @@
+use std::io;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::Deserializer;
+use crate::{Error, IoWriter};
+
+/// The bytes of an [io::Read], ending at the first error.
+struct ReadBytes<R> {
+    r: R,
+    error: Option<io::Error>,
+}
+
+impl<R: io::Read> Iterator for ReadBytes<R> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        let mut b = [0u8];
+        loop {
+            match self.r.read(&mut b) {
+                Ok(0) => return None,
+                Ok(_) => return Some(b[0]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.error = Some(e);
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Serializes the provided value into the writer.
+///
+/// The bytes are written as they are produced, so an unbuffered writer should be wrapped in an
+/// [io::BufWriter].
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<(), Error>
+where
+    W: io::Write,
+    T: Serialize + ?Sized,
+{
+    let mut w = IoWriter::new(writer);
+    let result = value.serialize(&mut super::Serializer::new(&mut w));
+    match w.into_inner() {
+        Err(e) => Err(Error::Io(e.kind())),
+        Ok(_) => result,
+    }
+}
+
+/// Deserializes the next value from the reader.
+///
+/// Only the bytes of the value are read, so consecutive values can be read from the same reader.
+/// The reader is read a byte at a time, and an unbuffered reader should be wrapped in an
+/// [io::BufReader]. Strings and bytes are copied out of the reader, so the deserialized type
+/// must own its data.
+pub fn from_reader<R, T>(reader: R) -> Result<T, Error>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    let mut de = Deserializer::new_iter(ReadBytes {
+        r: reader,
+        error: None,
+    });
+    let value = T::deserialize(&mut de);
+    match de.into_inner().error {
+        Some(e) => Err(Error::Io(e.kind())),
+        None => value,
+    }
+}
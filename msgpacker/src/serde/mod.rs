@@ -2,41 +2,169 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::Error;
+#[cfg(feature = "alloc")]
+use serde::de::DeserializeOwned;
+
+use core::fmt;
+
+use crate::{Error, ExtendWriter, FloatOptions};
 
 mod deserializer;
+mod extension;
+mod raw;
+mod read;
 mod serializer;
+mod stream;
+
+#[cfg(feature = "std")]
+mod io;
+
+pub use deserializer::Deserializer;
+pub use extension::EXT_STRUCT_NAME;
+pub use raw::RawValue;
+pub use read::{Read, SliceRead};
+pub use serializer::Serializer;
+pub use stream::StreamDeserializer;
+
+#[cfg(feature = "alloc")]
+pub use extension::timestamp;
+
+#[cfg(feature = "alloc")]
+pub use read::IterRead;
 
-/// Serializes the provided value into the extendable buffer.
+#[cfg(feature = "std")]
+pub use io::{from_reader, to_writer};
+/// The options of the [Serializer] and the [Deserializer].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Config {
+    struct_map: bool,
+    enum_names: bool,
+    human_readable: bool,
+    floats: FloatOptions,
+    compact_floats: bool,
+    #[cfg(feature = "alloc")]
+    strict: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            struct_map: false,
+            enum_names: false,
+            human_readable: true,
+            floats: FloatOptions::new(),
+            compact_floats: false,
+            #[cfg(feature = "alloc")]
+            strict: false,
+        }
+    }
+}
+
+/// The error of `ser::Error::custom` and `de::Error::custom`, keeping the message if possible.
+fn custom<T>(msg: T) -> Error
+where
+    T: fmt::Display,
+{
+    #[cfg(feature = "alloc")]
+    {
+        Error::Custom(::alloc::string::ToString::to_string(&msg))
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    {
+        let _ = msg;
+        Error::NotImplemented
+    }
+}
+
+/// Serializes the provided value into the buffer.
 ///
-/// This operation is infallible as it will only allocate bytes.
+/// Errors are ignored, leaving the bytes written up to the failure in the buffer; use
+/// [try_to_buffer] to detect them. The bytes are written through an [ExtendWriter]; a
+/// [Writer](crate::Writer) is serialized into with [Serializer::new].
 pub fn to_buffer<X, T>(buffer: &mut X, value: &T)
 where
     X: Extend<u8>,
     T: Serialize + ?Sized,
 {
-    value
-        .serialize(&mut serializer::MsgpackSerializer::from(buffer))
-        .ok();
+    try_to_buffer(buffer, value).ok();
+}
+
+/// Serializes the provided value into the buffer.
+///
+/// Fails if the value can't be represented, such as a sequence of unknown length without
+/// `alloc`. The bytes written up to the failure are kept in the buffer.
+pub fn try_to_buffer<X, T>(buffer: &mut X, value: &T) -> Result<(), Error>
+where
+    X: Extend<u8>,
+    T: Serialize + ?Sized,
+{
+    value.serialize(&mut Serializer::new(&mut ExtendWriter(buffer)))
 }
 
 /// Serializes the provided value into a [Vec<u8>].
+///
+/// Errors are ignored, returning the bytes written up to the failure; use [try_to_vec] to detect
+/// them.
 #[cfg(feature = "alloc")]
 pub fn to_vec<T>(value: &T) -> ::alloc::vec::Vec<u8>
 where
     T: Serialize + ?Sized,
 {
-    let mut v = Vec::new();
+    let mut v = ::alloc::vec::Vec::new();
 
-    to_buffer(&mut v, value);
+    value.serialize(&mut Serializer::new(&mut v)).ok();
 
     v
 }
 
+/// Serializes the provided value into a [Vec<u8>], failing if the value can't be represented.
+#[cfg(feature = "alloc")]
+pub fn try_to_vec<T>(value: &T) -> Result<::alloc::vec::Vec<u8>, Error>
+where
+    T: Serialize + ?Sized,
+{
+    let mut v = ::alloc::vec::Vec::new();
+
+    value.serialize(&mut Serializer::new(&mut v))?;
+
+    Ok(v)
+}
+
 /// Deserializes the data from the given slice.
+///
+/// Fails with [Error::TrailingBytes] if the slice has bytes left after the value; use
+/// [from_slice_partial] to read a value from the start of the slice.
 pub fn from_slice<'a, T>(s: &'a [u8]) -> Result<T, Error>
 where
     T: Deserialize<'a>,
 {
-    T::deserialize(&mut deserializer::MsgpackDeserializer(s))
+    let mut de = Deserializer::new(s);
+    let value = T::deserialize(&mut de)?;
+    de.end()?;
+    Ok(value)
+}
+
+/// Deserializes a value from the start of the slice, returning it with the bytes left after it.
+pub fn from_slice_partial<'a, T>(s: &'a [u8]) -> Result<(T, &'a [u8]), Error>
+where
+    T: Deserialize<'a>,
+{
+    let mut de = Deserializer::new(s);
+    let value = T::deserialize(&mut de)?;
+    Ok((value, de.remaining()))
+}
+
+/// Deserializes the next value from the iterator.
+///
+/// Only the bytes of the value are consumed, so consecutive values can be read from
+/// `bytes.by_ref()`. Strings and bytes are copied out of the iterator, so the deserialized type
+/// must own its data.
+#[cfg(feature = "alloc")]
+pub fn from_iter<I, T>(bytes: I) -> Result<T, Error>
+where
+    I: IntoIterator<Item = u8>,
+    T: DeserializeOwned,
+{
+    T::deserialize(&mut Deserializer::new_iter(bytes))
 }
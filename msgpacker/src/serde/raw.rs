@@ -0,0 +1,164 @@
+use core::fmt;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Error, Raw};
+
+/// The name of the newtype struct that the serializer and the deserializer handle as raw bytes.
+pub(super) const RAW_VALUE_NAME: &str = "$msgpacker::private::RawValue";
+
+/// The encoded bytes of a single value, captured by the [Deserializer](super::Deserializer)
+/// without decoding them, and packed verbatim by the [Serializer](super::Serializer).
+///
+/// It's borrowed from the slice as `&RawValue`; `Box<RawValue>` owns the bytes and can also be
+/// read from iterators. Like [Raw], it captures one msgpack value, so structs should be packed
+/// as maps or arrays with [Serializer::with_struct_map](super::Serializer::with_struct_map) to
+/// be captured as a whole.
+///
+/// ```rust
+/// use msgpacker::serde::RawValue;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Envelope<'a> {
+///     route: String,
+///     #[serde(borrow)]
+///     body: &'a RawValue,
+/// }
+///
+/// let body = msgpacker::serde::to_vec(&vec!["a", "large", "body"]);
+/// let envelope = Envelope {
+///     route: "archive".into(),
+///     body: RawValue::from_bytes(&body).unwrap(),
+/// };
+/// let bytes = msgpacker::serde::to_vec(&envelope);
+///
+/// let envelope: Envelope = msgpacker::serde::from_slice(&bytes).unwrap();
+/// assert_eq!(envelope.body.as_bytes(), body);
+/// ```
+#[derive(PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct RawValue([u8]);
+
+impl RawValue {
+    /// Wraps the bytes, failing unless they hold exactly one encoded value.
+    pub fn from_bytes(bytes: &[u8]) -> Result<&Self, Error> {
+        Raw::new(bytes).map(|raw| Self::from_raw(raw.as_bytes()))
+    }
+
+    fn from_raw(bytes: &[u8]) -> &Self {
+        // Safety: `RawValue` is a transparent wrapper of `[u8]`.
+        unsafe { &*(bytes as *const [u8] as *const Self) }
+    }
+
+    /// The encoded bytes of the value.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Borrows the value as a native [Raw].
+    pub fn as_raw(&self) -> Raw<'_> {
+        Raw(&self.0)
+    }
+}
+
+impl fmt::Debug for RawValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RawValue").field(&&self.0).finish()
+    }
+}
+
+/// The bytes of a [RawValue], serialized as bytes.
+struct Bytes<'a>(&'a [u8]);
+
+impl Serialize for Bytes<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+impl Serialize for RawValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(RAW_VALUE_NAME, &Bytes(&self.0))
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for &'a RawValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = &'de RawValue;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a borrowed msgpack value")
+            }
+
+            fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+                Ok(RawValue::from_raw(v))
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(RAW_VALUE_NAME, Visitor)
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod alloc {
+    use super::*;
+    use ::alloc::{boxed::Box, vec::Vec};
+
+    impl RawValue {
+        fn from_boxed(bytes: Box<[u8]>) -> Box<Self> {
+            // Safety: `RawValue` is a transparent wrapper of `[u8]`.
+            unsafe { Box::from_raw(Box::into_raw(bytes) as *mut Self) }
+        }
+
+        /// Copies the value into a box.
+        pub fn to_boxed(&self) -> Box<Self> {
+            Self::from_boxed(self.0.into())
+        }
+    }
+
+    impl Clone for Box<RawValue> {
+        fn clone(&self) -> Self {
+            self.to_boxed()
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Box<RawValue> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            struct Visitor;
+
+            impl<'de> de::Visitor<'de> for Visitor {
+                type Value = Box<RawValue>;
+
+                fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    f.write_str("a msgpack value")
+                }
+
+                fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> {
+                    Ok(RawValue::from_boxed(v.into()))
+                }
+
+                fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+                    Ok(RawValue::from_boxed(v.into_boxed_slice()))
+                }
+            }
+
+            deserializer.deserialize_newtype_struct(RAW_VALUE_NAME, Visitor)
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use serde::de::{self, value::BorrowedBytesDeserializer};
+
+use super::extension;
+use crate::{Error, FloatOptions, Unpackable};
+
+/// A function unpacking the length of an array or a map, such as [crate::unpack_array_len].
+type UnpackLen = fn(&[u8]) -> Result<(usize, usize), Error>;
+
+pub(super) mod private {
+    /// The state of the strict rules, which the [Read](super::Read) implementations apply to
+    /// every read once enabled.
+    pub trait Sealed {
+        /// Enables or disables the strict rules.
+        #[cfg(feature = "alloc")]
+        fn set_strict(&mut self, strict: bool);
+
+        /// Starts recording the read bytes, returning the mark of the start.
+        #[cfg(feature = "alloc")]
+        fn mark(&mut self) -> usize;
+
+        /// Stops recording, returning the bytes read since the mark.
+        #[cfg(feature = "alloc")]
+        fn recorded(&mut self, mark: usize) -> ::alloc::vec::Vec<u8>;
+    }
+}
+
+/// A source of bytes for the [Deserializer](super::Deserializer).
+///
+/// This trait is sealed; it's implemented by [SliceRead], which borrows strings and bytes from
+/// the slice, and [IterRead], which copies them out of an iterator.
+pub trait Read<'de>: private::Sealed {
+    /// Returns the next byte without consuming it.
+    fn peek(&mut self) -> Result<u8, Error>;
+
+    /// Unpacks the next value.
+    fn unpack<T>(&mut self) -> Result<T, Error>
+    where
+        T: Unpackable<Error = Error>;
+
+    /// Unpacks an `f32` with the options.
+    fn unpack_f32(&mut self, options: FloatOptions) -> Result<f32, Error>;
+
+    /// Unpacks an `f64` with the options.
+    fn unpack_f64(&mut self, options: FloatOptions) -> Result<f64, Error>;
+
+    /// Unpacks the length of an array or a map with the provided function of the buffer.
+    fn unpack_len(&mut self, f: UnpackLen) -> Result<usize, Error>;
+
+    /// Unpacks a string into the visitor.
+    fn visit_str<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>;
+
+    /// Unpacks a bin into the visitor.
+    fn visit_bytes<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>;
+
+    /// Unpacks the encoded bytes of the next value into the visitor, without decoding them.
+    fn visit_raw<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>;
+
+    /// Unpacks an extension into the visitor, as the `(i8, bytes)` tuple, wrapped in a newtype
+    /// struct if `newtype`.
+    fn visit_ext<V>(&mut self, visitor: V, newtype: bool) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>;
+}
+
+/// A [Read] of a slice, borrowing strings and bytes from it.
+pub struct SliceRead<'de> {
+    pub(super) buf: &'de [u8],
+    /// The whole slice, which marks are offsets of.
+    #[cfg(feature = "alloc")]
+    origin: &'de [u8],
+    #[cfg(feature = "alloc")]
+    strict: bool,
+}
+
+impl<'de> SliceRead<'de> {
+    pub(super) fn new(buf: &'de [u8]) -> Self {
+        Self {
+            buf,
+            #[cfg(feature = "alloc")]
+            origin: buf,
+            #[cfg(feature = "alloc")]
+            strict: false,
+        }
+    }
+
+    /// Advances the slice past the read bytes of the result, checking them under the strict
+    /// rules if enabled.
+    fn advance<T>(&mut self, result: Result<(usize, T), Error>) -> Result<T, Error> {
+        let (n, v) = result?;
+        #[cfg(feature = "alloc")]
+        if self.strict {
+            crate::strict::check(&self.buf[..n])?;
+        }
+        self.buf = &self.buf[n..];
+        Ok(v)
+    }
+}
+
+impl private::Sealed for SliceRead<'_> {
+    #[cfg(feature = "alloc")]
+    fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    #[cfg(feature = "alloc")]
+    fn mark(&mut self) -> usize {
+        self.origin.len() - self.buf.len()
+    }
+
+    #[cfg(feature = "alloc")]
+    fn recorded(&mut self, mark: usize) -> ::alloc::vec::Vec<u8> {
+        self.origin[mark..self.origin.len() - self.buf.len()].to_vec()
+    }
+}
+
+impl<'de> Read<'de> for SliceRead<'de> {
+    fn peek(&mut self) -> Result<u8, Error> {
+        self.buf.first().copied().ok_or(Error::BufferTooShort)
+    }
+
+    fn unpack<T>(&mut self) -> Result<T, Error>
+    where
+        T: Unpackable<Error = Error>,
+    {
+        #[cfg(feature = "alloc")]
+        if self.strict {
+            return self.advance(T::unpack_strict(self.buf));
+        }
+        self.advance(T::unpack(self.buf))
+    }
+
+    fn unpack_f32(&mut self, options: FloatOptions) -> Result<f32, Error> {
+        self.advance(options.unpack_f32(self.buf))
+    }
+
+    fn unpack_f64(&mut self, options: FloatOptions) -> Result<f64, Error> {
+        self.advance(options.unpack_f64(self.buf))
+    }
+
+    fn unpack_len(&mut self, f: UnpackLen) -> Result<usize, Error> {
+        self.advance(f(self.buf))
+    }
+
+    fn visit_str<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let v = self.advance(crate::unpack_str(self.buf))?;
+        visitor.visit_borrowed_str(v)
+    }
+
+    fn visit_bytes<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let v = self.advance(crate::unpack_bytes(self.buf))?;
+        visitor.visit_borrowed_bytes(v)
+    }
+
+    fn visit_raw<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let v = self.advance(crate::unpack_raw(self.buf))?;
+        visitor.visit_borrowed_bytes(v.as_bytes())
+    }
+
+    fn visit_ext<V>(&mut self, visitor: V, newtype: bool) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let (t, data) =
+            self.advance(extension::unpack_ext(self.buf).map(|(n, t, d)| (n, (t, d))))?;
+        extension::visit(
+            t,
+            data,
+            BorrowedBytesDeserializer::new(data),
+            visitor,
+            newtype,
+        )
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub use self::alloc::IterRead;
+
+#[cfg(feature = "alloc")]
+mod alloc {
+    use super::*;
+    use crate::{
+        format::Format,
+        helpers::{take_buffer_iter, take_byte_iter, take_num_iter},
+        strict,
+    };
+    use ::alloc::{string::String, vec::Vec};
+    use serde::de::value::BytesDeserializer;
+
+    /// A [Read] of an iterator, copying strings and bytes out of it.
+    ///
+    /// Only the bytes of the deserialized values are consumed.
+    pub struct IterRead<I> {
+        pub(in crate::serde) iter: I,
+        peeked: Option<u8>,
+        strict: bool,
+        /// The consumed bytes, kept under the strict rules until checked and unmarked.
+        recorded: Vec<u8>,
+        marks: usize,
+    }
+
+    impl<I> IterRead<I>
+    where
+        I: Iterator<Item = u8>,
+    {
+        pub(in crate::serde) fn new(iter: I) -> Self {
+            Self {
+                iter,
+                peeked: None,
+                strict: false,
+                recorded: Vec::new(),
+                marks: 0,
+            }
+        }
+
+        fn bytes(&mut self) -> impl Iterator<Item = u8> + '_ {
+            let mut recorded = self.strict.then_some(&mut self.recorded);
+            self.peeked
+                .take()
+                .into_iter()
+                .chain(self.iter.by_ref())
+                .inspect(move |b| {
+                    if let Some(recorded) = &mut recorded {
+                        recorded.push(*b);
+                    }
+                })
+        }
+
+        /// Runs the read, checking the bytes it consumed under the strict rules if enabled.
+        fn read<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, Error>) -> Result<T, Error> {
+            let start = self.recorded.len();
+            let v = f(self)?;
+            if self.strict {
+                strict::check(&self.recorded[start..])?;
+                if self.marks == 0 {
+                    self.recorded.clear();
+                }
+            }
+            Ok(v)
+        }
+    }
+
+    impl<I> private::Sealed for IterRead<I> {
+        fn set_strict(&mut self, strict: bool) {
+            self.strict = strict;
+        }
+
+        fn mark(&mut self) -> usize {
+            self.marks += 1;
+            self.recorded.len()
+        }
+
+        fn recorded(&mut self, mark: usize) -> Vec<u8> {
+            let bytes = self.recorded[mark..].to_vec();
+            self.marks -= 1;
+            if self.marks == 0 {
+                self.recorded.clear();
+            }
+            bytes
+        }
+    }
+
+    impl<'de, I> Read<'de> for IterRead<I>
+    where
+        I: Iterator<Item = u8>,
+    {
+        fn peek(&mut self) -> Result<u8, Error> {
+            if self.peeked.is_none() {
+                self.peeked = self.iter.next();
+            }
+            self.peeked.ok_or(Error::BufferTooShort)
+        }
+
+        fn unpack<T>(&mut self) -> Result<T, Error>
+        where
+            T: Unpackable<Error = Error>,
+        {
+            self.read(|r| match r.strict {
+                // the checks of the type need the whole value at hand
+                true => T::unpack_strict(&crate::take_value_iter(r.bytes())?).map(|(_, v)| v),
+                false => T::unpack_iter(r.bytes()).map(|(_, v)| v),
+            })
+        }
+
+        fn unpack_f32(&mut self, options: FloatOptions) -> Result<f32, Error> {
+            self.read(|r| options.unpack_f32_iter(r.bytes()).map(|(_, v)| v))
+        }
+
+        fn unpack_f64(&mut self, options: FloatOptions) -> Result<f64, Error> {
+            self.read(|r| options.unpack_f64_iter(r.bytes()).map(|(_, v)| v))
+        }
+
+        fn unpack_len(&mut self, f: UnpackLen) -> Result<usize, Error> {
+            self.read(|r| {
+                let mut bytes = r.bytes();
+                let format = take_byte_iter(bytes.by_ref())?;
+                let len = match format {
+                    Format::ARRAY16 | Format::MAP16 => 2,
+                    Format::ARRAY32 | Format::MAP32 => 4,
+                    _ => 0,
+                };
+                let mut header = [format, 0, 0, 0, 0];
+                for b in &mut header[1..=len] {
+                    *b = take_byte_iter(bytes.by_ref())?;
+                }
+                f(&header[..=len]).map(|(_, len)| len)
+            })
+        }
+
+        fn visit_str<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+        where
+            V: de::Visitor<'de>,
+        {
+            visitor.visit_string(self.unpack::<String>()?)
+        }
+
+        fn visit_bytes<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+        where
+            V: de::Visitor<'de>,
+        {
+            visitor.visit_byte_buf(self.unpack::<Vec<u8>>()?)
+        }
+
+        fn visit_raw<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+        where
+            V: de::Visitor<'de>,
+        {
+            visitor.visit_byte_buf(self.read(|r| crate::take_value_iter(r.bytes()))?)
+        }
+
+        fn visit_ext<V>(&mut self, visitor: V, newtype: bool) -> Result<V::Value, Error>
+        where
+            V: de::Visitor<'de>,
+        {
+            let (t, data) = self.read(|r| {
+                let mut bytes = r.bytes();
+                let len = match take_byte_iter(bytes.by_ref())? {
+                    Format::FIXEXT1 => 1,
+                    Format::FIXEXT2 => 2,
+                    Format::FIXEXT4 => 4,
+                    Format::FIXEXT8 => 8,
+                    Format::FIXEXT16 => 16,
+                    Format::EXT8 => take_byte_iter(bytes.by_ref())? as usize,
+                    Format::EXT16 => take_num_iter(bytes.by_ref(), u16::from_be_bytes)? as usize,
+                    Format::EXT32 => take_num_iter(bytes.by_ref(), u32::from_be_bytes)? as usize,
+                    _ => return Err(Error::InvalidExtension),
+                };
+                let t = take_byte_iter(bytes.by_ref())? as i8;
+                Ok((t, take_buffer_iter(bytes, len)?))
+            })?;
+            extension::visit(t, &data, BytesDeserializer::new(&data), visitor, newtype)
+        }
+    }
+}
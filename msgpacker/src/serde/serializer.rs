@@ -2,35 +2,225 @@ use core::fmt;
 
 use serde::{ser, Serialize};
 
-use crate::{pack::collections, Error, Packable};
+use super::Config;
+use crate::{pack::collections, Error, Packable, Writer};
+
+#[cfg(feature = "alloc")]
+use crate::Unpackable as _;
+
+/// A configurable [serde] serializer into a [Writer].
+///
+/// The default options produce the same bytes as [Packable]: structs and tuples are the
+/// concatenation of their fields, and enums are the index of the variant followed by its fields.
+/// Other implementations expect self-contained values with names, enabled by
+/// [Serializer::with_struct_map] and [Serializer::with_enum_names].
+///
+/// ```rust
+/// use msgpacker::serde::Serializer;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Point {
+///     x: u8,
+/// }
+///
+/// let mut buf = Vec::new();
+/// Point { x: 1 }
+///     .serialize(&mut Serializer::new(&mut buf).with_struct_map(true))
+///     .unwrap();
+///
+/// assert_eq!(buf, [0x81, 0xa1, b'x', 0x01]);
+/// ```
+pub struct Serializer<'a, X: Writer> {
+    b: &'a mut X,
+    config: Config,
+}
+
+impl<'a, X: Writer> Serializer<'a, X> {
+    /// Creates a serializer into the buffer with the default options.
+    pub fn new(b: &'a mut X) -> Self {
+        Self {
+            b,
+            config: Config::default(),
+        }
+    }
+
+    /// Packs structs as maps keyed by the field names, instead of the bare fields.
+    pub fn with_struct_map(mut self, struct_map: bool) -> Self {
+        self.config.struct_map = struct_map;
+        self
+    }
+
+    /// Packs unit variants as their name, and other variants as a single-entry map from their
+    /// name to their fields, instead of the index followed by the bare fields.
+    pub fn with_enum_names(mut self, enum_names: bool) -> Self {
+        self.config.enum_names = enum_names;
+        self
+    }
+
+    /// Sets the value of `is_human_readable`, which types may check to pick a compact form.
+    /// Defaults to `true`.
+    pub fn with_human_readable(mut self, human_readable: bool) -> Self {
+        self.config.human_readable = human_readable;
+        self
+    }
+
+    /// Packs floats in the smallest format that represents them exactly, which may be an
+    /// integer; see [crate::pack_f64_compact]. Reading them back into floats requires
+    /// [Deserializer::with_floats](super::Deserializer::with_floats).
+    pub fn with_compact_floats(mut self, compact_floats: bool) -> Self {
+        self.config.compact_floats = compact_floats;
+        self
+    }
+
+    /// Packs the `(i8, bytes)` tuple of a newtype struct named [super::EXT_STRUCT_NAME] as an
+    /// extension.
+    #[cfg(feature = "alloc")]
+    fn pack_ext<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut bytes = ::alloc::vec::Vec::new();
+        value.serialize(&mut Serializer {
+            b: &mut bytes,
+            config: self.config,
+        })?;
+
+        let (n, t) = i8::unpack(&bytes)?;
+        let (_, data) = crate::unpack_bytes(&bytes[n..])?;
+        crate::Extension::Ext(t, data.to_vec()).pack(self.b);
+        Ok(())
+    }
+
+    /// Packs the bytes of a newtype struct named [super::raw::RAW_VALUE_NAME] verbatim.
+    #[cfg(feature = "alloc")]
+    fn pack_raw<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut bytes = ::alloc::vec::Vec::new();
+        value.serialize(&mut Serializer::new(&mut bytes))?;
+
+        let (_, raw) = crate::unpack_bytes(&bytes)?;
+        crate::Raw::new(raw)?.pack(self.b);
+        Ok(())
+    }
+
+    /// Packs the tag of a variant with fields.
+    fn pack_variant(&mut self, variant_index: u32, variant: &str) {
+        match self.config.enum_names {
+            true => collections::pack_map_len(self.b, 1) + variant.pack(self.b),
+            false => variant_index.pack(self.b),
+        };
+    }
+}
 
-pub struct MsgpackSerializer<'a, X: Extend<u8>> {
-    pub b: &'a mut X,
+/// Serializes the elements of a sequence or a map.
+///
+/// The header is packed upfront if the length is known. Otherwise, the elements are packed into
+/// a buffer and counted, as the header precedes them and the sink can't be patched.
+pub struct Collection<'a, X: Writer> {
+    b: &'a mut X,
+    config: Config,
+    #[cfg(feature = "alloc")]
+    unknown: Option<Unknown>,
 }
 
-impl<'a, X: Extend<u8>> From<&'a mut X> for MsgpackSerializer<'a, X> {
+/// The elements of a collection of unknown length, packed so far.
+#[cfg(feature = "alloc")]
+struct Unknown {
+    map: bool,
+    len: usize,
+    bytes: ::alloc::vec::Vec<u8>,
+}
+
+impl<'a, X: Writer> Collection<'a, X> {
+    fn new(b: &'a mut X, config: Config, len: Option<usize>, map: bool) -> Result<Self, Error> {
+        match len {
+            Some(len) if map => collections::pack_map_len(b, len),
+            Some(len) => collections::pack_array_len(b, len),
+            #[cfg(feature = "alloc")]
+            None => {
+                return Ok(Self {
+                    b,
+                    config,
+                    unknown: Some(Unknown {
+                        map,
+                        len: 0,
+                        bytes: ::alloc::vec::Vec::new(),
+                    }),
+                })
+            }
+            #[cfg(not(feature = "alloc"))]
+            None => return Err(Error::NotImplemented),
+        };
+
+        Ok(Self {
+            b,
+            config,
+            #[cfg(feature = "alloc")]
+            unknown: None,
+        })
+    }
+
+    /// Packs an element, counting it if `counted` and the length is unknown.
+    fn push<T>(&mut self, value: &T, counted: bool) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        #[cfg(feature = "alloc")]
+        if let Some(unknown) = &mut self.unknown {
+            unknown.len += counted as usize;
+            return value.serialize(&mut Serializer {
+                b: &mut unknown.bytes,
+                config: self.config,
+            });
+        }
+
+        let _ = counted;
+        value.serialize(&mut Serializer {
+            b: &mut *self.b,
+            config: self.config,
+        })
+    }
+
+    fn end(self) -> Result<(), Error> {
+        #[cfg(feature = "alloc")]
+        if let Some(unknown) = self.unknown {
+            match unknown.map {
+                true => collections::pack_map_len(self.b, unknown.len),
+                false => collections::pack_array_len(self.b, unknown.len),
+            };
+            self.b.write_all(&unknown.bytes);
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, X: Writer> From<&'a mut X> for Serializer<'a, X> {
     fn from(b: &'a mut X) -> Self {
-        Self { b }
+        Self::new(b)
     }
 }
 
 impl ser::Error for Error {
-    fn custom<T>(_msg: T) -> Self
+    fn custom<T>(msg: T) -> Self
     where
         T: fmt::Display,
     {
-        Error::NotImplemented
+        super::custom(msg)
     }
 }
 
-impl<'a, X: Extend<u8>> ser::Serializer for &'a mut MsgpackSerializer<'a, X> {
+impl<'a, 'b, X: Writer> ser::Serializer for &'b mut Serializer<'a, X> {
     type Ok = ();
     type Error = Error;
-    type SerializeSeq = Self;
+    type SerializeSeq = Collection<'b, X>;
     type SerializeTuple = Self;
     type SerializeTupleStruct = Self;
     type SerializeTupleVariant = Self;
-    type SerializeMap = Self;
+    type SerializeMap = Collection<'b, X>;
     type SerializeStruct = Self;
     type SerializeStructVariant = Self;
 
@@ -90,12 +280,18 @@ impl<'a, X: Extend<u8>> ser::Serializer for &'a mut MsgpackSerializer<'a, X> {
     }
 
     fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
-        v.pack(self.b);
+        match self.config.compact_floats {
+            true => crate::pack_f32_compact(self.b, v),
+            false => v.pack(self.b),
+        };
         Ok(())
     }
 
     fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
-        v.pack(self.b);
+        match self.config.compact_floats {
+            true => crate::pack_f64_compact(self.b, v),
+            false => v.pack(self.b),
+        };
         Ok(())
     }
 
@@ -123,8 +319,7 @@ impl<'a, X: Extend<u8>> ser::Serializer for &'a mut MsgpackSerializer<'a, X> {
     where
         T: ?Sized + Serialize,
     {
-        super::to_buffer(self.b, value);
-        Ok(())
+        value.serialize(self)
     }
 
     fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
@@ -141,43 +336,55 @@ impl<'a, X: Extend<u8>> ser::Serializer for &'a mut MsgpackSerializer<'a, X> {
         self,
         _name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
-        variant_index.pack(self.b);
+        match self.config.enum_names {
+            true => variant.pack(self.b),
+            false => variant_index.pack(self.b),
+        };
         Ok(())
     }
 
     fn serialize_newtype_struct<T>(
         self,
-        _name: &'static str,
+        name: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
         T: ?Sized + Serialize,
     {
-        super::to_buffer(self.b, value);
-        Ok(())
+        #[cfg(feature = "alloc")]
+        match name {
+            super::EXT_STRUCT_NAME => return self.pack_ext(value),
+            super::raw::RAW_VALUE_NAME => return self.pack_raw(value),
+            _ => (),
+        }
+
+        #[cfg(not(feature = "alloc"))]
+        if name == super::raw::RAW_VALUE_NAME {
+            return Err(Error::NotImplemented);
+        }
+
+        let _ = name;
+        value.serialize(self)
     }
 
     fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
         T: ?Sized + Serialize,
     {
-        variant_index.pack(self.b);
-        super::to_buffer(self.b, value);
-        Ok(())
+        self.pack_variant(variant_index, variant);
+        value.serialize(self)
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
-        let len = len.ok_or(Error::NotImplemented)?;
-        collections::pack_array_len(self.b, len);
-        Ok(self)
+        Collection::new(self.b, self.config, len, false)
     }
 
     fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
@@ -196,24 +403,28 @@ impl<'a, X: Extend<u8>> ser::Serializer for &'a mut MsgpackSerializer<'a, X> {
         self,
         _name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
-        variant_index.pack(self.b);
+        self.pack_variant(variant_index, variant);
+        if self.config.enum_names {
+            collections::pack_array_len(self.b, len);
+        }
         Ok(self)
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
-        let len = len.ok_or(Error::NotImplemented)?;
-        collections::pack_map_len(self.b, len);
-        Ok(self)
+        Collection::new(self.b, self.config, len, true)
     }
 
     fn serialize_struct(
         self,
         _name: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeStruct, Self::Error> {
+        if self.config.struct_map {
+            collections::pack_map_len(self.b, len);
+        }
         Ok(self)
     }
 
@@ -221,13 +432,22 @@ impl<'a, X: Extend<u8>> ser::Serializer for &'a mut MsgpackSerializer<'a, X> {
         self,
         _name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
-        variant_index.pack(self.b);
+        self.pack_variant(variant_index, variant);
+        match (self.config.struct_map, self.config.enum_names) {
+            (true, _) => collections::pack_map_len(self.b, len),
+            (false, true) => collections::pack_array_len(self.b, len),
+            (false, false) => 0,
+        };
         Ok(self)
     }
 
+    fn is_human_readable(&self) -> bool {
+        self.config.human_readable
+    }
+
     #[cfg(not(feature = "alloc"))]
     fn collect_str<T>(self, _value: &T) -> Result<Self::Ok, Self::Error>
     where
@@ -237,7 +457,7 @@ impl<'a, X: Extend<u8>> ser::Serializer for &'a mut MsgpackSerializer<'a, X> {
     }
 }
 
-impl<'a, X: Extend<u8>> ser::SerializeSeq for &'a mut MsgpackSerializer<'a, X> {
+impl<'a, X: Writer> ser::SerializeSeq for Collection<'a, X> {
     type Ok = ();
     type Error = Error;
 
@@ -245,16 +465,15 @@ impl<'a, X: Extend<u8>> ser::SerializeSeq for &'a mut MsgpackSerializer<'a, X> {
     where
         T: ?Sized + Serialize,
     {
-        super::to_buffer(self.b, value);
-        Ok(())
+        self.push(value, true)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        Collection::end(self)
     }
 }
 
-impl<'a, X: Extend<u8>> ser::SerializeTuple for &'a mut MsgpackSerializer<'a, X> {
+impl<'a, 'b, X: Writer> ser::SerializeTuple for &'b mut Serializer<'a, X> {
     type Ok = ();
     type Error = Error;
 
@@ -262,8 +481,7 @@ impl<'a, X: Extend<u8>> ser::SerializeTuple for &'a mut MsgpackSerializer<'a, X>
     where
         T: ?Sized + Serialize,
     {
-        super::to_buffer(self.b, value);
-        Ok(())
+        value.serialize(&mut **self)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -271,7 +489,7 @@ impl<'a, X: Extend<u8>> ser::SerializeTuple for &'a mut MsgpackSerializer<'a, X>
     }
 }
 
-impl<'a, X: Extend<u8>> ser::SerializeTupleStruct for &'a mut MsgpackSerializer<'a, X> {
+impl<'a, 'b, X: Writer> ser::SerializeTupleStruct for &'b mut Serializer<'a, X> {
     type Ok = ();
     type Error = Error;
 
@@ -279,8 +497,7 @@ impl<'a, X: Extend<u8>> ser::SerializeTupleStruct for &'a mut MsgpackSerializer<
     where
         T: ?Sized + Serialize,
     {
-        super::to_buffer(self.b, value);
-        Ok(())
+        value.serialize(&mut **self)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -288,7 +505,7 @@ impl<'a, X: Extend<u8>> ser::SerializeTupleStruct for &'a mut MsgpackSerializer<
     }
 }
 
-impl<'a, X: Extend<u8>> ser::SerializeTupleVariant for &'a mut MsgpackSerializer<'a, X> {
+impl<'a, 'b, X: Writer> ser::SerializeTupleVariant for &'b mut Serializer<'a, X> {
     type Ok = ();
     type Error = Error;
 
@@ -296,8 +513,7 @@ impl<'a, X: Extend<u8>> ser::SerializeTupleVariant for &'a mut MsgpackSerializer
     where
         T: ?Sized + Serialize,
     {
-        super::to_buffer(self.b, value);
-        Ok(())
+        value.serialize(&mut **self)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -305,7 +521,7 @@ impl<'a, X: Extend<u8>> ser::SerializeTupleVariant for &'a mut MsgpackSerializer
     }
 }
 
-impl<'a, X: Extend<u8>> ser::SerializeMap for &'a mut MsgpackSerializer<'a, X> {
+impl<'a, X: Writer> ser::SerializeMap for Collection<'a, X> {
     type Ok = ();
     type Error = Error;
 
@@ -313,33 +529,33 @@ impl<'a, X: Extend<u8>> ser::SerializeMap for &'a mut MsgpackSerializer<'a, X> {
     where
         T: ?Sized + Serialize,
     {
-        super::to_buffer(self.b, key);
-        Ok(())
+        self.push(key, true)
     }
 
     fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: ?Sized + Serialize,
     {
-        super::to_buffer(self.b, value);
-        Ok(())
+        self.push(value, false)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        Collection::end(self)
     }
 }
 
-impl<'a, X: Extend<u8>> ser::SerializeStruct for &'a mut MsgpackSerializer<'a, X> {
+impl<'a, 'b, X: Writer> ser::SerializeStruct for &'b mut Serializer<'a, X> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
     where
         T: ?Sized + Serialize,
     {
-        super::to_buffer(self.b, value);
-        Ok(())
+        if self.config.struct_map {
+            key.pack(self.b);
+        }
+        value.serialize(&mut **self)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -347,16 +563,18 @@ impl<'a, X: Extend<u8>> ser::SerializeStruct for &'a mut MsgpackSerializer<'a, X
     }
 }
 
-impl<'a, X: Extend<u8>> ser::SerializeStructVariant for &'a mut MsgpackSerializer<'a, X> {
+impl<'a, 'b, X: Writer> ser::SerializeStructVariant for &'b mut Serializer<'a, X> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
     where
         T: ?Sized + Serialize,
     {
-        super::to_buffer(self.b, value);
-        Ok(())
+        if self.config.struct_map {
+            key.pack(self.b);
+        }
+        value.serialize(&mut **self)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -0,0 +1,68 @@
+use core::marker::PhantomData;
+
+use serde::Deserialize;
+
+use super::{Deserializer, Read};
+use crate::Error;
+
+/// An iterator over the values packed back to back in the source of a [Deserializer].
+///
+/// The iterator ends once all the bytes are read, or after yielding an error, as the start of the
+/// next value is unknown.
+///
+/// ```rust
+/// use msgpacker::serde::{Deserializer, StreamDeserializer};
+///
+/// let mut bytes = msgpacker::serde::to_vec(&(1u8, "a"));
+/// bytes.extend(msgpacker::serde::to_vec(&(2u8, "b")));
+///
+/// let values = StreamDeserializer::new(Deserializer::new(&bytes))
+///     .collect::<Result<Vec<(u8, &str)>, _>>()
+///     .unwrap();
+///
+/// assert_eq!(values, [(1, "a"), (2, "b")]);
+/// ```
+pub struct StreamDeserializer<'de, R, T> {
+    de: Deserializer<'de, R>,
+    failed: bool,
+    marker: PhantomData<T>,
+}
+
+impl<'de, R, T> StreamDeserializer<'de, R, T>
+where
+    R: Read<'de>,
+    T: Deserialize<'de>,
+{
+    /// Creates an iterator over the values of the deserializer, with its options.
+    pub fn new(de: Deserializer<'de, R>) -> Self {
+        Self {
+            de,
+            failed: false,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, T> StreamDeserializer<'de, super::SliceRead<'de>, T> {
+    /// The bytes that weren't read yet.
+    pub fn remaining(&self) -> &'de [u8] {
+        self.de.remaining()
+    }
+}
+
+impl<'de, R, T> Iterator for StreamDeserializer<'de, R, T>
+where
+    R: Read<'de>,
+    T: Deserialize<'de>,
+{
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.de.end().is_ok() {
+            return None;
+        }
+        let value = T::deserialize(&mut self.de);
+        self.failed = value.is_err();
+        Some(value)
+    }
+}
@@ -0,0 +1,188 @@
+use core::{fmt, ops::Deref, str};
+
+use ::alloc::{string::String, vec::Vec};
+use ::bytes::Bytes;
+
+use super::{unpack_bytes, unpack_str, Error, PackError, Packable, Unpackable, Writer};
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    /// The buffer [unpack_from_bytes] is decoding, which [Bytes] and [ByteStr] are sliced from.
+    static SOURCE: core::cell::RefCell<Option<Bytes>> = const { core::cell::RefCell::new(None) };
+}
+
+/// A view into the buffer [unpack_from_bytes] is decoding, or a copy of the data if it isn't
+/// part of it.
+fn view(data: &[u8]) -> Bytes {
+    #[cfg(feature = "std")]
+    {
+        let source = SOURCE.with(|s| {
+            s.borrow()
+                .as_ref()
+                .filter(|s| {
+                    let (s, d) = (s.as_ptr_range(), data.as_ptr_range());
+                    s.start <= d.start && d.end <= s.end
+                })
+                .map(|s| s.slice_ref(data))
+        });
+        if let Some(bytes) = source {
+            return bytes;
+        }
+    }
+    Bytes::copy_from_slice(data)
+}
+
+/// Unpacks a value from the shared buffer, returning the amount of read bytes.
+///
+/// The [Bytes] and [ByteStr] parts of the value are views into the buffer, sharing its
+/// allocation instead of copying their data. They are copied without the `std` feature, or
+/// when unpacked by other means.
+pub fn unpack_from_bytes<T>(bytes: &Bytes) -> Result<(usize, T), T::Error>
+where
+    T: Unpackable,
+{
+    #[cfg(feature = "std")]
+    {
+        /// Restores the enclosing buffer, even if the value panics.
+        struct Restore(Option<Bytes>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                SOURCE.with(|s| *s.borrow_mut() = self.0.take());
+            }
+        }
+
+        let _restore = Restore(SOURCE.with(|s| s.replace(Some(bytes.clone()))));
+        T::unpack(bytes)
+    }
+
+    #[cfg(not(feature = "std"))]
+    T::unpack(bytes)
+}
+
+impl Packable for Bytes {
+    fn pack<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer,
+    {
+        self.as_ref().pack(buf)
+    }
+
+    fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+    where
+        T: Writer,
+    {
+        self.as_ref().try_pack(buf)
+    }
+}
+
+impl Unpackable for Bytes {
+    type Error = Error;
+
+    fn unpack(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+        unpack_bytes(buf).map(|(n, b)| (n, view(b)))
+    }
+
+    fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        Vec::<u8>::unpack_iter(bytes).map(|(n, b)| (n, b.into()))
+    }
+}
+
+/// A UTF-8 string backed by [Bytes], packed as a str.
+///
+/// Unpacked with [unpack_from_bytes], it's a view into the shared buffer instead of a copy.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteStr(Bytes);
+
+impl ByteStr {
+    /// Creates a string from a static str, without copying it.
+    pub const fn from_static(s: &'static str) -> Self {
+        Self(Bytes::from_static(s.as_bytes()))
+    }
+
+    /// Borrows the string.
+    pub fn as_str(&self) -> &str {
+        // the bytes are checked on creation
+        unsafe { str::from_utf8_unchecked(&self.0) }
+    }
+
+    /// Returns the bytes of the string.
+    pub fn into_bytes(self) -> Bytes {
+        self.0
+    }
+}
+
+impl Deref for ByteStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for ByteStr {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for ByteStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl From<String> for ByteStr {
+    fn from(s: String) -> Self {
+        Self(s.into_bytes().into())
+    }
+}
+
+impl From<&str> for ByteStr {
+    fn from(s: &str) -> Self {
+        Self(Bytes::copy_from_slice(s.as_bytes()))
+    }
+}
+
+impl TryFrom<Bytes> for ByteStr {
+    type Error = Error;
+
+    fn try_from(bytes: Bytes) -> Result<Self, Self::Error> {
+        str::from_utf8(&bytes).map_err(|_| Error::InvalidUtf8)?;
+        Ok(Self(bytes))
+    }
+}
+
+impl Packable for ByteStr {
+    fn pack<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer,
+    {
+        self.as_str().pack(buf)
+    }
+
+    fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+    where
+        T: Writer,
+    {
+        self.as_str().try_pack(buf)
+    }
+}
+
+impl Unpackable for ByteStr {
+    type Error = Error;
+
+    fn unpack(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+        unpack_str(buf).map(|(n, s)| (n, Self(view(s.as_bytes()))))
+    }
+
+    fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        String::unpack_iter(bytes).map(|(n, s)| (n, s.into()))
+    }
+}
@@ -0,0 +1,368 @@
+//! Strict decoding, rejecting the inputs that are well-formed but ambiguous.
+//!
+//! Under the strict rules:
+//!
+//! - integers use the smallest representation of their value among the formats of the same
+//!   signedness, failing with [Error::NonMinimalInteger] otherwise;
+//! - the lengths of strings, bins, arrays, maps and extensions use the smallest representation,
+//!   failing with [Error::NonMinimalLength] otherwise;
+//! - maps and sets have no repeated keys nor elements, failing with [Error::DuplicateKey];
+//! - timestamp extensions have a valid length and less than a billion nanoseconds, failing with
+//!   [Error::InvalidTimestamp] otherwise;
+//! - [unpack] fails with [Error::TrailingBytes] if the buffer has bytes left after the value.
+//!
+//! The lengths follow the [canonical](crate::canonical) form. [Packable::pack](crate::Packable::pack)
+//! packs positive signed integers with the signed formats, and
+//! [Packable::pack_canonical](crate::Packable::pack_canonical) with the unsigned ones, so the
+//! smallest format of either signedness is accepted: 200 may be an uint 8 or an int 16, but not an
+//! uint 16 nor an int 32. The integers packed by both methods are always accepted, and keys are
+//! compared with their non-negative integers in the unsigned formats, so 200 is the same key in
+//! both.
+//!
+//! [Unpackable::unpack_strict] unpacks a value under these rules; the [serde](crate::serde)
+//! deserializer enables them with `with_strict`.
+//!
+//! ```rust
+//! use msgpacker::prelude::*;
+//! use std::collections::HashMap;
+//!
+//! // 1 packed as an uint 8 instead of a positive fixint
+//! assert_eq!(msgpacker::strict::unpack::<u8>(&[0xcc, 0x01]), Err(Error::NonMinimalInteger));
+//! assert_eq!(msgpacker::strict::unpack::<u8>(&[0x01, 0x02]), Err(Error::TrailingBytes));
+//!
+//! // the key 1 is repeated
+//! let bytes = [0x82, 0x01, 0xc3, 0x01, 0xc2];
+//! assert_eq!(HashMap::<u8, bool>::unpack(&bytes).unwrap().1.len(), 1);
+//! assert_eq!(HashMap::<u8, bool>::unpack_strict(&bytes), Err(Error::DuplicateKey));
+//! ```
+
+use alloc::{borrow::Cow, vec::Vec};
+
+use crate::{
+    format::Format,
+    helpers::{take_buffer, take_byte, take_num},
+    unpack::take_int,
+    Error, Extension, Packable, Unpackable,
+};
+
+#[cfg(any(feature = "arrayvec", feature = "heapless"))]
+use crate::FixedCapacity;
+
+/// The amount of bytes of the smallest representation of the integer, with its format, among
+/// the formats of the same signedness as `format`.
+fn int_len(format: u8, v: i128) -> usize {
+    let signed = matches!(
+        format,
+        Format::INT8 | Format::INT16 | Format::INT32 | Format::INT64
+    );
+    match (signed, v) {
+        (_, -32..=127) => 1,
+        (false, 128..=0xff) | (true, -0x80..=0x7f) => 2,
+        (false, 0x100..=0xffff) | (true, -0x8000..=0x7fff) => 3,
+        (false, 0x1_0000..=0xffff_ffff) | (true, -0x8000_0000..=0x7fff_ffff) => 5,
+        _ => 9,
+    }
+}
+
+/// Takes a length of `width` bytes, failing if it's under `min`, the smallest length the
+/// format is needed for.
+fn take_len(buf: &mut &[u8], width: usize, min: usize) -> Result<usize, Error> {
+    let len = match width {
+        1 => take_byte(buf)? as usize,
+        2 => take_num(buf, u16::from_be_bytes)? as usize,
+        _ => take_num(buf, u32::from_be_bytes)? as usize,
+    };
+    if len < min {
+        return Err(Error::NonMinimalLength);
+    }
+    Ok(len)
+}
+
+/// Checks the data of a timestamp extension.
+fn check_timestamp(data: &[u8]) -> Result<(), Error> {
+    let nanos = match data.len() {
+        4 => 0,
+        8 => take_num(&mut &data[..], u64::from_be_bytes)? >> 34,
+        12 => take_num(&mut &data[..], u32::from_be_bytes)? as u64,
+        _ => return Err(Error::InvalidTimestamp),
+    };
+    if nanos >= 1_000_000_000 {
+        return Err(Error::InvalidTimestamp);
+    }
+    Ok(())
+}
+
+/// Checks the format at the start of the buffer, advancing past it and its data.
+///
+/// The elements of arrays and maps are the formats that follow.
+fn check_format(buf: &mut &[u8]) -> Result<(), Error> {
+    let format = take_byte(buf)?;
+    let len = match format {
+        0x00..=Format::POSITIVE_FIXINT | 0xe0.. | 0x80..=0x9f => return Ok(()),
+        Format::NIL | Format::TRUE | Format::FALSE => return Ok(()),
+        Format::UINT8
+        | Format::UINT16
+        | Format::UINT32
+        | Format::UINT64
+        | Format::INT8
+        | Format::INT16
+        | Format::INT32
+        | Format::INT64 => {
+            let (n, v) = take_int(format, buf)?;
+            if n != int_len(format, v) {
+                return Err(Error::NonMinimalInteger);
+            }
+            return Ok(());
+        }
+        Format::FLOAT32 => 4,
+        Format::FLOAT64 => 8,
+        0xa0..=0xbf => (format & 0x1f) as usize,
+        Format::STR8 => take_len(buf, 1, 32)?,
+        Format::BIN8 => take_len(buf, 1, 0)?,
+        Format::STR16 | Format::BIN16 => take_len(buf, 2, 0x100)?,
+        Format::STR32 | Format::BIN32 => take_len(buf, 4, 0x1_0000)?,
+        Format::ARRAY16 | Format::MAP16 => return take_len(buf, 2, 16).map(drop),
+        Format::ARRAY32 | Format::MAP32 => return take_len(buf, 4, 0x1_0000).map(drop),
+        Format::FIXEXT1
+        | Format::FIXEXT2
+        | Format::FIXEXT4
+        | Format::FIXEXT8
+        | Format::FIXEXT16
+        | Format::EXT8
+        | Format::EXT16
+        | Format::EXT32 => {
+            let len = match format {
+                Format::FIXEXT1 => 1,
+                Format::FIXEXT2 => 2,
+                Format::FIXEXT4 => 4,
+                Format::FIXEXT8 => 8,
+                Format::FIXEXT16 => 16,
+                Format::EXT8 => match take_len(buf, 1, 0)? {
+                    1 | 2 | 4 | 8 | 16 => return Err(Error::NonMinimalLength),
+                    len => len,
+                },
+                Format::EXT16 => take_len(buf, 2, 0x100)?,
+                _ => take_len(buf, 4, 0x1_0000)?,
+            };
+            let t = take_byte(buf)? as i8;
+            let data = take_buffer(buf, len)?;
+            if t == Extension::TIMESTAMP {
+                check_timestamp(data)?;
+            }
+            return Ok(());
+        }
+        _ => return Err(Error::UnexpectedFormatTag),
+    };
+    take_buffer(buf, len).map(drop)
+}
+
+/// Checks that the packed bytes follow the strict rules.
+///
+/// The bytes are read as a sequence of formats, so they may hold any amount of values, and
+/// arrays and maps may end after their headers. Repeated keys aren't detected, as they depend on
+/// the types the keys are unpacked as.
+pub fn check(mut buf: &[u8]) -> Result<(), Error> {
+    while !buf.is_empty() {
+        check_format(&mut buf)?;
+    }
+    Ok(())
+}
+
+/// The bytes of a key with its non-negative integers packed in the unsigned formats, as both
+/// signednesses are accepted for them.
+///
+/// The formats are rewritten one by one, so the bytes of strings, bins and extensions are kept.
+pub(crate) fn key_bytes(key: &[u8]) -> Cow<'_, [u8]> {
+    let mut rest = key;
+    let mut normalized: Option<Vec<u8>> = None;
+    while let Some(&format) = rest.first() {
+        let start = key.len() - rest.len();
+        let mut next = rest;
+        if check_format(&mut next).is_err() {
+            next = &[];
+        }
+        let signed = matches!(
+            format,
+            Format::INT8 | Format::INT16 | Format::INT32 | Format::INT64
+        );
+        match take_int(format, &mut &rest[1..]) {
+            Ok((_, v)) if signed && v >= 0 => {
+                let out = normalized.get_or_insert_with(|| key[..start].to_vec());
+                (v as u64).pack(out);
+            }
+            _ => {
+                if let Some(out) = &mut normalized {
+                    out.extend_from_slice(&rest[..rest.len() - next.len()]);
+                }
+            }
+        }
+        rest = next;
+    }
+    normalized.map_or(Cow::Borrowed(key), Cow::Owned)
+}
+
+/// The packed keys of a map, checked for repetitions once all of them are read.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Keys<'a> {
+    keys: Vec<Cow<'a, [u8]>>,
+}
+
+impl<'a> Keys<'a> {
+    /// Creates an empty set of keys.
+    pub const fn new() -> Self {
+        Self { keys: Vec::new() }
+    }
+
+    /// Adds the bytes of a key.
+    ///
+    /// Keys are compared by their bytes, once their non-negative integers are packed in the
+    /// unsigned formats; these bytes are unique to their value under the strict rules.
+    pub fn push(&mut self, key: &'a [u8]) {
+        self.keys.push(key_bytes(key));
+    }
+
+    /// Fails with [Error::DuplicateKey] if a key was added more than once.
+    pub fn check(mut self) -> Result<(), Error> {
+        self.keys.sort_unstable();
+        match self.keys.windows(2).any(|w| w[0] == w[1]) {
+            true => Err(Error::DuplicateKey),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Unpacks a value from the whole buffer under the strict rules.
+///
+/// Fails with [Error::TrailingBytes] if the buffer has bytes left after the value.
+pub fn unpack<T>(buf: &[u8]) -> Result<T, T::Error>
+where
+    T: Unpackable,
+{
+    let (n, value) = T::unpack_strict(buf)?;
+    if n != buf.len() {
+        return Err(Error::TrailingBytes.into());
+    }
+    Ok(value)
+}
+
+/// Unpacks a string under the strict rules, as [crate::unpack_str].
+pub fn unpack_str(buf: &[u8]) -> Result<(usize, &str), Error> {
+    let (n, s) = crate::unpack_str(buf)?;
+    check(&buf[..n])?;
+    Ok((n, s))
+}
+
+/// Unpacks the array length under the strict rules, as [crate::unpack_array_len].
+pub fn unpack_array_len(buf: &[u8]) -> Result<(usize, usize), Error> {
+    let (n, len) = crate::unpack_array_len(buf)?;
+    check(&buf[..n])?;
+    Ok((n, len))
+}
+
+/// Unpacks the map length under the strict rules, as [crate::unpack_map_len].
+pub fn unpack_map_len(buf: &[u8]) -> Result<(usize, usize), Error> {
+    let (n, len) = crate::unpack_map_len(buf)?;
+    check(&buf[..n])?;
+    Ok((n, len))
+}
+
+/// Unpacks an array under the strict rules, as [crate::unpack_array].
+///
+/// Repeated elements are accepted, as the collection may not be a set.
+pub fn unpack_array<V, C>(mut buf: &[u8]) -> Result<(usize, C), <V as Unpackable>::Error>
+where
+    V: Unpackable,
+    C: FromIterator<V>,
+{
+    let (mut n, len) = unpack_array_len(buf)?;
+    buf = &buf[n..];
+    let array: C = (0..len)
+        .map(|_| {
+            let (count, v) = V::unpack_strict(buf)?;
+            buf = &buf[count..];
+            n += count;
+            Ok(v)
+        })
+        .collect::<Result<_, <V as Unpackable>::Error>>()?;
+    Ok((n, array))
+}
+
+/// Unpacks a map under the strict rules, as [crate::unpack_map].
+///
+/// Fails with [Error::DuplicateKey] if two keys have the same value, as compared by [Keys].
+pub fn unpack_map<K, V, C>(mut buf: &[u8]) -> Result<(usize, C), <V as Unpackable>::Error>
+where
+    K: Unpackable,
+    V: Unpackable,
+    <V as Unpackable>::Error: From<<K as Unpackable>::Error>,
+    C: FromIterator<(K, V)>,
+{
+    let (mut n, len) = unpack_map_len(buf)?;
+    buf = &buf[n..];
+    let mut keys = Keys::new();
+    let map: C = (0..len)
+        .map(|_| {
+            let (count, k) = K::unpack_strict(buf)?;
+            let key = buf;
+            keys.push(&key[..count]);
+            buf = &buf[count..];
+            n += count;
+            let (count, v) = V::unpack_strict(buf)?;
+            buf = &buf[count..];
+            n += count;
+            Ok((k, v))
+        })
+        .collect::<Result<_, <V as Unpackable>::Error>>()?;
+    keys.check()?;
+    Ok((n, map))
+}
+
+/// Unpacks an array into a collection of a fixed capacity under the strict rules, as
+/// [crate::unpack_array_fixed].
+#[cfg(any(feature = "arrayvec", feature = "heapless"))]
+pub fn unpack_array_fixed<V, C>(mut buf: &[u8]) -> Result<(usize, C), <V as Unpackable>::Error>
+where
+    V: Unpackable,
+    C: FixedCapacity<V>,
+{
+    let (mut n, len) = unpack_array_len(buf)?;
+    buf = &buf[n..];
+    let mut array = C::default();
+    for _ in 0..len {
+        let (count, v) = V::unpack_strict(buf)?;
+        buf = &buf[count..];
+        n += count;
+        array.try_push(v)?;
+    }
+    Ok((n, array))
+}
+
+/// Unpacks a map into a collection of a fixed capacity under the strict rules, as
+/// [crate::unpack_map_fixed].
+///
+/// Fails with [Error::DuplicateKey] if two keys have the same value, as compared by [Keys].
+#[cfg(any(feature = "arrayvec", feature = "heapless"))]
+pub fn unpack_map_fixed<K, V, C>(mut buf: &[u8]) -> Result<(usize, C), <V as Unpackable>::Error>
+where
+    K: Unpackable,
+    V: Unpackable,
+    <V as Unpackable>::Error: From<<K as Unpackable>::Error>,
+    C: FixedCapacity<(K, V)>,
+{
+    let (mut n, len) = unpack_map_len(buf)?;
+    buf = &buf[n..];
+    let mut keys = Keys::new();
+    let mut map = C::default();
+    for _ in 0..len {
+        let (count, k) = K::unpack_strict(buf)?;
+        keys.push(&buf[..count]);
+        buf = &buf[count..];
+        n += count;
+        let (count, v) = V::unpack_strict(buf)?;
+        buf = &buf[count..];
+        n += count;
+        map.try_push((k, v))?;
+    }
+    keys.check()?;
+    Ok((n, map))
+}
@@ -1,7 +1,5 @@
-use crate::{
-    helpers::{take_byte_iter, take_num_iter},
-    Unpackable,
-};
+#[cfg(any(feature = "alloc", feature = "arrayvec", feature = "heapless"))]
+use crate::helpers::{take_byte_iter, take_num_iter};
 
 use super::{
     helpers::{take_byte, take_num},
@@ -9,6 +7,7 @@ use super::{
 };
 use core::str;
 
+/// Unpacks a bin from the buffer, returning the borrowed bytes and the amount of read bytes.
 pub fn unpack_bytes(mut buf: &[u8]) -> Result<(usize, &[u8]), Error> {
     let format = take_byte(&mut buf)?;
     let (n, len) = match format {
@@ -23,6 +22,7 @@ pub fn unpack_bytes(mut buf: &[u8]) -> Result<(usize, &[u8]), Error> {
     Ok((n + len, &buf[..len]))
 }
 
+/// Unpacks a str from the buffer, returning the borrowed string and the amount of read bytes.
 pub fn unpack_str(mut buf: &[u8]) -> Result<(usize, &str), Error> {
     let format = take_byte(&mut buf)?;
     let (n, len) = match format {
@@ -39,9 +39,57 @@ pub fn unpack_str(mut buf: &[u8]) -> Result<(usize, &str), Error> {
     Ok((n + len, str))
 }
 
+#[cfg(any(feature = "alloc", feature = "arrayvec", feature = "heapless"))]
+/// Unpacks the header of a bin from the iterator, returning its length and the amount of read
+/// bytes.
+pub(crate) fn unpack_bytes_len_iter<I>(bytes: &mut I) -> Result<(usize, usize), Error>
+where
+    I: Iterator<Item = u8>,
+{
+    let format = take_byte_iter(bytes.by_ref())?;
+    let (n, len) = match format {
+        Format::BIN8 => (2, take_byte_iter(bytes.by_ref())? as usize),
+        Format::BIN16 => (
+            3,
+            take_num_iter(bytes.by_ref(), u16::from_be_bytes)? as usize,
+        ),
+        Format::BIN32 => (
+            5,
+            take_num_iter(bytes.by_ref(), u32::from_be_bytes)? as usize,
+        ),
+        _ => return Err(Error::UnexpectedFormatTag),
+    };
+    Ok((n, len))
+}
+
+#[cfg(any(feature = "alloc", feature = "arrayvec", feature = "heapless"))]
+/// Unpacks the header of a str from the iterator, returning its length and the amount of read
+/// bytes.
+pub(crate) fn unpack_str_len_iter<I>(bytes: &mut I) -> Result<(usize, usize), Error>
+where
+    I: Iterator<Item = u8>,
+{
+    let format = take_byte_iter(bytes.by_ref())?;
+    let (n, len) = match format {
+        0xa0..=0xbf => (1, format as usize & 0x1f),
+        Format::STR8 => (2, take_byte_iter(bytes.by_ref())? as usize),
+        Format::STR16 => (
+            3,
+            take_num_iter(bytes.by_ref(), u16::from_be_bytes)? as usize,
+        ),
+        Format::STR32 => (
+            5,
+            take_num_iter(bytes.by_ref(), u32::from_be_bytes)? as usize,
+        ),
+        _ => return Err(Error::UnexpectedFormatTag),
+    };
+    Ok((n, len))
+}
+
 #[cfg(feature = "alloc")]
 mod alloc {
     use super::*;
+    use crate::Unpackable;
 
     use ::alloc::{string::String, vec::Vec};
 
@@ -57,19 +105,7 @@ mod alloc {
             I: IntoIterator<Item = u8>,
         {
             let mut bytes = bytes.into_iter();
-            let format = take_byte_iter(bytes.by_ref())?;
-            let (n, len) = match format {
-                Format::BIN8 => (2, take_byte_iter(bytes.by_ref())? as usize),
-                Format::BIN16 => (
-                    3,
-                    take_num_iter(bytes.by_ref(), u16::from_be_bytes)? as usize,
-                ),
-                Format::BIN32 => (
-                    5,
-                    take_num_iter(bytes.by_ref(), u32::from_be_bytes)? as usize,
-                ),
-                _ => return Err(Error::UnexpectedFormatTag),
-            };
+            let (n, len) = unpack_bytes_len_iter(bytes.by_ref())?;
             let v: Vec<_> = bytes.take(len).collect();
             if v.len() < len {
                 return Err(Error::BufferTooShort);
@@ -90,20 +126,7 @@ mod alloc {
             I: IntoIterator<Item = u8>,
         {
             let mut bytes = bytes.into_iter();
-            let format = take_byte_iter(bytes.by_ref())?;
-            let (n, len) = match format {
-                0xa0..=0xbf => (1, format as usize & 0x1f),
-                Format::STR8 => (2, take_byte_iter(bytes.by_ref())? as usize),
-                Format::STR16 => (
-                    3,
-                    take_num_iter(bytes.by_ref(), u16::from_be_bytes)? as usize,
-                ),
-                Format::STR32 => (
-                    5,
-                    take_num_iter(bytes.by_ref(), u32::from_be_bytes)? as usize,
-                ),
-                _ => return Err(Error::UnexpectedFormatTag),
-            };
+            let (n, len) = unpack_str_len_iter(bytes.by_ref())?;
             let v: Vec<_> = bytes.take(len).collect();
             if v.len() < len {
                 return Err(Error::BufferTooShort);
@@ -113,3 +136,34 @@ mod alloc {
         }
     }
 }
+
+#[cfg(feature = "smallvec")]
+mod smallvec {
+    use super::*;
+    use crate::Unpackable;
+    use ::smallvec::{Array, SmallVec};
+
+    impl<A> Unpackable for SmallVec<A>
+    where
+        A: Array<Item = u8>,
+    {
+        type Error = Error;
+
+        fn unpack(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+            unpack_bytes(buf).map(|(n, b)| (n, SmallVec::from_slice(b)))
+        }
+
+        fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
+        where
+            I: IntoIterator<Item = u8>,
+        {
+            let mut bytes = bytes.into_iter();
+            let (n, len) = unpack_bytes_len_iter(bytes.by_ref())?;
+            let v: SmallVec<A> = bytes.take(len).collect();
+            if v.len() < len {
+                return Err(Error::BufferTooShort);
+            }
+            Ok((n + len, v))
+        }
+    }
+}
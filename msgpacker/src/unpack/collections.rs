@@ -16,7 +16,28 @@ pub fn unpack_array_len(mut buf: &[u8]) -> Result<(usize, usize), Error> {
             5,
             take_num(&mut buf, u32::from_be_bytes).map(|v| v as usize)?,
         ),
-        _ => return Err(Error::UnexpectedFormatTag.into()),
+        _ => return Err(Error::UnexpectedFormatTag),
+    };
+    Ok((n, len))
+}
+
+/// Unpacks the array length from the iterator.
+pub(crate) fn unpack_array_len_iter<I>(bytes: &mut I) -> Result<(usize, usize), Error>
+where
+    I: Iterator<Item = u8>,
+{
+    let format = take_byte_iter(bytes.by_ref())?;
+    let (n, len) = match format {
+        0x90..=0x9f => (1, (format & 0x0f) as usize),
+        Format::ARRAY16 => (
+            3,
+            take_num_iter(bytes.by_ref(), u16::from_be_bytes).map(|v| v as usize)?,
+        ),
+        Format::ARRAY32 => (
+            5,
+            take_num_iter(bytes.by_ref(), u32::from_be_bytes).map(|v| v as usize)?,
+        ),
+        _ => return Err(Error::UnexpectedFormatTag),
     };
     Ok((n, len))
 }
@@ -48,19 +69,7 @@ where
     C: FromIterator<V>,
 {
     let mut bytes = iter.into_iter();
-    let format = take_byte_iter(bytes.by_ref())?;
-    let (mut n, len) = match format {
-        0x90..=0x9f => (1, (format & 0x0f) as usize),
-        Format::ARRAY16 => (
-            3,
-            take_num_iter(bytes.by_ref(), u16::from_be_bytes).map(|v| v as usize)?,
-        ),
-        Format::ARRAY32 => (
-            5,
-            take_num_iter(bytes.by_ref(), u32::from_be_bytes).map(|v| v as usize)?,
-        ),
-        _ => return Err(Error::UnexpectedFormatTag.into()),
-    };
+    let (mut n, len) = unpack_array_len_iter(bytes.by_ref())?;
     let array: C = (0..len)
         .map(|_| {
             let (count, v) = V::unpack_iter(bytes.by_ref())?;
@@ -84,7 +93,28 @@ pub fn unpack_map_len(mut buf: &[u8]) -> Result<(usize, usize), Error> {
             5,
             take_num(&mut buf, u32::from_be_bytes).map(|v| v as usize)?,
         ),
-        _ => return Err(Error::UnexpectedFormatTag.into()),
+        _ => return Err(Error::UnexpectedFormatTag),
+    };
+    Ok((n, len))
+}
+
+/// Unpacks a map length from the iterator.
+pub(crate) fn unpack_map_len_iter<I>(bytes: &mut I) -> Result<(usize, usize), Error>
+where
+    I: Iterator<Item = u8>,
+{
+    let format = take_byte_iter(bytes.by_ref())?;
+    let (n, len) = match format {
+        0x80..=0x8f => (1, (format & 0x0f) as usize),
+        Format::MAP16 => (
+            3,
+            take_num_iter(bytes.by_ref(), u16::from_be_bytes).map(|v| v as usize)?,
+        ),
+        Format::MAP32 => (
+            5,
+            take_num_iter(bytes.by_ref(), u32::from_be_bytes).map(|v| v as usize)?,
+        ),
+        _ => return Err(Error::UnexpectedFormatTag),
     };
     Ok((n, len))
 }
@@ -123,19 +153,7 @@ where
     C: FromIterator<(K, V)>,
 {
     let mut bytes = iter.into_iter();
-    let format = take_byte_iter(bytes.by_ref())?;
-    let (mut n, len) = match format {
-        0x80..=0x8f => (1, (format & 0x0f) as usize),
-        Format::MAP16 => (
-            3,
-            take_num_iter(bytes.by_ref(), u16::from_be_bytes).map(|v| v as usize)?,
-        ),
-        Format::MAP32 => (
-            5,
-            take_num_iter(bytes.by_ref(), u32::from_be_bytes).map(|v| v as usize)?,
-        ),
-        _ => return Err(Error::UnexpectedFormatTag.into()),
-    };
+    let (mut n, len) = unpack_map_len_iter(bytes.by_ref())?;
     let map: C = (0..len)
         .map(|_| {
             let (count, k) = K::unpack_iter(bytes.by_ref())?;
@@ -151,6 +169,7 @@ where
 #[cfg(feature = "alloc")]
 mod alloc {
     use super::*;
+    use crate::strict;
     use ::alloc::collections::{BTreeMap, BTreeSet, BinaryHeap, LinkedList, VecDeque};
 
     impl<X> Unpackable for BTreeSet<X>
@@ -169,6 +188,14 @@ mod alloc {
         {
             unpack_array_iter(bytes)
         }
+
+        fn unpack_strict(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+            let (n, set) = strict::unpack_array::<X, Self>(buf)?;
+            if set.len() != unpack_array_len(buf)?.1 {
+                return Err(Error::DuplicateKey.into());
+            }
+            Ok((n, set))
+        }
     }
 
     impl<X> Unpackable for BinaryHeap<X>
@@ -187,6 +214,10 @@ mod alloc {
         {
             unpack_array_iter(bytes)
         }
+
+        fn unpack_strict(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+            strict::unpack_array(buf)
+        }
     }
 
     impl<X> Unpackable for LinkedList<X>
@@ -205,6 +236,10 @@ mod alloc {
         {
             unpack_array_iter(bytes)
         }
+
+        fn unpack_strict(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+            strict::unpack_array(buf)
+        }
     }
 
     impl<X> Unpackable for VecDeque<X>
@@ -223,6 +258,10 @@ mod alloc {
         {
             unpack_array_iter(bytes)
         }
+
+        fn unpack_strict(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+            strict::unpack_array(buf)
+        }
     }
 
     impl<K, V> Unpackable for BTreeMap<K, V>
@@ -243,20 +282,158 @@ mod alloc {
         {
             unpack_map_iter(bytes)
         }
+
+        fn unpack_strict(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+            let (n, map) = strict::unpack_map::<K, V, Self>(buf)?;
+            if map.len() != unpack_map_len(buf)?.1 {
+                return Err(Error::DuplicateKey.into());
+            }
+            Ok((n, map))
+        }
     }
 }
 
 #[cfg(feature = "std")]
 mod std {
     use super::*;
+    use crate::strict;
     use ::std::{
         collections::{HashMap, HashSet},
-        hash::Hash,
+        hash::{BuildHasher, Hash},
     };
 
-    impl<X> Unpackable for HashSet<X>
+    impl<X, S> Unpackable for HashSet<X, S>
+    where
+        X: Unpackable + Hash + Eq,
+        S: BuildHasher + Default,
+    {
+        type Error = <X as Unpackable>::Error;
+
+        fn unpack(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+            unpack_array(buf)
+        }
+
+        fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
+        where
+            I: IntoIterator<Item = u8>,
+        {
+            unpack_array_iter(bytes)
+        }
+
+        fn unpack_strict(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+            let (n, set) = strict::unpack_array::<X, Self>(buf)?;
+            if set.len() != unpack_array_len(buf)?.1 {
+                return Err(Error::DuplicateKey.into());
+            }
+            Ok((n, set))
+        }
+    }
+
+    impl<K, V, S> Unpackable for HashMap<K, V, S>
+    where
+        K: Unpackable + Hash + Eq,
+        V: Unpackable,
+        <V as Unpackable>::Error: From<<K as Unpackable>::Error>,
+        S: BuildHasher + Default,
+    {
+        type Error = <V as Unpackable>::Error;
+
+        fn unpack(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+            unpack_map(buf)
+        }
+
+        fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
+        where
+            I: IntoIterator<Item = u8>,
+        {
+            unpack_map_iter(bytes)
+        }
+
+        fn unpack_strict(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+            let (n, map) = strict::unpack_map::<K, V, Self>(buf)?;
+            if map.len() != unpack_map_len(buf)?.1 {
+                return Err(Error::DuplicateKey.into());
+            }
+            Ok((n, map))
+        }
+    }
+}
+
+#[cfg(feature = "hashbrown")]
+mod hashbrown {
+    use super::*;
+    use crate::strict;
+    use ::hashbrown::{HashMap, HashSet};
+    use core::hash::{BuildHasher, Hash};
+
+    impl<X, S> Unpackable for HashSet<X, S>
+    where
+        X: Unpackable + Hash + Eq,
+        S: BuildHasher + Default,
+    {
+        type Error = <X as Unpackable>::Error;
+
+        fn unpack(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+            unpack_array(buf)
+        }
+
+        fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
+        where
+            I: IntoIterator<Item = u8>,
+        {
+            unpack_array_iter(bytes)
+        }
+
+        fn unpack_strict(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+            let (n, set) = strict::unpack_array::<X, Self>(buf)?;
+            if set.len() != unpack_array_len(buf)?.1 {
+                return Err(Error::DuplicateKey.into());
+            }
+            Ok((n, set))
+        }
+    }
+
+    impl<K, V, S> Unpackable for HashMap<K, V, S>
+    where
+        K: Unpackable + Hash + Eq,
+        V: Unpackable,
+        <V as Unpackable>::Error: From<<K as Unpackable>::Error>,
+        S: BuildHasher + Default,
+    {
+        type Error = <V as Unpackable>::Error;
+
+        fn unpack(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+            unpack_map(buf)
+        }
+
+        fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
+        where
+            I: IntoIterator<Item = u8>,
+        {
+            unpack_map_iter(bytes)
+        }
+
+        fn unpack_strict(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+            let (n, map) = strict::unpack_map::<K, V, Self>(buf)?;
+            if map.len() != unpack_map_len(buf)?.1 {
+                return Err(Error::DuplicateKey.into());
+            }
+            Ok((n, map))
+        }
+    }
+}
+
+#[cfg(feature = "indexmap")]
+mod indexmap {
+    use super::*;
+    use crate::strict;
+    use ::indexmap::{IndexMap, IndexSet};
+    use core::hash::{BuildHasher, Hash};
+
+    impl<X, S> Unpackable for IndexSet<X, S>
     where
         X: Unpackable + Hash + Eq,
+        S: BuildHasher + Default,
     {
         type Error = <X as Unpackable>::Error;
 
@@ -270,13 +447,22 @@ mod std {
         {
             unpack_array_iter(bytes)
         }
+
+        fn unpack_strict(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+            let (n, set) = strict::unpack_array::<X, Self>(buf)?;
+            if set.len() != unpack_array_len(buf)?.1 {
+                return Err(Error::DuplicateKey.into());
+            }
+            Ok((n, set))
+        }
     }
 
-    impl<K, V> Unpackable for HashMap<K, V>
+    impl<K, V, S> Unpackable for IndexMap<K, V, S>
     where
         K: Unpackable + Hash + Eq,
         V: Unpackable,
         <V as Unpackable>::Error: From<<K as Unpackable>::Error>,
+        S: BuildHasher + Default,
     {
         type Error = <V as Unpackable>::Error;
 
@@ -290,5 +476,13 @@ mod std {
         {
             unpack_map_iter(bytes)
         }
+
+        fn unpack_strict(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+            let (n, map) = strict::unpack_map::<K, V, Self>(buf)?;
+            if map.len() != unpack_map_len(buf)?.1 {
+                return Err(Error::DuplicateKey.into());
+            }
+            Ok((n, map))
+        }
     }
 }
@@ -121,6 +121,37 @@ where
         }
         X::unpack_iter(bytes).map(|(n, x)| (n, Some(x)))
     }
+
+    #[cfg(feature = "alloc")]
+    fn unpack_strict(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+        if buf.first() == Some(&Format::NIL) {
+            return Ok((1, None));
+        }
+        X::unpack_strict(buf).map(|(n, x)| (n, Some(x)))
+    }
+}
+
+/// Unpacks the elements of an array one after another with `f`.
+fn unpack_elements<X, E, F, const N: usize>(mut buf: &[u8], f: F) -> Result<(usize, [X; N]), E>
+where
+    F: Fn(&[u8]) -> Result<(usize, X), E>,
+{
+    let mut array = [const { MaybeUninit::uninit() }; N];
+    let n = array
+        .iter_mut()
+        .try_fold::<_, _, Result<_, E>>(0, |count, a| {
+            let (n, x) = f(buf)?;
+            buf = &buf[n..];
+            a.write(x);
+            Ok(count + n)
+        })?;
+    // Safety: array is initialized
+    let array = ::core::array::from_fn(|i| {
+        let mut x = MaybeUninit::zeroed();
+        ::core::mem::swap(&mut array[i], &mut x);
+        unsafe { MaybeUninit::assume_init(x) }
+    });
+    Ok((n, array))
 }
 
 macro_rules! array {
@@ -131,26 +162,11 @@ macro_rules! array {
         {
             type Error = <X as Unpackable>::Error;
 
-            fn unpack(mut buf: &[u8]) -> Result<(usize, Self), Self::Error> {
-                let mut array = [const { MaybeUninit::uninit() }; $n];
-                let n =
-                    array
-                        .iter_mut()
-                        .try_fold::<_, _, Result<_, Self::Error>>(0, |count, a| {
-                            let (n, x) = X::unpack(buf)?;
-                            buf = &buf[n..];
-                            a.write(x);
-                            Ok(count + n)
-                        })?;
-                // Safety: array is initialized
-                let array = ::core::array::from_fn(|i| {
-                    let mut x = MaybeUninit::zeroed();
-                    ::core::mem::swap(&mut array[i], &mut x);
-                    unsafe { MaybeUninit::assume_init(x) }
-                });
-                Ok((n, array))
+            fn unpack(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+                unpack_elements(buf, X::unpack)
             }
 
+            #[allow(clippy::zero_repeat_side_effects)]
             fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
             where
                 I: IntoIterator<Item = u8>,
@@ -173,6 +189,11 @@ macro_rules! array {
                 });
                 Ok((n, array))
             }
+
+            #[cfg(feature = "alloc")]
+            fn unpack_strict(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+                unpack_elements(buf, X::unpack_strict)
+            }
         }
     };
 }
@@ -274,6 +295,17 @@ macro_rules! tuple {
 
                 Ok((n, ($($name, )+)))
             }
+
+            #[cfg(feature = "alloc")]
+            #[allow(non_snake_case)]
+            fn unpack_strict(mut buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+                let mut n = 0;
+
+                $(let (c, $name) = $name::unpack_strict(buf)?; n += c; buf = &buf[c..];)+
+                let _ = buf;
+
+                Ok((n, ($($name, )+)))
+            }
         }
     );
 }
@@ -0,0 +1,38 @@
+use super::{
+    helpers::{take_buffer, take_byte, take_num},
+    Error, Format,
+};
+
+/// Whether the format byte starts an extension.
+#[cfg(feature = "serde")]
+pub(crate) fn is_ext(format: u8) -> bool {
+    matches!(
+        format,
+        Format::FIXEXT1
+            | Format::FIXEXT2
+            | Format::FIXEXT4
+            | Format::FIXEXT8
+            | Format::FIXEXT16
+            | Format::EXT8
+            | Format::EXT16
+            | Format::EXT32
+    )
+}
+
+/// Unpacks the type and the data of an extension, returning the amount of read bytes.
+pub(crate) fn unpack_ext(mut buf: &[u8]) -> Result<(usize, i8, &[u8]), Error> {
+    let (n, len) = match take_byte(&mut buf)? {
+        Format::FIXEXT1 => (2, 1),
+        Format::FIXEXT2 => (2, 2),
+        Format::FIXEXT4 => (2, 4),
+        Format::FIXEXT8 => (2, 8),
+        Format::FIXEXT16 => (2, 16),
+        Format::EXT8 => (3, take_byte(&mut buf)? as usize),
+        Format::EXT16 => (4, take_num(&mut buf, u16::from_be_bytes)? as usize),
+        Format::EXT32 => (6, take_num(&mut buf, u32::from_be_bytes)? as usize),
+        _ => return Err(Error::InvalidExtension),
+    };
+    let t = take_byte(&mut buf)? as i8;
+    let data = take_buffer(&mut buf, len)?;
+    Ok((n + len, t, data))
+}
@@ -1,5 +1,6 @@
 use super::{
     helpers::{take_byte, take_byte_iter, take_num, take_num_iter},
+    int::{take_int, take_int_iter},
     Error, Format, Unpackable,
 };
 
@@ -52,3 +53,149 @@ impl Unpackable for f64 {
         }
     }
 }
+
+/// A number of any format, before its conversion to the target float.
+enum Number {
+    Int(i128),
+    F32(f32),
+    F64(f64),
+}
+
+/// Options to decode floats from the formats of other numbers.
+///
+/// By default, an `f32` accepts only `FLOAT32` and an `f64` accepts `FLOAT32` and `FLOAT64`, as
+/// their [Unpackable] implementations do.
+///
+/// ```rust
+/// use msgpacker::{prelude::*, FloatOptions};
+///
+/// let bytes = 1u8.pack_to_vec();
+/// let options = FloatOptions::new().with_integers(true);
+///
+/// assert!(f64::unpack(&bytes).is_err());
+/// assert_eq!(options.unpack_f64(&bytes).unwrap(), (1, 1.0));
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FloatOptions {
+    integers: bool,
+    narrowing: bool,
+    lossy: bool,
+}
+
+impl FloatOptions {
+    /// Creates the default options, accepting the float formats only.
+    pub const fn new() -> Self {
+        Self {
+            integers: false,
+            narrowing: false,
+            lossy: false,
+        }
+    }
+
+    /// Accepts the integer formats, if the float represents the value exactly.
+    pub const fn with_integers(mut self, integers: bool) -> Self {
+        self.integers = integers;
+        self
+    }
+
+    /// Accepts `FLOAT64` into an `f32`, if the `f32` represents the value exactly.
+    pub const fn with_narrowing(mut self, narrowing: bool) -> Self {
+        self.narrowing = narrowing;
+        self
+    }
+
+    /// Rounds the accepted values the float can't represent exactly, instead of failing with
+    /// [Error::InexactFloat].
+    pub const fn with_lossy(mut self, lossy: bool) -> Self {
+        self.lossy = lossy;
+        self
+    }
+
+    /// Unpacks an `f32`, returning the amount of read bytes.
+    pub fn unpack_f32(&self, buf: &[u8]) -> Result<(usize, f32), Error> {
+        let (n, v) = take_number(buf)?;
+        self.to_f32(v).map(|v| (n, v))
+    }
+
+    /// Unpacks an `f32` from the iterator, returning the amount of read bytes.
+    pub fn unpack_f32_iter<I>(&self, bytes: I) -> Result<(usize, f32), Error>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        let (n, v) = take_number_iter(bytes.into_iter())?;
+        self.to_f32(v).map(|v| (n, v))
+    }
+
+    /// Unpacks an `f64`, returning the amount of read bytes.
+    pub fn unpack_f64(&self, buf: &[u8]) -> Result<(usize, f64), Error> {
+        let (n, v) = take_number(buf)?;
+        self.to_f64(v).map(|v| (n, v))
+    }
+
+    /// Unpacks an `f64` from the iterator, returning the amount of read bytes.
+    pub fn unpack_f64_iter<I>(&self, bytes: I) -> Result<(usize, f64), Error>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        let (n, v) = take_number_iter(bytes.into_iter())?;
+        self.to_f64(v).map(|v| (n, v))
+    }
+
+    /// Whether a value, converted with `exact` telling if nothing was lost, is accepted.
+    fn check<F>(&self, v: F, exact: bool) -> Result<F, Error> {
+        match exact || self.lossy {
+            true => Ok(v),
+            false => Err(Error::InexactFloat),
+        }
+    }
+
+    fn to_f32(self, v: Number) -> Result<f32, Error> {
+        match v {
+            Number::F32(v) => Ok(v),
+            Number::F64(v) if self.narrowing => {
+                let f = v as f32;
+                self.check(f, f as f64 == v || v.is_nan())
+            }
+            Number::Int(v) if self.integers => {
+                let f = v as f32;
+                self.check(f, f as i128 == v)
+            }
+            _ => Err(Error::UnexpectedFormatTag),
+        }
+    }
+
+    fn to_f64(self, v: Number) -> Result<f64, Error> {
+        match v {
+            Number::F32(v) => Ok(v as f64),
+            Number::F64(v) => Ok(v),
+            Number::Int(v) if self.integers => {
+                let f = v as f64;
+                self.check(f, f as i128 == v)
+            }
+            _ => Err(Error::UnexpectedFormatTag),
+        }
+    }
+}
+
+/// Unpacks a float or an integer, returning the amount of read bytes.
+fn take_number(mut buf: &[u8]) -> Result<(usize, Number), Error> {
+    let format = take_byte(&mut buf)?;
+    match format {
+        Format::FLOAT32 => Ok((5, Number::F32(take_num(&mut buf, f32::from_be_bytes)?))),
+        Format::FLOAT64 => Ok((9, Number::F64(take_num(&mut buf, f64::from_be_bytes)?))),
+        _ => take_int(format, &mut buf).map(|(n, v)| (n, Number::Int(v))),
+    }
+}
+
+/// Unpacks a float or an integer from the iterator, returning the amount of read bytes.
+fn take_number_iter<I>(mut bytes: I) -> Result<(usize, Number), Error>
+where
+    I: Iterator<Item = u8>,
+{
+    let format = take_byte_iter(bytes.by_ref())?;
+    match format {
+        Format::FLOAT32 => Ok((5, Number::F32(take_num_iter(bytes, f32::from_be_bytes)?))),
+        Format::FLOAT64 => Ok((9, Number::F64(take_num_iter(bytes, f64::from_be_bytes)?))),
+        _ => take_int_iter(format, bytes).map(|(n, v)| (n, Number::Int(v))),
+    }
+}
@@ -3,140 +3,95 @@ use super::{
     Error, Format, Unpackable,
 };
 
-impl Unpackable for u8 {
-    type Error = Error;
-
-    fn unpack(mut buf: &[u8]) -> Result<(usize, Self), Self::Error> {
-        let format = take_byte(&mut buf)?;
-        match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, format)),
-            Format::UINT8 => take_byte(&mut buf).map(|v| (2, v)),
-            _ => Err(Error::UnexpectedFormatTag),
-        }
-    }
-
-    fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
-    where
-        I: IntoIterator<Item = u8>,
-    {
-        let mut bytes = bytes.into_iter();
-        let format = take_byte_iter(bytes.by_ref())?;
-        match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, format)),
-            Format::UINT8 => take_byte_iter(bytes).map(|v| (2, v)),
-            _ => Err(Error::UnexpectedFormatTag),
-        }
+/// Unpacks an integer of any format, returning the amount of read bytes with the format byte.
+///
+/// Every integer format fits in an `i128`, so the marker chosen by the encoder doesn't matter;
+/// only the value is checked against the range of the target type.
+pub(crate) fn take_int(format: u8, buf: &mut &[u8]) -> Result<(usize, i128), Error> {
+    match format {
+        0x00..=Format::POSITIVE_FIXINT => Ok((1, format as i128)),
+        0xe0.. => Ok((1, format as i8 as i128)),
+        Format::UINT8 => take_byte(buf).map(|v| (2, v as i128)),
+        Format::UINT16 => take_num(buf, u16::from_be_bytes).map(|v| (3, v as i128)),
+        Format::UINT32 => take_num(buf, u32::from_be_bytes).map(|v| (5, v as i128)),
+        Format::UINT64 => take_num(buf, u64::from_be_bytes).map(|v| (9, v as i128)),
+        Format::INT8 => take_byte(buf).map(|v| (2, v as i8 as i128)),
+        Format::INT16 => take_num(buf, i16::from_be_bytes).map(|v| (3, v as i128)),
+        Format::INT32 => take_num(buf, i32::from_be_bytes).map(|v| (5, v as i128)),
+        Format::INT64 => take_num(buf, i64::from_be_bytes).map(|v| (9, v as i128)),
+        _ => Err(Error::UnexpectedFormatTag),
     }
 }
 
-impl Unpackable for u16 {
-    type Error = Error;
-
-    fn unpack(mut buf: &[u8]) -> Result<(usize, Self), Self::Error> {
-        let format = take_byte(&mut buf)?;
-        match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, format as u16)),
-            Format::UINT8 => take_byte(&mut buf).map(|v| (2, v as u16)),
-            Format::UINT16 => take_num(&mut buf, u16::from_be_bytes).map(|v| (3, v)),
-            _ => Err(Error::UnexpectedFormatTag),
-        }
-    }
-
-    fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
-    where
-        I: IntoIterator<Item = u8>,
-    {
-        let mut bytes = bytes.into_iter();
-        let format = take_byte_iter(bytes.by_ref())?;
-        match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, format as u16)),
-            Format::UINT8 => take_byte_iter(bytes).map(|v| (2, v as u16)),
-            Format::UINT16 => take_num_iter(bytes, u16::from_be_bytes).map(|v| (3, v)),
-            _ => Err(Error::UnexpectedFormatTag),
-        }
+/// Unpacks an integer of any format from the bytes that follow the format byte.
+pub(super) fn take_int_iter<I>(format: u8, bytes: I) -> Result<(usize, i128), Error>
+where
+    I: Iterator<Item = u8>,
+{
+    match format {
+        0x00..=Format::POSITIVE_FIXINT => Ok((1, format as i128)),
+        0xe0.. => Ok((1, format as i8 as i128)),
+        Format::UINT8 => take_byte_iter(bytes).map(|v| (2, v as i128)),
+        Format::UINT16 => take_num_iter(bytes, u16::from_be_bytes).map(|v| (3, v as i128)),
+        Format::UINT32 => take_num_iter(bytes, u32::from_be_bytes).map(|v| (5, v as i128)),
+        Format::UINT64 => take_num_iter(bytes, u64::from_be_bytes).map(|v| (9, v as i128)),
+        Format::INT8 => take_byte_iter(bytes).map(|v| (2, v as i8 as i128)),
+        Format::INT16 => take_num_iter(bytes, i16::from_be_bytes).map(|v| (3, v as i128)),
+        Format::INT32 => take_num_iter(bytes, i32::from_be_bytes).map(|v| (5, v as i128)),
+        Format::INT64 => take_num_iter(bytes, i64::from_be_bytes).map(|v| (9, v as i128)),
+        _ => Err(Error::UnexpectedFormatTag),
     }
 }
 
-impl Unpackable for u32 {
-    type Error = Error;
-
-    fn unpack(mut buf: &[u8]) -> Result<(usize, Self), Self::Error> {
-        let format = take_byte(&mut buf)?;
-        match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, format as u32)),
-            Format::UINT8 => take_byte(&mut buf).map(|v| (2, v as u32)),
-            Format::UINT16 => take_num(&mut buf, u16::from_be_bytes).map(|v| (3, v as u32)),
-            Format::UINT32 => take_num(&mut buf, u32::from_be_bytes).map(|v| (5, v)),
-            _ => Err(Error::UnexpectedFormatTag),
-        }
-    }
-
-    fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
-    where
-        I: IntoIterator<Item = u8>,
-    {
-        let mut bytes = bytes.into_iter();
-        let format = take_byte_iter(bytes.by_ref())?;
-        match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, format as u32)),
-            Format::UINT8 => take_byte_iter(bytes).map(|v| (2, v as u32)),
-            Format::UINT16 => take_num_iter(bytes, u16::from_be_bytes).map(|v| (3, v as u32)),
-            Format::UINT32 => take_num_iter(bytes, u32::from_be_bytes).map(|v| (5, v)),
-            _ => Err(Error::UnexpectedFormatTag),
-        }
-    }
+/// Narrows the unpacked integer to the target type.
+fn narrow<V>((n, v): (usize, i128)) -> Result<(usize, V), Error>
+where
+    V: TryFrom<i128>,
+{
+    V::try_from(v)
+        .map(|v| (n, v))
+        .map_err(|_| Error::IntegerOutOfRange)
 }
 
-impl Unpackable for u64 {
-    type Error = Error;
+macro_rules! unpack_int {
+    ($($t:ty),*) => {
+        $(
+            impl Unpackable for $t {
+                type Error = Error;
 
-    fn unpack(mut buf: &[u8]) -> Result<(usize, Self), Self::Error> {
-        let format = take_byte(&mut buf)?;
-        match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, format as u64)),
-            Format::UINT8 => take_byte(&mut buf).map(|v| (2, v as u64)),
-            Format::UINT16 => take_num(&mut buf, u16::from_be_bytes).map(|v| (3, v as u64)),
-            Format::UINT32 => take_num(&mut buf, u32::from_be_bytes).map(|v| (5, v as u64)),
-            Format::UINT64 => take_num(&mut buf, u64::from_be_bytes).map(|v| (9, v)),
-            _ => Err(Error::UnexpectedFormatTag),
-        }
-    }
+                fn unpack(mut buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+                    let format = take_byte(&mut buf)?;
+                    take_int(format, &mut buf).and_then(narrow)
+                }
 
-    fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
-    where
-        I: IntoIterator<Item = u8>,
-    {
-        let mut bytes = bytes.into_iter();
-        let format = take_byte_iter(bytes.by_ref())?;
-        match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, format as u64)),
-            Format::UINT8 => take_byte_iter(bytes).map(|v| (2, v as u64)),
-            Format::UINT16 => take_num_iter(bytes, u16::from_be_bytes).map(|v| (3, v as u64)),
-            Format::UINT32 => take_num_iter(bytes, u32::from_be_bytes).map(|v| (5, v as u64)),
-            Format::UINT64 => take_num_iter(bytes, u64::from_be_bytes).map(|v| (9, v)),
-            _ => Err(Error::UnexpectedFormatTag),
-        }
-    }
+                fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
+                where
+                    I: IntoIterator<Item = u8>,
+                {
+                    let mut bytes = bytes.into_iter();
+                    let format = take_byte_iter(bytes.by_ref())?;
+                    take_int_iter(format, bytes).and_then(narrow)
+                }
+            }
+        )*
+    };
 }
 
+unpack_int!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
 impl Unpackable for u128 {
     type Error = Error;
 
     fn unpack(mut buf: &[u8]) -> Result<(usize, Self), Self::Error> {
         let format = take_byte(&mut buf)?;
         match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, format as u128)),
-            Format::UINT8 => take_byte(&mut buf).map(|v| (2, v as u128)),
-            Format::UINT16 => take_num(&mut buf, u16::from_be_bytes).map(|v| (3, v as u128)),
-            Format::UINT32 => take_num(&mut buf, u32::from_be_bytes).map(|v| (5, v as u128)),
-            Format::UINT64 => take_num(&mut buf, u64::from_be_bytes).map(|v| (9, v as u128)),
             Format::BIN8 => {
                 if take_byte(&mut buf)? != 16 {
                     return Err(Error::UnexpectedBinLength);
                 }
                 take_num(&mut buf, u128::from_be_bytes).map(|v| (18, v))
             }
-            _ => Err(Error::UnexpectedFormatTag),
+            _ => take_int(format, &mut buf).and_then(narrow),
         }
     }
 
@@ -147,174 +102,24 @@ impl Unpackable for u128 {
         let mut bytes = bytes.into_iter();
         let format = take_byte_iter(bytes.by_ref())?;
         match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, format as u128)),
-            Format::UINT8 => take_byte_iter(bytes).map(|v| (2, v as u128)),
-            Format::UINT16 => take_num_iter(bytes, u16::from_be_bytes).map(|v| (3, v as u128)),
-            Format::UINT32 => take_num_iter(bytes, u32::from_be_bytes).map(|v| (5, v as u128)),
-            Format::UINT64 => take_num_iter(bytes, u64::from_be_bytes).map(|v| (9, v as u128)),
             Format::BIN8 => {
                 if take_byte_iter(bytes.by_ref())? != 16 {
                     return Err(Error::UnexpectedBinLength);
                 }
                 take_num_iter(bytes, u128::from_be_bytes).map(|v| (18, v))
             }
-            _ => Err(Error::UnexpectedFormatTag),
-        }
-    }
-}
-
-impl Unpackable for usize {
-    type Error = Error;
-
-    fn unpack(mut buf: &[u8]) -> Result<(usize, Self), Self::Error> {
-        let format = take_byte(&mut buf)?;
-        match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, format as usize)),
-            Format::UINT8 => take_byte(&mut buf).map(|v| (2, v as usize)),
-            Format::UINT16 => take_num(&mut buf, u16::from_be_bytes).map(|v| (3, v as usize)),
-            Format::UINT32 => take_num(&mut buf, u32::from_be_bytes).map(|v| (5, v as usize)),
-            Format::UINT64 => take_num(&mut buf, u64::from_be_bytes).map(|v| (9, v as usize)),
-            _ => Err(Error::UnexpectedFormatTag),
-        }
-    }
-
-    fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
-    where
-        I: IntoIterator<Item = u8>,
-    {
-        let mut bytes = bytes.into_iter();
-        let format = take_byte_iter(bytes.by_ref())?;
-        match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, format as usize)),
-            Format::UINT8 => take_byte_iter(bytes).map(|v| (2, v as usize)),
-            Format::UINT16 => take_num_iter(bytes, u16::from_be_bytes).map(|v| (3, v as usize)),
-            Format::UINT32 => take_num_iter(bytes, u32::from_be_bytes).map(|v| (5, v as usize)),
-            Format::UINT64 => take_num_iter(bytes, usize::from_be_bytes).map(|v| (9, v)),
-            _ => Err(Error::UnexpectedFormatTag),
-        }
-    }
-}
-
-impl Unpackable for i8 {
-    type Error = Error;
-
-    fn unpack(mut buf: &[u8]) -> Result<(usize, Self), Self::Error> {
-        let format = take_byte(&mut buf)?;
-        match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, format as i8)),
-            0xe0.. => Ok((1, format as i8)),
-            Format::INT8 => take_byte(&mut buf).map(|v| (2, v as i8)),
-            _ => Err(Error::UnexpectedFormatTag),
-        }
-    }
-
-    fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
-    where
-        I: IntoIterator<Item = u8>,
-    {
-        let mut bytes = bytes.into_iter();
-        let format = take_byte_iter(bytes.by_ref())?;
-        match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, format as i8)),
-            0xe0.. => Ok((1, format as i8)),
-            Format::INT8 => take_byte_iter(bytes).map(|v| (2, v as i8)),
-            _ => Err(Error::UnexpectedFormatTag),
-        }
-    }
-}
-
-impl Unpackable for i16 {
-    type Error = Error;
-
-    fn unpack(mut buf: &[u8]) -> Result<(usize, Self), Self::Error> {
-        let format = take_byte(&mut buf)?;
-        match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, (format as i8) as i16)),
-            0xe0.. => Ok((1, (format as i8) as i16)),
-            Format::INT8 => take_byte(&mut buf).map(|v| (2, v as i8 as i16)),
-            Format::INT16 => take_num(&mut buf, i16::from_be_bytes).map(|v| (3, v)),
-            _ => Err(Error::UnexpectedFormatTag),
-        }
-    }
-
-    fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
-    where
-        I: IntoIterator<Item = u8>,
-    {
-        let mut bytes = bytes.into_iter();
-        let format = take_byte_iter(bytes.by_ref())?;
-        match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, (format as i8) as i16)),
-            0xe0.. => Ok((1, (format as i8) as i16)),
-            Format::INT8 => take_byte_iter(bytes).map(|v| (2, v as i8 as i16)),
-            Format::INT16 => take_num_iter(bytes, i16::from_be_bytes).map(|v| (3, v)),
-            _ => Err(Error::UnexpectedFormatTag),
-        }
-    }
-}
-
-impl Unpackable for i32 {
-    type Error = Error;
-
-    fn unpack(mut buf: &[u8]) -> Result<(usize, Self), Self::Error> {
-        let format = take_byte(&mut buf)?;
-        match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, (format as i8) as i32)),
-            0xe0.. => Ok((1, (format as i8) as i32)),
-            Format::INT8 => take_byte(&mut buf).map(|v| (2, v as i8 as i32)),
-            Format::INT16 => take_num(&mut buf, i16::from_be_bytes).map(|v| (3, v as i32)),
-            Format::INT32 => take_num(&mut buf, i32::from_be_bytes).map(|v| (5, v)),
-            _ => Err(Error::UnexpectedFormatTag),
-        }
-    }
-
-    fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
-    where
-        I: IntoIterator<Item = u8>,
-    {
-        let mut bytes = bytes.into_iter();
-        let format = take_byte_iter(bytes.by_ref())?;
-        match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, (format as i8) as i32)),
-            0xe0.. => Ok((1, (format as i8) as i32)),
-            Format::INT8 => take_byte_iter(bytes).map(|v| (2, v as i8 as i32)),
-            Format::INT16 => take_num_iter(bytes, i16::from_be_bytes).map(|v| (3, v as i32)),
-            Format::INT32 => take_num_iter(bytes, i32::from_be_bytes).map(|v| (5, v)),
-            _ => Err(Error::UnexpectedFormatTag),
+            _ => take_int_iter(format, bytes).and_then(narrow),
         }
     }
-}
-
-impl Unpackable for i64 {
-    type Error = Error;
 
-    fn unpack(mut buf: &[u8]) -> Result<(usize, Self), Self::Error> {
-        let format = take_byte(&mut buf)?;
-        match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, (format as i8) as i64)),
-            0xe0.. => Ok((1, (format as i8) as i64)),
-            Format::INT8 => take_byte(&mut buf).map(|v| (2, v as i8 as i64)),
-            Format::INT16 => take_num(&mut buf, i16::from_be_bytes).map(|v| (3, v as i64)),
-            Format::INT32 => take_num(&mut buf, i32::from_be_bytes).map(|v| (5, v as i64)),
-            Format::INT64 => take_num(&mut buf, i64::from_be_bytes).map(|v| (9, v)),
-            _ => Err(Error::UnexpectedFormatTag),
-        }
-    }
-
-    fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
-    where
-        I: IntoIterator<Item = u8>,
-    {
-        let mut bytes = bytes.into_iter();
-        let format = take_byte_iter(bytes.by_ref())?;
-        match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, (format as i8) as i64)),
-            0xe0.. => Ok((1, (format as i8) as i64)),
-            Format::INT8 => take_byte_iter(bytes).map(|v| (2, v as i8 as i64)),
-            Format::INT16 => take_num_iter(bytes, i16::from_be_bytes).map(|v| (3, v as i64)),
-            Format::INT32 => take_num_iter(bytes, i32::from_be_bytes).map(|v| (5, v as i64)),
-            Format::INT64 => take_num_iter(bytes, i64::from_be_bytes).map(|v| (9, v)),
-            _ => Err(Error::UnexpectedFormatTag),
+    #[cfg(feature = "alloc")]
+    fn unpack_strict(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+        let (n, v) = Self::unpack(buf)?;
+        match buf[0] {
+            // the value is packed as a bin only if no integer format fits it
+            Format::BIN8 if u64::try_from(v).is_ok() => Err(Error::NonMinimalInteger),
+            Format::BIN8 => Ok((n, v)),
+            _ => crate::strict::check(&buf[..n]).map(|_| (n, v)),
         }
     }
 }
@@ -325,19 +130,13 @@ impl Unpackable for i128 {
     fn unpack(mut buf: &[u8]) -> Result<(usize, Self), Self::Error> {
         let format = take_byte(&mut buf)?;
         match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, (format as i8) as i128)),
-            0xe0.. => Ok((1, (format as i8) as i128)),
-            Format::INT8 => take_byte(&mut buf).map(|v| (2, v as i8 as i128)),
-            Format::INT16 => take_num(&mut buf, i16::from_be_bytes).map(|v| (3, v as i128)),
-            Format::INT32 => take_num(&mut buf, i32::from_be_bytes).map(|v| (5, v as i128)),
-            Format::INT64 => take_num(&mut buf, i64::from_be_bytes).map(|v| (9, v as i128)),
             Format::BIN8 => {
                 if take_byte(&mut buf)? != 16 {
                     return Err(Error::UnexpectedBinLength);
                 }
                 take_num(&mut buf, i128::from_be_bytes).map(|v| (18, v))
             }
-            _ => Err(Error::UnexpectedFormatTag),
+            _ => take_int(format, &mut buf),
         }
     }
 
@@ -348,53 +147,24 @@ impl Unpackable for i128 {
         let mut bytes = bytes.into_iter();
         let format = take_byte_iter(bytes.by_ref())?;
         match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, (format as i8) as i128)),
-            0xe0.. => Ok((1, (format as i8) as i128)),
-            Format::INT8 => take_byte_iter(bytes).map(|v| (2, v as i8 as i128)),
-            Format::INT16 => take_num_iter(bytes, i16::from_be_bytes).map(|v| (3, v as i128)),
-            Format::INT32 => take_num_iter(bytes, i32::from_be_bytes).map(|v| (5, v as i128)),
-            Format::INT64 => take_num_iter(bytes, i64::from_be_bytes).map(|v| (9, v as i128)),
             Format::BIN8 => {
                 if take_byte_iter(bytes.by_ref())? != 16 {
                     return Err(Error::UnexpectedBinLength);
                 }
                 take_num_iter(bytes, i128::from_be_bytes).map(|v| (18, v))
             }
-            _ => Err(Error::UnexpectedFormatTag),
-        }
-    }
-}
-
-impl Unpackable for isize {
-    type Error = Error;
-
-    fn unpack(mut buf: &[u8]) -> Result<(usize, Self), Self::Error> {
-        let format = take_byte(&mut buf)?;
-        match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, (format as i8) as isize)),
-            0xe0.. => Ok((1, (format as i8) as isize)),
-            Format::INT8 => take_byte(&mut buf).map(|v| (2, v as i8 as isize)),
-            Format::INT16 => take_num(&mut buf, i16::from_be_bytes).map(|v| (3, v as isize)),
-            Format::INT32 => take_num(&mut buf, i32::from_be_bytes).map(|v| (5, v as isize)),
-            Format::INT64 => take_num(&mut buf, i64::from_be_bytes).map(|v| (9, v as isize)),
-            _ => Err(Error::UnexpectedFormatTag),
+            _ => take_int_iter(format, bytes),
         }
     }
 
-    fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
-    where
-        I: IntoIterator<Item = u8>,
-    {
-        let mut bytes = bytes.into_iter();
-        let format = take_byte_iter(bytes.by_ref())?;
-        match format {
-            0x00..=Format::POSITIVE_FIXINT => Ok((1, (format as i8) as isize)),
-            0xe0.. => Ok((1, (format as i8) as isize)),
-            Format::INT8 => take_byte_iter(bytes).map(|v| (2, v as i8 as isize)),
-            Format::INT16 => take_num_iter(bytes, i16::from_be_bytes).map(|v| (3, v as isize)),
-            Format::INT32 => take_num_iter(bytes, i32::from_be_bytes).map(|v| (5, v as isize)),
-            Format::INT64 => take_num_iter(bytes, i64::from_be_bytes).map(|v| (9, v as isize)),
-            _ => Err(Error::UnexpectedFormatTag),
+    #[cfg(feature = "alloc")]
+    fn unpack_strict(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+        let (n, v) = Self::unpack(buf)?;
+        match buf[0] {
+            // the value is packed as a bin only if no signed integer format fits it
+            Format::BIN8 if i64::try_from(v).is_ok() => Err(Error::NonMinimalInteger),
+            Format::BIN8 => Ok((n, v)),
+            _ => crate::strict::check(&buf[..n]).map(|_| (n, v)),
         }
     }
 }
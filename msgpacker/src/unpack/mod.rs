@@ -3,7 +3,26 @@ use super::{helpers, Error, Format, Unpackable};
 pub(crate) mod binary;
 pub(crate) mod collections;
 mod common;
+mod ext;
 mod float;
 mod int;
+#[cfg(feature = "alloc")]
+mod numeric;
+mod skip;
 
-pub use collections::{unpack_array, unpack_array_iter, unpack_map, unpack_map_iter};
+pub use binary::{unpack_bytes, unpack_str};
+pub use collections::{
+    unpack_array, unpack_array_iter, unpack_array_len, unpack_map, unpack_map_iter, unpack_map_len,
+};
+#[cfg(feature = "serde")]
+pub(crate) use ext::is_ext;
+pub(crate) use ext::unpack_ext;
+pub use float::FloatOptions;
+#[cfg(feature = "alloc")]
+pub(crate) use int::take_int;
+pub use skip::skip_value;
+
+#[cfg(feature = "alloc")]
+pub use numeric::unpack_numbers;
+#[cfg(feature = "alloc")]
+pub use skip::take_value_iter;
@@ -0,0 +1,67 @@
+use super::{unpack_array_len, Error};
+use crate::numeric::{class, width, Numeric};
+use alloc::vec::Vec;
+
+/// Unpacks an array of numbers from the buffer, returning them and the amount of read bytes.
+///
+/// It accepts the bytes [unpack_array](crate::unpack_array) does, but a run of numbers sharing
+/// a fixed-width format that always fits the type is checked and converted in a single pass;
+/// the other numbers are unpacked one by one.
+pub fn unpack_numbers<N>(buf: &[u8]) -> Result<(usize, Vec<N>), Error>
+where
+    N: Numeric,
+{
+    let (mut n, len) = unpack_array_len(buf)?;
+    // every number takes at least a byte, so a bogus length can't allocate past the buffer
+    let mut values = Vec::with_capacity(len.min(buf.len() - n));
+    while values.len() < len {
+        let rest = &buf[n..];
+        let left = len - values.len();
+        let format = rest.first().map(|b| class(*b)).filter(|c| N::decodes(*c));
+        let c = match format.and_then(|c| Some((c, width(c)?))) {
+            Some((format, 1)) => decode::<0, N>(format, rest, left, &mut values),
+            Some((format, 2)) => decode::<1, N>(format, rest, left, &mut values),
+            Some((format, 3)) => decode::<2, N>(format, rest, left, &mut values),
+            Some((format, 5)) => decode::<4, N>(format, rest, left, &mut values),
+            Some((format, _)) => decode::<8, N>(format, rest, left, &mut values),
+            None => 0,
+        };
+        n += match c {
+            // a number the run can't decode, such as one out of range or truncated
+            0 => {
+                let (c, v) = N::unpack(rest)?;
+                values.push(v);
+                c
+            }
+            c => c,
+        };
+    }
+    Ok((n, values))
+}
+
+/// Decodes the run of up to `left` numbers of the format at the start of the buffer, returning
+/// the amount of read bytes.
+///
+/// Each number is the format byte followed by `D` bytes in big endian, or the fixint itself.
+fn decode<const D: usize, N>(format: u8, buf: &[u8], left: usize, values: &mut Vec<N>) -> usize
+where
+    N: Numeric,
+{
+    let w = if D == 0 { 1 } else { D + 1 };
+    let start = values.len();
+    for b in buf.chunks_exact(w).take(left) {
+        if class(b[0]) != format {
+            break;
+        }
+        let raw = match D {
+            0 => b[0] as u64,
+            _ => {
+                let mut raw = [0u8; 8];
+                raw[8 - D..].copy_from_slice(&b[1..]);
+                u64::from_be_bytes(raw)
+            }
+        };
+        values.push(N::from_raw(format, raw));
+    }
+    (values.len() - start) * w
+}
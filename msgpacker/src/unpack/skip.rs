@@ -0,0 +1,147 @@
+use super::{
+    helpers::{take_buffer, take_byte, take_num},
+    Error, Format,
+};
+
+/// Returns the amount of bytes the next encoded value occupies in the buffer, without decoding
+/// it.
+///
+/// Arrays and maps are skipped as a whole, including their nested elements.
+pub fn skip_value(buf: &[u8]) -> Result<usize, Error> {
+    let mut cursor = buf;
+    let mut remaining = 1usize;
+
+    while remaining > 0 {
+        remaining -= 1;
+
+        let format = take_byte(&mut cursor)?;
+        let (len, children) = match format {
+            0x00..=Format::POSITIVE_FIXINT | 0xe0..=0xff => (0, 0),
+            Format::NIL | Format::TRUE | Format::FALSE => (0, 0),
+            0x80..=0x8f => (0, (format & 0x0f) as usize * 2),
+            0x90..=0x9f => (0, (format & 0x0f) as usize),
+            0xa0..=0xbf => (format as usize & 0x1f, 0),
+            Format::UINT8 | Format::INT8 => (1, 0),
+            Format::UINT16 | Format::INT16 => (2, 0),
+            Format::UINT32 | Format::INT32 | Format::FLOAT32 => (4, 0),
+            Format::UINT64 | Format::INT64 | Format::FLOAT64 => (8, 0),
+            Format::BIN8 | Format::STR8 => (take_byte(&mut cursor)? as usize, 0),
+            Format::BIN16 | Format::STR16 => {
+                (take_num(&mut cursor, u16::from_be_bytes)? as usize, 0)
+            }
+            Format::BIN32 | Format::STR32 => {
+                (take_num(&mut cursor, u32::from_be_bytes)? as usize, 0)
+            }
+            Format::ARRAY16 => (0, take_num(&mut cursor, u16::from_be_bytes)? as usize),
+            Format::ARRAY32 => (0, take_num(&mut cursor, u32::from_be_bytes)? as usize),
+            Format::MAP16 => (0, take_num(&mut cursor, u16::from_be_bytes)? as usize * 2),
+            Format::MAP32 => {
+                let len = take_num(&mut cursor, u32::from_be_bytes)? as usize;
+                (0, len.checked_mul(2).ok_or(Error::BufferTooShort)?)
+            }
+            Format::FIXEXT1 => (2, 0),
+            Format::FIXEXT2 => (3, 0),
+            Format::FIXEXT4 => (5, 0),
+            Format::FIXEXT8 => (9, 0),
+            Format::FIXEXT16 => (17, 0),
+            Format::EXT8 => (take_byte(&mut cursor)? as usize + 1, 0),
+            Format::EXT16 => (take_num(&mut cursor, u16::from_be_bytes)? as usize + 1, 0),
+            Format::EXT32 => (take_num(&mut cursor, u32::from_be_bytes)? as usize + 1, 0),
+            _ => return Err(Error::UnexpectedFormatTag),
+        };
+
+        take_buffer(&mut cursor, len)?;
+        remaining = remaining
+            .checked_add(children)
+            .ok_or(Error::BufferTooShort)?;
+    }
+
+    Ok(buf.len() - cursor.len())
+}
+
+#[cfg(feature = "alloc")]
+mod alloc {
+    use super::*;
+    use ::alloc::vec::Vec;
+
+    /// Takes the bytes of the next encoded value from the iterator, without decoding it.
+    ///
+    /// The returned buffer can be decoded with [crate::Unpackable::unpack]; its length is the
+    /// amount of consumed bytes.
+    pub fn take_value_iter<I>(bytes: I) -> Result<Vec<u8>, Error>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        let mut bytes = bytes.into_iter();
+        let mut value = Vec::new();
+        let mut remaining = 1usize;
+
+        let mut take = |value: &mut Vec<u8>, len: usize| -> Result<(), Error> {
+            let start = value.len();
+            value.extend(bytes.by_ref().take(len));
+            if value.len() - start < len {
+                return Err(Error::BufferTooShort);
+            }
+            Ok(())
+        };
+
+        while remaining > 0 {
+            remaining -= 1;
+
+            take(&mut value, 1)?;
+            let format = value[value.len() - 1];
+            let header = match format {
+                Format::BIN8 | Format::STR8 | Format::EXT8 => 1,
+                Format::BIN16 | Format::STR16 | Format::EXT16 => 2,
+                Format::ARRAY16 | Format::MAP16 => 2,
+                Format::BIN32 | Format::STR32 | Format::EXT32 => 4,
+                Format::ARRAY32 | Format::MAP32 => 4,
+                _ => 0,
+            };
+            take(&mut value, header)?;
+
+            let mut header_bytes = &value[value.len() - header..];
+            let header_len = match header {
+                1 => take_byte(&mut header_bytes)? as usize,
+                2 => take_num(&mut header_bytes, u16::from_be_bytes)? as usize,
+                4 => take_num(&mut header_bytes, u32::from_be_bytes)? as usize,
+                _ => 0,
+            };
+
+            let (len, children) = match format {
+                0x00..=Format::POSITIVE_FIXINT | 0xe0..=0xff => (0, 0),
+                Format::NIL | Format::TRUE | Format::FALSE => (0, 0),
+                0x80..=0x8f => (0, (format & 0x0f) as usize * 2),
+                0x90..=0x9f => (0, (format & 0x0f) as usize),
+                0xa0..=0xbf => (format as usize & 0x1f, 0),
+                Format::UINT8 | Format::INT8 => (1, 0),
+                Format::UINT16 | Format::INT16 => (2, 0),
+                Format::UINT32 | Format::INT32 | Format::FLOAT32 => (4, 0),
+                Format::UINT64 | Format::INT64 | Format::FLOAT64 => (8, 0),
+                Format::BIN8 | Format::BIN16 | Format::BIN32 => (header_len, 0),
+                Format::STR8 | Format::STR16 | Format::STR32 => (header_len, 0),
+                Format::ARRAY16 | Format::ARRAY32 => (0, header_len),
+                Format::MAP16 | Format::MAP32 => {
+                    (0, header_len.checked_mul(2).ok_or(Error::BufferTooShort)?)
+                }
+                Format::FIXEXT1 => (2, 0),
+                Format::FIXEXT2 => (3, 0),
+                Format::FIXEXT4 => (5, 0),
+                Format::FIXEXT8 => (9, 0),
+                Format::FIXEXT16 => (17, 0),
+                Format::EXT8 | Format::EXT16 | Format::EXT32 => (header_len + 1, 0),
+                _ => return Err(Error::UnexpectedFormatTag),
+            };
+
+            take(&mut value, len)?;
+            remaining = remaining
+                .checked_add(children)
+                .ok_or(Error::BufferTooShort)?;
+        }
+
+        Ok(value)
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub use alloc::take_value_iter;
@@ -0,0 +1,128 @@
+use core::mem;
+
+#[cfg(feature = "std")]
+use std::io;
+
+/// A sink for the packed bytes.
+///
+/// The bytes are written a slice at a time, so a growable buffer copies each string or bin at
+/// once. An `Extend<u8>` buffer is written through [ExtendWriter], and, with `std`, an
+/// [io::Write] through [IoWriter].
+pub trait Writer {
+    /// Writes all the bytes.
+    ///
+    /// A sink of fixed size, such as `&mut [u8]`, panics if the bytes don't fit.
+    fn write_all(&mut self, bytes: &[u8]);
+
+    /// Reserves room for at least `additional` more bytes, so a growable sink allocates once.
+    ///
+    /// The default implementation does nothing.
+    fn reserve(&mut self, additional: usize) {
+        let _ = additional;
+    }
+}
+
+impl<W> Writer for &mut W
+where
+    W: Writer + ?Sized,
+{
+    fn write_all(&mut self, bytes: &[u8]) {
+        W::write_all(self, bytes)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        W::reserve(self, additional)
+    }
+}
+
+/// Writes at the start of the slice, advancing it past the written bytes.
+impl Writer for &mut [u8] {
+    fn write_all(&mut self, bytes: &[u8]) {
+        assert!(
+            bytes.len() <= self.len(),
+            "the slice is too short for the packed bytes"
+        );
+        let (head, tail) = mem::take(self).split_at_mut(bytes.len());
+        head.copy_from_slice(bytes);
+        *self = tail;
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Writer for ::alloc::vec::Vec<u8> {
+    fn write_all(&mut self, bytes: &[u8]) {
+        self.extend_from_slice(bytes);
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        ::alloc::vec::Vec::reserve(self, additional);
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl Writer for ::bytes::BytesMut {
+    fn write_all(&mut self, bytes: &[u8]) {
+        self.extend_from_slice(bytes);
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        ::bytes::BytesMut::reserve(self, additional);
+    }
+}
+
+/// A [Writer] for an `Extend<u8>` buffer, which receives the bytes one by one.
+#[derive(Debug)]
+pub struct ExtendWriter<'a, T>(pub &'a mut T);
+
+impl<T> Writer for ExtendWriter<'_, T>
+where
+    T: Extend<u8>,
+{
+    fn write_all(&mut self, bytes: &[u8]) {
+        self.0.extend(bytes.iter().copied());
+    }
+}
+
+/// A [Writer] for an [io::Write], keeping the first error.
+///
+/// The bytes are written as they are packed, so an unbuffered writer should be wrapped in an
+/// [io::BufWriter].
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct IoWriter<W> {
+    w: W,
+    error: Option<io::Error>,
+}
+
+#[cfg(feature = "std")]
+impl<W> IoWriter<W> {
+    /// Creates a writer for the provided [io::Write].
+    pub const fn new(w: W) -> Self {
+        Self { w, error: None }
+    }
+
+    /// Returns the inner writer, or the first error it failed with.
+    ///
+    /// The bytes written after the failure are discarded.
+    pub fn into_inner(self) -> io::Result<W> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.w),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W> Writer for IoWriter<W>
+where
+    W: io::Write,
+{
+    fn write_all(&mut self, bytes: &[u8]) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(e) = self.w.write_all(bytes) {
+            self.error = Some(e);
+        }
+    }
+}
@@ -23,7 +23,7 @@ fn vec_u8_uses_binary_format() {
     // NOT an array format tag (0x90..=0x9f / 0xdc / 0xdd).
     let tag = bytes[0];
     assert!(
-        matches!(tag, 0xc4 | 0xc5 | 0xc6,),
+        matches!(tag, 0xc4..=0xc6),
         "expected binary format tag (0xc4..=0xc6), got 0x{:02x}",
         tag,
     );
@@ -0,0 +1,116 @@
+use bytes::{Bytes, BytesMut};
+use msgpacker::{prelude::*, unpack_from_bytes, ByteStr};
+use proptest::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+pub struct Frame {
+    pub id: u32,
+    pub topic: ByteStr,
+    pub payload: Bytes,
+    pub parts: Vec<Bytes>,
+    pub note: Option<ByteStr>,
+}
+
+/// Whether the bytes are a view into the buffer.
+fn within(buf: &Bytes, bytes: &[u8]) -> bool {
+    let (b, d) = (buf.as_ptr_range(), bytes.as_ptr_range());
+    b.start <= d.start && d.end <= b.end
+}
+
+fn frame() -> Frame {
+    Frame {
+        id: 9,
+        topic: "sensors/a".into(),
+        payload: Bytes::from(vec![1u8; 1000]),
+        parts: vec![Bytes::from_static(b"x"), Bytes::from_static(b"yz")],
+        note: Some(ByteStr::from_static("ok")),
+    }
+}
+
+#[test]
+fn bytes_encoded_as_bin() {
+    let bytes = Bytes::from_static(b"abc");
+    assert_eq!(bytes.pack_to_vec(), b"abc".to_vec().pack_to_vec());
+    let s = ByteStr::from("abc");
+    assert_eq!(s.pack_to_vec(), "abc".to_string().pack_to_vec());
+    assert_eq!(&*s, "abc");
+}
+
+#[test]
+fn bytes_unpack_from_bytes_is_zero_copy() {
+    let frame = frame();
+    let mut buf = BytesMut::new();
+    frame.pack(&mut buf);
+    let buf = buf.freeze();
+
+    let (n, decoded) = unpack_from_bytes::<Frame>(&buf).unwrap();
+    assert_eq!(n, buf.len());
+    assert_eq!(decoded, frame);
+    assert!(within(&buf, decoded.topic.as_bytes()));
+    assert!(within(&buf, &decoded.payload));
+    assert!(decoded.parts.iter().all(|p| within(&buf, p)));
+    assert!(within(&buf, decoded.note.as_ref().unwrap().as_bytes()));
+
+    // the views outlive the buffer handle
+    drop(buf);
+    assert_eq!(decoded.payload, frame.payload);
+}
+
+#[test]
+fn bytes_unpack_copies() {
+    let frame = frame();
+    let buf = Bytes::from(frame.pack_to_vec());
+
+    let (_, decoded) = Frame::unpack(&buf).unwrap();
+    assert_eq!(decoded, frame);
+    assert!(!within(&buf, &decoded.payload));
+
+    let (_, decoded) = Frame::unpack_iter(buf.iter().copied()).unwrap();
+    assert_eq!(decoded, frame);
+}
+
+#[test]
+fn bytes_sources_are_scoped() {
+    let inner = Bytes::from(Bytes::from_static(b"inner").pack_to_vec());
+    let outer = Bytes::from((Bytes::from_static(b"outer"), 1u8).pack_to_vec());
+
+    let (_, (a, _)) = unpack_from_bytes::<(Bytes, u8)>(&outer).unwrap();
+    let (_, b) = unpack_from_bytes::<Bytes>(&inner).unwrap();
+    assert!(within(&outer, &a));
+    assert!(within(&inner, &b));
+
+    // the source is cleared once decoded
+    let (_, c) = Bytes::unpack(&outer).unwrap();
+    assert!(!within(&outer, &c));
+}
+
+#[test]
+fn bytes_invalid_utf8() {
+    let buf = Bytes::from_static(&[0xa2, 0xff, 0xfe]);
+    assert_eq!(
+        unpack_from_bytes::<ByteStr>(&buf).unwrap_err(),
+        Error::InvalidUtf8
+    );
+    assert_eq!(
+        ByteStr::try_from(Bytes::from_static(&[0xff])).unwrap_err(),
+        Error::InvalidUtf8
+    );
+}
+
+proptest! {
+    #[test]
+    fn bytes_proptest(id: u32, topic: String, payload: Vec<u8>, parts: Vec<Vec<u8>>, note: Option<String>) {
+        let frame = Frame {
+            id,
+            topic: topic.into(),
+            payload: payload.into(),
+            parts: parts.into_iter().map(Bytes::from).collect(),
+            note: note.map(ByteStr::from),
+        };
+        let buf = Bytes::from(frame.pack_to_vec());
+        let (n, decoded) = unpack_from_bytes::<Frame>(&buf).unwrap();
+        prop_assert_eq!(n, buf.len());
+        prop_assert_eq!(&decoded, &frame);
+        prop_assert!(decoded.payload.is_empty() || within(&buf, &decoded.payload));
+    }
+}
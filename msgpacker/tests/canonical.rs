@@ -0,0 +1,322 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use msgpacker::canonical::{self, is_canonical};
+use msgpacker::prelude::*;
+use proptest::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+pub struct Document {
+    pub id: i32,
+    pub tags: HashSet<String>,
+    #[msgpacker(map)]
+    pub scores: HashMap<String, i64>,
+    pub ratio: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+#[msgpacker(tag = "kind")]
+pub enum Event {
+    Created {
+        labels: HashMap<String, u8>,
+    },
+    Deleted {
+        ids: HashSet<u16>,
+    },
+    Renamed {
+        name: String,
+        #[msgpacker(flatten)]
+        extra: BTreeMap<String, u8>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+pub struct Point {
+    pub x: i16,
+    pub tags: HashSet<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+pub struct Path {
+    pub points: Vec<Point>,
+    pub named: BTreeMap<String, Point>,
+}
+
+fn canonical<V: Packable>(value: &V) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let n = value.pack_canonical(&mut bytes);
+    assert_eq!(n, bytes.len());
+    bytes
+}
+
+#[test]
+fn canonical_integers() {
+    let bytes = canonical(&200u8);
+    assert!(is_canonical(&bytes));
+    assert_eq!(bytes, canonical(&200i16));
+    assert_eq!(bytes, canonical(&200i64));
+    assert_eq!(bytes, canonical(&Some(200i128)));
+    assert_eq!(bytes, [0xcc, 200]);
+
+    assert_eq!(canonical(&-1i64), [0xff]);
+    assert_eq!(canonical(&-200i32), [0xd1, 0xff, 0x38]);
+
+    // the default encoding of positive signed integers isn't canonical
+    assert!(!is_canonical(&200i16.pack_to_vec()));
+    assert!(!is_canonical(&[0xcd, 0x00, 0x01]));
+}
+
+#[test]
+fn canonical_floats() {
+    let nan = f64::from_bits(0x7ff8_0000_dead_beef);
+    assert_eq!(canonical(&nan), canonical(&f64::NAN));
+    assert_eq!(canonical(&-f32::NAN), [0xca, 0x7f, 0xc0, 0x00, 0x00]);
+    assert!(!is_canonical(&(-f32::NAN).pack_to_vec()));
+
+    assert_eq!(canonical(&1.5f32), 1.5f32.pack_to_vec());
+    assert_eq!(canonical(&1.5f64), 1.5f64.pack_to_vec());
+}
+
+#[test]
+fn canonical_maps() {
+    let mut map = HashMap::new();
+    map.insert(vec![1u8], 0u8);
+    map.insert(vec![], 1);
+    map.insert(vec![0], 2);
+    let bytes = canonical(&map);
+    assert!(is_canonical(&bytes));
+    assert_eq!(
+        bytes,
+        [0x83, 0xc4, 0x00, 0x01, 0xc4, 0x01, 0x00, 0x02, 0xc4, 0x01, 0x01, 0x00]
+    );
+
+    let map: BTreeMap<_, _> = map.into_iter().collect();
+    assert_eq!(canonical(&map), bytes);
+
+    let mut bytes = Vec::new();
+    msgpacker::pack_map_len(&mut bytes, 2);
+    2u8.pack(&mut bytes);
+    true.pack(&mut bytes);
+    1u8.pack(&mut bytes);
+    false.pack(&mut bytes);
+    assert!(!is_canonical(&bytes));
+
+    let mut sorted = Vec::new();
+    assert_eq!(canonical::canonicalize(&bytes, &mut sorted), Ok(5));
+    assert_eq!(sorted, [0x82, 0x01, 0xc2, 0x02, 0xc3]);
+}
+
+#[test]
+fn canonical_duplicate_keys() {
+    // 1 as u8 and as i16 are the same key once canonical
+    let mut bytes = Vec::new();
+    msgpacker::pack_map_len(&mut bytes, 2);
+    1u8.pack(&mut bytes);
+    ().pack(&mut bytes);
+    bytes.extend([0xd1, 0x00, 0x01]);
+    ().pack(&mut bytes);
+
+    let mut out = Vec::new();
+    assert_eq!(
+        canonical::canonicalize(&bytes, &mut out),
+        Err(Error::DuplicateKey)
+    );
+    assert!(!is_canonical(&bytes));
+}
+
+#[test]
+fn canonical_malformed() {
+    let mut out = Vec::new();
+    assert_eq!(
+        canonical::canonicalize(&[0x92, 0x01], &mut out),
+        Err(Error::BufferTooShort)
+    );
+    assert_eq!(
+        canonical::canonicalize(&[0xc1], &mut out),
+        Err(Error::UnexpectedFormatTag)
+    );
+    assert!(!is_canonical(&[0x81, 0x01]));
+}
+
+#[test]
+fn canonical_deep_nesting() {
+    let depth = 1_000_000;
+    let mut bytes = [0x81, 0x00].repeat(depth);
+    bytes.push(0x80);
+    assert!(is_canonical(&bytes));
+}
+
+#[test]
+fn canonical_derive() {
+    let a = Document {
+        id: 7,
+        tags: ["b", "a", "c"].into_iter().map(String::from).collect(),
+        scores: [("x".into(), 300), ("y".into(), -3), ("w".into(), 0)].into(),
+        ratio: f64::NAN,
+    };
+    let mut b = a.clone();
+    b.tags = ["c", "b", "a"].into_iter().map(String::from).collect();
+    b.scores = [("w".into(), 0), ("y".into(), -3), ("x".into(), 300)].into();
+
+    let bytes = canonical(&a);
+    assert_eq!(bytes, canonical(&b));
+    assert!(is_canonical(&bytes));
+
+    let tags: BTreeSet<String> = a.tags.iter().cloned().collect();
+    let scores: BTreeMap<String, u16> = [("w".into(), 0), ("x".into(), 300)].into();
+    let mut expected = 7u8.pack_to_vec();
+    tags.pack(&mut expected);
+    msgpacker::pack_map_len(&mut expected, 3);
+    for (k, v) in &scores {
+        k.pack(&mut expected);
+        v.pack(&mut expected);
+    }
+    "y".pack(&mut expected);
+    (-3i8).pack(&mut expected);
+    f64::NAN.pack(&mut expected);
+    assert_eq!(bytes, expected);
+}
+
+#[test]
+fn canonical_tagged() {
+    let a = Event::Created {
+        labels: [("b".into(), 1), ("a".into(), 2)].into(),
+    };
+    let bytes = canonical(&a);
+    assert!(is_canonical(&bytes));
+    assert_eq!(Event::unpack(&bytes).unwrap().1, a);
+
+    let mut expected = Vec::new();
+    msgpacker::pack_map_len(&mut expected, 2);
+    "kind".pack(&mut expected);
+    "Created".pack(&mut expected);
+    "labels".pack(&mut expected);
+    msgpacker::pack_map_len(&mut expected, 2);
+    "a".pack(&mut expected);
+    2u8.pack(&mut expected);
+    "b".pack(&mut expected);
+    1u8.pack(&mut expected);
+    assert_eq!(bytes, expected);
+
+    let a = Event::Deleted {
+        ids: (0..100).collect(),
+    };
+    let b = Event::Deleted {
+        ids: (0..100).rev().collect(),
+    };
+    assert_eq!(canonical(&a), canonical(&b));
+
+    let a = Event::Renamed {
+        name: "n".into(),
+        extra: [("z".into(), 1), ("a".into(), 2)].into(),
+    };
+    let bytes = canonical(&a);
+    assert!(is_canonical(&bytes));
+    assert_eq!(Event::unpack(&bytes).unwrap().1, a);
+
+    let mut expected = Vec::new();
+    msgpacker::pack_map_len(&mut expected, 4);
+    "a".pack(&mut expected);
+    2u8.pack(&mut expected);
+    "z".pack(&mut expected);
+    1u8.pack(&mut expected);
+    "kind".pack(&mut expected);
+    "Renamed".pack(&mut expected);
+    "name".pack(&mut expected);
+    "n".pack(&mut expected);
+    assert_eq!(bytes, expected);
+}
+
+#[test]
+fn canonical_nested_structs() {
+    let point = |tags: [u8; 3]| Point {
+        x: 200,
+        tags: tags.into(),
+    };
+    let a = Path {
+        points: vec![point([1, 2, 3])],
+        named: [
+            ("b".into(), point([3, 1, 2])),
+            ("a".into(), point([2, 3, 1])),
+        ]
+        .into(),
+    };
+    let mut b = a.clone();
+    b.points = vec![point([3, 2, 1])];
+
+    let bytes = canonical(&a);
+    assert_eq!(bytes, canonical(&b));
+    assert_eq!(Path::unpack(&bytes).unwrap().1, a);
+
+    let mut expected = Vec::new();
+    msgpacker::pack_array_len(&mut expected, 1);
+    let mut point = 200u8.pack_to_vec();
+    msgpacker::pack_array(&mut point, [1u8, 2, 3]);
+    expected.extend(&point);
+    msgpacker::pack_map_len(&mut expected, 2);
+    for key in ["a", "b"] {
+        key.pack(&mut expected);
+        expected.extend(&point);
+    }
+    assert_eq!(bytes, expected);
+}
+
+#[test]
+fn canonical_no_canonical_form() {
+    // the flattened map holds the key of another field
+    let a = Event::Renamed {
+        name: "n".into(),
+        extra: [("name".into(), 1)].into(),
+    };
+    let mut bytes = Vec::new();
+    assert_eq!(a.try_pack_canonical(&mut bytes), Err(Error::DuplicateKey));
+    assert!(bytes.is_empty());
+    #[cfg(not(feature = "strict"))]
+    assert_eq!(canonical(&a), a.pack_to_vec());
+
+    assert_eq!(
+        canonical::pack_map(&mut bytes, [(1u8, ()), (1, ())]),
+        Err(Error::DuplicateKey)
+    );
+    let entries = vec![(vec![0x01], vec![0xc0]), (vec![0x01], vec![0xc3])];
+    assert_eq!(
+        canonical::pack_entries(&mut bytes, entries),
+        Err(Error::DuplicateKey)
+    );
+    assert!(bytes.is_empty());
+
+    let map: HashMap<u8, ()> = [(1, ())].into();
+    assert_eq!(map.try_pack_canonical(&mut bytes), Ok(3));
+    assert!(is_canonical(&bytes));
+}
+
+proptest! {
+    #[test]
+    fn canonical_sets(values: Vec<(i8, Option<String>)>) {
+        let a: HashSet<_> = values.iter().cloned().collect();
+        let b: HashSet<_> = values.iter().rev().cloned().collect();
+        let bytes = canonical(&a);
+        prop_assert_eq!(&bytes, &canonical(&b));
+
+        let (_, x) = HashSet::<(i8, Option<String>)>::unpack(&bytes).unwrap();
+        prop_assert_eq!(x, a);
+    }
+
+    #[test]
+    fn canonical_maps_match_sorted(map: HashMap<u32, (i8, String)>) {
+        let bytes = canonical(&map);
+        let sorted: BTreeMap<_, _> = map.iter().collect();
+        prop_assert_eq!(&bytes, &canonical(&sorted));
+
+        let (_, x) = HashMap::<u32, (i8, String)>::unpack(&bytes).unwrap();
+        prop_assert_eq!(x, map);
+    }
+
+    #[test]
+    fn canonicalize_matches_pack_canonical(map: HashMap<i64, Option<String>>) {
+        let mut bytes = Vec::new();
+        let n = canonical::canonicalize(&map.pack_to_vec(), &mut bytes).unwrap();
+        prop_assert_eq!(n, bytes.len());
+        prop_assert!(is_canonical(&bytes));
+        prop_assert_eq!(bytes, canonical(&map));
+    }
+}
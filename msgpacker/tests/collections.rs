@@ -61,4 +61,21 @@ proptest! {
         assert_eq!(map, x);
         assert_eq!(map, y);
     }
+
+    #[test]
+    fn skip(
+        value in prop::collection::vec(
+            prop::collection::btree_map("[a-z]{0,40}", any::<Option<f64>>(), 0..8),
+            0..32,
+        ),
+        trailing in any::<u64>(),
+    ) {
+        let mut bytes = Vec::new();
+        let n = msgpacker::pack_array(&mut bytes, &value);
+        trailing.pack(&mut bytes);
+        assert_eq!(msgpacker::skip_value(&bytes).unwrap(), n);
+        let x = msgpacker::take_value_iter(bytes.iter().copied()).unwrap();
+        assert_eq!(x, &bytes[..n]);
+        assert_eq!(msgpacker::skip_value(&bytes[..n - 1]).unwrap_err(), Error::BufferTooShort);
+    }
 }
@@ -1,5 +1,6 @@
 use msgpacker::prelude::*;
 use proptest::prelude::*;
+use std::collections::BTreeMap;
 
 mod utils;
 
@@ -18,7 +19,39 @@ fn bool() {
 pub enum Foo {
     Bar,
     Baz(u32, String),
-    Qux { a: Vec<u8>, b: u64 },
+    Qux {
+        a: Vec<u8>,
+        b: u64,
+    },
+    Quux {
+        /// Keyed by name.
+        #[msgpacker(map)]
+        c: BTreeMap<String, u8>,
+        d: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker, proptest_derive::Arbitrary)]
+pub struct Pair(#[msgpacker(map)] BTreeMap<u8, String>, Vec<String>);
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker)]
+pub struct Unit;
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker)]
+pub enum Never {}
+
+#[test]
+fn unit() {
+    utils::case(Unit);
+}
+
+#[test]
+fn empty_enum() {
+    assert_eq!(Never::unpack(&[0]).unwrap_err(), Error::InvalidEnumVariant);
+    assert_eq!(
+        Never::unpack_iter([0]).unwrap_err(),
+        Error::InvalidEnumVariant
+    );
 }
 
 proptest! {
@@ -36,4 +69,9 @@ proptest! {
     fn enum_foo(a: Foo) {
         utils::case(a);
     }
+
+    #[test]
+    fn tuple_struct(a: Pair) {
+        utils::case(a);
+    }
 }
@@ -0,0 +1,277 @@
+use std::time::Duration;
+
+use arrayvec::{ArrayString, ArrayVec};
+use heapless::index_map::FnvIndexMap;
+use msgpacker::{prelude::*, unpack_extension};
+use proptest::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+pub struct Reading {
+    pub id: u32,
+    pub name: heapless::String<16>,
+    pub blob: heapless::Vec<u8, 8>,
+    pub samples: heapless::Vec<f32, 4>,
+    pub label: ArrayString<8>,
+    pub raw: ArrayVec<u8, 4>,
+    pub deltas: ArrayVec<i16, 4>,
+    #[msgpacker(array)]
+    pub flags: heapless::Vec<u8, 4>,
+    pub tags: FnvIndexMap<u8, heapless::String<4>, 4>,
+}
+
+/// The same message, as the servers decode it.
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+pub struct Server {
+    pub id: u32,
+    pub name: String,
+    pub blob: Vec<u8>,
+    pub samples: Vec<f32>,
+    pub label: String,
+    pub raw: Vec<u8>,
+    pub deltas: Vec<i16>,
+    #[msgpacker(array)]
+    pub flags: Vec<u8>,
+    #[msgpacker(map)]
+    pub tags: Vec<(u8, String)>,
+}
+
+fn reading() -> Reading {
+    let mut tags = FnvIndexMap::new();
+    tags.insert(2, "b".try_into().unwrap()).unwrap();
+    tags.insert(1, "a".try_into().unwrap()).unwrap();
+    Reading {
+        id: 7,
+        name: "probe".try_into().unwrap(),
+        blob: heapless::Vec::from_slice(&[1, 2, 3]).unwrap(),
+        samples: heapless::Vec::from_slice(&[0.5, 1.5]).unwrap(),
+        label: ArrayString::from("ok").unwrap(),
+        raw: ArrayVec::from([9, 8, 7, 6]),
+        deltas: [-1, 300].into_iter().collect(),
+        flags: heapless::Vec::from_slice(&[1, 0]).unwrap(),
+        tags,
+    }
+}
+
+fn assert_unpacks<T>(bytes: &[u8], expected: Result<(usize, T), Error>)
+where
+    T: Unpackable<Error = Error> + std::fmt::Debug + PartialEq,
+{
+    assert_eq!(T::unpack(bytes), expected);
+    assert_eq!(T::unpack_iter(bytes.iter().copied()), expected);
+}
+
+#[test]
+fn embedded_same_bytes_as_alloc() {
+    let reading = reading();
+    let bytes = reading.pack_to_vec();
+    assert_eq!(bytes, reading.try_pack_to_vec().unwrap());
+
+    let server = Server {
+        id: 7,
+        name: "probe".into(),
+        blob: vec![1, 2, 3],
+        samples: vec![0.5, 1.5],
+        label: "ok".into(),
+        raw: vec![9, 8, 7, 6],
+        deltas: vec![-1, 300],
+        flags: vec![1, 0],
+        tags: vec![(2, "b".into()), (1, "a".into())],
+    };
+    assert_eq!(bytes, server.pack_to_vec());
+    assert_eq!(Server::unpack(&bytes), Ok((bytes.len(), server)));
+
+    assert_unpacks(&bytes, Ok((bytes.len(), reading.clone())));
+    assert_eq!(Reading::unpack_strict(&bytes), Ok((bytes.len(), reading)));
+}
+
+#[test]
+fn embedded_capacity_exceeded() {
+    let long = "a".repeat(17).pack_to_vec();
+    assert_unpacks::<heapless::String<16>>(&long, Err(Error::CapacityExceeded));
+    assert_unpacks::<ArrayString<16>>(&long, Err(Error::CapacityExceeded));
+    let short = "a".repeat(16).pack_to_vec();
+    assert_unpacks(
+        &short,
+        Ok((
+            17,
+            heapless::String::<16>::try_from("a".repeat(16).as_str()).unwrap(),
+        )),
+    );
+
+    let blob = vec![1u8; 9].pack_to_vec();
+    assert_unpacks::<heapless::Vec<u8, 8>>(&blob, Err(Error::CapacityExceeded));
+    assert_unpacks::<ArrayVec<u8, 8>>(&blob, Err(Error::CapacityExceeded));
+
+    let mut array = Vec::new();
+    msgpacker::pack_array(&mut array, [1u16, 2, 3]);
+    type Small = heapless::Vec<u16, 2>;
+    assert_eq!(
+        msgpacker::unpack_array_fixed::<u16, Small>(&array),
+        Err(Error::CapacityExceeded)
+    );
+    assert_eq!(
+        msgpacker::unpack_array_fixed_iter::<_, u16, ArrayVec<u16, 2>>(array.iter().copied()),
+        Err(Error::CapacityExceeded)
+    );
+    let (n, v) = msgpacker::unpack_array_fixed::<u16, ArrayVec<u16, 3>>(&array).unwrap();
+    assert_eq!((n, v.as_slice()), (array.len(), &[1, 2, 3][..]));
+
+    let mut map = Vec::new();
+    msgpacker::pack_map(&mut map, [(1u8, 1u8), (2, 2), (3, 3)]);
+    assert_unpacks::<FnvIndexMap<u8, u8, 2>>(&map, Err(Error::CapacityExceeded));
+
+    // the derive reports the overflow of a field
+    let mut reading = Server {
+        id: 1,
+        name: String::new(),
+        blob: vec![],
+        samples: vec![0.0; 5],
+        label: String::new(),
+        raw: vec![],
+        deltas: vec![],
+        flags: vec![],
+        tags: vec![],
+    };
+    let bytes = reading.pack_to_vec();
+    assert_eq!(Reading::unpack(&bytes), Err(Error::CapacityExceeded));
+    reading.samples.clear();
+    reading.label = "a".repeat(9);
+    let bytes = reading.pack_to_vec();
+    assert_eq!(
+        Reading::unpack_iter(bytes.iter().copied()),
+        Err(Error::CapacityExceeded)
+    );
+}
+
+#[test]
+fn embedded_map_keeps_order() {
+    let mut map = Vec::new();
+    msgpacker::pack_map(&mut map, [(3u8, 30u8), (1, 10), (2, 20)]);
+    let (_, decoded) = FnvIndexMap::<u8, u8, 4>::unpack(&map).unwrap();
+    assert_eq!(decoded.keys().copied().collect::<Vec<_>>(), [3, 1, 2]);
+    assert_eq!(decoded.pack_to_vec(), map);
+
+    // repeated keys replace the value without taking room
+    let mut map = Vec::new();
+    msgpacker::pack_map(&mut map, [(1u8, 1u8), (1, 2), (2, 3)]);
+    let (_, decoded) = FnvIndexMap::<u8, u8, 2>::unpack(&map).unwrap();
+    assert_eq!(decoded.get(&1), Some(&2));
+    assert_eq!(
+        FnvIndexMap::<u8, u8, 2>::unpack_strict(&map),
+        Err(Error::DuplicateKey)
+    );
+
+    // 200 as an uint 8 and as an int 16
+    let map = [0x82, 0xcc, 0xc8, 0xc3, 0xd1, 0x00, 0xc8, 0xc2];
+    assert_eq!(
+        FnvIndexMap::<u16, bool, 4>::unpack(&map).unwrap().1.len(),
+        1
+    );
+    assert_eq!(
+        FnvIndexMap::<u16, bool, 4>::unpack_strict(&map),
+        Err(Error::DuplicateKey)
+    );
+}
+
+#[test]
+fn embedded_extension_ref() {
+    let exts = [
+        ExtensionRef::Ext(3, &[1]),
+        ExtensionRef::Ext(3, &[1, 2, 3, 4]),
+        ExtensionRef::Ext(-1, &[1, 2, 3]),
+        ExtensionRef::Ext(-5, &[7; 300]),
+        ExtensionRef::Timestamp(Duration::from_secs(5)),
+        ExtensionRef::Timestamp(Duration::new(5, 7)),
+        ExtensionRef::Timestamp(Duration::new(1 << 40, 7)),
+    ];
+    for ext in exts {
+        let owned = Extension::from(ext);
+        let bytes = ext.pack_to_vec();
+        assert_eq!(bytes, owned.pack_to_vec());
+        assert_eq!(owned.as_borrowed(), ext);
+        assert_eq!(unpack_extension(&bytes), Ok((bytes.len(), ext)));
+        assert_eq!(Extension::unpack(&bytes), Ok((bytes.len(), owned)));
+    }
+
+    // the data is borrowed from the buffer
+    let bytes = ExtensionRef::Ext(1, &[5; 20]).pack_to_vec();
+    let (_, ext) = unpack_extension(&bytes).unwrap();
+    let ExtensionRef::Ext(_, data) = ext else {
+        panic!("not a custom extension: {ext:?}");
+    };
+    assert_eq!(data.as_ptr(), bytes[3..].as_ptr());
+
+    // only the fixext and ext 8 layouts are timestamps
+    let mut ext16 = vec![0xc8, 0x00, 0x0c, 0xff];
+    ext16.extend([0; 12]);
+    assert_eq!(
+        unpack_extension(&ext16),
+        Ok((16, ExtensionRef::Ext(-1, &[0; 12])))
+    );
+    assert_eq!(unpack_extension(&[0xc0]), Err(Error::InvalidExtension));
+    assert_eq!(
+        unpack_extension(&[0xd6, 0xff, 0x00]),
+        Err(Error::BufferTooShort)
+    );
+
+    // the nanoseconds carry into the seconds, failing on overflow
+    let mut overflow = vec![0xc7, 0x0c, 0xff];
+    overflow.extend([0xff; 12]);
+    assert_eq!(unpack_extension(&overflow), Err(Error::InvalidExtension));
+    assert_eq!(Extension::unpack(&overflow), Err(Error::InvalidExtension));
+    assert_eq!(
+        Extension::unpack_iter(overflow.iter().copied()),
+        Err(Error::InvalidExtension)
+    );
+    let carried = [0xd7, 0xff, 0xee, 0x6b, 0x28, 0x00, 0x00, 0x00, 0x00, 0x00];
+    assert_eq!(
+        unpack_extension(&carried),
+        Ok((10, ExtensionRef::Timestamp(Duration::from_secs(1))))
+    );
+}
+
+#[test]
+fn embedded_pack_into_slice() {
+    // a firmware packs into a stack buffer
+    let reading = reading();
+    let mut buf = [0u8; 128];
+    let mut slice = buf.as_mut_slice();
+    let n = reading.pack(&mut slice);
+    assert_eq!(&buf[..n], reading.pack_to_vec());
+    assert_eq!(Reading::unpack(&buf[..n]), Ok((n, reading)));
+}
+
+proptest! {
+    #[test]
+    fn embedded_proptest(name: String, blob: Vec<u8>, deltas: Vec<i16>) {
+        let bytes = (name.clone(), blob.clone()).pack_to_vec();
+        type Fixed = (heapless::String<32>, ArrayVec<u8, 32>);
+        let decoded = <Fixed>::unpack(&bytes);
+        let fits = name.len() <= 32 && blob.len() <= 32;
+        match decoded {
+            Ok((_, (s, b))) => {
+                prop_assert!(fits);
+                prop_assert_eq!(s.as_str(), name.as_str());
+                prop_assert_eq!(b.as_slice(), blob.as_slice());
+            }
+            Err(e) => {
+                prop_assert!(!fits);
+                prop_assert_eq!(e, Error::CapacityExceeded);
+            }
+        }
+
+        let mut array = Vec::new();
+        msgpacker::pack_array(&mut array, &deltas);
+        let decoded = msgpacker::unpack_array_fixed::<i16, heapless::Vec<i16, 16>>(&array);
+        match decoded {
+            Ok((n, v)) => {
+                prop_assert_eq!(n, array.len());
+                prop_assert_eq!(v.as_slice(), deltas.as_slice());
+            }
+            Err(e) => {
+                prop_assert!(deltas.len() > 16);
+                prop_assert_eq!(e, Error::CapacityExceeded);
+            }
+        }
+    }
+}
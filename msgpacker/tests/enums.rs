@@ -0,0 +1,309 @@
+use msgpacker::prelude::*;
+use proptest::prelude::*;
+use serde_json::json;
+
+mod utils;
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker, proptest_derive::Arbitrary)]
+#[msgpacker(external)]
+pub enum External {
+    Unit,
+    Newtype(u64),
+    Tuple(u32, String),
+    Struct { a: Option<u32>, b: Vec<String> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker, proptest_derive::Arbitrary)]
+#[msgpacker(tag = "type")]
+pub enum Internal {
+    Unit,
+    Struct { a: u64, b: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker, proptest_derive::Arbitrary)]
+#[msgpacker(tag = "t", content = "c")]
+pub enum Adjacent {
+    Unit,
+    Newtype(String),
+    Tuple(i32, bool),
+    Struct { a: Option<u16> },
+}
+
+#[derive(Debug, Clone, PartialEq, MsgPacker, proptest_derive::Arbitrary)]
+#[msgpacker(untagged)]
+pub enum Num {
+    I(i64),
+    F(f64),
+    S(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker)]
+#[msgpacker(untagged)]
+pub enum Loose {
+    Flag(bool),
+    Text(String),
+    Any(Option<u32>),
+    Pair(u8, u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, MsgPacker, proptest_derive::Arbitrary)]
+#[repr(i8)]
+pub enum Signed {
+    Neg = -1,
+    Zero,
+    Pos = 5,
+    Next,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker, proptest_derive::Arbitrary)]
+#[msgpacker(tag_type = "u16")]
+pub enum Protocol {
+    #[msgpacker(tag = 0x1001)]
+    Hello {
+        version: u8,
+    },
+    #[msgpacker(tag = 0x1002)]
+    Data(Vec<u8>),
+    Next,
+    #[msgpacker(tag = "bye")]
+    Bye(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker, proptest_derive::Arbitrary)]
+#[msgpacker(external)]
+pub enum Renamed {
+    #[msgpacker(tag = "a")]
+    A(u8),
+    B,
+}
+
+/// Packed as its fields in sequence, without a header.
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker)]
+pub struct Id(u64);
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker)]
+#[msgpacker(tag = "t", content = "c")]
+pub enum Shape {
+    Dot(Id),
+    Line { from: Vec<[i32; 1]>, to: (Id,) },
+}
+
+fn packed<T: Packable>(x: T) -> Vec<u8> {
+    let mut bytes = vec![];
+    x.pack(&mut bytes);
+    bytes
+}
+
+fn from_json<T>(value: serde_json::Value) -> T
+where
+    T: Unpackable,
+    <T as Unpackable>::Error: core::fmt::Debug,
+{
+    let bytes = msgpacker::serde::to_vec(&value);
+    let (n, x) = T::unpack(&bytes).unwrap();
+    assert_eq!(n, bytes.len());
+    x
+}
+
+#[test]
+fn external_by_name() {
+    assert_eq!(External::Unit, from_json(json!({"Unit": null})));
+    assert_eq!(External::Unit, from_json(json!("Unit")));
+    assert_eq!(External::Newtype(5), from_json(json!({"Newtype": 5})));
+    assert_eq!(
+        External::Tuple(7, "x".into()),
+        from_json(json!({"Tuple": [7, "x"]}))
+    );
+    assert_eq!(
+        External::Struct {
+            a: Some(1),
+            b: vec!["y".into()]
+        },
+        from_json(json!({"Struct": {"unknown": [1, {"z": 2}], "b": ["y"], "a": 1}}))
+    );
+
+    let mut bytes = vec![];
+    External::Newtype(5).pack(&mut bytes);
+    assert_eq!(bytes, msgpacker::serde::to_vec(&json!({"Newtype": 5})));
+}
+
+#[test]
+fn external_rejects_unknown_variant() {
+    let bytes = msgpacker::serde::to_vec(&json!({"Other": 5}));
+    assert_eq!(
+        External::unpack(&bytes).unwrap_err(),
+        Error::InvalidEnumVariant
+    );
+    let bytes = msgpacker::serde::to_vec(&json!({"Struct": {"a": 1}}));
+    assert_eq!(External::unpack(&bytes).unwrap_err(), Error::MissingField);
+}
+
+#[test]
+fn internal_tag_key() {
+    assert_eq!(Internal::Unit, from_json(json!({"type": "Unit"})));
+    assert_eq!(
+        Internal::Struct {
+            a: 3,
+            b: "x".into()
+        },
+        from_json(json!({"a": 3, "b": "x", "type": "Struct"}))
+    );
+
+    let mut bytes = vec![];
+    Internal::Unit.pack(&mut bytes);
+    assert_eq!(bytes, msgpacker::serde::to_vec(&json!({"type": "Unit"})));
+
+    let bytes = msgpacker::serde::to_vec(&json!({"a": 3}));
+    assert_eq!(Internal::unpack(&bytes).unwrap_err(), Error::MissingField);
+}
+
+#[test]
+fn adjacent_tag_and_content_keys() {
+    assert_eq!(Adjacent::Unit, from_json(json!({"t": "Unit"})));
+    assert_eq!(
+        Adjacent::Newtype("x".into()),
+        from_json(json!({"c": "x", "t": "Newtype"}))
+    );
+    assert_eq!(
+        Adjacent::Tuple(-1, true),
+        from_json(json!({"t": "Tuple", "c": [-1, true]}))
+    );
+    assert_eq!(
+        Adjacent::Struct { a: None },
+        from_json(json!({"t": "Struct", "c": {"a": null}}))
+    );
+}
+
+#[test]
+fn untagged_non_uniform_array() {
+    let bytes: &[u8] = &[147, 0, 203, 66, 120, 167, 66, 234, 244, 144, 0, 161, 120];
+    let (n, values): (usize, Vec<Num>) = msgpacker::unpack_array(bytes).unwrap();
+    assert_eq!(n, bytes.len());
+    assert_eq!(
+        values,
+        vec![Num::I(0), Num::F(1694166331209.0), Num::S("x".into())]
+    );
+}
+
+#[test]
+fn untagged_dispatch() {
+    let cases = [
+        (Loose::Flag(true), vec![0xc3]),
+        (Loose::Text("x".into()), vec![0xa1, b'x']),
+        (Loose::Any(Some(5)), vec![0x05]),
+        (Loose::Any(None), vec![0xc0]),
+        (Loose::Pair(1, 2), vec![0x92, 0x01, 0x02]),
+    ];
+    for (value, bytes) in cases {
+        assert_eq!(value.pack_to_vec(), bytes);
+        assert_eq!(Loose::unpack(&bytes).unwrap(), (bytes.len(), value.clone()));
+        assert_eq!(Loose::unpack_iter(bytes).unwrap().1, value);
+    }
+
+    // the error of the variant expecting the marker is kept, or else of the catch-all one
+    assert_eq!(
+        Loose::unpack(&[0xca, 0, 0, 0, 0]),
+        Err(Error::UnexpectedFormatTag)
+    );
+    assert_eq!(Loose::unpack(&[0x92, 0x01]), Err(Error::BufferTooShort));
+    assert_eq!(Loose::unpack(&[0xa1, 0xff]), Err(Error::InvalidUtf8));
+    // no variant starts with a bool
+    assert_eq!(Num::unpack(&[0xc3]), Err(Error::InvalidEnumVariant));
+}
+
+#[test]
+fn signed_discriminants() {
+    assert_eq!(packed(Signed::Neg), [0xff]);
+    assert_eq!(packed(Signed::Zero), [0x00]);
+    assert_eq!(packed(Signed::Pos), [0x05]);
+    assert_eq!(packed(Signed::Next), [0x06]);
+    assert_eq!(Signed::unpack(&[0xff]).unwrap(), (1, Signed::Neg));
+    assert_eq!(
+        Signed::unpack(&[0x01]).unwrap_err(),
+        Error::InvalidEnumVariant
+    );
+}
+
+#[test]
+fn explicit_tags() {
+    assert_eq!(
+        packed(Protocol::Hello { version: 1 }),
+        [0xcd, 0x10, 0x01, 0x01]
+    );
+    assert_eq!(packed(Protocol::Next), [0xcd, 0x10, 0x03]);
+    assert_eq!(
+        packed(Protocol::Bye("x".into())),
+        [0xa3, b'b', b'y', b'e', 0xa1, b'x']
+    );
+    assert_eq!(
+        Protocol::unpack(&[0xa3, b'h', b'e', b'y']).unwrap_err(),
+        Error::InvalidEnumVariant
+    );
+    assert_eq!(Renamed::A(1), from_json(json!({"a": 1})));
+    assert_eq!(Renamed::B, from_json(json!("B")));
+}
+
+#[test]
+fn self_delimiting() {
+    const { assert!(Id::SELF_DELIMITING) };
+    const { assert!(!Point::SELF_DELIMITING) };
+    const { assert!(Option::<Id>::SELF_DELIMITING) };
+    const { assert!(!Option::<Point>::SELF_DELIMITING) };
+    const { assert!(!<(u8, u8)>::SELF_DELIMITING) };
+    const { assert!(!<[u8; 2]>::SELF_DELIMITING) };
+    const { assert!(!std::collections::BTreeMap::<u8, Point>::SELF_DELIMITING) };
+    const { assert!(!Protocol::SELF_DELIMITING) };
+    const { assert!(External::SELF_DELIMITING) };
+    const { assert!(Internal::SELF_DELIMITING) };
+    const { assert!(Num::SELF_DELIMITING) };
+
+    // a struct of one field, and an array or a tuple of one element, pack the single value
+    utils::case(Shape::Dot(Id(7)));
+    utils::case(Shape::Line {
+        from: vec![[1], [2]],
+        to: (Id(3),),
+    });
+}
+
+proptest! {
+    #[test]
+    fn external(x: External) {
+        utils::case(x);
+    }
+
+    #[test]
+    fn internal(x: Internal) {
+        utils::case(x);
+    }
+
+    #[test]
+    fn adjacent(x: Adjacent) {
+        utils::case(x);
+    }
+
+    #[test]
+    fn untagged(x: Num) {
+        utils::case(x);
+    }
+
+    #[test]
+    fn signed(x: Signed) {
+        utils::case(x);
+    }
+
+    #[test]
+    fn protocol(x: Protocol) {
+        utils::case(x);
+    }
+
+    #[test]
+    fn renamed(x: Renamed) {
+        utils::case(x);
+    }
+}
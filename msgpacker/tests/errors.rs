@@ -0,0 +1,188 @@
+use msgpacker::prelude::*;
+
+mod utils;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DomainError {
+    Msgpack(Error),
+    NotPositive,
+}
+
+impl From<Error> for DomainError {
+    fn from(e: Error) -> Self {
+        DomainError::Msgpack(e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppError {
+    Msgpack(Error),
+    Domain(DomainError),
+}
+
+impl From<Error> for AppError {
+    fn from(e: Error) -> Self {
+        AppError::Msgpack(e)
+    }
+}
+
+impl From<DomainError> for AppError {
+    fn from(e: DomainError) -> Self {
+        AppError::Domain(e)
+    }
+}
+
+/// A number validated on decode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Positive(i64);
+
+impl Packable for Positive {
+    fn pack<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer,
+    {
+        self.0.pack(buf)
+    }
+}
+
+impl Unpackable for Positive {
+    type Error = DomainError;
+
+    fn unpack(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+        let (n, x) = i64::unpack(buf)?;
+        (x > 0)
+            .then_some((n, Positive(x)))
+            .ok_or(DomainError::NotPositive)
+    }
+
+    fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        let (n, x) = i64::unpack_iter(bytes)?;
+        (x > 0)
+            .then_some((n, Positive(x)))
+            .ok_or(DomainError::NotPositive)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker)]
+pub struct Inferred {
+    pub id: u32,
+    pub amount: Positive,
+    pub history: Vec<Positive>,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker)]
+#[msgpacker(error = "AppError")]
+pub struct Explicit {
+    pub note: Option<String>,
+    pub amount: Positive,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker)]
+#[msgpacker(external)]
+pub enum Tree {
+    Node(Vec<Tree>),
+    Leaf(Positive),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker)]
+#[msgpacker(tag = "type")]
+pub enum Event {
+    Deposit { amount: Positive },
+    Closed,
+}
+
+fn packed<T: Packable>(x: T) -> Vec<u8> {
+    let mut bytes = vec![];
+    x.pack(&mut bytes);
+    bytes
+}
+
+#[test]
+fn inferred_from_fields() {
+    let x = Inferred {
+        id: 1,
+        amount: Positive(5),
+        history: vec![Positive(1), Positive(2)],
+        name: "x".into(),
+    };
+    utils::case(x.clone());
+
+    let mut y = x.clone();
+    y.history[1].0 = -2;
+    let bytes = packed(y);
+    let e: DomainError = Inferred::unpack(&bytes).unwrap_err();
+    assert_eq!(e, DomainError::NotPositive);
+    assert_eq!(
+        Inferred::unpack_iter(bytes).unwrap_err(),
+        DomainError::NotPositive
+    );
+
+    let bytes = packed(x);
+    assert_eq!(
+        Inferred::unpack(&bytes[..bytes.len() - 1]).unwrap_err(),
+        DomainError::Msgpack(Error::BufferTooShort)
+    );
+}
+
+#[test]
+fn explicit_error() {
+    let x = Explicit {
+        note: None,
+        amount: Positive(3),
+    };
+    utils::case(x);
+
+    let bytes = packed(Explicit {
+        note: Some("x".into()),
+        amount: Positive(0),
+    });
+    let e: AppError = Explicit::unpack(&bytes).unwrap_err();
+    assert_eq!(e, AppError::Domain(DomainError::NotPositive));
+    assert_eq!(
+        Explicit::unpack(&[0xc3]).unwrap_err(),
+        AppError::Msgpack(Error::UnexpectedFormatTag)
+    );
+}
+
+#[test]
+fn enums() {
+    utils::case(Tree::Node(vec![
+        Tree::Leaf(Positive(1)),
+        Tree::Node(vec![]),
+    ]));
+    assert_eq!(
+        Tree::unpack(&packed(Tree::Leaf(Positive(-1)))).unwrap_err(),
+        DomainError::NotPositive
+    );
+    assert_eq!(
+        Tree::unpack(&[0xa1, b'x']).unwrap_err(),
+        DomainError::Msgpack(Error::InvalidEnumVariant)
+    );
+
+    #[derive(Debug, PartialEq, MsgPacker)]
+    enum Op {
+        Push(Positive),
+        Pop,
+    }
+    utils::case(Op::Push(Positive(9)));
+    assert_eq!(
+        Op::unpack(&[0x07]).unwrap_err(),
+        DomainError::Msgpack(Error::InvalidEnumVariant)
+    );
+
+    utils::case(Event::Deposit {
+        amount: Positive(2),
+    });
+    utils::case(Event::Closed);
+    assert_eq!(
+        Event::unpack(&packed(Event::Deposit {
+            amount: Positive(-2)
+        }))
+        .unwrap_err(),
+        DomainError::NotPositive
+    );
+}
@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+
+use msgpacker::prelude::*;
+use msgpacker::MapEntries;
+use proptest::prelude::*;
+use serde_json::json;
+
+mod utils;
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker, proptest_derive::Arbitrary)]
+pub struct Header {
+    pub id: u32,
+    pub sender: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker, proptest_derive::Arbitrary)]
+pub struct Envelope {
+    #[msgpacker(flatten)]
+    pub header: Header,
+    pub trace: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker, proptest_derive::Arbitrary)]
+pub struct Positional {
+    #[msgpacker(flatten)]
+    pub header: Header,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker, proptest_derive::Arbitrary)]
+#[msgpacker(tag = "type")]
+pub enum Message {
+    Ping {
+        #[msgpacker(flatten)]
+        header: Header,
+    },
+    Text {
+        #[msgpacker(flatten)]
+        envelope: Envelope,
+        text: String,
+        #[msgpacker(flatten)]
+        #[proptest(strategy = "extra()")]
+        extra: BTreeMap<String, String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker, proptest_derive::Arbitrary)]
+#[msgpacker(external)]
+pub enum Request {
+    Get {
+        #[msgpacker(flatten)]
+        header: Header,
+        path: String,
+    },
+    Cancel(u32),
+}
+
+/// Keys that none of the fields of [Message] decode, so they are collected by the catch-all.
+fn extra() -> impl Strategy<Value = BTreeMap<String, String>> {
+    proptest::collection::btree_map("x-[a-z]{0,4}", any::<String>(), 0..4)
+}
+
+fn packed<T: Packable>(x: &T) -> Vec<u8> {
+    let mut bytes = vec![];
+    x.pack(&mut bytes);
+    bytes
+}
+
+fn from_json<T>(value: serde_json::Value) -> T
+where
+    T: Unpackable,
+    <T as Unpackable>::Error: core::fmt::Debug,
+{
+    let bytes = msgpacker::serde::to_vec(&value);
+    let (n, x) = T::unpack(&bytes).unwrap();
+    assert_eq!(n, bytes.len());
+    x
+}
+
+fn to_json<T: Packable>(x: &T) -> serde_json::Value {
+    msgpacker::serde::from_slice(&packed(x)).unwrap()
+}
+
+#[test]
+fn positional_is_inline() {
+    let header = Header {
+        id: 7,
+        sender: "a".into(),
+    };
+    let body = vec![1, 2];
+    let mut bytes = packed(&header);
+    bytes.extend(packed(&body));
+    assert_eq!(packed(&Positional { header, body }), bytes);
+}
+
+#[test]
+fn static_keys() {
+    assert_eq!(<Header as MapEntries>::KEYS, ["id", "sender"]);
+    assert_eq!(<Envelope as MapEntries>::KEYS, ["trace", "id", "sender"]);
+    assert!(<BTreeMap<String, String> as MapEntries>::KEYS.is_empty());
+}
+
+#[test]
+fn keys_are_merged() {
+    let message = Message::Text {
+        envelope: Envelope {
+            header: Header {
+                id: 1,
+                sender: "a".into(),
+            },
+            trace: Some(9),
+        },
+        text: "hi".into(),
+        extra: [("x-lang".to_string(), "en".to_string())].into(),
+    };
+    let value = json!({
+        "type": "Text",
+        "id": 1,
+        "sender": "a",
+        "trace": 9,
+        "text": "hi",
+        "x-lang": "en",
+    });
+    assert_eq!(to_json(&message), value);
+    assert_eq!(from_json::<Message>(value), message);
+
+    let request = Request::Get {
+        header: Header {
+            id: 2,
+            sender: "b".into(),
+        },
+        path: "/".into(),
+    };
+    let value = json!({"Get": {"id": 2, "sender": "b", "path": "/"}});
+    assert_eq!(to_json(&request), value);
+    assert_eq!(from_json::<Request>(value), request);
+}
+
+#[test]
+fn catch_all_skips_known_keys() {
+    let message: Message = from_json(json!({
+        "type": "Text",
+        "x-a": "1",
+        "sender": "a",
+        "id": 1,
+        "trace": null,
+        "text": "hi",
+        "x-b": "2",
+    }));
+    let Message::Text {
+        envelope, extra, ..
+    } = message
+    else {
+        panic!("unexpected variant");
+    };
+    assert_eq!(envelope.trace, None);
+    assert_eq!(extra.into_keys().collect::<Vec<_>>(), ["x-a", "x-b"]);
+}
+
+#[test]
+fn flattened_missing_field() {
+    let bytes = msgpacker::serde::to_vec(&json!({"type": "Ping", "id": 1}));
+    assert_eq!(Message::unpack(&bytes).unwrap_err(), Error::MissingField);
+}
+
+proptest! {
+    #[test]
+    fn positional(x: Positional) {
+        utils::case(x);
+    }
+
+    #[test]
+    fn internal(x: Message) {
+        utils::case(x);
+    }
+
+    #[test]
+    fn external(x: Request) {
+        utils::case(x);
+    }
+}
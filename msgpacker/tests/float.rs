@@ -1,7 +1,150 @@
+use msgpacker::{prelude::*, FloatOptions};
 use proptest::prelude::*;
 
 mod utils;
 
+const LENIENT: FloatOptions = FloatOptions::new().with_integers(true).with_narrowing(true);
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+pub struct Reading {
+    pub sensor: String,
+    #[msgpacker(floats = "LENIENT")]
+    pub value: f64,
+    #[msgpacker(floats = "LENIENT")]
+    pub ratio: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+#[msgpacker(tag = "kind")]
+pub enum Event {
+    Sample {
+        #[msgpacker(floats = "LENIENT")]
+        value: f64,
+    },
+}
+
+fn compact(v: f64) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let n = msgpacker::pack_f64_compact(&mut bytes, v);
+    assert_eq!(n, bytes.len());
+    bytes
+}
+
+#[test]
+fn integers() {
+    let options = FloatOptions::new().with_integers(true);
+    for bytes in [1u8.pack_to_vec(), 1i64.pack_to_vec(), 1u64.pack_to_vec()] {
+        assert_eq!(f64::unpack(&bytes), Err(Error::UnexpectedFormatTag));
+        assert_eq!(options.unpack_f64(&bytes).unwrap(), (bytes.len(), 1.0));
+        assert_eq!(
+            options.unpack_f32_iter(bytes.clone()).unwrap(),
+            (bytes.len(), 1.0)
+        );
+    }
+    assert_eq!(options.unpack_f64(&(-3i16).pack_to_vec()).unwrap().1, -3.0);
+
+    let bytes = u64::MAX.pack_to_vec();
+    assert_eq!(options.unpack_f64(&bytes), Err(Error::InexactFloat));
+    let lossy = options.with_lossy(true);
+    assert_eq!(lossy.unpack_f64(&bytes).unwrap().1, u64::MAX as f64);
+    assert_eq!(
+        options.unpack_f32(&16_777_217u32.pack_to_vec()),
+        Err(Error::InexactFloat)
+    );
+
+    assert_eq!(options.unpack_f64(&[0xc3]), Err(Error::UnexpectedFormatTag));
+}
+
+#[test]
+fn narrowing() {
+    let bytes = 0.5f64.pack_to_vec();
+    assert_eq!(f32::unpack(&bytes), Err(Error::UnexpectedFormatTag));
+    let options = FloatOptions::new().with_narrowing(true);
+    assert_eq!(options.unpack_f32(&bytes).unwrap(), (9, 0.5));
+    assert!(options
+        .unpack_f32(&f64::NAN.pack_to_vec())
+        .unwrap()
+        .1
+        .is_nan());
+
+    let bytes = 0.1f64.pack_to_vec();
+    assert_eq!(options.unpack_f32(&bytes), Err(Error::InexactFloat));
+    let lossy = options.with_lossy(true);
+    assert_eq!(lossy.unpack_f32_iter(bytes).unwrap(), (9, 0.1f32));
+
+    // integers stay rejected unless enabled
+    assert_eq!(lossy.unpack_f32(&[0x01]), Err(Error::UnexpectedFormatTag));
+}
+
+#[test]
+fn compact_floats() {
+    assert_eq!(compact(1.0), [0x01]);
+    assert_eq!(compact(-1.0), [0xff]);
+    assert_eq!(compact(300.0), 300u16.pack_to_vec());
+    assert_eq!(compact(-0.0), (-0.0f32).pack_to_vec());
+    assert_eq!(compact(0.5), 0.5f32.pack_to_vec());
+    assert_eq!(compact(f64::INFINITY), f32::INFINITY.pack_to_vec());
+    assert_eq!(compact(0.1), 0.1f64.pack_to_vec());
+    assert_eq!(compact(2f64.powi(64)), 2f32.powi(64).pack_to_vec());
+    assert_eq!(compact(-(2f64.powi(63))), i64::MIN.pack_to_vec());
+
+    let mut bytes = Vec::new();
+    msgpacker::pack_f32_compact(&mut bytes, 7.0);
+    assert_eq!(bytes, [0x07]);
+}
+
+#[test]
+fn derived_fields() {
+    let mut bytes = "a".pack_to_vec();
+    1u8.pack(&mut bytes);
+    0.5f64.pack(&mut bytes);
+    let reading = Reading {
+        sensor: "a".into(),
+        value: 1.0,
+        ratio: 0.5,
+    };
+    assert_eq!(
+        Reading::unpack(&bytes).unwrap(),
+        (bytes.len(), reading.clone())
+    );
+    assert_eq!(
+        Reading::unpack_iter(bytes.iter().copied()).unwrap(),
+        (bytes.len(), reading.clone())
+    );
+    assert_eq!(
+        Reading::unpack_strict(&bytes),
+        Ok((bytes.len(), reading.clone()))
+    );
+
+    // the fields are packed as plain floats
+    let packed = reading.pack_to_vec();
+    assert_eq!(Reading::unpack(&packed).unwrap(), (packed.len(), reading));
+
+    // strict mode still checks the integers
+    let mut bytes = "a".pack_to_vec();
+    bytes.extend([0xcc, 0x01]);
+    0.5f64.pack(&mut bytes);
+    assert_eq!(Reading::unpack(&bytes).unwrap().1.value, 1.0);
+    assert_eq!(
+        Reading::unpack_strict(&bytes),
+        Err(Error::NonMinimalInteger)
+    );
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn derived_fields_from_json() {
+    let bytes = msgpacker::json::from_str(r#"{"kind":"Sample","value":1}"#).unwrap();
+    assert_eq!(
+        f64::unpack(&1u8.pack_to_vec()),
+        Err(Error::UnexpectedFormatTag)
+    );
+    assert_eq!(
+        Event::unpack(&bytes).unwrap(),
+        (bytes.len(), Event::Sample { value: 1.0 })
+    );
+}
+
 proptest! {
     #[test]
     fn f32(x: f32) {
@@ -12,4 +155,15 @@ proptest! {
     fn f64(x: f64) {
         utils::case(x);
     }
+
+    #[test]
+    fn compact_round_trip(x: f64) {
+        let bytes = compact(x);
+        prop_assert!(bytes.len() <= 9);
+        let options = FloatOptions::new().with_integers(true);
+        let (n, y) = options.unpack_f64(&bytes).unwrap();
+        prop_assert_eq!(n, bytes.len());
+        prop_assert!(x == y || x.is_nan() && y.is_nan());
+        prop_assert_eq!(x.is_sign_negative(), y.is_sign_negative());
+    }
 }
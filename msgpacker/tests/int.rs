@@ -1,3 +1,4 @@
+use msgpacker::prelude::*;
 use proptest::prelude::*;
 
 mod utils;
@@ -63,3 +64,69 @@ proptest! {
         utils::case(x);
     }
 }
+
+#[test]
+fn any_marker() {
+    let markers: [&[u8]; 9] = [
+        &[0x05],
+        &[0xcc, 0x05],
+        &[0xcd, 0x00, 0x05],
+        &[0xce, 0x00, 0x00, 0x00, 0x05],
+        &[0xcf, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05],
+        &[0xd0, 0x05],
+        &[0xd1, 0x00, 0x05],
+        &[0xd2, 0x00, 0x00, 0x00, 0x05],
+        &[0xd3, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05],
+    ];
+    for bytes in markers {
+        let n = bytes.len();
+        assert_eq!(u8::unpack(bytes).unwrap(), (n, 5));
+        assert_eq!(u16::unpack(bytes).unwrap(), (n, 5));
+        assert_eq!(u32::unpack_iter(bytes.to_vec()).unwrap(), (n, 5));
+        assert_eq!(u128::unpack(bytes).unwrap(), (n, 5));
+        assert_eq!(i8::unpack(bytes).unwrap(), (n, 5));
+        assert_eq!(i64::unpack_iter(bytes.to_vec()).unwrap(), (n, 5));
+        assert_eq!(isize::unpack(bytes).unwrap(), (n, 5));
+        assert_eq!(i128::unpack(bytes).unwrap(), (n, 5));
+    }
+
+    assert_eq!(i16::unpack(&200u8.pack_to_vec()).unwrap().1, 200);
+    assert_eq!(u8::unpack(&200i64.pack_to_vec()).unwrap().1, 200);
+    assert_eq!(
+        i64::unpack(&u32::MAX.pack_to_vec()).unwrap().1,
+        u32::MAX as i64
+    );
+    assert_eq!(
+        u64::unpack(&i64::MAX.pack_to_vec()).unwrap().1,
+        i64::MAX as u64
+    );
+}
+
+#[test]
+fn out_of_range() {
+    assert_eq!(
+        u8::unpack(&256u16.pack_to_vec()),
+        Err(Error::IntegerOutOfRange)
+    );
+    assert_eq!(
+        u64::unpack(&(-1i8).pack_to_vec()),
+        Err(Error::IntegerOutOfRange)
+    );
+    assert_eq!(
+        u128::unpack_iter((-1i64).pack_to_vec()),
+        Err(Error::IntegerOutOfRange)
+    );
+    assert_eq!(
+        i8::unpack(&128u8.pack_to_vec()),
+        Err(Error::IntegerOutOfRange)
+    );
+    assert_eq!(
+        i16::unpack(&(-40000i32).pack_to_vec()),
+        Err(Error::IntegerOutOfRange)
+    );
+    assert_eq!(
+        i64::unpack(&u64::MAX.pack_to_vec()),
+        Err(Error::IntegerOutOfRange)
+    );
+    assert_eq!(u32::unpack(&[0xc3]), Err(Error::UnexpectedFormatTag));
+}
@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+
+use arbitrary::{Arbitrary as _, Unstructured};
+use arbitrary_json::ArbitraryValue;
+use msgpacker::json::{self, BinPolicy, ExtPolicy, KeyPolicy, NonFinitePolicy, Transcoder};
+use msgpacker::prelude::*;
+use proptest::prelude::*;
+
+#[test]
+fn json_bin() {
+    let cases: [(&[u8], &str); 5] = [
+        (b"", r#""""#),
+        (b"f", r#""Zg==""#),
+        (b"fo", r#""Zm8=""#),
+        (b"foo", r#""Zm9v""#),
+        (b"foob", r#""Zm9vYg==""#),
+    ];
+    for (bin, base64) in cases {
+        let bytes = bin.to_vec().pack_to_vec();
+        assert_eq!(json::to_string(&bytes).unwrap(), base64);
+    }
+
+    let bytes = vec![1u8, 2, 255].pack_to_vec();
+    let array = Transcoder::new().with_bin(BinPolicy::Array);
+    assert_eq!(array.to_string(&bytes).unwrap(), "[1,2,255]");
+}
+
+#[test]
+fn json_ext() {
+    let bytes = Extension::Ext(5, vec![0xca, 0xfe]).pack_to_vec();
+
+    assert_eq!(
+        json::to_string(&bytes).unwrap(),
+        r#"{"type":5,"data":"yv4="}"#
+    );
+    let array = Transcoder::new()
+        .with_ext(ExtPolicy::Array)
+        .with_bin(BinPolicy::Array);
+    assert_eq!(array.to_string(&bytes).unwrap(), "[5,[202,254]]");
+    let error = Transcoder::new().with_ext(ExtPolicy::Error);
+    assert_eq!(error.to_string(&bytes), Err(Error::Unrepresentable));
+}
+
+#[test]
+fn json_keys() {
+    let mut map = BTreeMap::new();
+    map.insert(-1i64, true);
+    map.insert(7, false);
+    let bytes = map.pack_to_vec();
+
+    assert_eq!(json::to_string(&bytes).unwrap(), r#"{"-1":true,"7":false}"#);
+    let error = Transcoder::new().with_keys(KeyPolicy::Error);
+    assert_eq!(error.to_string(&bytes), Err(Error::Unrepresentable));
+
+    let mut map = BTreeMap::new();
+    map.insert(vec![1u8], ());
+    let bytes = map.pack_to_vec();
+    assert_eq!(json::to_string(&bytes).unwrap(), r#"{"AQ==":null}"#);
+    let array = Transcoder::new().with_bin(BinPolicy::Array);
+    assert_eq!(array.to_string(&bytes), Err(Error::Unrepresentable));
+
+    let mut bytes = Vec::new();
+    msgpacker::pack_map_len(&mut bytes, 1);
+    msgpacker::pack_array_len(&mut bytes, 0);
+    0u8.pack(&mut bytes);
+    assert_eq!(json::to_string(&bytes), Err(Error::Unrepresentable));
+}
+
+#[test]
+fn json_non_finite() {
+    let bytes = [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, 1.5]
+        .iter()
+        .flat_map(|f| f.pack_to_vec())
+        .collect::<Vec<u8>>();
+    let mut array = Vec::new();
+    msgpacker::pack_array_len(&mut array, 4);
+    array.extend(bytes);
+
+    assert_eq!(json::to_string(&array).unwrap(), "[null,null,null,1.5]");
+    let string = Transcoder::new().with_non_finite(NonFinitePolicy::String);
+    assert_eq!(
+        string.to_string(&array).unwrap(),
+        r#"["NaN","Infinity","-Infinity",1.5]"#
+    );
+    let error = Transcoder::new().with_non_finite(NonFinitePolicy::Error);
+    assert_eq!(error.to_string(&array), Err(Error::Unrepresentable));
+
+    let mut map = BTreeMap::new();
+    map.insert(0u8, f32::NAN);
+    let bytes = map.pack_to_vec();
+    assert_eq!(string.to_string(&bytes).unwrap(), r#"{"0":"NaN"}"#);
+}
+
+#[test]
+fn json_partial() {
+    let mut bytes = 1u8.pack_to_vec();
+    "two".pack(&mut bytes);
+
+    let mut json = Vec::new();
+    assert_eq!(json::to_writer(&bytes, &mut json).unwrap(), 1);
+    assert_eq!(json, b"1");
+    assert_eq!(json::to_string(&bytes), Err(Error::TrailingBytes));
+    assert_eq!(json::to_string(&bytes[..1]).unwrap(), "1");
+    assert_eq!(json::to_string(&[0x92, 0x01]), Err(Error::BufferTooShort));
+
+    assert!(matches!(json::from_str("[1,"), Err(Error::Custom(_))));
+    assert!(matches!(json::from_str("1 2"), Err(Error::Custom(_))));
+}
+
+#[test]
+fn json_deep_nesting() {
+    let depth = 1_000_000;
+    let mut bytes = vec![0x91; depth];
+    bytes.push(0x90);
+
+    let json = json::to_string(&bytes).unwrap();
+    assert_eq!(json.len(), 2 * (depth + 1));
+    assert!(json.starts_with("[[[") && json.ends_with("]]]"));
+}
+
+proptest! {
+    #[test]
+    fn json_matches_serde_json(seed: [u8; 32]) {
+        let seed = Unstructured::new(&seed);
+        let value = ArbitraryValue::arbitrary_take_rest(seed);
+        prop_assume!(value.is_ok());
+        let value = value.unwrap().take();
+        let text = serde_json::to_string(&value).unwrap();
+        let bytes = msgpacker::serde::to_vec(&value);
+
+        prop_assert_eq!(&json::to_string(&bytes).unwrap(), &text);
+        prop_assert_eq!(&json::from_str(&text).unwrap(), &bytes);
+
+        let mut packed = Vec::new();
+        let n = json::from_reader(text.as_bytes(), &mut packed).unwrap();
+        prop_assert_eq!(n, bytes.len());
+        prop_assert_eq!(packed, bytes);
+    }
+}
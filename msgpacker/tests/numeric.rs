@@ -0,0 +1,176 @@
+use msgpacker::{prelude::*, Numeric};
+use proptest::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+pub struct Telemetry {
+    pub id: u32,
+    pub samples: Vec<f32>,
+    pub readings: Vec<f64>,
+    pub counters: Vec<u64>,
+    pub deltas: Vec<i32>,
+    #[msgpacker(array)]
+    pub flags: Vec<u8>,
+}
+
+/// Packs the numbers with both paths, checking they write the same bytes.
+fn pack<N>(values: &[N]) -> Vec<u8>
+where
+    N: Numeric,
+{
+    let mut expected = Vec::new();
+    let n = msgpacker::pack_array(&mut expected, values);
+    assert_eq!(n, expected.len());
+
+    let mut bytes = Vec::new();
+    assert_eq!(msgpacker::pack_numbers(&mut bytes, values), bytes.len());
+    assert_eq!(bytes, expected);
+    bytes
+}
+
+/// Unpacks the numbers with both paths, checking they agree.
+fn unpack<N>(bytes: &[u8]) -> Result<(usize, Vec<N>), Error>
+where
+    N: Numeric + std::fmt::Debug + PartialEq,
+{
+    let numbers = msgpacker::unpack_numbers(bytes);
+    assert_eq!(numbers, msgpacker::unpack_array(bytes));
+    numbers
+}
+
+fn roundtrip<N>(values: &[N])
+where
+    N: Numeric + std::fmt::Debug + PartialEq,
+{
+    let bytes = pack(values);
+    assert_eq!(unpack(&bytes), Ok((bytes.len(), values.to_vec())));
+}
+
+#[test]
+fn numeric_uniform_formats() {
+    roundtrip::<u8>(&[]);
+    roundtrip(&[1u8, 2, 3]);
+    roundtrip(&[200u8; 300]);
+    roundtrip(&[0x1234u16; 20]);
+    roundtrip(&[0x1234_5678u32; 20]);
+    roundtrip(&[u64::MAX; 20]);
+    roundtrip(&[-5i8; 20]);
+    roundtrip(&[-100i16; 20]);
+    roundtrip(&[1000i32; 20]);
+    roundtrip(&[i64::MIN; 20]);
+    roundtrip(&[1.5f32; 1000]);
+    roundtrip(&[-2.25f64; 1000]);
+}
+
+#[test]
+fn numeric_mixed_formats() {
+    roundtrip(&[1u32, 300, 70_000, 5, 200]);
+    roundtrip(&[-1i64, 1, -40, 40, 200, -200, 1 << 40, -(1 << 40)]);
+    let values: Vec<i32> = (-600..600).map(|v| v * 997).collect();
+    roundtrip(&values);
+}
+
+#[test]
+fn numeric_widening() {
+    // runs of narrower formats are decoded into wider types
+    let bytes = pack(&[1.5f32, 2.5]);
+    assert_eq!(unpack(&bytes), Ok((bytes.len(), vec![1.5f64, 2.5])));
+    let bytes = pack(&[100u32, 200, 300]);
+    assert_eq!(unpack(&bytes), Ok((bytes.len(), vec![100i64, 200, 300])));
+    let bytes = pack(&[-3i8, 4, -5]);
+    assert_eq!(unpack(&bytes), Ok((bytes.len(), vec![-3i64, 4, -5])));
+}
+
+#[test]
+fn numeric_errors() {
+    let bytes = pack(&[1u32, 70_000]);
+    assert_eq!(unpack::<u16>(&bytes), Err(Error::IntegerOutOfRange));
+    let bytes = pack(&[-1i32, 2]);
+    assert_eq!(unpack::<u32>(&bytes), Err(Error::IntegerOutOfRange));
+    let bytes = pack(&[1.5f64]);
+    assert_eq!(unpack::<f32>(&bytes), Err(Error::UnexpectedFormatTag));
+
+    let bytes = pack(&[1.5f32; 4]);
+    for len in 0..bytes.len() {
+        assert_eq!(unpack::<f32>(&bytes[..len]), Err(Error::BufferTooShort));
+    }
+
+    // a bogus length doesn't allocate past the buffer
+    let bytes = [0xdd, 0xff, 0xff, 0xff, 0xff, 0x01];
+    assert_eq!(unpack::<u8>(&bytes), Err(Error::BufferTooShort));
+}
+
+#[test]
+fn numeric_derive() {
+    let telemetry = Telemetry {
+        id: 7,
+        samples: (0..1000).map(|i| i as f32 / 3.0).collect(),
+        readings: vec![1.0, f64::MAX, -0.0],
+        counters: vec![0, 1 << 20, u64::MAX],
+        deltas: vec![-1, 1, -70_000],
+        flags: vec![1, 0, 255],
+    };
+    let bytes = telemetry.pack_to_vec();
+    assert_eq!(bytes, telemetry.try_pack_to_vec().unwrap());
+
+    let mut expected = Vec::new();
+    telemetry.id.pack(&mut expected);
+    msgpacker::pack_array(&mut expected, &telemetry.samples);
+    msgpacker::pack_array(&mut expected, &telemetry.readings);
+    msgpacker::pack_array(&mut expected, &telemetry.counters);
+    msgpacker::pack_array(&mut expected, &telemetry.deltas);
+    msgpacker::pack_array(&mut expected, &telemetry.flags);
+    assert_eq!(bytes, expected);
+
+    assert_eq!(
+        Telemetry::unpack(&bytes),
+        Ok((bytes.len(), telemetry.clone()))
+    );
+    assert_eq!(
+        Telemetry::unpack_iter(bytes.iter().copied()),
+        Ok((bytes.len(), telemetry.clone()))
+    );
+    assert_eq!(
+        Telemetry::unpack_strict(&bytes),
+        Ok((bytes.len(), telemetry))
+    );
+}
+
+proptest! {
+    #[test]
+    fn numeric_proptest_u64(values: Vec<u64>) {
+        roundtrip(&values);
+    }
+
+    #[test]
+    fn numeric_proptest_i64(values: Vec<i64>) {
+        roundtrip(&values);
+    }
+
+    #[test]
+    fn numeric_proptest_i16(values: Vec<i16>) {
+        roundtrip(&values);
+    }
+
+    #[test]
+    fn numeric_proptest_f64(values: Vec<f64>) {
+        // NaNs aren't equal to themselves, so the bits are compared
+        let bits = |values: &[f64]| values.iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+        let bytes = pack(&values);
+        let (n, decoded) = msgpacker::unpack_numbers::<f64>(&bytes).unwrap();
+        let (_, expected): (_, Vec<f64>) = msgpacker::unpack_array(&bytes).unwrap();
+        prop_assert_eq!(n, bytes.len());
+        prop_assert_eq!(bits(&decoded), bits(&values));
+        prop_assert_eq!(bits(&expected), bits(&values));
+    }
+
+    #[test]
+    fn numeric_proptest_bytes(bytes: Vec<u8>) {
+        // arbitrary input is decoded as the element by element path does
+        let mut buf = Vec::new();
+        msgpacker::pack_array_len(&mut buf, bytes.len() / 2);
+        buf.extend(&bytes);
+        let _ = unpack::<i32>(&buf);
+        let _ = unpack::<u16>(&buf);
+        let _ = unpack::<i8>(&buf);
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::BTreeMap;
+
+use msgpacker::{prelude::*, unpack_raw, Raw, RawBuf};
+use proptest::prelude::*;
+
+mod utils;
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+struct Header {
+    route: String,
+    id: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+struct Envelope {
+    header: Header,
+    body: RawBuf,
+}
+
+/// A body that packs as a single value.
+type Body = BTreeMap<String, BTreeMap<u32, Option<String>>>;
+
+fn body() -> impl Strategy<Value = Body> {
+    let inner = prop::collection::btree_map(any::<u32>(), any::<Option<String>>(), 0..4);
+    prop::collection::btree_map(".{0,8}", inner, 0..8)
+}
+
+#[test]
+fn raw_rejects_partial_values() {
+    let bytes = (7u8, "x".to_string()).pack_to_vec();
+    assert_eq!(Raw::new(&bytes), Err(Error::TrailingBytes));
+    assert_eq!(Raw::new(&bytes[..1]).map(|r| r.decode::<u8>()), Ok(Ok(7)));
+    assert_eq!(Raw::new(&bytes[1..2]), Err(Error::BufferTooShort));
+    assert_eq!(
+        RawBuf::new(bytes[1..].to_vec())
+            .unwrap()
+            .as_raw()
+            .decode::<String>(),
+        Ok("x".into())
+    );
+}
+
+proptest! {
+    #[test]
+    fn raw_envelope(route: String, id: u64, items in body()) {
+        let body = items.pack_to_vec();
+        let envelope = Envelope {
+            header: Header { route, id },
+            body: RawBuf::new(body.clone()).unwrap(),
+        };
+        utils::case(envelope.clone());
+
+        let bytes = envelope.pack_to_vec();
+        let (n, header) = Header::unpack(&bytes).unwrap();
+        prop_assert_eq!(header, envelope.header);
+
+        let (m, raw) = unpack_raw(&bytes[n..]).unwrap();
+        prop_assert_eq!(n + m, bytes.len());
+        prop_assert_eq!(raw.as_bytes(), &body[..]);
+        prop_assert_eq!(raw.pack_to_vec(), body);
+        prop_assert_eq!(raw.decode::<Body>().unwrap(), items);
+    }
+}
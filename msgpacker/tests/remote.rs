@@ -0,0 +1,108 @@
+use msgpacker::prelude::*;
+use proptest::prelude::*;
+
+mod utils;
+
+/// Stands for a crate that doesn't depend on msgpacker.
+mod other {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Point {
+        pub x: f64,
+        pub y: f64,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Shape {
+        Dot,
+        Label(String),
+        Segment { from: u32, to: u32 },
+    }
+}
+
+#[derive(MsgPacker)]
+#[msgpacker(remote = "other::Point")]
+struct PointDef {
+    x: f64,
+    y: f64,
+}
+
+#[derive(MsgPacker)]
+#[msgpacker(remote = "other::Shape")]
+enum ShapeDef {
+    Dot,
+    Label(String),
+    Segment { from: u32, to: u32 },
+}
+
+#[derive(MsgPacker)]
+#[msgpacker(remote = "other::Shape", external)]
+pub enum NamedShapeDef {
+    Dot,
+    Label(String),
+    Segment { from: u32, to: u32 },
+}
+
+#[derive(Debug, Clone, PartialEq, MsgPacker, proptest_derive::Arbitrary)]
+pub struct Drawing {
+    pub name: String,
+    #[msgpacker(with = "PointDef")]
+    #[proptest(strategy = "point()")]
+    pub origin: other::Point,
+    #[msgpacker(with = "ShapeDef")]
+    #[proptest(strategy = "shape()")]
+    pub shape: other::Shape,
+}
+
+#[derive(Debug, Clone, PartialEq, MsgPacker, proptest_derive::Arbitrary)]
+#[msgpacker(external)]
+pub enum Layer {
+    Empty,
+    Shape(
+        #[msgpacker(with = "NamedShapeDef")]
+        #[proptest(strategy = "shape()")]
+        other::Shape,
+    ),
+}
+
+fn point() -> impl Strategy<Value = other::Point> {
+    (any::<f64>(), any::<f64>()).prop_map(|(x, y)| other::Point { x, y })
+}
+
+fn shape() -> impl Strategy<Value = other::Shape> {
+    prop_oneof![
+        Just(other::Shape::Dot),
+        any::<String>().prop_map(other::Shape::Label),
+        (any::<u32>(), any::<u32>()).prop_map(|(from, to)| other::Shape::Segment { from, to }),
+    ]
+}
+
+#[test]
+fn remote_functions() {
+    let point = other::Point { x: 1.0, y: -2.0 };
+
+    let mut bytes = vec![];
+    let n = PointDef::pack(&point, &mut bytes);
+    assert_eq!(n, bytes.len());
+    assert_eq!(PointDef::unpack(&bytes).unwrap(), (n, point));
+    assert_eq!(PointDef::unpack_iter(bytes).unwrap(), (n, point));
+
+    // a foreign value packs the same as the equivalent derived one
+    let mut bytes = vec![];
+    NamedShapeDef::pack(&other::Shape::Label("x".into()), &mut bytes);
+    assert_eq!(
+        bytes,
+        [0x81, 0xa5, b'L', b'a', b'b', b'e', b'l', 0xa1, b'x']
+    );
+}
+
+proptest! {
+    #[test]
+    fn remote_with(x: Drawing) {
+        utils::case(x);
+    }
+
+    #[test]
+    fn remote_with_tagged(x: Layer) {
+        utils::case(x);
+    }
+}
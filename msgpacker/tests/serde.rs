@@ -1,4 +1,4 @@
-use core::marker::PhantomData;
+use core::{marker::PhantomData, time::Duration};
 
 use arbitrary::{Arbitrary as _, Unstructured};
 use arbitrary_json::ArbitraryValue;
@@ -89,6 +89,46 @@ fn serde_works_u128() {
     case(u128::MAX - 3);
 }
 
+#[test]
+fn serde_compact_floats() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Reading {
+        value: f64,
+        ratio: f32,
+    }
+
+    let reading = Reading {
+        value: 3.0,
+        ratio: 0.5,
+    };
+    let mut bytes = Vec::new();
+    reading
+        .serialize(&mut msgpacker::serde::Serializer::new(&mut bytes).with_compact_floats(true))
+        .unwrap();
+    assert_eq!(bytes, [0x03, 0xca, 0x3f, 0x00, 0x00, 0x00]);
+
+    assert!(msgpacker::serde::from_slice::<Reading>(&bytes).is_err());
+    let floats = msgpacker::FloatOptions::new().with_integers(true);
+    let mut de = msgpacker::serde::Deserializer::new(&bytes).with_floats(floats);
+    assert_eq!(Reading::deserialize(&mut de).unwrap(), reading);
+
+    let bytes = msgpacker::serde::to_vec(&0.25f64);
+    let floats = floats.with_narrowing(true);
+    let mut de = msgpacker::serde::Deserializer::new_iter(bytes).with_floats(floats);
+    assert_eq!(f32::deserialize(&mut de).unwrap(), 0.25);
+}
+
+#[test]
+fn serde_integers_by_value() {
+    let bytes = msgpacker::serde::to_vec(&200i64);
+    assert_eq!(msgpacker::serde::from_slice::<u8>(&bytes), Ok(200));
+    assert_eq!(msgpacker::serde::from_slice::<i16>(&bytes), Ok(200));
+    assert_eq!(
+        msgpacker::serde::from_slice::<i8>(&bytes),
+        Err(msgpacker::Error::IntegerOutOfRange)
+    );
+}
+
 #[test]
 fn serde_works_usize() {
     case(usize::MAX - 3);
@@ -185,6 +225,473 @@ fn serde_non_uniform_deserialization_to_json() {
     );
 }
 
+#[test]
+fn serde_try_to_vec_reports_errors() {
+    struct Failing;
+
+    impl Serialize for Failing {
+        fn serialize<S: serde::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+            Err(serde::ser::Error::custom("not today"))
+        }
+    }
+
+    assert_eq!(
+        msgpacker::serde::try_to_vec(&Failing).unwrap_err(),
+        msgpacker::Error::Custom("not today".into())
+    );
+    assert_eq!(
+        msgpacker::serde::try_to_vec(&(1u8, vec![Failing])).unwrap_err(),
+        msgpacker::Error::Custom("not today".into())
+    );
+    assert_eq!(
+        msgpacker::serde::try_to_vec(&(1u8, "x")).unwrap(),
+        msgpacker::serde::to_vec(&(1u8, "x"))
+    );
+}
+
+#[test]
+fn serde_unknown_length() {
+    struct Evens(u32);
+
+    impl Serialize for Evens {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq((0..self.0).filter(|i| i % 2 == 0))
+        }
+    }
+
+    struct Squares(u32);
+
+    impl Serialize for Squares {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_map((0..self.0).filter(|i| i % 2 == 0).map(|i| (i, i * i)))
+        }
+    }
+
+    let evens = (0..40).filter(|i| i % 2 == 0).collect::<Vec<u32>>();
+    let bytes = msgpacker::serde::try_to_vec(&Evens(40)).unwrap();
+    assert_eq!(bytes, msgpacker::serde::to_vec(&evens));
+    assert_eq!(bytes[0], 0xdc);
+
+    let squares = evens
+        .iter()
+        .map(|i| (*i, i * i))
+        .collect::<std::collections::BTreeMap<u32, u32>>();
+    let bytes = msgpacker::serde::try_to_vec(&Squares(40)).unwrap();
+    assert_eq!(bytes, msgpacker::serde::to_vec(&squares));
+
+    // nested collections of unknown length
+    let bytes = msgpacker::serde::try_to_vec(&vec![Evens(3), Evens(5)]).unwrap();
+    let value: Value = msgpacker::serde::from_slice(&bytes).unwrap();
+    assert_eq!(value, serde_json::json!([[0, 2], [0, 2, 4]]));
+}
+
+#[test]
+fn serde_flatten() {
+    #[derive(Serialize)]
+    struct Inner {
+        b: u32,
+    }
+
+    #[derive(Serialize)]
+    struct Outer {
+        a: u32,
+        #[serde(flatten)]
+        inner: Inner,
+    }
+
+    let bytes = msgpacker::serde::try_to_vec(&Outer {
+        a: 1,
+        inner: Inner { b: 2 },
+    })
+    .unwrap();
+    let value: Value = msgpacker::serde::from_slice(&bytes).unwrap();
+    assert_eq!(value, serde_json::json!({"a": 1, "b": 2}));
+}
+
+#[test]
+fn serde_custom_error_message() {
+    struct Incomplete;
+
+    impl<'de> Deserialize<'de> for Incomplete {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            u8::deserialize(deserializer)?;
+            Err(serde::de::Error::missing_field("b"))
+        }
+    }
+
+    let e = msgpacker::serde::from_slice::<Incomplete>(&[0x01])
+        .err()
+        .unwrap();
+    assert_eq!(e, msgpacker::Error::Custom("missing field `b`".into()));
+    assert_eq!(e.to_string(), "missing field `b`");
+}
+
+#[test]
+fn serde_struct_map() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Point {
+        x: i32,
+        label: Option<String>,
+    }
+
+    let point = Point {
+        x: -3,
+        label: Some("p".into()),
+    };
+
+    let mut bytes = vec![];
+    point
+        .serialize(&mut msgpacker::serde::Serializer::new(&mut bytes).with_struct_map(true))
+        .unwrap();
+    let value: Value = msgpacker::serde::from_slice(&bytes).unwrap();
+    assert_eq!(value, serde_json::json!({"x": -3, "label": "p"}));
+
+    // maps in any order and arrays are both accepted
+    for value in [
+        serde_json::json!({"label": "p", "x": -3}),
+        serde_json::json!({"x": -3, "unknown": [1, 2], "label": "p"}),
+        serde_json::json!([-3, "p"]),
+    ] {
+        let bytes = msgpacker::serde::to_vec(&value);
+        let mut de = msgpacker::serde::Deserializer::new(&bytes).with_struct_map(true);
+        assert_eq!(Point::deserialize(&mut de).unwrap(), point);
+        assert!(de.remaining().is_empty());
+    }
+
+    // the default layout is the bare fields
+    assert!(Point::deserialize(&mut msgpacker::serde::Deserializer::new(&bytes)).is_err());
+}
+
+#[test]
+fn serde_enum_names() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Empty,
+        Circle(u32),
+        Segment(u32, u32),
+        Rect { w: u32, h: u32 },
+    }
+
+    let shapes = [
+        (Shape::Empty, serde_json::json!("Empty")),
+        (Shape::Circle(3), serde_json::json!({"Circle": 3})),
+        (Shape::Segment(1, 2), serde_json::json!({"Segment": [1, 2]})),
+        (
+            Shape::Rect { w: 4, h: 5 },
+            serde_json::json!({"Rect": [4, 5]}),
+        ),
+    ];
+
+    for (shape, json) in shapes {
+        let mut bytes = vec![];
+        shape
+            .serialize(&mut msgpacker::serde::Serializer::new(&mut bytes).with_enum_names(true))
+            .unwrap();
+        assert_eq!(bytes, msgpacker::serde::to_vec(&json));
+
+        let mut de = msgpacker::serde::Deserializer::new(&bytes).with_enum_names(true);
+        assert_eq!(Shape::deserialize(&mut de).unwrap(), shape);
+        assert!(de.remaining().is_empty());
+    }
+
+    // variants are also accepted by index, and struct variants as maps
+    let bytes = msgpacker::serde::to_vec(&serde_json::json!({"3": {"h": 5, "w": 4}}));
+    let bytes = [&[0x81, 0x03][..], &bytes[3..]].concat();
+    let mut de = msgpacker::serde::Deserializer::new(&bytes).with_struct_map(true);
+    assert_eq!(
+        Shape::deserialize(&mut de).unwrap(),
+        Shape::Rect { w: 4, h: 5 }
+    );
+
+    let bytes = msgpacker::serde::to_vec(&serde_json::json!("Empty"));
+    assert_eq!(
+        msgpacker::serde::from_slice::<Shape>(&bytes).unwrap(),
+        Shape::Empty
+    );
+
+    // a unit variant in a map takes a nil or empty array payload
+    for payload in [serde_json::json!(null), serde_json::json!([])] {
+        let bytes = msgpacker::serde::to_vec(&serde_json::json!({"Empty": payload}));
+        assert_eq!(
+            msgpacker::serde::from_slice::<Shape>(&bytes).unwrap(),
+            Shape::Empty
+        );
+        // the payload is consumed before the next field; the tuple fields are packed bare
+        let bytes = msgpacker::serde::to_vec(&serde_json::json!([{"Empty": payload}, 7]));
+        assert_eq!(
+            msgpacker::serde::from_slice::<(Vec<Shape>, u32)>(&[&[0x91], &bytes[1..]].concat()),
+            Ok((vec![Shape::Empty], 7))
+        );
+    }
+    let bytes = msgpacker::serde::to_vec(&serde_json::json!({"Empty": 1}));
+    assert_eq!(
+        msgpacker::serde::from_slice::<Shape>(&bytes),
+        Err(msgpacker::Error::UnexpectedFormatTag)
+    );
+}
+
+#[test]
+fn serde_named_round_trip() {
+    let foos = [
+        Foo::Bar,
+        Foo::Baz(15, "xxx".into()),
+        Foo::Qux {
+            a: vec![1, 2, 3],
+            b: 42,
+        },
+    ];
+
+    for (struct_map, enum_names) in [(false, false), (false, true), (true, false), (true, true)] {
+        for foo in &foos {
+            let mut bytes = vec![];
+            foo.serialize(
+                &mut msgpacker::serde::Serializer::new(&mut bytes)
+                    .with_struct_map(struct_map)
+                    .with_enum_names(enum_names),
+            )
+            .unwrap();
+            let mut de = msgpacker::serde::Deserializer::new(&bytes)
+                .with_struct_map(struct_map)
+                .with_enum_names(enum_names);
+            assert_eq!(&Foo::deserialize(&mut de).unwrap(), foo);
+            assert!(de.remaining().is_empty());
+        }
+    }
+}
+
+#[test]
+fn serde_human_readable() {
+    struct Readable;
+
+    impl Serialize for Readable {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let readable = serializer.is_human_readable();
+            serializer.serialize_bool(readable)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Readable {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            assert!(!deserializer.is_human_readable());
+            bool::deserialize(deserializer).map(|_| Readable)
+        }
+    }
+
+    assert_eq!(msgpacker::serde::to_vec(&Readable), [0xc3]);
+
+    let mut bytes = vec![];
+    Readable
+        .serialize(&mut msgpacker::serde::Serializer::new(&mut bytes).with_human_readable(false))
+        .unwrap();
+    assert_eq!(bytes, [0xc2]);
+
+    let mut de = msgpacker::serde::Deserializer::new(&bytes).with_human_readable(false);
+    Readable::deserialize(&mut de).unwrap();
+}
+
+#[test]
+fn serde_works_extension() {
+    case(msgpacker::Extension::Ext(5, vec![]));
+    case(msgpacker::Extension::Ext(-3, vec![1, 2, 3, 4]));
+    case(msgpacker::Extension::Ext(127, vec![0xaa; 300]));
+    case(msgpacker::Extension::Timestamp(Duration::from_secs(
+        1_700_000_000,
+    )));
+    case(msgpacker::Extension::Timestamp(Duration::new(
+        1_700_000_000,
+        999,
+    )));
+    case(msgpacker::Extension::Timestamp(Duration::new(u64::MAX, 7)));
+}
+
+#[test]
+fn serde_extension_fields() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Event {
+        #[serde(with = "msgpacker::serde::timestamp")]
+        at: Duration,
+        payload: msgpacker::Extension,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(untagged)]
+    enum Any {
+        Int(u64),
+        Ext(msgpacker::Extension),
+    }
+
+    let event = Event {
+        at: Duration::new(1_700_000_000, 5),
+        payload: msgpacker::Extension::Ext(9, vec![1, 2]),
+    };
+    let bytes = msgpacker::serde::to_vec(&event);
+    let mut expected = vec![];
+    msgpacker::Extension::Timestamp(event.at).pack(&mut expected);
+    event.payload.pack(&mut expected);
+    assert_eq!(bytes, expected);
+    assert_eq!(
+        msgpacker::serde::from_slice::<Event>(&bytes).unwrap(),
+        event
+    );
+
+    // extensions go through `deserialize_any`
+    let bytes = msgpacker::serde::to_vec(&event.payload);
+    assert_eq!(
+        msgpacker::serde::from_slice::<Any>(&bytes).unwrap(),
+        Any::Ext(event.payload)
+    );
+
+    let bytes = msgpacker::serde::to_vec(&msgpacker::Extension::Ext(9, vec![1, 2]));
+    assert!(msgpacker::serde::from_slice::<Event>(&[&bytes[..], &bytes[..]].concat()).is_err());
+}
+
+#[test]
+fn serde_extension_any() {
+    // generic visitors get the `(type, bytes)` tuple as a sequence
+    let value: Value = msgpacker::serde::from_slice(&[0xd4, 0x05, 0xaa]).unwrap();
+    assert_eq!(value, serde_json::json!([5, [0xaa]]));
+    let value: Value = msgpacker::serde::from_iter([0xd4, 0x05, 0xaa]).unwrap();
+    assert_eq!(value, serde_json::json!([5, [0xaa]]));
+
+    let map =
+        std::collections::BTreeMap::from([("ext", msgpacker::Extension::Ext(-2, vec![1, 2, 3]))]);
+    let bytes = msgpacker::serde::to_vec(&map);
+    let value: Value = msgpacker::serde::from_slice(&bytes).unwrap();
+    assert_eq!(value, serde_json::json!({"ext": [-2, [1, 2, 3]]}));
+}
+
+#[test]
+fn serde_trailing_bytes() {
+    let mut bytes = msgpacker::serde::to_vec(&(7u32, "x"));
+    bytes.push(0xc0);
+
+    assert_eq!(
+        msgpacker::serde::from_slice::<(u32, &str)>(&bytes),
+        Err(msgpacker::Error::TrailingBytes)
+    );
+    assert_eq!(
+        msgpacker::serde::from_slice_partial::<(u32, &str)>(&bytes),
+        Ok(((7, "x"), &[0xc0][..]))
+    );
+
+    let mut de = msgpacker::serde::Deserializer::new(&bytes);
+    <(u32, &str)>::deserialize(&mut de).unwrap();
+    assert_eq!(de.end(), Err(msgpacker::Error::TrailingBytes));
+    <()>::deserialize(&mut de).unwrap();
+    assert_eq!(de.end(), Ok(()));
+}
+
+#[test]
+fn serde_stream_deserializer() {
+    let values = [
+        Foo::Bar,
+        Foo::Baz(1, "a".into()),
+        Foo::Qux {
+            a: vec![1, 2],
+            b: 3,
+        },
+    ];
+
+    let mut bytes = vec![];
+    for value in &values {
+        value
+            .serialize(&mut msgpacker::serde::Serializer::new(&mut bytes).with_enum_names(true))
+            .unwrap();
+    }
+
+    let de = msgpacker::serde::Deserializer::new(&bytes).with_enum_names(true);
+    let stream = msgpacker::serde::StreamDeserializer::<_, Foo>::new(de);
+    assert_eq!(stream.collect::<Result<Vec<_>, _>>().unwrap(), values);
+
+    let de = msgpacker::serde::Deserializer::new_iter(bytes.iter().copied()).with_enum_names(true);
+    let stream = msgpacker::serde::StreamDeserializer::<_, Foo>::new(de);
+    assert_eq!(stream.collect::<Result<Vec<_>, _>>().unwrap(), values);
+
+    // a truncated value ends the stream with an error
+    let de = msgpacker::serde::Deserializer::new(&bytes[..bytes.len() - 1]).with_enum_names(true);
+    let mut stream = msgpacker::serde::StreamDeserializer::<_, Foo>::new(de);
+    assert_eq!(stream.next(), Some(Ok(Foo::Bar)));
+    assert_eq!(stream.next(), Some(Ok(Foo::Baz(1, "a".into()))));
+    assert_eq!(stream.next(), Some(Err(msgpacker::Error::BufferTooShort)));
+    assert_eq!(stream.next(), None);
+
+    let mut stream =
+        msgpacker::serde::StreamDeserializer::<_, u8>::new(msgpacker::serde::Deserializer::new(&[
+            1, 2,
+        ]));
+    assert_eq!(stream.next(), Some(Ok(1)));
+    assert_eq!(stream.remaining(), [2]);
+}
+
+#[test]
+fn serde_raw_value() {
+    use msgpacker::serde::RawValue;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Envelope<'a> {
+        route: String,
+        #[serde(borrow)]
+        body: &'a RawValue,
+        trailer: u8,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct OwnedEnvelope {
+        route: String,
+        body: Box<RawValue>,
+        trailer: u8,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Body {
+        items: Vec<String>,
+        size: u64,
+    }
+
+    // a struct is captured as a whole when packed as a map
+    let mut body = vec![];
+    let value = Body {
+        items: vec!["x".into(), "y".into()],
+        size: 1 << 40,
+    };
+    value
+        .serialize(&mut msgpacker::serde::Serializer::new(&mut body).with_struct_map(true))
+        .unwrap();
+
+    let envelope = Envelope {
+        route: "a".into(),
+        body: RawValue::from_bytes(&body).unwrap(),
+        trailer: 9,
+    };
+    let bytes = msgpacker::serde::to_vec(&envelope);
+
+    let mut expected = msgpacker::serde::to_vec("a");
+    expected.extend(&body);
+    expected.push(9);
+    assert_eq!(bytes, expected);
+
+    let decoded: Envelope = msgpacker::serde::from_slice(&bytes).unwrap();
+    assert_eq!(decoded.body.as_bytes(), body);
+    assert_eq!(decoded.body.as_raw().as_bytes(), body);
+    assert_eq!(decoded.trailer, 9);
+
+    let owned: OwnedEnvelope = msgpacker::serde::from_slice(&bytes).unwrap();
+    assert_eq!(owned.body.as_bytes(), body);
+    assert_eq!(owned.body.clone(), owned.body);
+    let from_iter: OwnedEnvelope = msgpacker::serde::from_iter(bytes.iter().copied()).unwrap();
+    assert_eq!(from_iter, owned);
+    assert_eq!(msgpacker::serde::to_vec(&owned), bytes);
+
+    let mut de = msgpacker::serde::Deserializer::new(decoded.body.as_bytes()).with_struct_map(true);
+    assert_eq!(Body::deserialize(&mut de).unwrap(), value);
+
+    assert_eq!(
+        RawValue::from_bytes(&[0x01, 0x02]).unwrap_err(),
+        msgpacker::Error::TrailingBytes
+    );
+    assert!(msgpacker::serde::from_iter::<_, OwnedEnvelope>(bytes[..4].iter().copied()).is_err());
+}
+
 proptest! {
     #[test]
     fn serde_proptest_json(seed: [u8; 32]) {
@@ -0,0 +1,173 @@
+use std::io::{self, Cursor, Read, Write};
+
+use arbitrary::{Arbitrary as _, Unstructured};
+use arbitrary_json::ArbitraryValue;
+use msgpacker::{Error, Extension};
+use proptest::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Record {
+    id: u64,
+    name: String,
+    tags: Vec<String>,
+    score: Option<f64>,
+}
+
+fn record() -> impl Strategy<Value = Record> {
+    (
+        any::<u64>(),
+        ".{0,40}",
+        prop::collection::vec(".{0,8}", 0..20),
+        any::<Option<f64>>().prop_filter("nan", |f| !f.is_some_and(f64::is_nan)),
+    )
+        .prop_map(|(id, name, tags, score)| Record {
+            id,
+            name,
+            tags,
+            score,
+        })
+}
+
+/// A writer that accepts `left` bytes, then fails.
+struct Full {
+    left: usize,
+}
+
+impl Write for Full {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.left == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        let n = buf.len().min(self.left);
+        self.left -= n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A reader that fails after the wrapped bytes.
+struct Broken<'a>(&'a [u8]);
+
+impl Read for Broken<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.0.is_empty() {
+            return Err(io::ErrorKind::ConnectionReset.into());
+        }
+        self.0.read(buf)
+    }
+}
+
+#[test]
+fn serde_io_errors() {
+    let record = Record {
+        id: 1,
+        name: "x".repeat(200),
+        tags: vec![],
+        score: None,
+    };
+
+    assert_eq!(
+        msgpacker::serde::to_writer(Full { left: 100 }, &record),
+        Err(Error::Io(io::ErrorKind::WriteZero))
+    );
+    assert_eq!(
+        msgpacker::serde::to_writer(Full { left: 1000 }, &record),
+        Ok(())
+    );
+
+    let bytes = msgpacker::serde::to_vec(&record);
+    assert_eq!(
+        msgpacker::serde::from_reader::<_, Record>(Broken(&bytes[..50])),
+        Err(Error::Io(io::ErrorKind::ConnectionReset))
+    );
+    assert_eq!(
+        msgpacker::serde::from_reader::<_, Record>(&bytes[..50]),
+        Err(Error::BufferTooShort)
+    );
+    assert_eq!(
+        msgpacker::serde::from_reader::<_, Record>(Broken(&bytes)),
+        Ok(record)
+    );
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Frame {
+    Ping,
+    Data(#[serde(with = "serde_bytes")] Vec<u8>),
+    Ext(Extension),
+    Named { id: u32, parts: (String, bool) },
+}
+
+#[test]
+fn serde_io_iter_matches_slice() {
+    let frames = vec![
+        Frame::Ping,
+        Frame::Data(vec![1, 2, 3]),
+        Frame::Ext(Extension::Ext(4, vec![9; 20])),
+        Frame::Ext(Extension::Timestamp(std::time::Duration::new(7, 8))),
+        Frame::Named {
+            id: 3,
+            parts: ("x".into(), true),
+        },
+    ];
+
+    for (struct_map, enum_names) in [(false, false), (true, true)] {
+        let mut bytes = vec![];
+        frames
+            .serialize(
+                &mut msgpacker::serde::Serializer::new(&mut bytes)
+                    .with_struct_map(struct_map)
+                    .with_enum_names(enum_names),
+            )
+            .unwrap();
+
+        let mut de = msgpacker::serde::Deserializer::new_iter(bytes.iter().copied())
+            .with_struct_map(struct_map)
+            .with_enum_names(enum_names);
+        assert_eq!(Vec::<Frame>::deserialize(&mut de).unwrap(), frames);
+    }
+}
+
+proptest! {
+    #[test]
+    fn serde_io_iter_json(seed: [u8; 32]) {
+        let seed = Unstructured::new(&seed);
+        let value = ArbitraryValue::arbitrary_take_rest(seed);
+        prop_assume!(value.is_ok());
+        let value = value.unwrap().take();
+
+        let bytes = msgpacker::serde::to_vec(&value);
+        let y: serde_json::Value = msgpacker::serde::from_iter(bytes.iter().copied()).unwrap();
+        prop_assert_eq!(value, y);
+    }
+
+    #[test]
+    fn serde_io_stream(records in prop::collection::vec(record(), 0..8)) {
+        let mut writer = io::BufWriter::new(Vec::new());
+        for record in &records {
+            msgpacker::serde::to_writer(&mut writer, record).unwrap();
+        }
+        let bytes = writer.into_inner().unwrap();
+
+        let expected: Vec<u8> = records.iter().flat_map(msgpacker::serde::to_vec).collect();
+        prop_assert_eq!(&bytes, &expected);
+
+        let mut reader = io::BufReader::new(Cursor::new(&bytes));
+        for record in &records {
+            let r: Record = msgpacker::serde::from_reader(&mut reader).unwrap();
+            prop_assert_eq!(&r, record);
+        }
+        prop_assert_eq!(reader.read(&mut [0u8]).unwrap(), 0);
+
+        let mut iter = bytes.iter().copied();
+        for record in &records {
+            let r: Record = msgpacker::serde::from_iter(iter.by_ref()).unwrap();
+            prop_assert_eq!(&r, record);
+        }
+        prop_assert_eq!(iter.next(), None);
+    }
+}
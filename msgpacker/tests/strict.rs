@@ -0,0 +1,473 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use msgpacker::prelude::*;
+use msgpacker::strict;
+use proptest::prelude::*;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+pub struct Account {
+    pub id: u32,
+    #[msgpacker(map)]
+    pub balances: HashMap<String, u64>,
+    pub flags: Option<BTreeSet<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+#[msgpacker(tag = "kind")]
+pub enum Payment {
+    Transfer {
+        amount: u64,
+        memo: String,
+    },
+    Batch {
+        #[msgpacker(flatten)]
+        amounts: BTreeMap<String, HashSet<u16>>,
+    },
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Transfer {
+    amount: u64,
+    memo: String,
+}
+
+fn canonical<V: Packable>(value: &V) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    value.pack_canonical(&mut bytes);
+    bytes
+}
+
+#[test]
+fn strict_integers() {
+    assert_eq!(strict::unpack::<u8>(&[0x05]), Ok(5));
+    assert_eq!(
+        strict::unpack::<u8>(&[0xcc, 0x05]),
+        Err(Error::NonMinimalInteger)
+    );
+    assert_eq!(
+        strict::unpack::<i8>(&[0xd0, 0xff]),
+        Err(Error::NonMinimalInteger)
+    );
+    assert_eq!(strict::unpack::<i8>(&[0xd0, 0x80]), Ok(-128));
+    assert_eq!(
+        strict::unpack::<u32>(&[0xce, 0x00, 0x00, 0xff, 0xff]),
+        Err(Error::NonMinimalInteger)
+    );
+
+    // positive signed integers are packed with the signed formats by default, and with the
+    // unsigned ones in canonical form; the smallest format of either signedness is accepted
+    assert_eq!(strict::unpack::<u16>(&[0xcc, 200]), Ok(200));
+    assert_eq!(strict::unpack::<u16>(&[0xd1, 0x00, 200]), Ok(200));
+    assert_eq!(strict::unpack::<i16>(&200i16.pack_to_vec()), Ok(200));
+    assert_eq!(strict::unpack::<i16>(&canonical(&200i16)), Ok(200));
+    assert_eq!(
+        strict::unpack::<i16>(&[0xcd, 0x00, 200]),
+        Err(Error::NonMinimalInteger)
+    );
+    assert_eq!(
+        strict::unpack::<i32>(&[0xd2, 0x00, 0x00, 0x00, 200]),
+        Err(Error::NonMinimalInteger)
+    );
+    assert_eq!(
+        strict::unpack::<i16>(&[0xd1, 0x00, 0x05]),
+        Err(Error::NonMinimalInteger)
+    );
+
+    assert_eq!(
+        strict::unpack::<u128>(&u128::MAX.pack_to_vec()),
+        Ok(u128::MAX)
+    );
+    let mut bytes = vec![0xc4, 16];
+    bytes.extend(7u128.to_be_bytes());
+    assert_eq!(u128::unpack(&bytes), Ok((18, 7)));
+    assert_eq!(
+        strict::unpack::<u128>(&bytes),
+        Err(Error::NonMinimalInteger)
+    );
+    let mut bytes = vec![0xc4, 16];
+    bytes.extend(7i128.to_be_bytes());
+    assert_eq!(
+        strict::unpack::<i128>(&bytes),
+        Err(Error::NonMinimalInteger)
+    );
+}
+
+/// Checks that the packed and the canonical bytes of the integer are accepted.
+fn strict_int<V>(v: V) -> Result<(), TestCaseError>
+where
+    V: Packable + Unpackable<Error = Error> + PartialEq + Copy + std::fmt::Debug,
+{
+    prop_assert_eq!(strict::unpack::<V>(&v.pack_to_vec()), Ok(v));
+    prop_assert_eq!(strict::unpack::<V>(&canonical(&v)), Ok(v));
+    Ok(())
+}
+
+/// The integers around the limits of every format, in the range of the type.
+fn boundaries<V: TryFrom<i128>>() -> impl Iterator<Item = V> {
+    let limits = [
+        -32i128,
+        -33,
+        i8::MIN as i128,
+        i16::MIN as i128,
+        i32::MIN as i128,
+        i64::MIN as i128,
+        i128::MIN,
+        127,
+        u8::MAX as i128,
+        i16::MAX as i128,
+        u16::MAX as i128,
+        i32::MAX as i128,
+        u32::MAX as i128,
+        i64::MAX as i128,
+        u64::MAX as i128,
+        i128::MAX,
+    ];
+    limits
+        .into_iter()
+        .flat_map(|v| [v.saturating_sub(1), v, v.saturating_add(1)])
+        .filter_map(|v| V::try_from(v).ok())
+}
+
+#[test]
+fn strict_integer_boundaries() {
+    boundaries::<u8>().try_for_each(strict_int).unwrap();
+    boundaries::<u16>().try_for_each(strict_int).unwrap();
+    boundaries::<u32>().try_for_each(strict_int).unwrap();
+    boundaries::<u64>().try_for_each(strict_int).unwrap();
+    boundaries::<u128>().try_for_each(strict_int).unwrap();
+    boundaries::<usize>().try_for_each(strict_int).unwrap();
+    boundaries::<i8>().try_for_each(strict_int).unwrap();
+    boundaries::<i16>().try_for_each(strict_int).unwrap();
+    boundaries::<i32>().try_for_each(strict_int).unwrap();
+    boundaries::<i64>().try_for_each(strict_int).unwrap();
+    boundaries::<i128>().try_for_each(strict_int).unwrap();
+    boundaries::<isize>().try_for_each(strict_int).unwrap();
+
+    // every 8 and 16 bits integer
+    (u8::MIN..=u8::MAX).try_for_each(strict_int).unwrap();
+    (i8::MIN..=i8::MAX).try_for_each(strict_int).unwrap();
+    (u16::MIN..=u16::MAX).try_for_each(strict_int).unwrap();
+    (i16::MIN..=i16::MAX).try_for_each(strict_int).unwrap();
+}
+
+#[test]
+fn strict_lengths() {
+    assert_eq!(
+        strict::unpack::<String>(&[0xd9, 0x01, b'a']),
+        Err(Error::NonMinimalLength)
+    );
+    assert_eq!(
+        strict::unpack::<Vec<u8>>(&[0xc5, 0x00, 0x01, 0x00]),
+        Err(Error::NonMinimalLength)
+    );
+    assert_eq!(strict::unpack::<Vec<u8>>(&[0xc4, 0x01, 0x00]), Ok(vec![0]));
+    assert_eq!(
+        strict::unpack::<BTreeSet<u8>>(&[0xdc, 0x00, 0x01, 0x00]),
+        Err(Error::NonMinimalLength)
+    );
+    assert_eq!(
+        strict::unpack::<Extension>(&[0xc7, 0x02, 0x05, 0x00, 0x00]),
+        Err(Error::NonMinimalLength)
+    );
+
+    let long = "a".repeat(40);
+    assert_eq!(strict::unpack::<String>(&long.pack_to_vec()), Ok(long));
+}
+
+#[test]
+fn strict_duplicates() {
+    let mut bytes = Vec::new();
+    msgpacker::pack_map_len(&mut bytes, 2);
+    "a".pack(&mut bytes);
+    1u8.pack(&mut bytes);
+    "a".pack(&mut bytes);
+    2u8.pack(&mut bytes);
+
+    assert_eq!(HashMap::<String, u8>::unpack(&bytes).unwrap().1.len(), 1);
+    assert_eq!(
+        strict::unpack::<HashMap<String, u8>>(&bytes),
+        Err(Error::DuplicateKey)
+    );
+    assert_eq!(
+        strict::unpack::<BTreeMap<String, u8>>(&bytes),
+        Err(Error::DuplicateKey)
+    );
+
+    let bytes = [0x93, 0x01, 0x02, 0x01];
+    assert_eq!(
+        strict::unpack::<HashSet<u8>>(&bytes),
+        Err(Error::DuplicateKey)
+    );
+    assert_eq!(
+        strict::unpack::<BTreeSet<u8>>(&bytes),
+        Err(Error::DuplicateKey)
+    );
+    assert_eq!(
+        strict::unpack::<Option<(u8, [BTreeSet<u8>; 1])>>(&[0x00, 0x93, 0x01, 0x02, 0x01]),
+        Err(Error::DuplicateKey)
+    );
+    assert!(strict::unpack::<Vec<u8>>(&[0xc4, 0x02, 0x01, 0x01]).is_ok());
+}
+
+#[test]
+fn strict_duplicates_mixed_signedness() {
+    // 200 as an uint 8 and as an int 16, both accepted, but the same key
+    let bytes = [0x82, 0xcc, 0xc8, 0xc3, 0xd1, 0x00, 0xc8, 0xc2];
+    assert_eq!(
+        strict::unpack_map::<u16, bool, Vec<(u16, bool)>>(&bytes),
+        Err(Error::DuplicateKey)
+    );
+    assert_eq!(
+        strict::unpack::<HashMap<u16, bool>>(&bytes),
+        Err(Error::DuplicateKey)
+    );
+    let mut de = msgpacker::serde::Deserializer::new(&bytes).with_strict(true);
+    assert_eq!(
+        HashMap::<u16, bool>::deserialize(&mut de),
+        Err(Error::DuplicateKey)
+    );
+    let mut de = msgpacker::serde::Deserializer::new_iter(bytes.iter().copied()).with_strict(true);
+    assert_eq!(
+        HashMap::<u16, bool>::deserialize(&mut de),
+        Err(Error::DuplicateKey)
+    );
+
+    // negative integers and the bytes of bins are kept
+    let bytes = [0x82, 0xd0, 0x80, 0xc3, 0xcc, 0x80, 0xc2];
+    assert_eq!(
+        strict::unpack_map::<i16, bool, Vec<(i16, bool)>>(&bytes),
+        Ok((bytes.len(), vec![(-128, true), (128, false)]))
+    );
+    let bytes = [
+        0x82, 0xc4, 0x03, 0xd1, 0x00, 0xc8, 0xc3, 0xc4, 0x02, 0xcc, 0xc8, 0xc2,
+    ];
+    let map = strict::unpack_map::<Vec<u8>, bool, Vec<(Vec<u8>, bool)>>(&bytes);
+    assert_eq!(map.map(|(_, m)| m.len()), Ok(2));
+}
+
+#[test]
+fn strict_timestamps() {
+    let d = core::time::Duration::new(1_700_000_000, 999_999_999);
+    let ext = Extension::Timestamp(d);
+    assert_eq!(strict::unpack::<Extension>(&ext.pack_to_vec()), Ok(ext));
+
+    // a billion nanoseconds in the timestamp 64 format
+    let data = (1_000_000_000u64 << 34) | 1;
+    let mut bytes = vec![0xd7, 0xff];
+    bytes.extend(data.to_be_bytes());
+    assert_eq!(
+        strict::unpack::<Extension>(&bytes),
+        Err(Error::InvalidTimestamp)
+    );
+
+    // the nanoseconds would overflow the seconds of the timestamp 96 format
+    let mut bytes = vec![0xc7, 12, 0xff];
+    bytes.extend(u32::MAX.to_be_bytes());
+    bytes.extend(u64::MAX.to_be_bytes());
+    assert_eq!(
+        strict::unpack::<Extension>(&bytes),
+        Err(Error::InvalidTimestamp)
+    );
+
+    assert_eq!(
+        strict::unpack::<Extension>(&[0xd4, 0xff, 0x00]),
+        Err(Error::InvalidTimestamp)
+    );
+}
+
+#[test]
+fn strict_trailing_bytes() {
+    assert_eq!(strict::unpack::<bool>(&[0xc3]), Ok(true));
+    assert_eq!(
+        strict::unpack::<bool>(&[0xc3, 0xc0]),
+        Err(Error::TrailingBytes)
+    );
+    assert_eq!(strict::unpack::<bool>(&[]), Err(Error::BufferTooShort));
+}
+
+#[test]
+fn strict_derive() {
+    let account = Account {
+        id: 300,
+        balances: [("usd".into(), 1_000), ("eur".into(), 5)].into(),
+        flags: Some([1, 2, 3].into()),
+    };
+    let bytes = canonical(&account);
+    assert_eq!(strict::unpack::<Account>(&bytes), Ok(account));
+
+    let mut bytes = 1u8.pack_to_vec();
+    msgpacker::pack_map_len(&mut bytes, 2);
+    for _ in 0..2 {
+        "usd".pack(&mut bytes);
+        1u8.pack(&mut bytes);
+    }
+    ().pack(&mut bytes);
+    assert!(Account::unpack(&bytes).is_ok());
+    assert_eq!(strict::unpack::<Account>(&bytes), Err(Error::DuplicateKey));
+
+    let mut bytes = 1u8.pack_to_vec();
+    msgpacker::pack_map_len(&mut bytes, 0);
+    bytes.extend([0x93, 0x01, 0x02, 0x02]);
+    assert!(Account::unpack(&bytes).is_ok());
+    assert_eq!(strict::unpack::<Account>(&bytes), Err(Error::DuplicateKey));
+}
+
+#[test]
+fn strict_tagged() {
+    let transfer = Payment::Transfer {
+        amount: 10,
+        memo: "rent".into(),
+    };
+    assert_eq!(
+        strict::unpack::<Payment>(&canonical(&transfer)),
+        Ok(transfer)
+    );
+
+    let mut bytes = Vec::new();
+    msgpacker::pack_map_len(&mut bytes, 4);
+    "kind".pack(&mut bytes);
+    "Transfer".pack(&mut bytes);
+    "amount".pack(&mut bytes);
+    10u8.pack(&mut bytes);
+    "memo".pack(&mut bytes);
+    "rent".pack(&mut bytes);
+    "amount".pack(&mut bytes);
+    1_000u16.pack(&mut bytes);
+    assert!(Payment::unpack(&bytes).is_ok());
+    assert_eq!(strict::unpack::<Payment>(&bytes), Err(Error::DuplicateKey));
+
+    // the flattened entries are checked along with the fields
+    let mut bytes = Vec::new();
+    msgpacker::pack_map_len(&mut bytes, 2);
+    "kind".pack(&mut bytes);
+    "Batch".pack(&mut bytes);
+    "a".pack(&mut bytes);
+    bytes.extend([0x92, 0x01, 0x01]);
+    assert!(Payment::unpack(&bytes).is_ok());
+    assert_eq!(strict::unpack::<Payment>(&bytes), Err(Error::DuplicateKey));
+
+    let mut bytes = Vec::new();
+    msgpacker::pack_map_len(&mut bytes, 2);
+    "kind".pack(&mut bytes);
+    "Batch".pack(&mut bytes);
+    "a".pack(&mut bytes);
+    bytes.extend([0x91, 0xcc, 0x01]);
+    assert!(Payment::unpack(&bytes).is_ok());
+    assert_eq!(
+        strict::unpack::<Payment>(&bytes),
+        Err(Error::NonMinimalInteger)
+    );
+}
+
+#[test]
+fn strict_serde() {
+    let from_slice = |bytes: &[u8]| -> Result<HashMap<String, u8>, Error> {
+        let mut de = msgpacker::serde::Deserializer::new(bytes).with_strict(true);
+        let map = HashMap::deserialize(&mut de)?;
+        de.end()?;
+        Ok(map)
+    };
+    let from_iter = |bytes: &[u8]| -> Result<HashMap<String, u8>, Error> {
+        let mut de =
+            msgpacker::serde::Deserializer::new_iter(bytes.iter().copied()).with_strict(true);
+        HashMap::deserialize(&mut de)
+    };
+
+    let map: HashMap<String, u8> = [("a".into(), 1), ("b".into(), 200)].into();
+    let bytes = canonical(&map);
+    assert_eq!(from_slice(&bytes), Ok(map.clone()));
+    assert_eq!(from_iter(&bytes), Ok(map));
+
+    let mut bytes = Vec::new();
+    msgpacker::pack_map_len(&mut bytes, 2);
+    "a".pack(&mut bytes);
+    1u8.pack(&mut bytes);
+    "a".pack(&mut bytes);
+    2u8.pack(&mut bytes);
+    assert!(msgpacker::serde::from_slice::<HashMap<String, u8>>(&bytes).is_ok());
+    assert_eq!(from_slice(&bytes), Err(Error::DuplicateKey));
+    assert_eq!(from_iter(&bytes), Err(Error::DuplicateKey));
+
+    let bytes = [0x81, 0xd9, 0x01, b'a', 0x01];
+    assert_eq!(from_slice(&bytes), Err(Error::NonMinimalLength));
+    assert_eq!(from_iter(&bytes), Err(Error::NonMinimalLength));
+
+    let bytes = [0x81, 0xa1, b'a', 0xcc, 0x01];
+    assert_eq!(from_slice(&bytes), Err(Error::NonMinimalInteger));
+    assert_eq!(from_iter(&bytes), Err(Error::NonMinimalInteger));
+
+    let bytes = [0xde, 0x00, 0x00];
+    assert_eq!(from_slice(&bytes), Err(Error::NonMinimalLength));
+    assert_eq!(from_iter(&bytes), Err(Error::NonMinimalLength));
+
+    // struct fields packed as a map can't be repeated either
+    let mut bytes = Vec::new();
+    msgpacker::pack_map_len(&mut bytes, 3);
+    "amount".pack(&mut bytes);
+    1u8.pack(&mut bytes);
+    "memo".pack(&mut bytes);
+    "x".pack(&mut bytes);
+    "amount".pack(&mut bytes);
+    2u8.pack(&mut bytes);
+    let mut de = msgpacker::serde::Deserializer::new(&bytes)
+        .with_struct_map(true)
+        .with_strict(true);
+    assert_eq!(Transfer::deserialize(&mut de), Err(Error::DuplicateKey));
+}
+
+#[test]
+fn strict_serde_timestamps() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Event {
+        #[serde(with = "msgpacker::serde::timestamp")]
+        at: core::time::Duration,
+    }
+
+    let data = (1_000_000_000u64 << 34) | 1;
+    let mut bytes = vec![0xd7, 0xff];
+    bytes.extend(data.to_be_bytes());
+
+    let mut de = msgpacker::serde::Deserializer::new(&bytes).with_strict(true);
+    assert_eq!(Event::deserialize(&mut de), Err(Error::InvalidTimestamp));
+    let mut de = msgpacker::serde::Deserializer::new_iter(bytes.iter().copied()).with_strict(true);
+    assert_eq!(Event::deserialize(&mut de), Err(Error::InvalidTimestamp));
+}
+
+proptest! {
+    #[test]
+    fn strict_accepts_integers(
+        a: u32,
+        b: u64,
+        c: u128,
+        d: usize,
+        e: i32,
+        f: i64,
+        g: i128,
+        h: isize,
+    ) {
+        strict_int(a)?;
+        strict_int(b)?;
+        strict_int(c)?;
+        strict_int(d)?;
+        strict_int(e)?;
+        strict_int(f)?;
+        strict_int(g)?;
+        strict_int(h)?;
+    }
+
+    #[test]
+    fn strict_accepts_canonical(map: HashMap<String, (i64, Option<Vec<u8>>, BTreeSet<i32>)>) {
+        let bytes = canonical(&map);
+        prop_assert_eq!(strict::unpack(&bytes), Ok(map.clone()));
+
+        let mut de = msgpacker::serde::Deserializer::new(&bytes).with_strict(true);
+        let x: HashMap<String, (i64, Option<serde_bytes::ByteBuf>, BTreeSet<i32>)> =
+            HashMap::deserialize(&mut de).unwrap();
+        let x: HashMap<_, _> = x
+            .into_iter()
+            .map(|(k, (i, b, s))| (k, (i, b.map(|b| b.into_vec()), s)))
+            .collect();
+        prop_assert_eq!(x, map);
+    }
+}
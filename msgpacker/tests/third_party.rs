@@ -0,0 +1,191 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
+};
+
+use indexmap::{IndexMap, IndexSet};
+use msgpacker::prelude::*;
+use proptest::prelude::*;
+use rustc_hash::FxBuildHasher;
+use smallvec::SmallVec;
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+pub struct Index {
+    pub fx: HashMap<u32, String, FxBuildHasher>,
+    pub seen: HashSet<u64, FxBuildHasher>,
+    pub ordered: IndexMap<String, u8>,
+    pub unique: IndexSet<i16>,
+    pub brown: hashbrown::HashMap<u8, bool, FxBuildHasher>,
+    pub small: SmallVec<[u8; 4]>,
+    pub ids: SmallVec<[u32; 2]>,
+}
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+#[msgpacker(tag = "type")]
+pub enum Event {
+    Note {
+        text: String,
+        #[msgpacker(flatten)]
+        extra: IndexMap<String, String>,
+    },
+}
+
+/// A key sorted in descending order, as `BTreeMap` takes the order of its keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, MsgPacker)]
+pub struct Desc(u8);
+
+impl PartialOrd for Desc {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Desc {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.cmp(&self.0)
+    }
+}
+
+fn roundtrip<T>(x: &T) -> Vec<u8>
+where
+    T: Packable + Unpackable + Clone + std::fmt::Debug + PartialEq,
+    <T as Unpackable>::Error: std::fmt::Debug,
+{
+    let bytes = x.pack_to_vec();
+    assert_eq!(T::unpack(&bytes).unwrap(), (bytes.len(), x.clone()));
+    assert_eq!(
+        T::unpack_iter(bytes.iter().copied()).unwrap(),
+        (bytes.len(), x.clone())
+    );
+    bytes
+}
+
+fn canonical<T: Packable>(x: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    x.pack_canonical(&mut bytes);
+    bytes
+}
+
+#[test]
+fn third_party_custom_hashers() {
+    let mut fx = HashMap::with_hasher(FxBuildHasher);
+    fx.insert(1u32, "a".to_string());
+    fx.insert(2, "b".to_string());
+    let bytes = roundtrip(&fx);
+    let std: HashMap<u32, String> = fx.clone().into_iter().collect();
+    assert_eq!(HashMap::<u32, String>::unpack(&bytes).unwrap().1, std);
+    assert_eq!(canonical(&fx), canonical(&std));
+
+    let set: HashSet<u64, FxBuildHasher> = [1, 1 << 40].into_iter().collect();
+    roundtrip(&set);
+
+    let brown: hashbrown::HashSet<String, FxBuildHasher> =
+        ["a".into(), "b".into()].into_iter().collect();
+    roundtrip(&brown);
+}
+
+#[test]
+fn third_party_indexmap_keeps_order() {
+    let map: IndexMap<String, u8> = [("z".into(), 1), ("a".into(), 2), ("m".into(), 3)].into();
+    let bytes = roundtrip(&map);
+    let mut expected = Vec::new();
+    msgpacker::pack_map(&mut expected, map.iter());
+    assert_eq!(bytes, expected);
+    let (_, decoded) = IndexMap::<String, u8>::unpack(&bytes).unwrap();
+    assert_eq!(decoded.keys().collect::<Vec<_>>(), ["z", "a", "m"]);
+
+    // canonical packing still sorts the keys
+    let sorted: BTreeMap<String, u8> = map.clone().into_iter().collect();
+    assert_eq!(canonical(&map), sorted.pack_to_vec());
+
+    let set: IndexSet<i16> = [300, -1, 7].into();
+    let (_, decoded) = IndexSet::<i16>::unpack(&roundtrip(&set)).unwrap();
+    assert_eq!(decoded.into_iter().collect::<Vec<_>>(), [300, -1, 7]);
+}
+
+#[test]
+fn third_party_flattened_indexmap() {
+    let event = Event::Note {
+        text: "hi".into(),
+        extra: [
+            ("x-z".to_string(), "1".to_string()),
+            ("x-a".to_string(), "2".to_string()),
+        ]
+        .into(),
+    };
+    roundtrip(&event);
+    let (_, Event::Note { extra, .. }) = Event::unpack(&event.pack_to_vec()).unwrap();
+    assert_eq!(extra.keys().collect::<Vec<_>>(), ["x-z", "x-a"]);
+}
+
+#[test]
+fn third_party_strict_duplicates() {
+    let mut bytes = Vec::new();
+    msgpacker::pack_map(&mut bytes, [(1u8, 1u8), (1, 2)]);
+    assert_eq!(
+        IndexMap::<u8, u8>::unpack_strict(&bytes),
+        Err(Error::DuplicateKey)
+    );
+    assert_eq!(
+        HashMap::<u8, u8, FxBuildHasher>::unpack_strict(&bytes),
+        Err(Error::DuplicateKey)
+    );
+    assert_eq!(
+        hashbrown::HashMap::<u8, u8, FxBuildHasher>::unpack_strict(&bytes),
+        Err(Error::DuplicateKey)
+    );
+
+    let mut bytes = Vec::new();
+    msgpacker::pack_array(&mut bytes, [1u8, 1]);
+    assert_eq!(
+        IndexSet::<u8>::unpack_strict(&bytes),
+        Err(Error::DuplicateKey)
+    );
+}
+
+#[test]
+fn third_party_smallvec() {
+    let small: SmallVec<[u8; 4]> = SmallVec::from_slice(&[1, 2, 3, 4, 5]);
+    let bytes = roundtrip(&small);
+    assert_eq!(bytes, vec![1u8, 2, 3, 4, 5].pack_to_vec());
+
+    let index = Index {
+        fx: [(1, "a".into())].into_iter().collect(),
+        seen: [5].into_iter().collect(),
+        ordered: [("b".into(), 1), ("a".into(), 2)].into(),
+        unique: [3, 2, 1].into(),
+        brown: [(1, true)].into_iter().collect(),
+        small,
+        ids: [1, 2, 3].into_iter().collect(),
+    };
+    let bytes = roundtrip(&index);
+    assert_eq!(bytes, index.try_pack_to_vec().unwrap());
+    assert_eq!(Index::unpack_strict(&bytes), Ok((bytes.len(), index)));
+}
+
+#[test]
+fn third_party_btreemap_order() {
+    // the order of a `BTreeMap` comes from its key, so a newtype key replaces a comparator
+    let map: BTreeMap<Desc, u8> = [(Desc(1), 1), (Desc(3), 3), (Desc(2), 2)].into();
+    let bytes = roundtrip(&map);
+    let (_, decoded) = BTreeMap::<Desc, u8>::unpack(&bytes).unwrap();
+    assert_eq!(decoded.keys().map(|k| k.0).collect::<Vec<_>>(), [3, 2, 1]);
+}
+
+proptest! {
+    #[test]
+    fn third_party_proptest(map: Vec<(String, u32)>, small: Vec<u8>) {
+        let ordered: IndexMap<String, u32> = map.iter().cloned().collect();
+        let (_, decoded) = IndexMap::<String, u32>::unpack(&ordered.pack_to_vec()).unwrap();
+        prop_assert!(decoded.iter().eq(ordered.iter()));
+
+        let fx: HashMap<String, u32, FxBuildHasher> = map.into_iter().collect();
+        let (_, decoded) = HashMap::<String, u32, FxBuildHasher>::unpack(&fx.pack_to_vec()).unwrap();
+        prop_assert_eq!(decoded, fx);
+
+        let bytes = SmallVec::<[u8; 8]>::from_vec(small.clone()).pack_to_vec();
+        prop_assert_eq!(&bytes, &small.pack_to_vec());
+        let (_, decoded) = SmallVec::<[u8; 8]>::unpack(&bytes).unwrap();
+        prop_assert_eq!(decoded.as_slice(), small.as_slice());
+    }
+}
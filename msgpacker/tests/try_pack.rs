@@ -0,0 +1,250 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    iter,
+};
+
+use msgpacker::prelude::*;
+use proptest::prelude::*;
+
+/// Packs as an array of `len` nils, without holding them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Nils(usize);
+
+impl Packable for Nils {
+    fn pack<T>(&self, buf: &mut T) -> usize
+    where
+        T: Writer,
+    {
+        msgpacker::pack_array(buf, iter::repeat_n((), self.0))
+    }
+
+    fn try_pack<T>(&self, buf: &mut T) -> Result<usize, PackError>
+    where
+        T: Writer,
+    {
+        msgpacker::try_pack_array(buf, iter::repeat_n((), self.0))
+    }
+}
+
+impl Unpackable for Nils {
+    type Error = Error;
+
+    fn unpack(buf: &[u8]) -> Result<(usize, Self), Self::Error> {
+        msgpacker::unpack_array(buf).map(|(n, nils): (usize, Vec<()>)| (n, Nils(nils.len())))
+    }
+
+    fn unpack_iter<I>(bytes: I) -> Result<(usize, Self), Self::Error>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        msgpacker::unpack_array_iter(bytes).map(|(n, nils): (usize, Vec<()>)| (n, Nils(nils.len())))
+    }
+}
+
+const HUGE: Nils = Nils(u32::MAX as usize + 1);
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+#[msgpacker(into = "Vec<u8>")]
+pub struct Blob(Vec<u8>);
+
+impl From<Blob> for Vec<u8> {
+    fn from(blob: Blob) -> Self {
+        blob.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+pub struct Record {
+    pub id: u64,
+    pub name: String,
+    #[msgpacker(map)]
+    pub scores: HashMap<String, i32>,
+    #[msgpacker(array)]
+    pub tags: Vec<String>,
+    pub blob: Option<Blob>,
+}
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+pub struct Extra {
+    pub note: String,
+    #[msgpacker(flatten)]
+    pub rest: BTreeMap<String, u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+#[msgpacker(tag = "kind")]
+pub enum Event {
+    Created {
+        #[msgpacker(flatten)]
+        record: Record,
+    },
+    Noted {
+        at: u32,
+        #[msgpacker(flatten)]
+        extra: Extra,
+    },
+    Deleted,
+}
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+pub struct Batch {
+    pub id: u8,
+    pub nils: Nils,
+}
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+pub struct Entries {
+    pub nils: Nils,
+}
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+pub enum Indexed {
+    Batch(Batch),
+    Pair(u8, Nils),
+}
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+#[msgpacker(tag = "t", content = "c")]
+pub enum Adjacent {
+    Pair(u8, Nils),
+    Named {
+        #[msgpacker(flatten)]
+        entries: Entries,
+    },
+}
+
+#[test]
+fn try_pack_lengths() {
+    let mut buf = Vec::new();
+    assert_eq!(msgpacker::try_pack_array_len(&mut buf, 3), Ok(1));
+    assert_eq!(
+        msgpacker::try_pack_map_len(&mut buf, u32::MAX as usize),
+        Ok(5)
+    );
+    assert_eq!(buf, [0x93, 0xdf, 0xff, 0xff, 0xff, 0xff]);
+
+    let mut buf = Vec::new();
+    let len = u32::MAX as usize + 1;
+    assert_eq!(
+        msgpacker::try_pack_array_len(&mut buf, len),
+        Err(PackError::TooLarge)
+    );
+    assert_eq!(
+        msgpacker::try_pack_map_len(&mut buf, len),
+        Err(PackError::TooLarge)
+    );
+    assert_eq!(HUGE.try_pack(&mut buf), Err(PackError::TooLarge));
+    assert!(buf.is_empty());
+}
+
+#[cfg(not(feature = "strict"))]
+#[test]
+fn pack_skips_oversized() {
+    // the elements aren't packed after a missing header
+    let mut buf = Vec::new();
+    assert_eq!(HUGE.pack(&mut buf), 0);
+    assert!(buf.is_empty());
+}
+
+#[test]
+fn try_pack_nested() {
+    assert_eq!(Some(HUGE).try_pack_to_vec(), Err(PackError::TooLarge));
+    assert_eq!((1u8, HUGE).try_pack_to_vec(), Err(PackError::TooLarge));
+    assert_eq!([Nils(1), HUGE].try_pack_to_vec(), Err(PackError::TooLarge));
+    assert_eq!(
+        msgpacker::try_pack_to_vec(&[(1u8, [Nils(0), HUGE])]),
+        Err(PackError::TooLarge)
+    );
+
+    let mut buf = Vec::new();
+    assert_eq!(
+        msgpacker::try_pack_map(&mut buf, [(1u8, Nils(2)), (2u8, HUGE)]),
+        Err(PackError::TooLarge)
+    );
+    // the entries before the oversized one are left in the buffer
+    assert_eq!(buf, [0x82, 0x01, 0x92, 0xc0, 0xc0, 0x02]);
+
+    let mut buf = Vec::new();
+    assert_eq!(
+        msgpacker::try_pack_array(&mut buf, [Nils(1), Nils(2)]),
+        Ok(6)
+    );
+    assert_eq!(buf, [0x92, 0x91, 0xc0, 0x92, 0xc0, 0xc0]);
+}
+
+#[test]
+fn try_pack_derive() {
+    let batch = Batch { id: 1, nils: HUGE };
+    assert_eq!(batch.try_pack_to_vec(), Err(PackError::TooLarge));
+    assert_eq!(
+        Indexed::Batch(batch.clone()).try_pack_to_vec(),
+        Err(PackError::TooLarge)
+    );
+    assert_eq!(
+        Indexed::Pair(1, HUGE).try_pack_to_vec(),
+        Err(PackError::TooLarge)
+    );
+    assert_eq!(
+        Adjacent::Pair(1, HUGE).try_pack_to_vec(),
+        Err(PackError::TooLarge)
+    );
+    let entries = Entries { nils: HUGE };
+    assert_eq!(
+        Adjacent::Named { entries }.try_pack_to_vec(),
+        Err(PackError::TooLarge)
+    );
+
+    let batch = Batch {
+        id: 1,
+        nils: Nils(2),
+    };
+    assert_eq!(batch.try_pack_to_vec(), Ok(batch.pack_to_vec()));
+    let adjacent = Adjacent::Named {
+        entries: Entries { nils: Nils(3) },
+    };
+    let bytes = adjacent.try_pack_to_vec().unwrap();
+    assert_eq!(bytes, adjacent.pack_to_vec());
+    assert_eq!(Adjacent::unpack(&bytes).unwrap().1, adjacent);
+}
+
+#[test]
+fn try_pack_matches_pack() {
+    let record = Record {
+        id: 7,
+        name: "a".repeat(300),
+        scores: [("x".into(), -1), ("y".into(), 70_000)].into(),
+        tags: vec!["t".into(); 20],
+        blob: Some(Blob(vec![1, 2, 3])),
+    };
+    assert_eq!(record.try_pack_to_vec(), Ok(record.pack_to_vec()));
+
+    let extra = Extra {
+        note: "n".into(),
+        rest: [("k".into(), 1)].into(),
+    };
+    for event in [
+        Event::Created { record },
+        Event::Noted { at: 9, extra },
+        Event::Deleted,
+    ] {
+        let mut buf = Vec::new();
+        let n = event.try_pack(&mut buf).unwrap();
+        assert_eq!(n, buf.len());
+        assert_eq!(buf, event.pack_to_vec());
+        assert_eq!(Event::unpack(&buf).unwrap().1, event);
+    }
+}
+
+proptest! {
+    #[test]
+    fn try_pack_proptest(
+        id: u64,
+        name: String,
+        scores: HashMap<String, i32>,
+        tags: Vec<String>,
+        blob: Option<Vec<u8>>,
+    ) {
+        let record = Record { id, name, scores, tags, blob: blob.map(Blob) };
+        prop_assert_eq!(record.try_pack_to_vec(), Ok(record.pack_to_vec()));
+    }
+}
@@ -0,0 +1,9 @@
+use msgpacker::prelude::*;
+
+#[derive(MsgPacker)]
+#[msgpacker(tag = "a", tag = "b")]
+enum Foo {
+    Bar { a: u32 },
+}
+
+fn main() {}
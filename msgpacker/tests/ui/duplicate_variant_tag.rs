@@ -0,0 +1,11 @@
+use msgpacker::prelude::*;
+
+#[derive(MsgPacker)]
+#[msgpacker(external)]
+enum Foo {
+    Bar(u32),
+    #[msgpacker(tag = "Bar")]
+    Baz(String),
+}
+
+fn main() {}
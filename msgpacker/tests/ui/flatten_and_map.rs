@@ -0,0 +1,10 @@
+use msgpacker::prelude::*;
+use std::collections::BTreeMap;
+
+#[derive(MsgPacker)]
+struct Foo {
+    #[msgpacker(flatten, map)]
+    a: BTreeMap<String, u32>,
+}
+
+fn main() {}
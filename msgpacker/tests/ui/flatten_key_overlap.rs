@@ -0,0 +1,26 @@
+use msgpacker::prelude::*;
+
+#[derive(MsgPacker)]
+struct Header {
+    id: u32,
+    kind: String,
+}
+
+#[derive(MsgPacker)]
+struct Trace {
+    id: u32,
+}
+
+#[derive(MsgPacker)]
+#[msgpacker(tag = "kind")]
+enum Message {
+    Text {
+        id: u32,
+        #[msgpacker(flatten)]
+        header: Header,
+        #[msgpacker(flatten)]
+        trace: Trace,
+    },
+}
+
+fn main() {}
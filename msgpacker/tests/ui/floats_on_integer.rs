@@ -0,0 +1,11 @@
+use msgpacker::{prelude::*, FloatOptions};
+
+const LENIENT: FloatOptions = FloatOptions::new().with_integers(true);
+
+#[derive(MsgPacker)]
+struct Foo {
+    #[msgpacker(floats = "LENIENT")]
+    a: u32,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use msgpacker::prelude::*;
+
+#[derive(MsgPacker)]
+struct Wrapper<T> {
+    value: T,
+}
+
+fn main() {}
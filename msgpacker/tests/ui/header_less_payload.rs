@@ -0,0 +1,17 @@
+use msgpacker::prelude::*;
+
+#[derive(MsgPacker)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[derive(MsgPacker)]
+#[msgpacker(tag = "t", content = "c")]
+enum Shape {
+    Dot(Point),
+    Line { from: Point, to: Option<Point> },
+    Circle(f64),
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use msgpacker::prelude::*;
+
+#[derive(MsgPacker)]
+#[msgpacker(tag = "type")]
+enum Foo {
+    #[msgpacker(tag = 7)]
+    Bar { a: u32 },
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use msgpacker::prelude::*;
+
+#[derive(MsgPacker)]
+#[msgpacker(tag = "type")]
+enum Foo {
+    Bar { a: u32 },
+    Baz(u32, String),
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use msgpacker::prelude::*;
+
+#[derive(MsgPacker)]
+#[msgpacker(tag_type = "f32")]
+enum Foo {
+    Bar(u32),
+}
+
+fn main() {}
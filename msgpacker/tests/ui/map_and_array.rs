@@ -0,0 +1,10 @@
+use msgpacker::prelude::*;
+
+#[derive(MsgPacker)]
+struct Foo {
+    #[msgpacker(map)]
+    #[msgpacker(array)]
+    a: Vec<u32>,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use msgpacker::prelude::*;
+
+#[derive(MsgPacker)]
+#[msgpacker(tag = "type")]
+struct Foo {
+    a: u32,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use msgpacker::prelude::*;
+
+#[derive(MsgPacker)]
+#[msgpacker(untagged)]
+enum Foo {
+    #[msgpacker(tag = "bar")]
+    Bar(u32),
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use msgpacker::prelude::*;
+
+#[derive(MsgPacker)]
+#[msgpacker(external, tag_type = "u8")]
+enum Foo {
+    Bar(u32),
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use msgpacker::prelude::*;
+
+#[derive(MsgPacker)]
+union Bits {
+    a: u32,
+    b: f32,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use msgpacker::prelude::*;
+
+#[derive(MsgPacker)]
+#[msgpacker(untaged)]
+enum Foo {
+    Bar(u32),
+}
+
+fn main() {}
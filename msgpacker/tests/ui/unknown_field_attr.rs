@@ -0,0 +1,12 @@
+use msgpacker::prelude::*;
+use std::collections::HashMap;
+
+#[derive(MsgPacker)]
+struct Foo {
+    #[msgpacker(mapp)]
+    a: HashMap<u32, String>,
+    #[msgpacker(arary)]
+    b: Vec<u32>,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use msgpacker::prelude::*;
+
+#[derive(MsgPacker)]
+enum Foo {
+    #[msgpacker(rename = "bar")]
+    Bar(u32),
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+use msgpacker::prelude::*;
+
+fn positive(x: &i64) -> Result<(), Error> {
+    if *x > 0 {
+        Ok(())
+    } else {
+        Err(Error::UnexpectedFormatTag)
+    }
+}
+
+#[derive(MsgPacker)]
+#[msgpacker(try_from = "i64")]
+struct Foo {
+    #[msgpacker(validate = "positive")]
+    a: i64,
+}
+
+fn main() {}
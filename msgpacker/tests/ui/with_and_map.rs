@@ -0,0 +1,9 @@
+use msgpacker::prelude::*;
+
+#[derive(MsgPacker)]
+struct Foo {
+    #[msgpacker(map, with = "BarDef")]
+    a: Vec<u32>,
+}
+
+fn main() {}
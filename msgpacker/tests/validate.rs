@@ -0,0 +1,200 @@
+use msgpacker::prelude::*;
+
+mod utils;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidError {
+    Msgpack(Error),
+    EmptyName,
+    OutOfRange,
+    LengthMismatch,
+    MalformedEmail,
+}
+
+impl From<Error> for InvalidError {
+    fn from(e: Error) -> Self {
+        InvalidError::Msgpack(e)
+    }
+}
+
+fn non_empty(name: &str) -> Result<(), InvalidError> {
+    (!name.is_empty())
+        .then_some(())
+        .ok_or(InvalidError::EmptyName)
+}
+
+fn percent(x: &u8) -> Result<(), InvalidError> {
+    (*x <= 100).then_some(()).ok_or(InvalidError::OutOfRange)
+}
+
+fn same_len(series: &Series) -> Result<(), InvalidError> {
+    (series.xs.len() == series.ys.len())
+        .then_some(())
+        .ok_or(InvalidError::LengthMismatch)
+}
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+#[msgpacker(error = "InvalidError", validate = "same_len")]
+pub struct Series {
+    #[msgpacker(validate = "non_empty")]
+    pub name: String,
+    pub xs: Vec<f64>,
+    pub ys: Vec<f64>,
+    #[msgpacker(validate = "percent")]
+    pub confidence: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker)]
+#[msgpacker(external, error = "InvalidError")]
+pub enum Shape {
+    Named {
+        #[msgpacker(validate = "non_empty")]
+        name: String,
+    },
+    Anonymous,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker)]
+#[msgpacker(untagged, error = "InvalidError")]
+pub enum Label {
+    Id(u64),
+    Named {
+        #[msgpacker(validate = "non_empty")]
+        name: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, MsgPacker)]
+#[msgpacker(try_from = "u16", into = "u16", error = "InvalidError")]
+pub struct Port(u16);
+
+impl TryFrom<u16> for Port {
+    type Error = InvalidError;
+
+    fn try_from(port: u16) -> Result<Self, Self::Error> {
+        (port != 0)
+            .then_some(Port(port))
+            .ok_or(InvalidError::OutOfRange)
+    }
+}
+
+impl From<Port> for u16 {
+    fn from(port: Port) -> Self {
+        port.0
+    }
+}
+
+/// Packed as `user@domain`; the error is inferred from the `TryFrom` conversion.
+#[derive(Debug, Clone, PartialEq, Eq, MsgPacker)]
+#[msgpacker(try_from = "String", into = "String")]
+pub struct Email {
+    pub user: String,
+    pub domain: String,
+}
+
+impl TryFrom<String> for Email {
+    type Error = InvalidError;
+
+    fn try_from(email: String) -> Result<Self, Self::Error> {
+        let (user, domain) = email.split_once('@').ok_or(InvalidError::MalformedEmail)?;
+        Ok(Email {
+            user: user.into(),
+            domain: domain.into(),
+        })
+    }
+}
+
+impl From<Email> for String {
+    fn from(email: Email) -> Self {
+        format!("{}@{}", email.user, email.domain)
+    }
+}
+
+fn packed<T: Packable>(x: T) -> Vec<u8> {
+    let mut bytes = vec![];
+    x.pack(&mut bytes);
+    bytes
+}
+
+fn rejects<T>(bytes: Vec<u8>, e: InvalidError)
+where
+    T: Unpackable<Error = InvalidError> + core::fmt::Debug,
+{
+    assert_eq!(T::unpack(&bytes).unwrap_err(), e);
+    assert_eq!(T::unpack_iter(bytes).unwrap_err(), e);
+}
+
+#[test]
+fn fields() {
+    let series = Series {
+        name: "a".into(),
+        xs: vec![1.0, 2.0],
+        ys: vec![3.0, 4.0],
+        confidence: 95,
+    };
+    utils::case(series.clone());
+
+    let mut x = series.clone();
+    x.name.clear();
+    rejects::<Series>(packed(x), InvalidError::EmptyName);
+
+    let mut x = series.clone();
+    x.confidence = 101;
+    rejects::<Series>(packed(x), InvalidError::OutOfRange);
+
+    utils::case(Shape::Named { name: "x".into() });
+    utils::case(Shape::Anonymous);
+    rejects::<Shape>(
+        packed(Shape::Named {
+            name: String::new(),
+        }),
+        InvalidError::EmptyName,
+    );
+}
+
+#[test]
+fn untagged() {
+    utils::case(Label::Id(7));
+    utils::case(Label::Named { name: "x".into() });
+    // the error of the variant the payload is read as is kept
+    rejects::<Label>(
+        packed(Label::Named {
+            name: String::new(),
+        }),
+        InvalidError::EmptyName,
+    );
+    rejects::<Label>(
+        packed(true),
+        InvalidError::Msgpack(Error::InvalidEnumVariant),
+    );
+}
+
+#[test]
+fn container() {
+    let series = Series {
+        name: "a".into(),
+        xs: vec![1.0, 2.0],
+        ys: vec![3.0],
+        confidence: 95,
+    };
+    rejects::<Series>(packed(series), InvalidError::LengthMismatch);
+}
+
+#[test]
+fn proxies() {
+    utils::case(Port(8080));
+    assert_eq!(packed(Port(8080)), packed(8080u16));
+    rejects::<Port>(packed(0u16), InvalidError::OutOfRange);
+
+    let email = Email {
+        user: "a".into(),
+        domain: "b.c".into(),
+    };
+    utils::case(email.clone());
+    assert_eq!(packed(email), packed(String::from("a@b.c")));
+    rejects::<Email>(packed(String::from("a.b.c")), InvalidError::MalformedEmail);
+    rejects::<Email>(
+        packed(1u8),
+        InvalidError::Msgpack(Error::UnexpectedFormatTag),
+    );
+}
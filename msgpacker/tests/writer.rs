@@ -0,0 +1,160 @@
+use std::{collections::VecDeque, io};
+
+use msgpacker::{prelude::*, ExtendWriter, IoWriter};
+use proptest::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, MsgPacker)]
+pub struct Record {
+    pub id: u64,
+    pub name: String,
+    pub blob: Vec<u8>,
+    pub ratio: f64,
+    pub tags: Vec<String>,
+    pub ext: Option<Extension>,
+}
+
+/// Fails every write after the first `left` bytes.
+#[derive(Debug)]
+struct Failing {
+    left: usize,
+    written: Vec<u8>,
+}
+
+impl io::Write for Failing {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.len() > self.left {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        self.left -= buf.len();
+        self.written.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn record() -> Record {
+    Record {
+        id: 1 << 40,
+        name: "a".repeat(300),
+        blob: vec![7; 70_000],
+        ratio: 0.5,
+        tags: vec!["x".into(), "y".into()],
+        ext: Some(Extension::Ext(3, vec![1, 2, 3, 4, 5])),
+    }
+}
+
+#[test]
+fn writer_slice() {
+    let record = record();
+    let expected = record.pack_to_vec();
+
+    let mut bytes = vec![0u8; expected.len() + 2];
+    let mut slice = bytes.as_mut_slice();
+    let n = record.pack(&mut slice);
+    assert_eq!(n, expected.len());
+    assert_eq!(slice.len(), 2);
+    assert_eq!(&bytes[..n], expected);
+}
+
+#[test]
+#[should_panic(expected = "the slice is too short for the packed bytes")]
+fn writer_slice_too_short() {
+    let mut bytes = [0u8; 3];
+    "abc".pack(&mut bytes.as_mut_slice());
+}
+
+#[test]
+fn writer_extend() {
+    let record = record();
+    let mut deque = VecDeque::new();
+    let n = record.pack(&mut ExtendWriter(&mut deque));
+    assert_eq!(n, deque.len());
+    assert_eq!(Vec::from(deque), record.pack_to_vec());
+}
+
+#[test]
+fn writer_extend_compat() {
+    let record = record();
+    let expected = record.pack_to_vec();
+
+    let mut deque = VecDeque::new();
+    assert_eq!(record.pack_extend(&mut deque), expected.len());
+    assert_eq!(record.try_pack_extend(&mut deque), Ok(expected.len()));
+    assert_eq!(Vec::from(deque), [expected.clone(), expected].concat());
+
+    let mut canonical = Vec::new();
+    record.pack_canonical(&mut canonical);
+    let mut deque = VecDeque::new();
+    assert_eq!(record.pack_canonical_extend(&mut deque), canonical.len());
+    assert_eq!(Vec::from(deque), canonical);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn writer_serde_extend() {
+    let value = (1u8, "hi".to_string(), vec![0.5f64]);
+    let mut deque = VecDeque::new();
+    msgpacker::serde::to_buffer(&mut deque, &value);
+    msgpacker::serde::try_to_buffer(&mut deque, &value).unwrap();
+    let expected = msgpacker::serde::to_vec(&value);
+    assert_eq!(Vec::from(deque), [expected.clone(), expected].concat());
+}
+
+#[test]
+fn writer_io() {
+    let record = record();
+    let mut w = IoWriter::new(Vec::new());
+    record.pack(&mut w);
+    assert_eq!(w.into_inner().unwrap(), record.pack_to_vec());
+
+    let mut w = IoWriter::new(Failing {
+        left: 4,
+        written: Vec::new(),
+    });
+    (1u8, "abcdef".to_string(), 2u8).pack(&mut w);
+    let e = w.into_inner().unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::WriteZero);
+}
+
+#[test]
+fn writer_io_stops_at_error() {
+    let mut failing = Failing {
+        left: 2,
+        written: Vec::new(),
+    };
+    let mut w = IoWriter::new(&mut failing);
+    // the string body fails, and the byte after it isn't written
+    (1u8, "abc".to_string(), 2u8).pack(&mut w);
+    assert!(w.into_inner().is_err());
+    assert_eq!(failing.written, [0x01, 0xa3]);
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn writer_bytes_mut() {
+    let record = record();
+    let mut buf = bytes::BytesMut::new();
+    let n = record.pack(&mut buf);
+    assert_eq!(n, buf.len());
+    assert_eq!(buf.as_ref(), record.pack_to_vec());
+    assert_eq!(Record::unpack(&buf).unwrap().1, record);
+}
+
+proptest! {
+    #[test]
+    fn writer_proptest(id: u64, name: String, blob: Vec<u8>, ratio: f64, tags: Vec<String>) {
+        let record = Record { id, name, blob, ratio, tags, ext: None };
+        let expected = record.pack_to_vec();
+
+        let mut bytes = vec![0u8; expected.len()];
+        record.pack(&mut bytes.as_mut_slice());
+        prop_assert_eq!(&bytes, &expected);
+
+        let mut deque = VecDeque::new();
+        record.pack(&mut ExtendWriter(&mut deque));
+        prop_assert_eq!(Vec::from(deque), expected);
+    }
+}